@@ -11,5 +11,20 @@ fn main() -> io::Result<()> {
             .set_icon("assets/app.ico")
             .compile()?;
     }
+    // C header of the `ffi` feature
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(io::Error::other)?;
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .map_err(io::Error::other)?;
+        cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .map_err(io::Error::other)?
+            .write_to_file(format!("{}/include/counterlines.h", crate_dir));
+    }
     Ok(())
 }
@@ -0,0 +1,52 @@
+/* counterlines - C interface of the rustedbytes-counterlines library */
+
+#ifndef COUNTERLINES_H
+#define COUNTERLINES_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Count `paths` (files, directories or glob patterns, `path_count` of them) and return the
+// report as a JSON string, or `NULL` on failure. `recursive` descends into directories,
+// `threads` is the size of the thread pool (0: one per CPU) and `config`, when not `NULL`,
+// is a language configuration file to load. The string must be released with
+// `counterlines_free_string`.
+//
+// # Safety
+//
+// `paths` must point to `path_count` valid NUL-terminated strings, and `config` must be
+// `NULL` or a valid NUL-terminated string.
+char *counterlines_count(const char *const *paths,
+                         size_t path_count,
+                         bool recursive,
+                         size_t threads,
+                         const char *config);
+
+// The error of the last call that returned `NULL` on this thread, or `NULL`; valid until
+// the next call on the same thread
+const char *counterlines_last_error(void);
+
+// Release a string returned by the library; `NULL` is ignored
+//
+// # Safety
+//
+// `string` must be `NULL` or a string returned by this library that was not released yet.
+void counterlines_free_string(char *string);
+
+// Version of the library, as a static string
+const char *counterlines_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* COUNTERLINES_H */
@@ -0,0 +1,270 @@
+// bench.rs - Throughput benchmark (`bench`)
+// Implements: REQ-9.4 (parallel processing), REQ-9.7 (performance measurement)
+//
+// The same files are counted repeatedly for every combination of thread count and read
+// strategy, each on its own thread pool and without the count cache, so the fastest
+// `count -j N --read STRATEGY` for the machine and its storage can be picked from
+// measurements. Warm-up runs fill the filesystem cache first: the timings measure
+// counting, not the first read from disk.
+
+use crate::cli::{BenchArgs, ReadStrategy};
+use crate::config::AppConfig;
+use crate::counter::{collect_input_paths, count_file_with};
+use crate::error::{Result, SlocError};
+use crate::language::LanguageDetector;
+use crate::output::{heavy_rule, print_result, print_table};
+use colored::Colorize;
+use human_format::Formatter;
+use num_format::{Locale, ToFormattedString};
+use prettytable::{Cell, Row, Table};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Measurements of one thread count and read strategy
+#[derive(Serialize)]
+struct Measurement {
+    threads: usize,
+    read: &'static str,
+    /// Seconds of each timed run
+    runs: Vec<f64>,
+    median: f64,
+    files_per_sec: f64,
+    lines_per_sec: f64,
+    bytes_per_sec: f64,
+}
+
+/// `--print` output
+#[derive(Serialize)]
+struct Benchmark {
+    files: usize,
+    lines: usize,
+    bytes: u64,
+    iterations: u32,
+    warmup: u32,
+    measurements: Vec<Measurement>,
+}
+
+pub fn execute_bench(args: BenchArgs) -> Result<()> {
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+
+    let mut paths = collect_input_paths(&args.paths, args.recursive)?;
+    paths.sort();
+    paths.dedup();
+    // Unknown languages are skipped by `count` after detection, so they would only add noise
+    paths.retain(|path| detector.detect(path).is_some());
+    if paths.is_empty() {
+        return Err(SlocError::Parse(
+            "no files of a known language to benchmark".to_string(),
+        ));
+    }
+    let bytes: u64 = paths
+        .iter()
+        .filter_map(|path| path.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    let threads = if args.threads.is_empty() {
+        let (config, _) = AppConfig::resolve(args.config.as_deref())?;
+        default_thread_counts(config.performance.default_threads)
+    } else {
+        args.threads.clone()
+    };
+    if threads.contains(&0) {
+        return Err(SlocError::Parse(
+            "thread counts must be at least 1".to_string(),
+        ));
+    }
+    let strategies = if args.read.is_empty() {
+        vec![
+            ReadStrategy::Stream,
+            ReadStrategy::Whole,
+            ReadStrategy::Mmap,
+        ]
+    } else {
+        args.read.clone()
+    };
+
+    let interactive = args.print.is_none();
+    if interactive {
+        println!(
+            "Benchmarking {} files ({}B) with {} timed run(s) per configuration...",
+            paths.len().to_formatted_string(&Locale::en),
+            Formatter::new().with_decimals(1).format(bytes as f64),
+            args.iterations
+        );
+    }
+
+    let mut lines = 0;
+    let mut measurements = Vec::new();
+    for &thread_count in &threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(thread_count)
+            .build()
+            .map_err(|e| SlocError::Parse(e.to_string()))?;
+        for &strategy in &strategies {
+            let run = || {
+                pool.install(|| count_all(&paths, &detector, args.ignore_preprocessor, strategy))
+            };
+            for _ in 0..args.warmup {
+                run()?;
+            }
+            let mut runs = Vec::new();
+            for _ in 0..args.iterations {
+                let (elapsed, counted) = run()?;
+                lines = counted;
+                runs.push(elapsed.as_secs_f64());
+            }
+            let median = median(&runs);
+            let rate = |amount: f64| if median > 0.0 { amount / median } else { 0.0 };
+            if interactive {
+                println!(
+                    "  -j {:<3} --read {:<6} {:>8.3}s",
+                    thread_count,
+                    strategy.name(),
+                    median
+                );
+            }
+            measurements.push(Measurement {
+                threads: thread_count,
+                read: strategy.name(),
+                runs,
+                median,
+                files_per_sec: rate(paths.len() as f64),
+                lines_per_sec: rate(lines as f64),
+                bytes_per_sec: rate(bytes as f64),
+            });
+        }
+    }
+
+    let benchmark = Benchmark {
+        files: paths.len(),
+        lines,
+        bytes,
+        iterations: args.iterations,
+        warmup: args.warmup,
+        measurements,
+    };
+    match args.print {
+        Some(format) => print_result(&benchmark, format),
+        None => {
+            display_benchmark(&benchmark);
+            Ok(())
+        }
+    }
+}
+
+/// Count every file once; the elapsed time and the total lines
+fn count_all(
+    paths: &[PathBuf],
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    strategy: ReadStrategy,
+) -> Result<(Duration, usize)> {
+    let start = Instant::now();
+    let lines = paths
+        .par_iter()
+        .map(|path| {
+            count_file_with(path, detector, ignore_preprocessor, strategy)
+                .map(|stats| stats.total_lines)
+        })
+        .sum::<Result<usize>>()?;
+    Ok((start.elapsed(), lines))
+}
+
+/// 1, 2, 4, ... and the CPU count itself
+/// 1, 2, 4, ... up to the CPU count, and the configured `performance.default_threads`
+fn default_thread_counts(configured: usize) -> Vec<usize> {
+    let cpus = num_cpus::get().max(1);
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|n| *n < cpus)
+        .collect();
+    counts.push(cpus);
+    if configured > 0 && !counts.contains(&configured) {
+        counts.push(configured);
+        counts.sort_unstable();
+    }
+    counts
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+fn display_benchmark(benchmark: &Benchmark) {
+    let fastest = benchmark
+        .measurements
+        .iter()
+        .min_by(|a, b| a.median.total_cmp(&b.median))
+        .expect("at least one configuration is measured");
+    let mut formatter = Formatter::new();
+    formatter.with_decimals(2);
+
+    println!("\n{}", heavy_rule(80).blue());
+    println!("{}", "Benchmark".bold().cyan());
+    println!("{}", heavy_rule(80).blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        [
+            "Threads", "Read", "Median", "Min", "Max", "Files/s", "Lines/s", "MB/s", "Speedup",
+        ]
+        .iter()
+        .map(|h| Cell::new(h).style_spec("b"))
+        .collect(),
+    ));
+    // Speedup against the slowest configuration
+    let slowest = benchmark
+        .measurements
+        .iter()
+        .map(|m| m.median)
+        .fold(0.0, f64::max);
+    for m in &benchmark.measurements {
+        let min = m.runs.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = m.runs.iter().copied().fold(0.0, f64::max);
+        let style = if std::ptr::eq(m, fastest) {
+            "rbFg"
+        } else {
+            "r"
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&m.threads.to_string()).style_spec(style),
+            Cell::new(m.read),
+            Cell::new(&format!("{:.3}s", m.median)).style_spec(style),
+            Cell::new(&format!("{:.3}s", min)).style_spec("r"),
+            Cell::new(&format!("{:.3}s", max)).style_spec("r"),
+            Cell::new(&formatter.format(m.files_per_sec)).style_spec("r"),
+            Cell::new(&formatter.format(m.lines_per_sec)).style_spec("r"),
+            Cell::new(&format!("{:.1}", m.bytes_per_sec / 1_000_000.0)).style_spec("r"),
+            Cell::new(&format!("{:.2}x", slowest / m.median.max(f64::EPSILON))).style_spec("r"),
+        ]));
+    }
+    print_table(&table);
+
+    println!(
+        "{} files, {} lines, median of {} run(s) after {} warm-up run(s)",
+        benchmark.files.to_formatted_string(&Locale::en),
+        benchmark.lines.to_formatted_string(&Locale::en),
+        benchmark.iterations,
+        benchmark.warmup
+    );
+    println!(
+        "Fastest: {}",
+        format!("count -j {} --read {}", fastest.threads, fastest.read)
+            .green()
+            .bold()
+    );
+}
@@ -0,0 +1,204 @@
+// cache.rs - Incremental counting cache (`count`, `report`)
+// Implements: REQ-1.1 (line counting), REQ-9.4 (performance)
+//
+// Per-file statistics are kept between runs, keyed by canonical path and stamped with the
+// file's size and modification time: a file whose stamp is unchanged is not read again.
+// Every setting that changes the counts (release, language configuration and overrides,
+// `--ignore-preprocessor`, encodings) is hashed into the cache file name, so changing one
+// of them starts from an empty cache instead of reusing stale counts.
+
+use crate::config::EncodingConfig;
+use crate::encoding::Decoding;
+use crate::error::{Result, SlocError};
+use crate::report::{FileStats, GENERATOR_VERSION};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+/// Statistics of one file and the stamp they were counted at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    size: u64,
+    /// Modification time, nanoseconds since the Unix epoch
+    modified: u128,
+    stats: FileStats,
+}
+
+pub struct CountCache {
+    path: PathBuf,
+    /// Entries loaded from the previous runs
+    entries: HashMap<String, CachedFile>,
+    /// Entries counted during this run
+    updates: Mutex<HashMap<String, CachedFile>>,
+    hits: AtomicUsize,
+}
+
+impl CountCache {
+    /// Default directory: `rustedbytes-counterlines` in the user cache directory
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .map(|dir| dir.join("rustedbytes-counterlines"))
+            .unwrap_or_else(|| PathBuf::from(".sloc-cache"))
+    }
+
+    /// Remove every cache file of `dir`, and `dir` once empty; other files are left alone,
+    /// as `--cache-dir` may name any directory
+    pub fn clear(dir: &Path) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && is_cache_file(&path) {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        if std::fs::read_dir(dir)?.next().is_none() {
+            std::fs::remove_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// The cache of `dir` for counts with the given settings (`nested`: the nested
+    /// configurations defining languages or encodings; `encodings`: the `[[encodings]]` of
+    /// the configuration, which may come from the user configuration); a missing or
+    /// unreadable cache file is an empty cache
+    pub fn open(
+        dir: &Path,
+        config: Option<&Path>,
+        nested: &[PathBuf],
+        overrides: &[(String, String)],
+        ignore_preprocessor: bool,
+        decoding: Decoding,
+        encodings: &[EncodingConfig],
+    ) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(GENERATOR_VERSION.as_bytes());
+        hasher.update([ignore_preprocessor as u8, decoding.skip_invalid_lines as u8]);
+        let encoding = decoding.encoding.map_or("auto", |encoding| encoding.name());
+        hasher.update(format!("\0encoding={}", encoding).as_bytes());
+        let encodings = serde_json::to_string(encodings)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        hasher.update(format!("\0encodings={}", encodings).as_bytes());
+        if let Some(config) = config {
+            hasher.update(std::fs::read(config)?);
+        }
+        // A nested file only applies below its directory, so its path counts too
+        for file in nested {
+            hasher.update(format!("\0{}\0", file.display()).as_bytes());
+            hasher.update(std::fs::read(file)?);
+        }
+        let mut overrides = overrides.to_vec();
+        overrides.sort();
+        for (ext, lang) in &overrides {
+            hasher.update(format!("\0{}={}", ext, lang).as_bytes());
+        }
+        let path = dir.join(format!("{}.json", &hex::encode(hasher.finalize())[..16]));
+
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("ignoring corrupt cache {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Ok(CountCache {
+            path,
+            entries,
+            updates: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Statistics of `path` from the cache when its size and modification time are
+    /// unchanged, otherwise from `count` (remembered for the next run)
+    pub fn get_or_count(
+        &self,
+        path: &Path,
+        count: impl FnOnce() -> Result<FileStats>,
+    ) -> Result<FileStats> {
+        let Some((key, size, modified)) = stamp(path) else {
+            return count();
+        };
+        if let Some(cached) = self.entries.get(&key)
+            && cached.size == size
+            && cached.modified == modified
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            debug!("{}: unchanged, taken from the cache", path.display());
+            return Ok(FileStats {
+                path: path.to_path_buf(),
+                ..cached.stats.clone()
+            });
+        }
+
+        let stats = count()?;
+        self.updates.lock().unwrap().insert(
+            key,
+            CachedFile {
+                size,
+                modified,
+                stats: stats.clone(),
+            },
+        );
+        Ok(stats)
+    }
+
+    /// Files served from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Write the cache back, without the entries of deleted files
+    pub fn save(mut self) -> Result<()> {
+        self.flush()
+    }
+
+    /// Write the cache back and keep using it (`daemon`): this run's entries serve the
+    /// next runs from memory
+    pub fn flush(&mut self) -> Result<()> {
+        self.entries.retain(|key, _| Path::new(key).exists());
+        self.entries
+            .extend(std::mem::take(self.updates.get_mut().unwrap()));
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(&self.entries)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+/// Whether `path` is named as `open` names the cache files: 16 hex digits and `.json`
+fn is_cache_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    name.strip_suffix(".json")
+        .is_some_and(|stem| stem.len() == 16 && stem.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Cache key, size and modification time of `path`; `None` when unavailable
+fn stamp(path: &Path) -> Option<(String, u64, u128)> {
+    let canonical = std::fs::canonicalize(path).ok()?;
+    let metadata = std::fs::metadata(&canonical).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some((
+        canonical.to_string_lossy().into_owned(),
+        metadata.len(),
+        modified,
+    ))
+}
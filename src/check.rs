@@ -0,0 +1,324 @@
+// check.rs - Policy rules on a report (`check`)
+// Implements: REQ-7.1 (report processing), REQ-8.4 (exit status for CI pipelines)
+//
+// The rules file sets limits on file length, on the comment density of each language and
+// on the growth of the total lines against a baseline report. Every rule is listed with
+// its verdict; any failed rule makes the command exit with `EXIT_THRESHOLD`. Growth rules
+// without `--baseline` are skipped rather than failed.
+//
+//     max_file_lines = 1000
+//     min_comment_density = 10.0
+//     max_growth_lines = 5000
+//     max_growth_percent = 10.0
+//
+//     [languages.Rust]
+//     min_comment_density = 15.0
+
+use crate::cli::{CheckArgs, OutputFormat};
+use crate::config::CheckRules;
+use crate::error::{Result, SlocError};
+use crate::output::{glyph, print_result, rule};
+use crate::report::Report;
+use colored::Colorize;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+
+/// Violations listed per rule on the console (all of them in `--print json`)
+const SHOWN_VIOLATIONS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+/// A file or language over a limit, with its actual value
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub subject: String,
+    pub actual: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleResult {
+    pub rule: String,
+    /// Language the limit applies to, or `all`
+    pub scope: String,
+    pub limit: f64,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Verdict {
+    pub passed: bool,
+    pub rules: Vec<RuleResult>,
+}
+
+pub fn execute_check(args: CheckArgs) -> Result<()> {
+    let rules = CheckRules::from_file(&args.rules)?;
+    let report = load(&args.report)?;
+    let baseline = match &args.baseline {
+        Some(path) => Some(load(path)?),
+        None => None,
+    };
+
+    let results = evaluate(&rules, &report, baseline.as_ref());
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|result| result.status == Status::Fail)
+        .map(|result| format!("{} ({})", result.rule, result.scope))
+        .collect();
+    let verdict = Verdict {
+        passed: failed.is_empty(),
+        rules: results,
+    };
+
+    match args.print {
+        Some(print) => print_result(&verdict, print)?,
+        None => display_verdict(&verdict),
+    }
+
+    if !failed.is_empty() {
+        return Err(SlocError::ThresholdBreached(failed.join("; ")));
+    }
+    Ok(())
+}
+
+fn load(path: &std::path::Path) -> Result<Report> {
+    Report::from_file(
+        path,
+        OutputFormat::from_path(path).unwrap_or(OutputFormat::Json),
+    )
+}
+
+/// Every rule of the file, in file-length, comment-density, growth order
+fn evaluate(rules: &CheckRules, report: &Report, baseline: Option<&Report>) -> Vec<RuleResult> {
+    let mut results = Vec::new();
+    let language_rules = |language: &str| {
+        rules
+            .languages
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(language))
+            .map(|(_, rules)| rules)
+    };
+
+    // File length: the language limit when set, the top-level limit otherwise
+    let mut scopes: Vec<(String, usize)> = rules
+        .max_file_lines
+        .map(|limit| ("all".to_string(), limit))
+        .into_iter()
+        .collect();
+    scopes.extend(
+        rules
+            .languages
+            .iter()
+            .filter_map(|(name, rules)| rules.max_file_lines.map(|limit| (name.clone(), limit))),
+    );
+    for (scope, limit) in scopes {
+        let mut files: Vec<_> = report
+            .files
+            .iter()
+            .filter(|file| {
+                let applies = if scope == "all" {
+                    language_rules(&file.language)
+                        .and_then(|rules| rules.max_file_lines)
+                        .is_none()
+                } else {
+                    file.language.eq_ignore_ascii_case(&scope)
+                };
+                applies && file.total_lines > limit
+            })
+            .collect();
+        files.sort_by(|a, b| b.total_lines.cmp(&a.total_lines).then(a.path.cmp(&b.path)));
+        results.push(result(
+            "max_file_lines",
+            scope,
+            limit as f64,
+            files
+                .iter()
+                .map(|file| Violation {
+                    subject: file.path.display().to_string(),
+                    actual: file.total_lines as f64,
+                })
+                .collect(),
+        ));
+    }
+
+    // Comment density of each language present in the report
+    let mut scopes: Vec<(String, f64)> = rules
+        .min_comment_density
+        .map(|limit| ("all".to_string(), limit))
+        .into_iter()
+        .collect();
+    scopes.extend(
+        rules.languages.iter().filter_map(|(name, rules)| {
+            rules.min_comment_density.map(|limit| (name.clone(), limit))
+        }),
+    );
+    for (scope, limit) in scopes {
+        let violations = report
+            .languages
+            .iter()
+            .filter(|lang| {
+                let own = language_rules(&lang.language).and_then(|r| r.min_comment_density);
+                if scope == "all" {
+                    own.is_none()
+                } else {
+                    lang.language.eq_ignore_ascii_case(&scope)
+                }
+            })
+            .map(|lang| Violation {
+                subject: lang.language.clone(),
+                actual: percent(lang.comment_lines as f64, lang.total_lines),
+            })
+            .filter(|violation| violation.actual < limit)
+            .collect();
+        results.push(result("min_comment_density", scope, limit, violations));
+    }
+
+    // Growth of the total lines against the baseline
+    let (before, after) = (
+        baseline.map(|b| b.summary.total_lines as f64),
+        report.summary.total_lines as f64,
+    );
+    let growth: [(&str, Option<f64>, Option<f64>); 2] = [
+        (
+            "max_growth_lines",
+            rules.max_growth_lines.map(|l| l as f64),
+            before.map(|before| after - before),
+        ),
+        (
+            "max_growth_percent",
+            rules.max_growth_percent,
+            before.map(|before| percent(after - before, before as usize)),
+        ),
+    ];
+    for (name, limit, actual) in growth {
+        let Some(limit) = limit else { continue };
+        results.push(match actual {
+            Some(actual) => result(
+                name,
+                "all".to_string(),
+                limit,
+                (actual > limit)
+                    .then(|| Violation {
+                        subject: "total_lines".to_string(),
+                        actual,
+                    })
+                    .into_iter()
+                    .collect(),
+            ),
+            None => RuleResult {
+                rule: name.to_string(),
+                scope: "all".to_string(),
+                limit,
+                status: Status::Skipped,
+                violations: Vec::new(),
+            },
+        });
+    }
+
+    results
+}
+
+fn result(rule: &str, scope: String, limit: f64, violations: Vec<Violation>) -> RuleResult {
+    RuleResult {
+        rule: rule.to_string(),
+        scope,
+        limit,
+        status: if violations.is_empty() {
+            Status::Pass
+        } else {
+            Status::Fail
+        },
+        violations,
+    }
+}
+
+fn percent(part: f64, whole: usize) -> f64 {
+    if whole > 0 {
+        part / whole as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+fn display_verdict(verdict: &Verdict) {
+    println!("\n{}", "Policy check".bold().green());
+    println!("{}", rule(40).green());
+    for result in &verdict.rules {
+        let status = match result.status {
+            Status::Pass => glyph("✓", "ok").green(),
+            Status::Fail => glyph("✗", "FAIL").red(),
+            Status::Skipped => glyph("-", "skip").yellow(),
+        };
+        let operator = if result.rule.starts_with("min_") {
+            ">="
+        } else {
+            "<="
+        };
+        let scope = if result.scope == "all" {
+            String::new()
+        } else {
+            format!(" [{}]", result.scope)
+        };
+        let note = match result.status {
+            Status::Skipped => " (needs --baseline)".to_string(),
+            Status::Fail => format!(" ({} violation(s))", result.violations.len()),
+            Status::Pass => String::new(),
+        };
+        println!(
+            "  {} {} {} {}{}{}",
+            status,
+            result.rule,
+            operator,
+            format_value(result.limit),
+            scope,
+            note
+        );
+        for violation in result.violations.iter().take(SHOWN_VIOLATIONS) {
+            println!(
+                "      {} {}",
+                violation.subject,
+                format_value(violation.actual).red()
+            );
+        }
+        if result.violations.len() > SHOWN_VIOLATIONS {
+            println!(
+                "      ... and {} more",
+                result.violations.len() - SHOWN_VIOLATIONS
+            );
+        }
+    }
+
+    let failed = verdict
+        .rules
+        .iter()
+        .filter(|result| result.status == Status::Fail)
+        .count();
+    println!();
+    if verdict.passed {
+        println!(
+            "{}",
+            format!("All {} rule(s) passed", verdict.rules.len()).green()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("{} of {} rule(s) failed", failed, verdict.rules.len()).red()
+        );
+    }
+}
+
+/// Counts with thousands separators, percentages with two decimals
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        (value as i64).to_formatted_string(&Locale::en)
+    } else {
+        format!("{:.2}", value)
+    }
+}
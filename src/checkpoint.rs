@@ -0,0 +1,208 @@
+// checkpoint.rs - Checkpoints of long counts (`count --checkpoint`, `--resume`)
+// Implements: REQ-1.1 (line counting), REQ-9.4 (performance)
+//
+// Every file result of a count is appended to the checkpoint file as a JSON line, and the
+// file is flushed to disk at most every `--checkpoint-interval` seconds. When the scan is
+// interrupted, `--resume` takes the recorded results as they are and only counts the files
+// still missing; the checkpoint is removed once the report is written.
+//
+// The first line identifies the scan (release, paths, recursion, language configuration,
+// overrides, `--ignore-preprocessor` and metric plugins), so a checkpoint is never resumed
+// by a different scan. A line cut short by the interruption is ignored.
+
+use crate::cli::{CountArgs, InvalidBytes};
+use crate::error::{Result, SlocError};
+use crate::report::{FileStats, GENERATOR_VERSION};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// First line of a checkpoint file
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    checkpoint: String,
+    scan: String,
+}
+
+/// One recorded file: counted, or listed as unsupported
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Entry {
+    Counted(FileStats),
+    Unsupported(PathBuf),
+}
+
+/// `Entry` as written, without copying the statistics
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum EntryRef<'a> {
+    Counted(&'a FileStats),
+    Unsupported(&'a Path),
+}
+
+impl Entry {
+    /// The result as `count` collects it
+    pub fn into_result(self) -> std::result::Result<FileStats, PathBuf> {
+        match self {
+            Entry::Counted(stats) => Ok(stats),
+            Entry::Unsupported(path) => Err(path),
+        }
+    }
+
+    pub fn total_lines(&self) -> usize {
+        match self {
+            Entry::Counted(stats) => stats.total_lines,
+            Entry::Unsupported(_) => 0,
+        }
+    }
+
+    fn borrowed(&self) -> EntryRef<'_> {
+        match self {
+            Entry::Counted(stats) => EntryRef::Counted(stats),
+            Entry::Unsupported(path) => EntryRef::Unsupported(path),
+        }
+    }
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+    interval: Duration,
+    /// When the file was last flushed
+    flushed: Mutex<Instant>,
+}
+
+impl Checkpoint {
+    /// Identity of the scan of `args` with the `nested` configurations defining languages or
+    /// encodings, written in the header
+    pub fn scan_key(args: &CountArgs, nested: &[PathBuf]) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(GENERATOR_VERSION.as_bytes());
+        hasher.update([
+            args.recursive as u8,
+            args.ignore_preprocessor as u8,
+            (args.on_invalid_utf8 == InvalidBytes::SkipLine) as u8,
+        ]);
+        hasher.update(format!("\0encoding={}", args.encoding.name()).as_bytes());
+        for path in &args.paths {
+            hasher.update(format!("\0path={}", path).as_bytes());
+        }
+        if let Some(config) = &args.config {
+            hasher.update(std::fs::read(config)?);
+        }
+        for file in nested {
+            hasher.update(format!("\0nested={}\0", file.display()).as_bytes());
+            hasher.update(std::fs::read(file)?);
+        }
+        for (ext, lang) in &args.language_override {
+            hasher.update(format!("\0{}={}", ext, lang).as_bytes());
+        }
+        for plugin in &args.metric_plugin {
+            hasher.update(format!("\0plugin={}", plugin).as_bytes());
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// The results recorded in the checkpoint at `path` for the scan `scan`; none when
+    /// the file doesn't exist
+    pub fn load(path: &Path, scan: &str) -> Result<HashMap<PathBuf, Entry>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut lines = BufReader::new(file).lines();
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| {
+                SlocError::Parse(format!("{} is not a checkpoint: {}", path.display(), e))
+            })?,
+            None => return Ok(HashMap::new()),
+        };
+        if header.scan != scan {
+            return Err(SlocError::Parse(format!(
+                "{} was written by a different scan (paths, options or release); \
+                 remove it or drop --resume to start over",
+                path.display()
+            )));
+        }
+
+        let mut entries = HashMap::new();
+        for line in lines {
+            let line = line?;
+            match serde_json::from_str::<Entry>(&line) {
+                Ok(entry) => {
+                    let key = match &entry {
+                        Entry::Counted(stats) => stats.path.clone(),
+                        Entry::Unsupported(path) => path.clone(),
+                    };
+                    entries.insert(key, entry);
+                }
+                // The line being written when the scan stopped
+                Err(_) => warn!("ignoring an incomplete line of {}", path.display()),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Start the checkpoint at `path` with the results already `recorded`
+    pub fn create(
+        path: &Path,
+        scan: &str,
+        interval: Duration,
+        recorded: &HashMap<PathBuf, Entry>,
+    ) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = Header {
+            checkpoint: GENERATOR_VERSION.to_string(),
+            scan: scan.to_string(),
+        };
+        write_line(&mut writer, &header)?;
+        for entry in recorded.values() {
+            write_line(&mut writer, &entry.borrowed())?;
+        }
+        writer.flush()?;
+        Ok(Checkpoint {
+            path: path.to_path_buf(),
+            writer: Mutex::new(writer),
+            interval,
+            flushed: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Record the result of one file; written to disk with the next periodic flush
+    pub fn record(&self, result: &std::result::Result<FileStats, PathBuf>) -> Result<()> {
+        let entry = match result {
+            Ok(stats) => EntryRef::Counted(stats),
+            Err(path) => EntryRef::Unsupported(path),
+        };
+        let mut writer = self.writer.lock().unwrap();
+        write_line(&mut *writer, &entry)?;
+        let mut flushed = self.flushed.lock().unwrap();
+        if flushed.elapsed() >= self.interval {
+            writer.flush()?;
+            writer.get_ref().sync_data()?;
+            *flushed = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// The scan is complete and its report written: the checkpoint is no longer needed
+    pub fn finish(self) -> Result<()> {
+        drop(self.writer);
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *writer, value)
+        .map_err(|e| SlocError::Serialization(e.to_string()))?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
@@ -2,11 +2,17 @@
 // Implements:
 //   REQ-8.1: CLI interface
 //   REQ-8.2: Help/version
-//   REQ-8.3: Subcommands (count, report, process, compare)
+//   REQ-8.3: Subcommands (count, report, process, compare, validate, merge, trend)
 //   REQ-9.7: Metrics CLI options
 
+pub use crate::compare::DeltaMetric;
+use crate::config::AppConfig;
+pub use crate::counter::ReadStrategy;
+pub use crate::encoding::TextEncoding;
+use crate::language::LineType;
+use crate::threshold::{Condition, Metric, Operator};
 use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = env!("CARGO_PKG_NAME"))]
@@ -16,6 +22,61 @@ pub struct Cli {
     // REQ-8.2: Display help via --help or -h
     #[command(subcommand)]
     pub command: Commands,
+
+    /// When to color console output (`auto` honors NO_COLOR and disables colors when stdout is not a terminal)
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Table layout: bordered (`pretty`), borderless (`plain`) or tab-separated (`tsv`)
+    #[arg(long, global = true, value_enum, default_value_t = TableStyle::Pretty)]
+    pub table_style: TableStyle,
+
+    /// Plain ASCII decorations (rules, tree branches, bars); also used when the locale is not UTF-8
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Do not load the user configuration, nor the .counterlines.toml or counterlines.toml found in the current directory or its ancestors
+    #[arg(long, global = true)]
+    pub no_config: bool,
+
+    /// More diagnostic logging: -v for the steps of a scan, -vv for what is decided per file, -vvv for everything
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Level of the diagnostic logging, instead of -v (default: warn)
+    #[arg(long, global = true, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// Append the diagnostic log to this file instead of writing it to stderr
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Set the configuration file of the command: `--config`, or else the project
+    /// configuration found from the current directory (unless `--no-config`)
+    pub fn resolve_config(&mut self) {
+        crate::config::set_discovery(!self.no_config);
+        if let Some(config) = self.command.config_mut() {
+            *config = crate::config::config_path(config.as_deref());
+        }
+    }
+}
+
+impl CountArgs {
+    /// Take the settings the command line leaves unset from the `[defaults]` and
+    /// `[performance]` of the configuration; a switch is on when set on either side, unless
+    /// its negation (`--no-recursive`, `--progress`) is given
+    pub fn apply_defaults(&mut self, config: &AppConfig) {
+        let defaults = &config.defaults;
+        self.threads
+            .get_or_insert(config.performance.default_threads);
+        self.recursive = !self.no_recursive && (self.recursive || defaults.recursive);
+        self.no_progress = !self.progress && (self.no_progress || defaults.no_progress);
+        if self.sort.is_none() {
+            self.sort = defaults.sort_metric();
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -35,44 +96,161 @@ pub enum Commands {
     // REQ-8.3: compare command
     /// Compare two reports
     Compare(CompareArgs),
+
+    /// Validate a report (structure, format version, internal consistency)
+    Validate(ValidateArgs),
+
+    /// Verify a report's checksum and summaries to detect edited reports
+    Verify(VerifyArgs),
+
+    /// Extract values from a report with a JMESPath-like expression
+    Query(QueryArgs),
+
+    /// Print files with each line tagged as counted (logical, mixed, comment, empty)
+    Annotate(AnnotateArgs),
+
+    /// Show how the language of files is detected and which definition applies
+    Explain(ExplainArgs),
+
+    /// Count, then recount changed files and refresh the summary on every change
+    Watch(WatchArgs),
+
+    /// Browse a report (or a fresh count) interactively in the terminal
+    Tui(TuiArgs),
+
+    /// Serve a dashboard and REST API to trigger counts and browse or compare stored reports
+    Serve(ServeArgs),
+
+    /// Merge several reports into one report with recomputed summaries
+    Merge(MergeArgs),
+
+    /// Show how the counts evolve over a series of reports
+    Trend(TrendArgs),
+
+    /// Write a commented .counterlines.toml for a project, pre-filled from its files
+    Init(InitArgs),
+
+    /// Check a report against policy rules (file length, comment density, growth)
+    Check(CheckArgs),
+
+    /// Count past revisions of the git repository and show how the counts evolve
+    History(HistoryArgs),
+
+    /// Append a count's summary and commit to a history database, flagging regressions
+    Record(RecordArgs),
+
+    /// Count repeatedly with different thread counts and read strategies and compare throughput
+    Bench(BenchArgs),
+
+    /// Keep languages, cache and threads warm and answer count/compare requests on a socket
+    Daemon(DaemonArgs),
+
+    /// Inspect the configuration files
+    Config(ConfigArgs),
 }
 
 #[derive(Parser)]
 pub struct CountArgs {
-    /// Print per-file statistics and unsupported file list (default: false)
+    /// Print the 20 largest files and the unsupported file list (--show-files --top 20 --show-unsupported)
     #[arg(long)]
     pub details: bool,
     // REQ-2.1: Accept file and/or directory paths
     // REQ-2.2: Accept wildcards
     /// Paths to files or directories to count
-    #[arg(required = true)]
+    #[arg(required_unless_present = "from_diff")]
     pub paths: Vec<String>,
 
     // REQ-2.3: Recursive directory traversal
     /// Recursively traverse directories
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_recursive")]
     pub recursive: bool,
 
+    /// Do not traverse directories, even when defaults.recursive is set
+    #[arg(long, overrides_with = "recursive")]
+    pub no_recursive: bool,
+
+    /// Walk the directories matching the defaults.exclude patterns of the configuration too
+    #[arg(long)]
+    pub no_default_excludes: bool,
+
     // REQ-2.4: Accept input via stdin
     /// Read file paths from stdin
     #[arg(long)]
     pub stdin: bool,
 
+    /// Account the lines added and removed by a unified diff (`-` or no value: stdin) per language
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-",
+          conflicts_with_all = ["paths", "stdin", "format", "output", "template", "baseline"])]
+    pub from_diff: Option<PathBuf>,
+
     // REQ-6.1, REQ-6.2, REQ-6.3: Support JSON, XML, CSV
-    /// Output format for report (auto-saves to <base>.<ext> if -o not provided; default base: sloc-report)
-    #[arg(short = 'f', long, value_enum)]
-    pub format: Option<OutputFormat>,
+    /// Output format for report: json, xml, csv, cloc-json, cloc-csv, junit or ext:CMD for an
+    /// external exporter (repeatable; auto-saves to <base>.<ext> if -o not provided; default base: sloc-report)
+    #[arg(short = 'f', long, value_name = "FORMAT", value_parser = parse_export_format)]
+    pub format: Vec<ExportFormat>,
 
     // REQ-6.8: Customize output paths
-    /// Output file path for report (default auto-generated: sloc-report.<ext>)
+    /// Output file path for report (repeatable, paired with -f in order or inferred from the extension; `-` for stdout)
     #[arg(short, long)]
-    pub output: Option<PathBuf>,
+    pub output: Vec<PathBuf>,
+
+    /// Render the report through a Tera template instead of a built-in format
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// XML layout: scalar fields as child elements (default) or as attributes
+    #[arg(long, value_enum, default_value_t = XmlStyle::Elements)]
+    pub xml_style: XmlStyle,
 
     // REQ-5.4: Sort console output
     /// Sort output by metric
     #[arg(short, long, value_enum)]
     pub sort: Option<SortMetric>,
 
+    /// Show only the N largest files by the sort metric in the file details (implies --show-files)
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Show the file details table with every file
+    #[arg(long, visible_alias = "all-files")]
+    pub show_files: bool,
+
+    /// Show the list of unsupported files
+    #[arg(long)]
+    pub show_unsupported: bool,
+
+    /// Group counts per directory, up to DEPTH levels below the common root (default: 1)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub by_dir: Option<u32>,
+
+    /// Path form stored in the report and shown in the file tables
+    #[arg(long = "paths", value_enum, value_name = "STYLE")]
+    pub path_style: Option<PathStyle>,
+
+    /// Show a directory tree with per-node totals, DEPTH levels deep (default: 3)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    pub tree: Option<u32>,
+
+    /// Only show these languages in the language and file tables (comma-separated, e.g. rust,python)
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub lang: Vec<String>,
+
+    /// Metric columns of the language and file tables, in order (e.g. files,logical,comment,density)
+    #[arg(long, value_enum, value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Count only the files of these languages (comma-separated names, e.g. Rust,Python);
+    /// replaces languages.only of the configuration
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub only_lang: Vec<String>,
+
+    /// Leave out the files of these languages (comma-separated names); replaces
+    /// languages.disabled of the configuration
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub exclude_lang: Vec<String>,
+
     // REQ-3.4: Override language detection
     /// Override language detection for specific extensions
     #[arg(long, value_parser = parse_language_override)]
@@ -85,24 +263,121 @@ pub struct CountArgs {
 
     // REQ-9.5: Progress indicators (inverted logic - enabled by default)
     /// Disable progress bar
-    #[arg(long)]
+    #[arg(long, overrides_with = "progress")]
     pub no_progress: bool,
 
+    /// Show the progress bar, even when defaults.no_progress is set
+    #[arg(long, overrides_with = "no_progress")]
+    pub progress: bool,
+
+    /// Write progress events to stderr as JSON lines instead of the progress bar
+    #[arg(long)]
+    pub progress_json: bool,
+
     // REQ-9.4: Parallel processing
     /// Number of parallel threads (0 = auto)
-    #[arg(short = 'j', long, default_value = "0")]
-    pub threads: usize,
+    ///
+    /// With 0, counting starts with one thread per CPU; every half second during the first
+    /// seconds, the number of files counted at once is halved for as long as the throughput
+    /// (lines per second) stays within 10%, and restored as soon as it drops further. On
+    /// storage that cannot feed every core, such as network filesystems and spinning disks,
+    /// fewer threads read faster. The chosen number is shown with the throughput and logged as
+    /// the `auto_threads` metric. Default: `performance.default_threads` of the
+    /// configuration.
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+
+    /// Record every file result in FILE while counting, to continue an interrupted scan with --resume
+    #[arg(long, value_name = "FILE")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Take the results recorded in the --checkpoint file and only count the remaining files
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Seconds between two writes of the checkpoint to disk
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 30,
+        requires = "checkpoint"
+    )]
+    pub checkpoint_interval: u64,
+
+    /// How files are read (measure with `bench`)
+    #[arg(long, value_enum, value_name = "STRATEGY", default_value_t = ReadStrategy::Stream)]
+    pub read: ReadStrategy,
+
+    /// Encoding of the counted files; auto detects it for each file (byte order mark, UTF-16, UTF-8, or the most likely legacy encoding)
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = TextEncoding::Auto)]
+    pub encoding: TextEncoding,
+
+    /// What to do with lines holding bytes their encoding cannot decode; the files are listed in the report whatever the policy
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = InvalidBytes::Replace)]
+    pub on_invalid_utf8: InvalidBytes,
+
+    /// Fail the run, with a summary of the errors, when a file or a directory entry cannot be read instead of listing it as unsupported
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Most files read at the same time, whatever the thread count (0 = no limit); for network filesystems and spinning disks
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub io_concurrency: usize,
+
+    /// What to do with minified files (JavaScript/CSS bundles and the like, told by their very long lines)
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = MinifiedFiles::Separate)]
+    pub minified: MinifiedFiles,
+
+    /// Resident memory (e.g. 512M, 2G) past which per-file results are dropped and only language and global totals are kept
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_memory: Option<u64>,
 
     // REQ-6.9: Optional checksum
     /// Include checksum in report
     #[arg(long)]
     pub checksum: bool,
 
+    /// After counting, compare against this earlier report and show the delta
+    #[arg(long, value_name = "REPORT")]
+    pub baseline: Option<PathBuf>,
+
+    /// Export the comparison with --baseline (JSON, CSV or HTML by extension; `-` for stdout)
+    #[arg(long, value_name = "FILE", requires = "baseline")]
+    pub baseline_export: Option<PathBuf>,
+
     // REQ-4.5: Ignore preprocessor directives
     /// Ignore preprocessor directives
     #[arg(long)]
     pub ignore_preprocessor: bool,
 
+    /// Command run on every counted file that prints extra metrics as a JSON object (repeatable)
+    #[arg(long, value_name = "CMD")]
+    pub metric_plugin: Vec<String>,
+
+    /// Count every file again, without reading or updating the count cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Empty the count cache before counting
+    #[arg(long)]
+    pub clear_cache: bool,
+
+    /// Directory of the count cache (default: rustedbytes-counterlines in the user cache directory)
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Print the result to stdout in a machine-readable format (no tables, colors or status lines)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+
+    /// Print only a compact summary instead of tables, progress and status lines
+    #[arg(short, long, conflicts_with = "silent")]
+    pub quiet: bool,
+
+    /// Print nothing to stdout (exports are still written, errors go to stderr)
+    #[arg(long)]
+    pub silent: bool,
+
     // REQ-9.7: Performance metrics logging
     /// Enable performance metrics logging
     #[arg(long)]
@@ -126,29 +401,87 @@ pub struct ReportArgs {
     #[arg(required = true)]
     pub paths: Vec<String>,
 
-    /// Output format
-    #[arg(short = 'f', long, value_enum, default_value = "json")]
-    pub format: OutputFormat,
+    /// Output format: json, xml, csv, cloc-json, cloc-csv, junit or ext:CMD for an external
+    /// exporter (repeatable; default: json)
+    #[arg(short = 'f', long, value_name = "FORMAT", value_parser = parse_export_format)]
+    pub format: Vec<ExportFormat>,
 
-    /// Output file path (optional; auto-generated as sloc-report.<ext> if omitted)
+    /// Output file path (repeatable, paired with -f in order or inferred from the extension;
+    /// auto-generated as sloc-report.<ext> if omitted; `-` for stdout)
     #[arg(short, long)]
-    pub output: Option<PathBuf>,
+    pub output: Vec<PathBuf>,
+
+    /// Render the report through a Tera template instead of a built-in format
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// XML layout: scalar fields as child elements (default) or as attributes
+    #[arg(long, value_enum, default_value_t = XmlStyle::Elements)]
+    pub xml_style: XmlStyle,
 
     /// Recursively traverse directories
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_recursive")]
     pub recursive: bool,
 
+    /// Do not traverse directories, even when defaults.recursive is set
+    #[arg(long, overrides_with = "recursive")]
+    pub no_recursive: bool,
+
+    /// Walk the directories matching the defaults.exclude patterns of the configuration too
+    #[arg(long)]
+    pub no_default_excludes: bool,
+
+    /// Group counts per directory, up to DEPTH levels below the common root (default: 1)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub by_dir: Option<u32>,
+
+    /// Path form stored in the report and shown in the file tables
+    #[arg(long = "paths", value_enum, value_name = "STYLE")]
+    pub path_style: Option<PathStyle>,
+
     /// Include checksum in report
     #[arg(long)]
     pub checksum: bool,
 
+    /// Count only the files of these languages (comma-separated names, e.g. Rust,Python);
+    /// replaces languages.only of the configuration
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub only_lang: Vec<String>,
+
+    /// Leave out the files of these languages (comma-separated names); replaces
+    /// languages.disabled of the configuration
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub exclude_lang: Vec<String>,
+
+    /// Encoding of the counted files; auto detects it for each file
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = TextEncoding::Auto)]
+    pub encoding: TextEncoding,
+
+    /// What to do with lines holding bytes their encoding cannot decode
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = InvalidBytes::Replace)]
+    pub on_invalid_utf8: InvalidBytes,
+
+    /// Fail the run when a file or a directory entry cannot be read
+    #[arg(long)]
+    pub strict: bool,
+
     /// Path to language configuration file
     #[arg(long)]
     pub config: Option<PathBuf>,
 
-    /// Number of parallel threads
-    #[arg(short = 'j', long, default_value = "0")]
-    pub threads: usize,
+    /// Number of parallel threads (0 = auto; default: `performance.default_threads` of the
+    /// configuration)
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+
+    /// Print only a compact summary instead of tables, progress and status lines
+    #[arg(short, long, conflicts_with = "silent")]
+    pub quiet: bool,
+
+    /// Print nothing to stdout (exports are still written, errors go to stderr)
+    #[arg(long)]
+    pub silent: bool,
 
     /// Enable performance metrics logging
     #[arg(long)]
@@ -170,7 +503,54 @@ pub struct ProcessArgs {
     #[arg(short, long, value_enum)]
     pub sort: Option<SortMetric>,
 
-    /// Export processed results
+    /// Show only the N largest files by the sort metric in a file details table (implies --show-files)
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Show the file details table with every file
+    #[arg(long, visible_alias = "all-files")]
+    pub show_files: bool,
+
+    /// Show the list of unsupported files
+    #[arg(long)]
+    pub show_unsupported: bool,
+
+    /// Group counts per directory, up to DEPTH levels below the common root (default: 1)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub by_dir: Option<u32>,
+
+    /// Path form shown in the file tables
+    #[arg(long = "paths", value_enum, value_name = "STYLE")]
+    pub path_style: Option<PathStyle>,
+
+    /// Show a directory tree with per-node totals, DEPTH levels deep (default: 3)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    pub tree: Option<u32>,
+
+    /// Only keep files of these languages (comma-separated, e.g. rust,python); summaries,
+    /// exports and the printed result are recomputed from the kept files
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub lang: Vec<String>,
+
+    /// Only keep files whose path matches this glob, e.g. "src/**" (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub path_filter: Vec<glob::Pattern>,
+
+    /// Only keep files with at least N total lines
+    #[arg(long, value_name = "N")]
+    pub min_lines: Option<usize>,
+
+    /// Refuse a report without a matching checksum or with summaries that do not match its files
+    #[arg(long)]
+    pub verify_checksum: bool,
+
+    /// Metric columns of the language and file tables, in order (e.g. files,logical,comment,density)
+    #[arg(long, value_enum, value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Export processed results (`-` for stdout)
     #[arg(short, long)]
     pub export: Option<PathBuf>,
 
@@ -178,6 +558,26 @@ pub struct ProcessArgs {
     #[arg(short = 'f', long, value_enum)]
     pub format: Option<OutputFormat>,
 
+    /// Render the exported results through a Tera template instead of a built-in format
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// XML layout: scalar fields as child elements (default) or as attributes
+    #[arg(long, value_enum, default_value_t = XmlStyle::Elements)]
+    pub xml_style: XmlStyle,
+
+    /// Print the result to stdout in a machine-readable format (no tables, colors or status lines)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+
+    /// Print only a compact summary instead of tables, progress and status lines
+    #[arg(short, long, conflicts_with = "silent")]
+    pub quiet: bool,
+
+    /// Print nothing to stdout (exports are still written, errors go to stderr)
+    #[arg(long)]
+    pub silent: bool,
+
     /// Enable performance metrics logging
     #[arg(long)]
     pub enable_metrics: bool,
@@ -191,15 +591,20 @@ pub struct ProcessArgs {
 pub struct CompareArgs {
     // REQ-7.2: Compare two reports
     /// Path to the first report
-    #[arg(required = true)]
-    pub report1: PathBuf,
+    #[arg(required_unless_present = "git_ref")]
+    pub report1: Option<PathBuf>,
 
     /// Path to the second report
-    #[arg(required = true)]
-    pub report2: PathBuf,
+    #[arg(required_unless_present = "git_ref")]
+    pub report2: Option<PathBuf>,
+
+    /// Compare the files at a git revision (branch, tag, commit) with the working tree
+    /// instead of two reports
+    #[arg(long, value_name = "REF", conflicts_with_all = ["report1", "report2"])]
+    pub git_ref: Option<String>,
 
     // REQ-7.4: Export comparison results
-    /// Export comparison results
+    /// Export comparison results (`-` for stdout)
     #[arg(short, long)]
     pub export: Option<PathBuf>,
 
@@ -207,6 +612,84 @@ pub struct CompareArgs {
     #[arg(short = 'f', long, value_enum)]
     pub format: Option<OutputFormat>,
 
+    /// Print the result to stdout in a machine-readable format (no tables, colors or status lines)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+
+    /// Print only a compact summary instead of tables, progress and status lines
+    #[arg(short, long, conflicts_with = "silent")]
+    pub quiet: bool,
+
+    /// Print nothing to stdout (exports are still written, errors go to stderr)
+    #[arg(long)]
+    pub silent: bool,
+
+    /// Show line deltas per directory, up to DEPTH levels below the common root (default: 1)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub by_dir: Option<u32>,
+
+    /// Leave out modified files and language deltas changing by fewer than N lines
+    #[arg(long, value_name = "N")]
+    pub min_delta: Option<u64>,
+
+    /// Leave out modified files and language deltas changing by less than PCT percent of their lines
+    #[arg(long, value_name = "PCT")]
+    pub min_delta_pct: Option<f64>,
+
+    /// Only compute the global and language deltas; no per-file lists are built or printed
+    #[arg(long, conflicts_with_all = ["list_unchanged", "sort", "top"])]
+    pub summary_only: bool,
+
+    /// Order modified files by the absolute delta of this metric (default: total)
+    #[arg(long, value_enum, value_name = "METRIC")]
+    pub sort: Option<DeltaMetric>,
+
+    /// Show the N biggest movers among the modified files (0 shows every file)
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+
+    /// Refuse reports without a matching checksum or with summaries that do not match their files
+    #[arg(long, conflicts_with = "git_ref")]
+    pub verify_checksum: bool,
+
+    /// List the unchanged files (their count is always shown)
+    #[arg(long)]
+    pub list_unchanged: bool,
+
+    /// Remove this leading path from the files of both reports before matching them,
+    /// e.g. the checkout directory of absolute paths (repeatable, first match wins)
+    #[arg(long, value_name = "PREFIX")]
+    pub strip_prefix: Vec<PathBuf>,
+
+    /// Match paths regardless of `./`, `..` and `\` vs `/` separators
+    #[arg(long)]
+    pub normalize_paths: bool,
+
+    /// Only compare files of these languages (comma-separated, e.g. rust,python)
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub lang: Vec<String>,
+
+    /// Only compare files whose path matches this glob, e.g. "src/**" (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub path_filter: Vec<glob::Pattern>,
+
+    /// Exit with code 3 when a condition holds, e.g. "total_lines_delta > 5000" (repeatable)
+    #[arg(long, value_name = "CONDITION", value_parser = Condition::parse)]
+    pub fail_if: Vec<Condition>,
+
+    /// Exit with code 3 when the total lines grow by more than N
+    #[arg(long, value_name = "N")]
+    pub max_growth: Option<u64>,
+
+    /// Exit with code 3 when the comment density drops by more than PCT percentage points
+    #[arg(long, value_name = "PCT")]
+    pub max_density_drop: Option<f64>,
+
+    /// Exit with code 3 when the comment density of the second report is below PCT
+    #[arg(long, value_name = "PCT")]
+    pub min_comment_density: Option<f64>,
+
     /// Enable performance metrics logging
     #[arg(long)]
     pub enable_metrics: bool,
@@ -216,6 +699,593 @@ pub struct CompareArgs {
     pub metrics_file: Option<PathBuf>,
 }
 
+impl Commands {
+    /// The `--config` option of the command, for those that load languages
+    fn config_mut(&mut self) -> Option<&mut Option<PathBuf>> {
+        match self {
+            Commands::Count(args) => Some(&mut args.config),
+            Commands::Report(args) => Some(&mut args.config),
+            Commands::Annotate(args) => Some(&mut args.config),
+            Commands::Explain(args) => Some(&mut args.config),
+            Commands::Watch(args) => Some(&mut args.config),
+            Commands::Tui(args) => Some(&mut args.config),
+            Commands::Serve(args) => Some(&mut args.config),
+            Commands::History(args) => Some(&mut args.config),
+            Commands::Record(args) => Some(&mut args.config),
+            Commands::Bench(args) => Some(&mut args.config),
+            Commands::Daemon(args) => Some(&mut args.config),
+            Commands::Config(args) => match &mut args.command {
+                ConfigCommand::Check(args) => Some(&mut args.config),
+                ConfigCommand::Schema(_) => None,
+            },
+            Commands::Process(_)
+            | Commands::Compare(_)
+            | Commands::Validate(_)
+            | Commands::Verify(_)
+            | Commands::Query(_)
+            | Commands::Merge(_)
+            | Commands::Trend(_)
+            | Commands::Init(_)
+            | Commands::Check(_) => None,
+        }
+    }
+}
+
+impl CompareArgs {
+    /// `--fail-if` conditions followed by those of the threshold shorthands
+    pub fn conditions(&self) -> Vec<Condition> {
+        let mut conditions = self.fail_if.clone();
+        if let Some(n) = self.max_growth {
+            conditions.push(Condition {
+                metric: Metric::TotalLinesDelta,
+                operator: Operator::Greater,
+                value: n as f64,
+            });
+        }
+        if let Some(pct) = self.max_density_drop {
+            conditions.push(Condition {
+                metric: Metric::CommentDensityDelta,
+                operator: Operator::Less,
+                value: -pct,
+            });
+        }
+        if let Some(pct) = self.min_comment_density {
+            conditions.push(Condition {
+                metric: Metric::CommentDensity,
+                operator: Operator::Less,
+                value: pct,
+            });
+        }
+        conditions
+    }
+}
+
+#[derive(Parser)]
+pub struct MergeArgs {
+    /// Reports to merge (JSON, XML or CSV, optionally .gz/.zst compressed)
+    #[arg(required = true)]
+    pub reports: Vec<PathBuf>,
+
+    /// Path prefix for the files of a report (repeatable, paired with the reports in order;
+    /// `""` for none)
+    #[arg(short, long, value_name = "PREFIX")]
+    pub prefix: Vec<String>,
+
+    /// What to do when two reports contain the same path
+    #[arg(long, value_enum, default_value_t = ConflictPolicy::Error)]
+    pub on_conflict: ConflictPolicy,
+
+    /// Output file path of the merged report (`-` for stdout)
+    #[arg(short, long, required = true)]
+    pub output: PathBuf,
+
+    /// Output format (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// XML layout: scalar fields as child elements (default) or as attributes
+    #[arg(long, value_enum, default_value_t = XmlStyle::Elements)]
+    pub xml_style: XmlStyle,
+
+    /// Include checksum in the merged report
+    #[arg(long)]
+    pub checksum: bool,
+
+    /// Print only a compact summary instead of the merged inputs and status lines
+    #[arg(short, long, conflicts_with = "silent")]
+    pub quiet: bool,
+
+    /// Print nothing to stdout (the merged report is still written, errors go to stderr)
+    #[arg(long)]
+    pub silent: bool,
+}
+
+#[derive(Parser)]
+pub struct TrendArgs {
+    /// Reports in chronological order, or glob patterns (matches are taken in name order)
+    #[arg(required_unless_present = "db", conflicts_with = "db")]
+    pub reports: Vec<String>,
+
+    /// Read the runs recorded with `record` from this history database instead of reports
+    #[arg(long, value_name = "FILE")]
+    pub db: Option<PathBuf>,
+
+    /// Only the runs recorded on this git branch (with --db)
+    #[arg(long, value_name = "NAME", requires = "db")]
+    pub branch: Option<String>,
+
+    /// Only the N most recent runs (with --db)
+    #[arg(long, value_name = "N", requires = "db")]
+    pub last: Option<usize>,
+
+    /// Order the reports by their generation time instead of the given order
+    #[arg(long)]
+    pub by_date: bool,
+
+    /// Export the time series (`-` for stdout)
+    #[arg(short, long)]
+    pub export: Option<PathBuf>,
+
+    /// Export format: json or csv (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print the result to stdout in a machine-readable format (no tables, colors or status lines)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+}
+
+#[derive(Parser)]
+pub struct BenchArgs {
+    /// Paths to files or directories to count
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Recursively traverse directories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Thread counts to measure (comma-separated; default: 1, 2, 4, ... up to the CPU count,
+    /// and `performance.default_threads` of the configuration)
+    #[arg(short = 'j', long, value_name = "N", value_delimiter = ',')]
+    pub threads: Vec<usize>,
+
+    /// Read strategies to measure (comma-separated; default: all)
+    #[arg(long, value_enum, value_name = "STRATEGIES", value_delimiter = ',')]
+    pub read: Vec<ReadStrategy>,
+
+    /// Timed runs per configuration
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 3,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: u32,
+
+    /// Untimed runs before measuring, to warm up the filesystem cache
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub warmup: u32,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+
+    /// Print the measurements to stdout in a machine-readable format (no tables or colors)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+}
+
+#[derive(Parser)]
+pub struct RecordArgs {
+    /// Paths to files or directories to count
+    #[arg(required_unless_present = "report", conflicts_with = "report")]
+    pub paths: Vec<String>,
+
+    /// Recursively traverse directories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Record an existing report instead of counting
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// History database (SQLite, created on first use)
+    #[arg(long, value_name = "FILE", default_value = "sloc.db")]
+    pub db: PathBuf,
+
+    /// Exit with code 3 when a condition holds against the previous run, e.g.
+    /// "total_lines_delta_percent > 10" (repeatable; summary metrics only)
+    #[arg(long, value_name = "CONDITION", value_parser = Condition::parse)]
+    pub fail_if: Vec<Condition>,
+
+    /// Compare with the latest run of this branch (default: of the current branch, or the latest run)
+    #[arg(long, value_name = "NAME")]
+    pub baseline_branch: Option<String>,
+
+    /// Check against the history without recording the run
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+}
+
+#[derive(Parser)]
+pub struct AnnotateArgs {
+    /// Files to annotate
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Only print lines of these types (comma-separated, e.g. comment,mixed)
+    #[arg(long, value_enum, value_name = "TYPES", value_delimiter = ',')]
+    pub only: Vec<LineKind>,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+
+    /// Encoding of the files; auto detects it, as count does
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = TextEncoding::Auto)]
+    pub encoding: TextEncoding,
+}
+
+/// `annotate --only` line types
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LineKind {
+    Logical,
+    /// Code with a trailing comment (counted as logical)
+    Mixed,
+    Comment,
+    Empty,
+}
+
+impl LineKind {
+    pub fn matches(&self, line: LineType) -> bool {
+        matches!(
+            (self, line),
+            (LineKind::Logical, LineType::Logical)
+                | (LineKind::Mixed, LineType::Mixed)
+                | (LineKind::Comment, LineType::Comment)
+                | (LineKind::Empty, LineType::Empty)
+        )
+    }
+}
+
+#[derive(Parser)]
+pub struct ExplainArgs {
+    /// Files to explain (they don't need to exist)
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct InitArgs {
+    /// Project directory to scan for languages
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Configuration file to write (`-` for stdout; default: .counterlines.toml in the project)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite an existing configuration file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser)]
+pub struct CheckArgs {
+    /// Path to the report file (JSON, XML or CSV, optionally .gz/.zst compressed)
+    pub report: PathBuf,
+
+    /// TOML file with the rules
+    #[arg(long, value_name = "FILE")]
+    pub rules: PathBuf,
+
+    /// Earlier report the growth rules are measured against
+    #[arg(long, value_name = "REPORT")]
+    pub baseline: Option<PathBuf>,
+
+    /// Print the verdict to stdout in a machine-readable format instead of the console list
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+}
+
+#[derive(Parser)]
+pub struct HistoryArgs {
+    /// Revision whose history is counted
+    #[arg(default_value = "HEAD")]
+    pub revision: String,
+
+    /// Revisions to count: every first-parent commit, or every tag reachable from REVISION
+    #[arg(long, value_enum, default_value_t = HistoryEvery::Commit)]
+    pub every: HistoryEvery,
+
+    /// Count only every Nth revision (the newest is always counted)
+    #[arg(long, value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub step: u32,
+
+    /// Only revisions committed since DATE (e.g. 2024-01-01 or "6 months ago")
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<String>,
+
+    /// Export the time series (`-` for stdout)
+    #[arg(short, long)]
+    pub export: Option<PathBuf>,
+
+    /// Export format: json or csv (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print the result to stdout in a machine-readable format (no tables, colors or status lines)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print: Option<PrintFormat>,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+/// `history --every`: which revisions become points of the series
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum HistoryEvery {
+    /// Every commit of the first-parent history
+    Commit,
+    /// Every tag
+    Tag,
+}
+
+/// `merge --on-conflict`: handling of a path present in more than one report
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Fail and name the duplicate path
+    Error,
+    /// Keep the statistics of the first report listing the path
+    First,
+    /// Keep the statistics of the last report listing the path
+    Last,
+}
+
+#[derive(Parser)]
+pub struct ValidateArgs {
+    /// Path to the report file (JSON, XML or CSV, optionally .gz/.zst compressed)
+    #[arg(required_unless_present = "print_schema")]
+    pub report: Option<PathBuf>,
+
+    /// Report format (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print the JSON Schema of the report format and exit
+    #[arg(long)]
+    pub print_schema: bool,
+}
+
+#[derive(Parser)]
+pub struct VerifyArgs {
+    /// Path to the report file (JSON, XML or CSV, optionally .gz/.zst compressed)
+    pub report: PathBuf,
+
+    /// Report format (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+}
+
+#[derive(Parser)]
+pub struct WatchArgs {
+    /// Paths to files or directories to watch (wildcards allowed)
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Recursively traverse directories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Rewrite this report after every recount
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Report format (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Milliseconds without changes before recounting
+    #[arg(long, value_name = "MS", default_value_t = 300)]
+    pub debounce: u64,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+
+    /// Print one summary line per recount instead of redrawing the tables
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+#[derive(Parser)]
+pub struct TuiArgs {
+    /// A report file (JSON, XML or CSV, optionally .gz/.zst compressed), or files and
+    /// directories to count (wildcards allowed)
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Recursively traverse directories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+}
+
+#[derive(Parser)]
+pub struct ServeArgs {
+    /// Paths to files or directories counted on every triggered count (wildcards allowed)
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Recursively traverse directories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub bind: String,
+
+    /// Directory holding the reports of triggered counts (created if missing)
+    #[arg(long, value_name = "DIR", default_value = "sloc-reports")]
+    pub store: PathBuf,
+
+    /// Count once before accepting requests
+    #[arg(long)]
+    pub count_on_start: bool,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+}
+
+#[derive(Parser)]
+pub struct DaemonArgs {
+    /// Unix socket to listen on (default: rustedbytes-counterlines.sock in the runtime
+    /// directory, else in a private rustedbytes-counterlines-UID temporary directory)
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Send one JSON request line to a running daemon and print its response
+    #[arg(long, value_name = "JSON")]
+    pub request: Option<String>,
+
+    /// Number of threads of the counting pool (0 = auto)
+    #[arg(short = 'j', long, default_value = "0")]
+    pub threads: usize,
+
+    /// Override language detection for specific extensions
+    #[arg(long, value_parser = parse_language_override)]
+    pub language_override: Vec<(String, String)>,
+
+    /// Path to language configuration file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore preprocessor directives
+    #[arg(long)]
+    pub ignore_preprocessor: bool,
+
+    /// Count every file on every request, without the count cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Directory of the count cache (default: the user cache directory)
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Check the configuration files strictly and print the effective configuration
+    Check(ConfigCheckArgs),
+
+    /// Print the JSON Schema of the configuration files, for editor completion and validation
+    Schema(ConfigSchemaArgs),
+}
+
+#[derive(Parser)]
+pub struct ConfigSchemaArgs {
+    /// Schema of a plain language definition file (`[<key>]` sections only)
+    #[arg(long)]
+    pub languages: bool,
+}
+
+#[derive(Parser)]
+pub struct ConfigCheckArgs {
+    /// Configuration file to check instead of the project configuration
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct QueryArgs {
+    /// Path to the report file (JSON, XML or CSV, optionally .gz/.zst compressed)
+    pub report: PathBuf,
+
+    /// Expression, e.g. "languages[?logical_lines > 10000].language"
+    pub expression: String,
+
+    /// Report format (default: inferred from the extension, JSON otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print strings without quotes and arrays of strings or numbers one per line
+    #[arg(short, long)]
+    pub raw: bool,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputFormat {
     /// JSON format (REQ-6.1)
@@ -224,6 +1294,182 @@ pub enum OutputFormat {
     Xml,
     /// CSV format (REQ-6.3)
     Csv,
+    /// cloc-compatible JSON (same layout as `cloc --json`)
+    ClocJson,
+    /// cloc-compatible CSV (same layout as `cloc --csv`)
+    ClocCsv,
+    /// JUnit XML (one test case per language) for CI test dashboards
+    Junit,
+    /// Standalone HTML page (comparisons only)
+    Html,
+}
+
+impl OutputFormat {
+    /// Infer the format from a file extension (`.gz`/`.zst` suffixes are ignored)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match crate::compression::strip_compression_extension(path)
+            .extension()
+            .and_then(|e| e.to_str())
+        {
+            Some("json") => Some(OutputFormat::Json),
+            Some("xml") => Some(OutputFormat::Xml),
+            Some("csv") => Some(OutputFormat::Csv),
+            Some("html" | "htm") => Some(OutputFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// File extension used when auto-generating output file names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json | OutputFormat::ClocJson => "json",
+            OutputFormat::Xml | OutputFormat::Junit => "xml",
+            OutputFormat::Csv | OutputFormat::ClocCsv => "csv",
+            OutputFormat::Html => "html",
+        }
+    }
+}
+
+/// `-f` of `count` and `report`: a built-in format, or `ext:CMD` to pipe the JSON report
+/// through an external exporter (see `plugin.rs`)
+#[derive(Debug, Clone)]
+pub enum ExportFormat {
+    Builtin(OutputFormat),
+    External(String),
+}
+
+impl From<OutputFormat> for ExportFormat {
+    fn from(format: OutputFormat) -> Self {
+        ExportFormat::Builtin(format)
+    }
+}
+
+/// REQ-6.2: Layout of XML reports (both validate against a schema in `schemas/`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum XmlStyle {
+    /// Every field is a child element (`schemas/report.xsd`)
+    #[default]
+    Elements,
+    /// Record fields are attributes, e.g. `<file path=".." total_lines=".."/>`
+    /// (`schemas/report-attributes.xsd`)
+    Attributes,
+}
+
+/// `count --minified` choices
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum MinifiedFiles {
+    /// Count them as a language of their own, e.g. "JavaScript (minified)"
+    #[default]
+    Separate,
+    /// Leave them out of the report
+    Skip,
+}
+
+/// `count --on-invalid-utf8` choices: lines with bytes their encoding cannot decode
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum InvalidBytes {
+    /// Count them like the other lines, the bytes standing for replacement characters
+    #[default]
+    Replace,
+    /// Leave the lines out of the counts
+    SkipLine,
+    /// Leave their files out of the report
+    SkipFile,
+    /// Fail the run
+    Error,
+}
+
+/// `--log-level` choices, from the quietest
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// `--color` choices
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color only when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
+/// `--table-style` choices
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TableStyle {
+    /// Bordered tables, shrunk to the terminal width
+    Pretty,
+    /// No borders, shrunk to the terminal width
+    Plain,
+    /// Tab-separated values, never truncated
+    Tsv,
+}
+
+/// Machine-readable stdout formats for `--print`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PrintFormat {
+    /// Pretty-printed JSON (the report, or the comparison for `compare`)
+    Json,
+}
+
+/// Console verbosity selected with `-q/--quiet` and `--silent`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    /// Tables, progress bar and status lines
+    Normal,
+    /// Compact summary only
+    Quiet,
+    /// Nothing on stdout
+    Silent,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, silent: bool) -> Self {
+        if silent {
+            Verbosity::Silent
+        } else if quiet {
+            Verbosity::Quiet
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+/// `--columns`: metric columns of the language and file tables
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Column {
+    /// Number of files (language table only)
+    Files,
+    /// Total lines
+    Total,
+    /// Logical lines
+    Logical,
+    /// Comment lines
+    Comment,
+    /// Empty lines
+    Empty,
+    /// Density % (logical lines / total lines)
+    Density,
+    /// Share of all lines, with a bar (language table only)
+    Share,
+}
+
+/// `--paths`: how file paths are stored and displayed
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PathStyle {
+    /// Relative to the current directory
+    Relative,
+    /// Absolute
+    Absolute,
+    /// File name only
+    Filename,
 }
 
 #[derive(Clone, Copy, ValueEnum)]
@@ -234,12 +1480,57 @@ pub enum SortMetric {
     Logical,
     /// Sort by empty lines
     Empty,
+    /// Sort by comment lines
+    Comment,
+    /// Sort by density (logical lines / total lines, the Density % column)
+    Density,
     /// Sort by file name
     Name,
     /// Sort by language
     Language,
 }
 
+fn parse_export_format(s: &str) -> Result<ExportFormat, String> {
+    match s.strip_prefix("ext:") {
+        Some(command) if command.trim().is_empty() => {
+            Err("missing exporter command after `ext:`".to_string())
+        }
+        Some(command) => Ok(ExportFormat::External(command.to_string())),
+        None => OutputFormat::from_str(s, true)
+            .map(ExportFormat::Builtin)
+            .map_err(|_| {
+                format!(
+                    "unknown format `{}` (json, xml, csv, cloc-json, cloc-csv, junit, html or ext:CMD)",
+                    s
+                )
+            }),
+    }
+}
+
+/// A size in bytes with an optional binary unit: `1048576`, `512K`, `64MiB`, `2G`
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number.parse().map_err(|_| {
+        format!(
+            "invalid size '{}': expected a number and a unit, e.g. 512M",
+            s
+        )
+    })?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => return Err(format!("invalid size unit '{}': use K, M, G or T", unit)),
+    };
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
+
 fn parse_language_override(s: &str) -> Result<(String, String), String> {
     let parts: Vec<&str> = s.split('=').collect();
     if parts.len() != 2 {
@@ -0,0 +1,608 @@
+// compare.rs - Comparison of two reports
+// Implements: REQ-7.2, REQ-7.3
+//
+// The computation behind `compare`, `count --baseline` and `serve`: file matching,
+// renames, noise thresholds and directory deltas. Displaying and exporting the result
+// is left to the command modules.
+
+use crate::model::{
+    ComparisonResult, DirectoryDelta, FileDelta, GlobalDelta, LanguageDelta, RenamedFile,
+};
+use crate::report::{FileStats, Report, common_directory_of, directory_key};
+use hashbrown::{DefaultHashBuilder, HashTable};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::path::{Component, Path, PathBuf};
+
+/// `compare --sort`: line delta ranking the modified files
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DeltaMetric {
+    /// Total lines delta
+    #[default]
+    Total,
+    /// Logical lines delta
+    Logical,
+    /// Comment lines delta
+    Comment,
+    /// Empty lines delta
+    Empty,
+}
+
+impl DeltaMetric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeltaMetric::Total => "total",
+            DeltaMetric::Logical => "logical",
+            DeltaMetric::Comment => "comment",
+            DeltaMetric::Empty => "empty",
+        }
+    }
+}
+
+/// Every file of both reports, no noise threshold, biggest total line deltas first
+/// (`count --baseline`, `serve`)
+pub fn compare_reports(report1: &Report, report2: &Report) -> ComparisonResult {
+    let mut comparison = ComparisonResult::compare(
+        report1,
+        report2,
+        &FileFilter::default(),
+        &NoiseThreshold::default(),
+    );
+    comparison.sort_modified_files(DeltaMetric::Total);
+    comparison.unchanged_file_paths.clear();
+    comparison
+}
+
+impl FileDelta {
+    pub fn delta(&self, metric: DeltaMetric) -> i64 {
+        match metric {
+            DeltaMetric::Total => self.total_lines_delta,
+            DeltaMetric::Logical => self.logical_lines_delta,
+            DeltaMetric::Comment => self.comment_lines_delta,
+            DeltaMetric::Empty => self.empty_lines_delta,
+        }
+    }
+}
+
+/// `compare --strip-prefix/--normalize-paths`: rewrites the paths of a report so reports
+/// generated in different checkouts or on different platforms match file by file
+pub struct PathMapping {
+    /// Leading components removed from every path; the first matching prefix wins
+    pub strip_prefixes: Vec<PathBuf>,
+    /// Use `/` as the separator and drop `.` components, resolving `..` where possible
+    pub normalize: bool,
+}
+
+impl PathMapping {
+    pub fn apply(&self, report: &mut Report) {
+        if self.strip_prefixes.is_empty() && !self.normalize {
+            return;
+        }
+        for file in &mut report.files {
+            file.path = self.map(&file.path);
+        }
+        for path in &mut report.unsupported_files {
+            *path = self.map(path);
+        }
+        for dir in &mut report.directories {
+            dir.path = self.map(&dir.path);
+        }
+    }
+
+    fn map(&self, path: &Path) -> PathBuf {
+        let path = if self.normalize {
+            normalize_path(path)
+        } else {
+            path.to_path_buf()
+        };
+        self.strip_prefixes
+            .iter()
+            .find_map(|prefix| {
+                let prefix = if self.normalize {
+                    normalize_path(prefix)
+                } else {
+                    prefix.clone()
+                };
+                // `b` also strips `./b/...`, like `--path-filter`
+                path.strip_prefix(&prefix)
+                    .or_else(|_| without_cur_dir(&path).strip_prefix(without_cur_dir(&prefix)))
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or(path)
+    }
+}
+
+fn without_cur_dir(path: &Path) -> &Path {
+    path.strip_prefix(".").unwrap_or(path)
+}
+
+/// Lexical normalization: `.\\src\\a\\..\\b.rs` and `src/b.rs` become the same path.
+/// Backslashes are separators even on Unix, for reports generated on Windows.
+fn normalize_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let mut normalized = PathBuf::new();
+    for component in Path::new(&text).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) || !normalized.pop()
+                {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `compare --min-delta/--min-delta-pct`: changes too small to list. The size of a
+/// change is its largest absolute line delta (total, logical, comment or empty), so a
+/// blank line turned into code counts as a change of one line.
+#[derive(Default)]
+pub struct NoiseThreshold {
+    /// Minimum size in lines, 0 for no minimum
+    pub min_lines: u64,
+    /// Minimum size in percent of the lines before the change, 0 for no minimum
+    pub min_percent: f64,
+}
+
+impl NoiseThreshold {
+    /// Whether a change with these line deltas, on `base` lines before, is below a minimum
+    fn is_noise(&self, deltas: [i64; 4], base: usize) -> bool {
+        let size = deltas.iter().map(|d| d.unsigned_abs()).max().unwrap_or(0);
+        // Growth from nothing is never below a percentage
+        let below_percent = self.min_percent > 0.0
+            && base > 0
+            && (size as f64 / base as f64 * 100.0) < self.min_percent;
+        size < self.min_lines || below_percent
+    }
+}
+
+/// `--lang/--path-filter/--min-lines`: the subset of files taking part in a comparison
+/// (`compare`) or kept in a processed report (`process`)
+#[derive(Default)]
+pub struct FileFilter {
+    /// Languages (case-insensitive), all when empty
+    pub languages: Vec<String>,
+    /// Glob patterns on the file path, all files when empty
+    pub paths: Vec<glob::Pattern>,
+    /// Minimum total lines of a file, 0 for all files
+    pub min_lines: usize,
+}
+
+impl FileFilter {
+    fn is_empty(&self) -> bool {
+        self.languages.is_empty() && self.paths.is_empty() && self.min_lines == 0
+    }
+
+    pub fn matches(&self, file: &FileStats) -> bool {
+        let language = self.languages.is_empty()
+            || self
+                .languages
+                .iter()
+                .any(|l| l.eq_ignore_ascii_case(&file.language));
+        // `src/**` also matches paths recorded as `./src/...`
+        let path = file.path.strip_prefix(".").unwrap_or(&file.path);
+        let path = self.paths.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|p| p.matches_path(&file.path) || p.matches_path(path));
+        language && path && file.total_lines >= self.min_lines
+    }
+
+    /// The report restricted to the matching files, with recomputed summaries
+    pub fn apply<'a>(&self, report: &'a Report) -> Cow<'a, Report> {
+        if self.is_empty() {
+            return Cow::Borrowed(report);
+        }
+        let mut filtered = report.clone();
+        filtered.retain_files(|f| self.matches(f));
+        Cow::Owned(filtered)
+    }
+
+    /// `languages rust, python; paths src/**`, `None` without a filter
+    pub(crate) fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if !self.languages.is_empty() {
+            parts.push(format!("languages {}", self.languages.join(", ")));
+        }
+        if !self.paths.is_empty() {
+            let paths: Vec<_> = self.paths.iter().map(|p| p.as_str()).collect();
+            parts.push(format!("paths {}", paths.join(", ")));
+        }
+        if self.min_lines > 0 {
+            parts.push(format!("at least {} lines", self.min_lines));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+impl ComparisonResult {
+    /// Listed modified files as a percentage of the files present in both reports
+    pub fn churn_rate(&self) -> f64 {
+        let common = self.modified_files.len() + self.ignored_modified_files + self.unchanged_files;
+        if common > 0 {
+            self.modified_files.len() as f64 / common as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Biggest absolute `metric` delta first, growth before shrinkage on ties, then by path
+    pub fn sort_modified_files(&mut self, metric: DeltaMetric) {
+        self.modified_files.sort_by(|a, b| {
+            let (da, db) = (a.delta(metric), b.delta(metric));
+            db.unsigned_abs()
+                .cmp(&da.unsigned_abs())
+                .then(db.cmp(&da))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// REQ-7.2: Compare two reports, restricted to the files matching `filter`
+    /// Modified files and language deltas below `noise` are left out.
+    pub fn compare(
+        report1: &Report,
+        report2: &Report,
+        filter: &FileFilter,
+        noise: &NoiseThreshold,
+    ) -> Self {
+        let (report1, report2) = (&*filter.apply(report1), &*filter.apply(report2));
+        let mut comparison = Self::summary_deltas(report1, report2, noise);
+        // Without the files of one side, every file would look new or removed
+        if report1.is_totals_only() || report2.is_totals_only() {
+            return comparison;
+        }
+        comparison.summary_only = false;
+        comparison.compare_files(report1, report2, noise);
+        comparison
+    }
+
+    /// `--summary-only`: global and language deltas only, no per-file lists are built
+    pub fn compare_summaries(
+        report1: &Report,
+        report2: &Report,
+        filter: &FileFilter,
+        noise: &NoiseThreshold,
+    ) -> Self {
+        Self::summary_deltas(&filter.apply(report1), &filter.apply(report2), noise)
+    }
+
+    /// New, removed, renamed, modified and unchanged files
+    fn compare_files(&mut self, report1: &Report, report2: &Report, noise: &NoiseThreshold) {
+        // Equal checksums: the same files with the same counts, all unchanged
+        if report1.checksum.is_some() && report1.checksum == report2.checksum {
+            self.unchanged_file_paths = report2
+                .files
+                .par_iter()
+                .map(|f| f.path.to_string_lossy().to_string())
+                .collect();
+            self.unchanged_files = self.unchanged_file_paths.len();
+            return;
+        }
+
+        let hasher = DefaultHashBuilder::default();
+        let files1 = PathIndex::new(&report1.files, &hasher);
+        let files2 = PathIndex::new(&report2.files, &hasher);
+
+        // Each file of the second report is matched on the counting threads; the results
+        // keep the order of the report
+        let matches: Vec<FileMatch> = files2
+            .entries()
+            .map(|(file2, hash)| match files1.get(hash, &file2.path) {
+                None => FileMatch::New(file2),
+                Some(file1) => FileMatch::compare(file1, file2, noise),
+            })
+            .collect();
+        let mut removed: Vec<&FileStats> = files1
+            .entries()
+            .filter(|(file1, hash)| files2.get(*hash, &file1.path).is_none())
+            .map(|(file1, _)| file1)
+            .collect();
+
+        let mut added = Vec::new();
+        let mut modified_files = Vec::new();
+        let mut unchanged_file_paths = Vec::new();
+        let mut ignored_modified_files = 0;
+        for file_match in matches {
+            match file_match {
+                FileMatch::New(file) => added.push(file),
+                FileMatch::Modified(delta) => modified_files.push(delta),
+                FileMatch::Ignored => ignored_modified_files += 1,
+                FileMatch::Unchanged(path) => unchanged_file_paths.push(path),
+            }
+        }
+
+        // A moved file is a rename, not one removal plus one addition
+        self.renamed_files = detect_renames(&mut removed, &mut added);
+        self.new_files = added
+            .iter()
+            .map(|f| f.path.to_string_lossy().to_string())
+            .collect();
+        self.removed_files = removed
+            .iter()
+            .map(|f| f.path.to_string_lossy().to_string())
+            .collect();
+        self.modified_files = modified_files;
+        self.ignored_modified_files = ignored_modified_files;
+        self.unchanged_files = unchanged_file_paths.len();
+        self.unchanged_file_paths = unchanged_file_paths;
+    }
+
+    /// Global and language deltas, with empty file lists
+    fn summary_deltas(report1: &Report, report2: &Report, noise: &NoiseThreshold) -> Self {
+        // Calculate global deltas
+        let global_delta = GlobalDelta {
+            files_delta: report2.summary.total_files as i64 - report1.summary.total_files as i64,
+            total_lines_delta: report2.summary.total_lines as i64
+                - report1.summary.total_lines as i64,
+            logical_lines_delta: report2.summary.logical_lines as i64
+                - report1.summary.logical_lines as i64,
+            comment_lines_delta: report2.summary.comment_lines as i64
+                - report1.summary.comment_lines as i64,
+            empty_lines_delta: report2.summary.empty_lines as i64
+                - report1.summary.empty_lines as i64,
+            languages_delta: report2.summary.languages_count as i64
+                - report1.summary.languages_count as i64,
+        };
+
+        // Calculate language deltas
+        let lang1: HashMap<_, _> = report1
+            .languages
+            .iter()
+            .map(|l| (l.language.clone(), l))
+            .collect();
+        let lang2: HashMap<_, _> = report2
+            .languages
+            .iter()
+            .map(|l| (l.language.clone(), l))
+            .collect();
+
+        let mut language_deltas = Vec::new();
+        let all_languages = lang1.keys().chain(lang2.keys()).collect::<HashSet<_>>();
+
+        for language in all_languages {
+            let stats1 = lang1.get(language);
+            let stats2 = lang2.get(language);
+
+            let delta = LanguageDelta {
+                language: language.to_string(),
+                files_delta: stats2.map(|s| s.file_count as i64).unwrap_or(0)
+                    - stats1.map(|s| s.file_count as i64).unwrap_or(0),
+                total_lines_delta: stats2.map(|s| s.total_lines as i64).unwrap_or(0)
+                    - stats1.map(|s| s.total_lines as i64).unwrap_or(0),
+                logical_lines_delta: stats2.map(|s| s.logical_lines as i64).unwrap_or(0)
+                    - stats1.map(|s| s.logical_lines as i64).unwrap_or(0),
+                comment_lines_delta: stats2.map(|s| s.comment_lines as i64).unwrap_or(0)
+                    - stats1.map(|s| s.comment_lines as i64).unwrap_or(0),
+                empty_lines_delta: stats2.map(|s| s.empty_lines as i64).unwrap_or(0)
+                    - stats1.map(|s| s.empty_lines as i64).unwrap_or(0),
+            };
+
+            let lines = [
+                delta.total_lines_delta,
+                delta.logical_lines_delta,
+                delta.comment_lines_delta,
+                delta.empty_lines_delta,
+            ];
+            let changed = delta.files_delta != 0
+                || delta.total_lines_delta != 0
+                || delta.logical_lines_delta != 0
+                || delta.comment_lines_delta != 0;
+            // A language gaining or losing files is never noise
+            let noise = delta.files_delta == 0
+                && noise.is_noise(lines, stats1.map(|s| s.total_lines).unwrap_or(0));
+            if changed && !noise {
+                language_deltas.push(delta);
+            }
+        }
+
+        language_deltas.sort_by(|a, b| a.language.cmp(&b.language));
+
+        ComparisonResult {
+            report1_generated: report1.generated_at,
+            report2_generated: report2.generated_at,
+            global_delta,
+            language_deltas,
+            directory_deltas: Vec::new(),
+            summary_only: true,
+            new_files: Vec::new(),
+            removed_files: Vec::new(),
+            renamed_files: Vec::new(),
+            modified_files: Vec::new(),
+            ignored_modified_files: 0,
+            unchanged_files: 0,
+            unchanged_file_paths: Vec::new(),
+        }
+    }
+}
+
+/// The files of a report by path. Each path is hashed once, in parallel, and the hash is
+/// used both to index the file and to look it up in the other report.
+struct PathIndex<'a> {
+    files: &'a [FileStats],
+    hashes: Vec<u64>,
+    /// Indices into `files`
+    table: HashTable<usize>,
+}
+
+impl<'a> PathIndex<'a> {
+    fn new(files: &'a [FileStats], hasher: &DefaultHashBuilder) -> Self {
+        let hashes: Vec<u64> = files.par_iter().map(|f| hasher.hash_one(&f.path)).collect();
+        let mut table = HashTable::with_capacity(files.len());
+        for (index, &hash) in hashes.iter().enumerate() {
+            table.insert_unique(hash, index, |&i| hashes[i]);
+        }
+        PathIndex {
+            files,
+            hashes,
+            table,
+        }
+    }
+
+    /// The file at `path`, `hash` being its hash
+    fn get(&self, hash: u64, path: &Path) -> Option<&'a FileStats> {
+        self.table
+            .find(hash, |&i| self.files[i].path == path)
+            .map(|&i| &self.files[i])
+    }
+
+    /// The files with their hashes, in parallel and in the order of the report
+    fn entries(&self) -> impl IndexedParallelIterator<Item = (&'a FileStats, u64)> + '_ {
+        self.files.par_iter().zip(self.hashes.par_iter().copied())
+    }
+}
+
+/// A file of the second report of a comparison, against the first one
+enum FileMatch<'a> {
+    New(&'a FileStats),
+    Modified(FileDelta),
+    /// Modified, below the noise threshold
+    Ignored,
+    Unchanged(String),
+}
+
+impl<'a> FileMatch<'a> {
+    pub fn compare(file1: &FileStats, file2: &'a FileStats, noise: &NoiseThreshold) -> Self {
+        let path = file2.path.to_string_lossy().to_string();
+        if file1.total_lines == file2.total_lines
+            && file1.logical_lines == file2.logical_lines
+            && file1.comment_lines == file2.comment_lines
+            && file1.empty_lines == file2.empty_lines
+        {
+            return FileMatch::Unchanged(path);
+        }
+        let delta = FileDelta {
+            path,
+            total_lines_delta: file2.total_lines as i64 - file1.total_lines as i64,
+            logical_lines_delta: file2.logical_lines as i64 - file1.logical_lines as i64,
+            comment_lines_delta: file2.comment_lines as i64 - file1.comment_lines as i64,
+            empty_lines_delta: file2.empty_lines as i64 - file1.empty_lines as i64,
+        };
+        let lines = [
+            delta.total_lines_delta,
+            delta.logical_lines_delta,
+            delta.comment_lines_delta,
+            delta.empty_lines_delta,
+        ];
+        if noise.is_noise(lines, file1.total_lines) {
+            FileMatch::Ignored
+        } else {
+            FileMatch::Modified(delta)
+        }
+    }
+}
+
+/// `--by-dir`: line deltas per directory. Both reports are grouped below the deepest
+/// directory containing the files of either, so the keys line up; new, removed and
+/// moved files count towards their directory like modified ones.
+pub(crate) fn directory_deltas(
+    report1: &Report,
+    report2: &Report,
+    depth: usize,
+) -> Vec<DirectoryDelta> {
+    let base = common_directory_of(
+        report1
+            .files
+            .iter()
+            .chain(&report2.files)
+            .map(|f| f.path.as_path()),
+    );
+
+    // Directory -> [files, total, logical, comment, empty] of report 2 minus report 1
+    let mut totals: BTreeMap<PathBuf, [i64; 5]> = BTreeMap::new();
+    for (files, sign) in [(&report1.files, -1), (&report2.files, 1)] {
+        for file in files {
+            let entry = totals
+                .entry(directory_key(&file.path, &base, depth))
+                .or_default();
+            entry[0] += sign;
+            entry[1] += sign * file.total_lines as i64;
+            entry[2] += sign * file.logical_lines as i64;
+            entry[3] += sign * file.comment_lines as i64;
+            entry[4] += sign * file.empty_lines as i64;
+        }
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, delta)| delta.iter().any(|d| *d != 0))
+        .map(
+            |(path, [files, total, logical, comment, empty])| DirectoryDelta {
+                path: path.display().to_string(),
+                files_delta: files,
+                total_lines_delta: total,
+                logical_lines_delta: logical,
+                comment_lines_delta: comment,
+                empty_lines_delta: empty,
+            },
+        )
+        .collect()
+}
+
+/// Pair removed and new files with the same language and line counts (empty files
+/// are never paired). A candidate with the same file name is preferred; otherwise a
+/// pair is only formed when the statistics match exactly one removed and one new file.
+fn detect_renames(removed: &mut Vec<&FileStats>, added: &mut Vec<&FileStats>) -> Vec<RenamedFile> {
+    type Candidates<'a> = (Vec<&'a FileStats>, Vec<&'a FileStats>);
+    let key = |f: &FileStats| {
+        (
+            f.language.clone(),
+            f.total_lines,
+            f.logical_lines,
+            f.comment_lines,
+            f.empty_lines,
+        )
+    };
+
+    let mut groups: HashMap<_, Candidates> = HashMap::new();
+    for file in removed.iter().filter(|f| f.total_lines > 0) {
+        groups.entry(key(file)).or_default().0.push(file);
+    }
+    for file in added.iter().filter(|f| f.total_lines > 0) {
+        groups.entry(key(file)).or_default().1.push(file);
+    }
+
+    let mut renamed_files = Vec::new();
+    for (mut from, mut to) in groups.into_values() {
+        to.retain(|target| {
+            match from
+                .iter()
+                .position(|source| source.path.file_name() == target.path.file_name())
+            {
+                Some(index) => {
+                    renamed_files.push((from.remove(index), *target));
+                    false
+                }
+                None => true,
+            }
+        });
+        if let ([source], [target]) = (from.as_slice(), to.as_slice()) {
+            renamed_files.push((source, target));
+        }
+    }
+
+    let sources: HashSet<_> = renamed_files.iter().map(|(from, _)| &from.path).collect();
+    let targets: HashSet<_> = renamed_files.iter().map(|(_, to)| &to.path).collect();
+    removed.retain(|f| !sources.contains(&f.path));
+    added.retain(|f| !targets.contains(&f.path));
+
+    let mut renamed_files: Vec<_> = renamed_files
+        .into_iter()
+        .map(|(from, to)| RenamedFile {
+            from: from.path.to_string_lossy().to_string(),
+            to: to.path.to_string_lossy().to_string(),
+        })
+        .collect();
+    renamed_files.sort_by(|a, b| a.to.cmp(&b.to));
+    renamed_files
+}
@@ -0,0 +1,95 @@
+// compression.rs - Transparent compression for report files
+// Implements: REQ-6.8 (compressed and stdout output paths), REQ-7.1/REQ-7.2 (loading compressed reports)
+
+use crate::error::Result;
+use flate2::Compression as GzLevel;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Magic bytes at the start of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Pick the compression from the output file suffix (`.gz`, `.zst`)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Path with any compression suffix removed, used for format detection:
+/// `report.json.gz` -> `report.json`
+pub fn strip_compression_extension(path: &Path) -> PathBuf {
+    match Compression::from_path(path) {
+        Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    }
+}
+
+/// REQ-6.8: `-` as output path means "write to stdout"
+pub fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Create an output file, compressing on the fly when the path ends in `.gz` or `.zst`.
+/// The path `-` writes to stdout instead.
+pub fn create_writer(path: &Path) -> Result<Box<dyn Write>> {
+    if is_stdout(path) {
+        return Ok(Box::new(BufWriter::new(std::io::stdout())));
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+
+    Ok(match Compression::from_path(path) {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(GzEncoder::new(file, GzLevel::default())),
+        Compression::Zstd => Box::new(zstd::Encoder::new(file, 0)?.auto_finish()),
+    })
+}
+
+/// Open an input file, transparently decompressing gzip or zstd content.
+/// Detection is based on the leading magic bytes, not on the file name.
+/// The returned reader is always buffered, so it can be handed to streaming parsers.
+pub fn open_reader(path: &Path) -> Result<Box<dyn Read>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 4];
+    let read = read_prefix(&mut file, &mut magic)?;
+
+    // Re-attach the sniffed bytes in front of the rest of the file
+    let reader = BufReader::new(std::io::Cursor::new(magic[..read].to_vec()).chain(file));
+
+    Ok(if magic[..read].starts_with(&GZIP_MAGIC) {
+        Box::new(BufReader::new(GzDecoder::new(reader)))
+    } else if magic[..read].starts_with(&ZSTD_MAGIC) {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Fill `buf` as far as possible (short files may hold fewer bytes than a magic number)
+fn read_prefix(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
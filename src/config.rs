@@ -1,9 +1,59 @@
 // config.rs - Configuration management
-// Implements: REQ-3.3, REQ-9.7
+// Implements: REQ-3.3, REQ-9.2, REQ-9.7
 
+use crate::language::Language;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+
+#[cfg(feature = "cli")]
+use std::collections::BTreeMap;
+#[cfg(feature = "cli")]
+use std::io::{BufWriter, Write};
+#[cfg(feature = "cli")]
 use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "cli")]
+use std::sync::{Mutex, mpsc};
+#[cfg(feature = "cli")]
+use std::thread::JoinHandle;
+
+/// `check --rules`: limits a report must respect. Each language section replaces the
+/// top-level file length and comment density limits for that language.
+#[cfg(feature = "cli")]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckRules {
+    /// Maximum total lines of a single file
+    pub max_file_lines: Option<usize>,
+    /// Minimum comment lines / total lines of each language, in percent
+    pub min_comment_density: Option<f64>,
+    /// Maximum growth of the total lines against `--baseline`
+    pub max_growth_lines: Option<i64>,
+    /// Maximum growth of the total lines against `--baseline`, in percent
+    pub max_growth_percent: Option<f64>,
+    #[serde(default)]
+    pub languages: BTreeMap<String, LanguageRules>,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageRules {
+    pub max_file_lines: Option<usize>,
+    pub min_comment_density: Option<f64>,
+}
+
+#[cfg(feature = "cli")]
+impl CheckRules {
+    pub fn from_file(path: &Path) -> crate::error::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| {
+            crate::error::SlocError::InvalidConfig(format!("{}: {}", path.display(), e))
+        })
+    }
+}
 
 /// Language configuration that can be loaded from TOML
 /// REQ-3.3: Language definitions via configuration files
@@ -12,62 +62,379 @@ pub struct LanguageConfig {
     pub languages: HashMap<String, LanguageDefinition>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// REQ-3.3: A `[languages.<key>]` section of the project configuration
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(description = "A custom language, or a built-in one redefined")]
 pub struct LanguageDefinition {
+    /// Language name shown in reports
+    #[serde(deserialize_with = "non_empty")]
+    #[schemars(length(min = 1))]
     pub name: String,
+    /// File extensions without the dot, e.g. "rs"
+    #[serde(deserialize_with = "extensions")]
+    #[schemars(length(min = 1), inner(regex(pattern = r"^[^.]")))]
     pub extensions: Vec<String>,
+    /// Markers of comments running to the end of the line
+    #[serde(default, deserialize_with = "comment_markers")]
+    #[schemars(inner(length(min = 1)))]
     pub single_line_comment: Vec<String>,
+    /// Start and end markers of block comments
+    #[serde(default)]
     pub multi_line_comment: Vec<MultiLineComment>,
+    /// Whether block comments nest
     #[serde(default)]
     pub nested_comments: bool,
+    /// Prefix of preprocessor directive lines (see `count --ignore-preprocessor`)
+    #[serde(default, deserialize_with = "optional_non_empty")]
+    #[schemars(length(min = 1))]
     pub preprocessor_prefix: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct MultiLineComment {
+    #[serde(deserialize_with = "non_empty")]
+    #[schemars(length(min = 1))]
     pub start: String,
+    #[serde(deserialize_with = "non_empty")]
+    #[schemars(length(min = 1))]
     pub end: String,
 }
 
-/// Application configuration
-#[derive(Debug, Serialize, Deserialize)]
+// Checks of the values of language definitions, made while deserializing so that the
+// errors point at the line and column of the value
+
+/// A name or comment marker, which cannot be empty
+pub(crate) fn non_empty<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.is_empty() {
+        return Err(serde::de::Error::custom("must not be empty"));
+    }
+    Ok(value)
+}
+
+pub(crate) fn optional_non_empty<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    non_empty(deserializer).map(Some)
+}
+
+/// At least one extension, each without the leading dot
+pub(crate) fn extensions<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let extensions = Vec::<String>::deserialize(deserializer)?;
+    if extensions.is_empty() {
+        return Err(serde::de::Error::custom("at least one extension is needed"));
+    }
+    for extension in &extensions {
+        if extension.is_empty() {
+            return Err(serde::de::Error::custom("extensions must not be empty"));
+        }
+        if let Some(bare) = extension.strip_prefix('.') {
+            return Err(serde::de::Error::custom(format!(
+                "extensions are written without the dot: \"{}\", not \"{}\"",
+                bare, extension
+            )));
+        }
+    }
+    Ok(extensions)
+}
+
+pub(crate) fn comment_markers<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let markers = Vec::<String>::deserialize(deserializer)?;
+    if markers.iter().any(String::is_empty) {
+        return Err(serde::de::Error::custom(
+            "comment markers must not be empty",
+        ));
+    }
+    Ok(markers)
+}
+
+/// Start and end markers of the multi-line comments of a plain language definition file
+pub(crate) fn comment_pairs<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    let pairs = Vec::<(String, String)>::deserialize(deserializer)?;
+    if pairs
+        .iter()
+        .any(|(start, end)| start.is_empty() || end.is_empty())
+    {
+        return Err(serde::de::Error::custom(
+            "comment markers must not be empty",
+        ));
+    }
+    Ok(pairs)
+}
+
+impl From<LanguageDefinition> for Language {
+    fn from(definition: LanguageDefinition) -> Self {
+        Language {
+            name: definition.name,
+            extensions: definition.extensions,
+            single_line_comment: definition.single_line_comment,
+            multi_line_comment: definition
+                .multi_line_comment
+                .into_iter()
+                .map(|comment| (comment.start, comment.end))
+                .collect(),
+            nested_comments: definition.nested_comments,
+            preprocessor_prefix: definition.preprocessor_prefix,
+        }
+    }
+}
+
+/// Application configuration (`.counterlines.toml`, see `init`)
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(description = "Configuration of rustedbytes-counterlines (.counterlines.toml)")]
 pub struct AppConfig {
     #[serde(default)]
     pub performance: PerformanceConfig,
     #[serde(default)]
     pub defaults: DefaultsConfig,
+    // REQ-3.3: Custom language definitions, and the languages counted
+    #[serde(default)]
+    pub languages: LanguagesConfig,
+    // REQ-9.2: Encodings of the files by path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encodings: Vec<EncodingConfig>,
+}
+
+/// REQ-9.2: An `[[encodings]]` section: the encoding of the files matching `paths` that
+/// have no byte order mark, instead of the detected one (`count --encoding` wins)
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EncodingConfig {
+    /// Encoding label, e.g. "windows-1252", "latin1", "shift_jis" or "utf-16le"
+    #[serde(deserialize_with = "encoding_label")]
+    #[schemars(length(min = 1))]
+    pub encoding: String,
+    /// Glob patterns of the files, relative to the directory of the configuration, e.g.
+    /// "*.rc" or "legacy/**"
+    #[cfg_attr(feature = "cli", serde(deserialize_with = "encoding_patterns"))]
+    #[schemars(length(min = 1), inner(length(min = 1)))]
+    pub paths: Vec<String>,
+}
+
+/// A label `encoding_rs` knows (the WHATWG Encoding Standard labels)
+fn encoding_label<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let label = String::deserialize(deserializer)?;
+    match encoding_rs::Encoding::for_label(label.as_bytes()) {
+        Some(encoding) if encoding != encoding_rs::REPLACEMENT => Ok(label),
+        _ => Err(serde::de::Error::custom(format!(
+            "unknown encoding \"{}\"",
+            label
+        ))),
+    }
+}
+
+// The values were checked when the configuration was read
+impl EncodingConfig {
+    #[cfg(feature = "cli")]
+    pub fn encoding(&self) -> &'static encoding_rs::Encoding {
+        encoding_rs::Encoding::for_label(self.encoding.as_bytes()).unwrap_or(encoding_rs::UTF_8)
+    }
+
+    /// The `paths` patterns, compiled
+    #[cfg(feature = "cli")]
+    pub fn patterns(&self) -> Vec<glob::Pattern> {
+        self.paths
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect()
+    }
+}
+
+/// REQ-3.3: The `[languages]` section: `only` and `disabled` name the languages counted,
+/// every other key is a language definition
+#[derive(Debug, Default, Serialize, JsonSchema)]
+#[schemars(
+    description = "Custom languages by key (the key of a built-in language redefines it), \
+                   and the languages counted"
+)]
+pub struct LanguagesConfig {
+    /// Count only the files of these languages, by name (e.g. "Rust"); empty counts all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(inner(length(min = 1)))]
+    pub only: Vec<String>,
+    /// Leave out the files of these languages, by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(inner(length(min = 1)))]
+    pub disabled: Vec<String>,
+    #[serde(flatten)]
+    pub definitions: HashMap<String, LanguageDefinition>,
+}
+
+// Read key by key rather than with `#[serde(flatten)]`, which would lose the position of
+// the errors in the language definitions
+impl<'de> Deserialize<'de> for LanguagesConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = LanguagesConfig;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a table of language definitions")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<LanguagesConfig, A::Error> {
+                let mut config = LanguagesConfig::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "only" => config.only = map.next_value::<LanguageNames>()?.0,
+                        "disabled" => config.disabled = map.next_value::<LanguageNames>()?.0,
+                        _ => {
+                            let definition = map.next_value()?;
+                            config.definitions.insert(key, definition);
+                        }
+                    }
+                }
+                Ok(config)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// The names of `languages.only` or `languages.disabled`, which cannot be empty
+struct LanguageNames(Vec<String>);
+
+impl<'de> Deserialize<'de> for LanguageNames {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        if names.iter().any(|name| name.trim().is_empty()) {
+            return Err(serde::de::Error::custom("language names must not be empty"));
+        }
+        Ok(LanguageNames(names))
+    }
+}
+
+/// The languages `count` and `report` count: `languages.only` and `languages.disabled`,
+/// each replaced by its command-line flag (`--only-lang`, `--exclude-lang`) when given.
+/// Names match regardless of case.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default)]
+pub struct LanguageFilter {
+    only: Vec<String>,
+    disabled: Vec<String>,
+}
+
+#[cfg(feature = "cli")]
+impl LanguageFilter {
+    pub fn new(config: &LanguagesConfig, only: &[String], disabled: &[String]) -> Self {
+        let pick = |flag: &[String], setting: &[String]| {
+            let names = if flag.is_empty() { setting } else { flag };
+            names.iter().map(|name| name.trim().to_string()).collect()
+        };
+        LanguageFilter {
+            only: pick(only, &config.only),
+            disabled: pick(disabled, &config.disabled),
+        }
+    }
+
+    /// Whether every language is counted
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.disabled.is_empty()
+    }
+
+    /// Whether the files of `language` are counted; minified files go with their language
+    pub fn counts(&self, language: &str) -> bool {
+        let language = language
+            .strip_suffix(crate::counter::MINIFIED_SUFFIX)
+            .unwrap_or(language);
+        let named = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(language));
+        (self.only.is_empty() || named(&self.only)) && !named(&self.disabled)
+    }
+
+    /// The names given, to check that they are known
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.only.iter().chain(&self.disabled).map(String::as_str)
+    }
+}
+
+/// Top-level sections of the application configuration; a file without any of them is a
+/// plain language definition file (`[<key>]` sections only)
+pub const APP_CONFIG_SECTIONS: [&str; 4] = ["performance", "defaults", "languages", "encodings"];
+
+/// Whether a configuration file is an application configuration (see `APP_CONFIG_SECTIONS`)
+pub fn is_app_config(table: &toml::Table) -> bool {
+    APP_CONFIG_SECTIONS
+        .iter()
+        .any(|section| table.contains_key(*section))
 }
 
 /// REQ-9.7: Performance metrics configuration
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct PerformanceConfig {
+    /// Worker threads, 0 for one per CPU (count -j)
     #[serde(default = "default_threads")]
     pub default_threads: usize,
-    #[serde(default = "default_chunk_size")]
-    pub chunk_size: usize,
+    /// Append timing metrics to metrics_file (count --enable-metrics)
     #[serde(default = "default_enable_metrics")]
     pub enable_metrics: bool,
+    /// Metrics log (count --metrics-file)
     #[serde(default = "default_metrics_file")]
     pub metrics_file: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct DefaultsConfig {
+    /// Traverse directories recursively (count -r)
     #[serde(default = "default_recursive")]
     pub recursive: bool,
+    /// Hide the progress bar (count --no-progress)
     #[serde(default = "default_no_progress")]
     pub no_progress: bool,
-    #[serde(default = "default_format")]
+    /// Format of `report` without -f and of -o files without a known extension
+    #[cfg_attr(
+        feature = "cli",
+        serde(default = "default_format", deserialize_with = "output_format"),
+        schemars(schema_with = "output_format_schema")
+    )]
+    #[cfg_attr(not(feature = "cli"), serde(default = "default_format"))]
     pub output_format: String,
+    /// Base name of auto-named reports (count -f without -o)
     #[serde(default = "default_output_file")]
-    pub output_file: String, // base name (without extension) for auto-generated report files
+    pub output_file: String,
+    /// Order of the console tables when `--sort` is not given; unset keeps the report order
+    #[cfg_attr(
+        feature = "cli",
+        serde(
+            default,
+            deserialize_with = "sort_metric",
+            skip_serializing_if = "Option::is_none"
+        ),
+        schemars(schema_with = "sort_metric_schema")
+    )]
+    #[cfg_attr(not(feature = "cli"), serde(default))]
+    pub sort: Option<String>,
+    /// Glob patterns of the files and directories left out while collecting the paths to
+    /// count, relative to each directory walked (`--no-default-excludes` ignores them)
+    #[cfg_attr(
+        feature = "cli",
+        serde(default = "default_exclude", deserialize_with = "exclude_patterns")
+    )]
+    #[cfg_attr(not(feature = "cli"), serde(default = "default_exclude"))]
+    pub exclude: Vec<String>,
 }
 
 impl Default for PerformanceConfig {
     fn default() -> Self {
         Self {
             default_threads: default_threads(),
-            chunk_size: default_chunk_size(),
             enable_metrics: default_enable_metrics(),
             metrics_file: default_metrics_file(),
         }
@@ -81,6 +448,8 @@ impl Default for DefaultsConfig {
             no_progress: default_no_progress(),
             output_format: default_format(),
             output_file: default_output_file(),
+            sort: None,
+            exclude: default_exclude(),
         }
     }
 }
@@ -88,9 +457,6 @@ impl Default for DefaultsConfig {
 fn default_threads() -> usize {
     0
 }
-fn default_chunk_size() -> usize {
-    1000
-}
 fn default_enable_metrics() -> bool {
     false
 }
@@ -111,36 +477,197 @@ fn default_output_file() -> String {
     DEFAULT_OUTPUT_FILE_BASE.to_string()
 }
 
-/// Public constant for the default base name of auto-generated report files
-pub const DEFAULT_OUTPUT_FILE_BASE: &str = "sloc-report";
+fn default_exclude() -> Vec<String> {
+    // version control metadata and the usual build and dependency directories
+    ["target/**", "node_modules/**", ".git/**"]
+        .map(String::from)
+        .to_vec()
+}
 
-impl AppConfig {
-    pub fn from_file(path: &Path) -> crate::error::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        toml::from_str(&content).map_err(|e| crate::error::SlocError::InvalidConfig(e.to_string()))
+/// Glob patterns that compile
+#[cfg(feature = "cli")]
+fn exclude_patterns<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    glob_patterns(deserializer, "exclude")
+}
+
+#[cfg(feature = "cli")]
+fn encoding_patterns<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let patterns = glob_patterns(deserializer, "path")?;
+    if patterns.is_empty() {
+        return Err(serde::de::Error::custom("no path patterns"));
     }
+    Ok(patterns)
+}
 
-    pub fn default() -> Self {
-        Self {
-            performance: PerformanceConfig::default(),
-            defaults: DefaultsConfig::default(),
+#[cfg(feature = "cli")]
+fn glob_patterns<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+    kind: &str,
+) -> Result<Vec<String>, D::Error> {
+    let patterns = Vec::<String>::deserialize(deserializer)?;
+    for pattern in &patterns {
+        if let Err(e) = glob::Pattern::new(pattern) {
+            return Err(serde::de::Error::custom(format!(
+                "invalid {} pattern \"{}\": {}",
+                kind, pattern, e
+            )));
         }
     }
+    Ok(patterns)
+}
+
+// The values were checked when the configuration was read
+#[cfg(feature = "cli")]
+impl DefaultsConfig {
+    /// The `exclude` patterns, compiled
+    pub fn exclude_patterns(&self) -> Vec<glob::Pattern> {
+        self.exclude
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect()
+    }
+
+    /// `output_format` as a format
+    pub fn format(&self) -> crate::cli::OutputFormat {
+        use clap::ValueEnum;
+        crate::cli::OutputFormat::from_str(&self.output_format, true)
+            .unwrap_or(crate::cli::OutputFormat::Json)
+    }
+
+    /// `sort` as a metric
+    pub fn sort_metric(&self) -> Option<crate::cli::SortMetric> {
+        use clap::ValueEnum;
+        let sort = self.sort.as_deref()?;
+        crate::cli::SortMetric::from_str(sort, true).ok()
+    }
+}
+
+/// One of the names of `count --format`
+#[cfg(feature = "cli")]
+fn output_format<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    value_name::<D, crate::cli::OutputFormat>(deserializer, "output format")
+}
+
+/// One of the names of `count --sort`
+#[cfg(feature = "cli")]
+fn sort_metric<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    value_name::<D, crate::cli::SortMetric>(deserializer, "sort metric").map(Some)
+}
+
+#[cfg(feature = "cli")]
+fn output_format_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    value_names_schema::<crate::cli::OutputFormat>()
+}
+
+#[cfg(feature = "cli")]
+fn sort_metric_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    value_names_schema::<crate::cli::SortMetric>()
+}
+
+/// A string among the names of the values of `T`
+#[cfg(feature = "cli")]
+fn value_names_schema<T: clap::ValueEnum>() -> schemars::Schema {
+    let names: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|value| value.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    schemars::json_schema!({ "type": "string", "enum": names })
+}
+
+/// The name of a value of the command-line option of type `T`, `what` in errors
+#[cfg(feature = "cli")]
+fn value_name<'de, D: serde::Deserializer<'de>, T: clap::ValueEnum>(
+    deserializer: D,
+    what: &str,
+) -> Result<String, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    if T::from_str(&name, true).is_err() {
+        let names: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|value| value.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        return Err(serde::de::Error::custom(format!(
+            "unknown {} \"{}\", expected one of {}",
+            what,
+            name,
+            names.join(", ")
+        )));
+    }
+    Ok(name)
+}
+
+/// Public constant for the default base name of auto-generated report files
+pub const DEFAULT_OUTPUT_FILE_BASE: &str = "sloc-report";
+
+/// Names of the project configuration, looked for in the current directory and then in
+/// each of its ancestors
+#[cfg(feature = "cli")]
+pub const PROJECT_CONFIG_FILES: [&str; 2] = [".counterlines.toml", "counterlines.toml"];
+
+/// The user configuration, below every project: `counterlines/config.toml` in the user
+/// configuration directory (`~/.config` on Linux, `~/Library/Application Support` on macOS,
+/// `%APPDATA%` on Windows)
+#[cfg(feature = "cli")]
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("counterlines").join("config.toml"))
+}
+
+/// Cleared by `--no-config`
+#[cfg(feature = "cli")]
+static DISCOVERY: AtomicBool = AtomicBool::new(true);
+
+/// Whether configuration files are looked for when `--config` is not given
+#[cfg(feature = "cli")]
+pub fn set_discovery(enabled: bool) {
+    DISCOVERY.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether configuration files are looked for (no `--no-config`)
+#[cfg(feature = "cli")]
+pub fn discovery() -> bool {
+    DISCOVERY.load(Ordering::Relaxed)
+}
+
+/// The configuration file to load: `explicit` (`--config`), or else the project
+/// configuration nearest to the current directory
+#[cfg(feature = "cli")]
+pub fn config_path(explicit: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    if !DISCOVERY.load(Ordering::Relaxed) {
+        return None;
+    }
+    find_project_config(&std::env::current_dir().ok()?)
+}
 
-    /// Create AppConfig with CLI overrides
+/// The first of `PROJECT_CONFIG_FILES` in `dir` or its nearest ancestor having one
+#[cfg(feature = "cli")]
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|dir| PROJECT_CONFIG_FILES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(feature = "cli")]
+impl AppConfig {
+    /// Create AppConfig with CLI overrides, over the `COUNTERLINES_*` environment variables,
+    /// over `config_path` or else the project configuration found from the current
+    /// directory, over the user configuration
     pub fn with_cli_overrides(
         config_path: Option<&Path>,
         enable_metrics: bool,
         metrics_file: Option<&PathBuf>,
     ) -> crate::error::Result<Self> {
-        let mut config = if let Some(path) = config_path {
-            Self::from_file(path).unwrap_or_else(|_| {
-                eprintln!("Warning: Could not load config file, using defaults");
-                Self::default()
-            })
-        } else {
-            Self::default()
-        };
+        let (mut config, _) = Self::resolve(config_path)?;
 
         // Override with CLI arguments
         if enable_metrics {
@@ -153,22 +680,229 @@ impl AppConfig {
 
         Ok(config)
     }
+
+    /// The configuration without CLI overrides, and where it comes from. Every file is
+    /// checked strictly: an unknown key or a bad value is an error, not a default.
+    pub fn resolve(config_path: Option<&Path>) -> crate::error::Result<(Self, ConfigSources)> {
+        let mut sources = ConfigSources::default();
+        // Each file sets what it names; the others keep their values from below
+        let user_config =
+            user_config_path().filter(|path| DISCOVERY.load(Ordering::Relaxed) && path.is_file());
+        let mut table = toml::Table::new();
+        for path in [user_config, self::config_path(config_path)]
+            .into_iter()
+            .flatten()
+        {
+            match read_config(&path)? {
+                Some(file) => merge_tables(&mut table, file),
+                None => sources.language_files.push(path.clone()),
+            }
+            sources.files.push(path);
+        }
+        sources.variables = apply_env_overrides(&mut table);
+        // The files were checked one by one: what is left are the variables
+        let config = table.try_into().map_err(|e: toml::de::Error| {
+            crate::error::SlocError::InvalidConfig(format!(
+                "{}: {}",
+                sources.variables.join(", "),
+                e.message().trim_end()
+            ))
+        })?;
+        Ok((config, sources))
+    }
+}
+
+/// Where a configuration comes from, lowest precedence first (`config check`)
+#[cfg(feature = "cli")]
+#[derive(Debug, Default)]
+pub struct ConfigSources {
+    /// The user configuration and the project (or `--config`) configuration that exist
+    pub files: Vec<PathBuf>,
+    /// Those of `files` that only define languages (`[<key>]` sections)
+    pub language_files: Vec<PathBuf>,
+    /// The `COUNTERLINES_*` variables applied
+    pub variables: Vec<&'static str>,
+}
+
+/// Type of the value of a configuration environment variable
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy)]
+enum EnvValue {
+    Integer,
+    Boolean,
+    Text,
+}
+
+/// Environment variables overriding the configuration files: variable, section, key, type
+#[cfg(feature = "cli")]
+const ENV_OVERRIDES: [(&str, &str, &str, EnvValue); 7] = [
+    (
+        "COUNTERLINES_THREADS",
+        "performance",
+        "default_threads",
+        EnvValue::Integer,
+    ),
+    (
+        "COUNTERLINES_ENABLE_METRICS",
+        "performance",
+        "enable_metrics",
+        EnvValue::Boolean,
+    ),
+    (
+        "COUNTERLINES_METRICS_FILE",
+        "performance",
+        "metrics_file",
+        EnvValue::Text,
+    ),
+    (
+        "COUNTERLINES_RECURSIVE",
+        "defaults",
+        "recursive",
+        EnvValue::Boolean,
+    ),
+    (
+        "COUNTERLINES_NO_PROGRESS",
+        "defaults",
+        "no_progress",
+        EnvValue::Boolean,
+    ),
+    (
+        "COUNTERLINES_FORMAT",
+        "defaults",
+        "output_format",
+        EnvValue::Text,
+    ),
+    (
+        "COUNTERLINES_OUTPUT_FILE",
+        "defaults",
+        "output_file",
+        EnvValue::Text,
+    ),
+];
+
+/// Set the values of the `ENV_OVERRIDES` variables present in the environment and return
+/// their names; a value of the wrong type is reported and ignored
+#[cfg(feature = "cli")]
+fn apply_env_overrides(table: &mut toml::Table) -> Vec<&'static str> {
+    let mut applied = Vec::new();
+    for (variable, section, key, kind) in ENV_OVERRIDES {
+        let Ok(text) = std::env::var(variable) else {
+            continue;
+        };
+        let value = match kind {
+            EnvValue::Integer => text.trim().parse().ok().map(toml::Value::Integer),
+            EnvValue::Boolean => match text.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Some(toml::Value::Boolean(true)),
+                "0" | "false" | "no" | "off" => Some(toml::Value::Boolean(false)),
+                _ => None,
+            },
+            EnvValue::Text => Some(toml::Value::String(text.clone())),
+        };
+        let Some(value) = value else {
+            let expected = match kind {
+                EnvValue::Integer => "a number",
+                _ => "true or false",
+            };
+            tracing::warn!("ignoring {}={:?}: expected {}", variable, text, expected);
+            continue;
+        };
+        let section = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(section) = section {
+            section.insert(key.to_string(), value);
+        }
+        applied.push(variable);
+    }
+    applied
+}
+
+/// Read and check a configuration file: the table of an application configuration, or
+/// `None` for a plain language definition file, which only `LanguageDetector` loads
+#[cfg(feature = "cli")]
+pub(crate) fn read_config(path: &Path) -> crate::error::Result<Option<toml::Table>> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        crate::error::SlocError::InvalidConfig(format!("{}: {}", path.display(), e))
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| invalid(&e))?;
+    let table: toml::Table = toml::from_str(&content).map_err(|e| invalid(&e))?;
+    if !is_app_config(&table) {
+        toml::from_str::<HashMap<String, Language>>(&content).map_err(|e| invalid(&e))?;
+        return Ok(None);
+    }
+    toml::from_str::<AppConfig>(&content).map_err(|e| invalid(&e))?;
+    Ok(Some(table))
 }
 
-/// REQ-9.7: Performance metrics logger
+/// Merge `overlay` into `base`: sections are merged key by key, other values replaced
+#[cfg(feature = "cli")]
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// REQ-9.7: Performance metrics logger. Records are sent to a writer thread that appends
+/// them to the log through one buffered file handle, so logging from the counting threads
+/// costs no system call; the log is complete once the logger is dropped.
+#[cfg(feature = "cli")]
 pub struct MetricsLogger {
     enabled: bool,
     start_time: std::time::Instant,
     file_path: String,
+    /// Channel to the writer thread, while logging is enabled
+    sender: Option<mpsc::Sender<MetricsRecord>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// A line of the metrics log, formatted by the writer thread
+#[cfg(feature = "cli")]
+enum MetricsRecord {
+    Raw(String),
+    Metric {
+        elapsed: f64,
+        name: String,
+        context: Option<String>,
+        value: f64,
+    },
+}
+
+#[cfg(feature = "cli")]
+impl MetricsRecord {
+    fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        match self {
+            MetricsRecord::Raw(message) => out.write_all(message.as_bytes()),
+            MetricsRecord::Metric {
+                elapsed,
+                name,
+                context: None,
+                value,
+            } => writeln!(out, "[{:.3}s] {}: {:.3}", elapsed, name, value),
+            MetricsRecord::Metric {
+                elapsed,
+                name,
+                context: Some(context),
+                value,
+            } => writeln!(
+                out,
+                "[{:.3}s] {} ({}): {:.3}",
+                elapsed, name, context, value
+            ),
+        }
+    }
 }
 
+#[cfg(feature = "cli")]
 impl MetricsLogger {
     pub fn new(config: &PerformanceConfig) -> Self {
-        Self {
-            enabled: config.enable_metrics,
-            start_time: std::time::Instant::now(),
-            file_path: config.metrics_file.clone(),
-        }
+        Self::open(config.enable_metrics, config.metrics_file.clone())
     }
 
     /// Create MetricsLogger from CLI arguments
@@ -177,10 +911,25 @@ impl MetricsLogger {
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|| "sloc_metrics.log".to_string());
 
+        Self::open(enable_metrics, file_path)
+    }
+
+    fn open(enabled: bool, file_path: String) -> Self {
+        let (sender, writer) = if enabled {
+            let (sender, receiver) = mpsc::channel();
+            let path = file_path.clone();
+            let writer = std::thread::spawn(move || write_records(&path, receiver));
+            (Some(sender), Some(writer))
+        } else {
+            (None, None)
+        };
+
         Self {
-            enabled: enable_metrics,
+            enabled,
             start_time: std::time::Instant::now(),
             file_path,
+            sender,
+            writer,
         }
     }
 
@@ -200,66 +949,33 @@ impl MetricsLogger {
 
     /// Log a raw message without timestamp prefix
     pub fn log_raw_message(&self, message: &str) {
-        if !self.enabled {
-            return;
-        }
-
-        if let Err(e) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.file_path)
-            .and_then(|mut file| {
-                use std::io::Write;
-                file.write_all(message.as_bytes())
-            })
-        {
-            eprintln!("Failed to log message: {}", e);
-        }
+        self.send(|| MetricsRecord::Raw(message.to_string()));
     }
 
     pub fn log_metric(&self, metric_name: &str, value: f64) {
-        if !self.enabled {
-            return;
-        }
-
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        let log_entry = format!("[{:.3}s] {}: {:.3}\n", elapsed, metric_name, value);
-
-        if let Err(e) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.file_path)
-            .and_then(|mut file| {
-                use std::io::Write;
-                file.write_all(log_entry.as_bytes())
-            })
-        {
-            eprintln!("Failed to log metric: {}", e);
-        }
+        self.send(|| MetricsRecord::Metric {
+            elapsed: self.start_time.elapsed().as_secs_f64(),
+            name: metric_name.to_string(),
+            context: None,
+            value,
+        });
     }
 
     /// Log a metric with additional context
-    pub fn _log_metric_with_context(&self, metric_name: &str, value: f64, context: &str) {
-        if !self.enabled {
-            return;
-        }
+    pub fn log_metric_with_context(&self, metric_name: &str, value: f64, context: &str) {
+        self.send(|| MetricsRecord::Metric {
+            elapsed: self.start_time.elapsed().as_secs_f64(),
+            name: metric_name.to_string(),
+            context: Some(context.to_string()),
+            value,
+        });
+    }
 
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        let log_entry = format!(
-            "[{:.3}s] {} ({}): {:.3}\n",
-            elapsed, metric_name, context, value
-        );
-
-        if let Err(e) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.file_path)
-            .and_then(|mut file| {
-                use std::io::Write;
-                file.write_all(log_entry.as_bytes())
-            })
-        {
-            eprintln!("Failed to log metric: {}", e);
+    /// Hand a record to the writer thread; nothing is built while logging is disabled
+    fn send(&self, record: impl FnOnce() -> MetricsRecord) {
+        if let Some(sender) = &self.sender {
+            // The writer only stops early after reporting a write error
+            let _ = sender.send(record());
         }
     }
 
@@ -304,6 +1020,37 @@ impl MetricsLogger {
         self.log_raw_message("=== Session Completed ===\n\n");
     }
 
+    /// Log the distribution of the per-file counting times (in milliseconds), the throughput
+    /// on large files and the slowest files
+    pub fn log_file_timings(&self, timings: &FileTimings) {
+        if !self.enabled {
+            return;
+        }
+
+        for (name, fraction) in [("file_time_p50_ms", 0.5), ("file_time_p95_ms", 0.95)] {
+            if let Some(micros) = timings.percentile(fraction) {
+                self.log_metric(name, micros as f64 / 1e3);
+            }
+        }
+        if timings.files.load(Ordering::Relaxed) > 0 {
+            let max = timings.max_micros.load(Ordering::Relaxed);
+            self.log_metric("file_time_max_ms", max as f64 / 1e3);
+        }
+        let large_micros = timings.large_micros.load(Ordering::Relaxed);
+        if large_micros > 0 {
+            let large_lines = timings.large_lines.load(Ordering::Relaxed);
+            let throughput = large_lines as f64 / (large_micros as f64 / 1e6);
+            self.log_metric("large_file_throughput", throughput);
+        }
+        for (micros, path) in timings.slowest.lock().unwrap().iter() {
+            self.log_metric_with_context(
+                "slowest_file_time_ms",
+                *micros as f64 / 1e3,
+                &path.display().to_string(),
+            );
+        }
+    }
+
     /// Check if metrics logging is enabled
     pub fn is_enabled(&self) -> bool {
         self.enabled
@@ -314,3 +1061,150 @@ impl MetricsLogger {
         &self.file_path
     }
 }
+
+/// Files named in the metrics log as the slowest to count
+#[cfg(feature = "cli")]
+const SLOWEST_FILES: usize = 10;
+
+/// Files of more lines count towards `large_file_throughput`
+#[cfg(feature = "cli")]
+const LARGE_FILE_LINES: usize = 1000;
+
+/// Histogram buckets per power of two of microseconds: percentiles are exact to 1/8
+#[cfg(feature = "cli")]
+const SUB_BUCKETS: u64 = 8;
+
+/// REQ-9.7: Counting times of the files of a run, recorded from the counting threads without
+/// a shared lock (but for the rare file slower than the slowest kept so far) and logged once
+/// with `MetricsLogger::log_file_timings`. Memory does not grow with the number of files.
+#[cfg(feature = "cli")]
+pub struct FileTimings {
+    /// Files per bucket of microseconds (see `bucket`)
+    buckets: Vec<AtomicU64>,
+    files: AtomicU64,
+    max_micros: AtomicU64,
+    large_lines: AtomicU64,
+    large_micros: AtomicU64,
+    /// The slowest files, slowest first
+    slowest: Mutex<Vec<(u64, PathBuf)>>,
+    /// Time a file must exceed to enter `slowest` once it is full
+    slowest_floor: AtomicU64,
+}
+
+#[cfg(feature = "cli")]
+impl Default for FileTimings {
+    fn default() -> Self {
+        Self {
+            buckets: (0..64 * SUB_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            files: AtomicU64::new(0),
+            max_micros: AtomicU64::new(0),
+            large_lines: AtomicU64::new(0),
+            large_micros: AtomicU64::new(0),
+            slowest: Mutex::new(Vec::with_capacity(SLOWEST_FILES + 1)),
+            slowest_floor: AtomicU64::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl FileTimings {
+    /// `path`, of `lines` lines, was counted in `time`
+    pub fn record(&self, path: &Path, time: std::time::Duration, lines: usize) {
+        let micros = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[bucket(micros)].fetch_add(1, Ordering::Relaxed);
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+        if lines > LARGE_FILE_LINES {
+            self.large_lines.fetch_add(lines as u64, Ordering::Relaxed);
+            self.large_micros.fetch_add(micros, Ordering::Relaxed);
+        }
+
+        if micros > self.slowest_floor.load(Ordering::Relaxed) {
+            let mut slowest = self.slowest.lock().unwrap();
+            let at = slowest.partition_point(|(time, _)| *time >= micros);
+            slowest.insert(at, (micros, path.to_path_buf()));
+            slowest.truncate(SLOWEST_FILES);
+            if slowest.len() == SLOWEST_FILES {
+                self.slowest_floor
+                    .store(slowest[SLOWEST_FILES - 1].0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Microseconds within which `fraction` of the files were counted (the lower bound of
+    /// the bucket holding that file), or `None` before any file
+    fn percentile(&self, fraction: f64) -> Option<u64> {
+        let files = self.files.load(Ordering::Relaxed);
+        let rank = ((files as f64 * fraction).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count.load(Ordering::Relaxed);
+            if seen >= rank {
+                return Some(bucket_floor(index));
+            }
+        }
+        None
+    }
+}
+
+/// Histogram bucket of a time: below `SUB_BUCKETS` microseconds one per microsecond, above
+/// `SUB_BUCKETS` per power of two
+#[cfg(feature = "cli")]
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = u64::from(micros.ilog2());
+    let sub = (micros >> (exponent - 3)) - SUB_BUCKETS;
+    (exponent * SUB_BUCKETS + sub) as usize
+}
+
+/// Smallest time of a histogram bucket
+#[cfg(feature = "cli")]
+fn bucket_floor(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let (exponent, sub) = (index / SUB_BUCKETS, index % SUB_BUCKETS);
+    (SUB_BUCKETS + sub) << (exponent - 3)
+}
+
+/// Closing the channel lets the writer flush the log; waiting for it makes the log complete
+/// when the command returns
+#[cfg(feature = "cli")]
+impl Drop for MetricsLogger {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Writer thread of a `MetricsLogger`: append the records to `path` until the logger is
+/// dropped
+#[cfg(feature = "cli")]
+fn write_records(path: &str, records: mpsc::Receiver<MetricsRecord>) {
+    let file = match std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::warn!("cannot open the metrics log {}: {}", path, e);
+            return;
+        }
+    };
+    let mut out = BufWriter::new(file);
+    for record in records {
+        if let Err(e) = record.write(&mut out) {
+            tracing::warn!("cannot log metrics: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = out.flush() {
+        tracing::warn!("cannot log metrics: {}", e);
+    }
+}
@@ -14,26 +14,80 @@
 //   REQ-9.5: Progress bar
 //   REQ-9.7: Metrics logging
 
-use crate::cli::CountArgs;
-use crate::config::{AppConfig, MetricsLogger};
-use crate::error::{Result, SlocError};
-use crate::language::{CommentParser, LanguageDetector, LineType};
-use crate::output::{ConsoleOutput, ReportExporter};
-use crate::report::{FileStats, Report};
+use crate::encoding::{self, Decoder, Decoding};
+use crate::error::Result;
+use crate::language::{Language, LanguageDetector, LineClassifier, LineType};
+use crate::report::FileStats;
+use encoding_rs::UTF_8;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::encoding::TextEncoding;
+#[cfg(feature = "fs")]
+use crate::error::SlocError;
+#[cfg(feature = "fs")]
+use crate::language::CommentState;
+#[cfg(feature = "fs")]
+use crate::report::{Diagnostic, DiagnosticCode, Report};
+#[cfg(feature = "fs")]
+use glob::{Pattern, glob};
+#[cfg(feature = "fs")]
+use rayon::prelude::*;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+#[cfg(feature = "fs")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "fs")]
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "fs")]
+use walkdir::WalkDir;
+
+#[cfg(feature = "cli")]
+use crate::cache::CountCache;
+#[cfg(feature = "cli")]
+use crate::checkpoint::Checkpoint;
+#[cfg(feature = "cli")]
+use crate::cli::{CountArgs, ExportFormat, InvalidBytes, MinifiedFiles, OutputFormat, Verbosity};
+#[cfg(feature = "cli")]
+use crate::compression::is_stdout;
+#[cfg(feature = "cli")]
+use crate::config::{AppConfig, DefaultsConfig, FileTimings, LanguageFilter, MetricsLogger};
+#[cfg(feature = "cli")]
+use crate::memory::{MemoryMonitor, format_bytes};
+#[cfg(feature = "cli")]
+use crate::output::{ConsoleOutput, FileRows, ReportExporter, colors_enabled, print_result};
+#[cfg(feature = "cli")]
+use crate::plugin::{ExternalExporter, MetricPlugin};
+#[cfg(feature = "cli")]
+use crate::processor::compare_to_baseline;
+#[cfg(feature = "cli")]
+use crate::progress::{BatchedBar, ProgressTracker};
+#[cfg(feature = "cli")]
+use crate::report::{InvalidFile, ReportTotals, SkippedFile};
+#[cfg(feature = "cli")]
+use crate::scopes::ConfigScopes;
+#[cfg(feature = "cli")]
+use crate::tuning::ThreadTuner;
+#[cfg(feature = "cli")]
 use colored::Colorize;
-use encoding_rs_io::DecodeReaderBytesBuilder;
-use glob::glob;
+#[cfg(feature = "cli")]
 use human_format::Formatter;
+#[cfg(feature = "cli")]
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::prelude::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "cli")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "cli")]
 use std::time::Instant;
-use walkdir::WalkDir;
 
-pub fn execute_count(args: CountArgs) -> Result<()> {
+#[cfg(feature = "cli")]
+pub fn execute_count(mut args: CountArgs) -> Result<()> {
+    if let Some(source) = &args.from_diff {
+        return crate::diff::execute_count_diff(&args, source);
+    }
     let start_time = Instant::now();
 
     // REQ-9.7: Initialize metrics logger with CLI overrides (metrics)
@@ -42,6 +96,30 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
         args.enable_metrics,
         args.metrics_file.as_ref(),
     )?;
+    args.apply_defaults(&app_config);
+    let threads = args.threads.unwrap_or_default();
+
+    // REQ-6.8: Resolve export targets up front so bad -f/-o combinations fail before scanning
+    let exports = resolve_exports(&args, &app_config.defaults)?;
+
+    // REQ-6.8: `-o -` and `--print` own stdout, so all console output is suppressed
+    let exports_to_stdout = exports.iter().any(|(_, path)| is_stdout(path));
+    if args.print.is_some() && exports_to_stdout {
+        return Err(SlocError::Parse(
+            "--print cannot be combined with `-o -` (both write to stdout)".to_string(),
+        ));
+    }
+    let baseline_to_stdout = args.baseline_export.as_deref().is_some_and(is_stdout);
+    if baseline_to_stdout && (exports_to_stdout || args.print.is_some()) {
+        return Err(SlocError::Parse(
+            "`--baseline-export -` cannot be combined with `-o -` or --print".to_string(),
+        ));
+    }
+    let verbosity = if exports_to_stdout || baseline_to_stdout || args.print.is_some() {
+        Verbosity::Silent
+    } else {
+        Verbosity::from_flags(args.quiet, args.silent)
+    };
 
     let metrics_logger = Arc::new(MetricsLogger::new(&app_config.performance));
 
@@ -50,13 +128,22 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
         "paths={}, recursive={}, threads={}, format={:?}",
         args.paths.len(),
         args.recursive,
-        args.threads,
+        threads,
         args.format
     );
     metrics_logger.init_session("count", &args_summary);
     metrics_logger.log_system_info();
     metrics_logger.log_metric("operation_start", start_time.elapsed().as_secs_f64());
 
+    // Loaded before scanning so a missing or unreadable baseline fails fast
+    let baseline = match &args.baseline {
+        Some(path) => {
+            let format = OutputFormat::from_path(path).unwrap_or(OutputFormat::Json);
+            Some(Report::from_file(path, format)?)
+        }
+        None => None,
+    };
+
     let mut detector = LanguageDetector::new();
 
     // REQ-3.3: Load custom language config (custom language definitions)
@@ -64,6 +151,7 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
         let load_start = Instant::now();
         detector.load_from_config(config_path)?;
         metrics_logger.log_metric("config_load_time", load_start.elapsed().as_secs_f64());
+        tracing::info!("languages loaded from {}", config_path.display());
     }
 
     // REQ-3.4: Apply language overrides (per estensione)
@@ -75,140 +163,502 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
         args.language_override.len() as f64,
     );
 
-    // REQ-2.1/2.2/2.3/2.4: Collect all file paths (input sources)
+    // Nested configurations of the directories below the project root, found while walking
+    let mut scopes = ConfigScopes::new(
+        args.config.as_deref(),
+        detector,
+        &app_config.encodings,
+        !args.no_default_excludes,
+    );
+
+    // Parsed before scanning so a malformed plugin command line fails fast
+    let plugins = args
+        .metric_plugin
+        .iter()
+        .map(|command| MetricPlugin::parse(command))
+        .collect::<Result<Vec<_>>>()?;
+
+    // REQ-9.5: A spinner counts the files found while the tree is walked, then turns into
+    // the progress bar of the count; none with -v, whose log lines would break it
+    let progress = (!args.no_progress
+        && !args.progress_json
+        && verbosity == Verbosity::Normal
+        && !tracing::enabled!(tracing::Level::INFO))
+    .then(|| {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner} Collecting files: {pos} found [{elapsed_precise}]")
+                .unwrap(),
+        );
+        pb.enable_steady_tick(std::time::Duration::from_millis(100));
+        pb
+    });
+
+    // REQ-2.1/2.2/2.3/2.4: Collect all file paths (input sources), without those of the
+    // configured exclude patterns
+    let exclude = if args.no_default_excludes {
+        Vec::new()
+    } else {
+        app_config.defaults.exclude_patterns()
+    };
     let path_collection_start = Instant::now();
-    let paths = collect_paths(&args)?;
+    let mut diagnostics = Vec::new();
+    let mut keep = |entry: &walkdir::DirEntry| scopes.keep(entry);
+    let found = |found: usize| {
+        if let Some(pb) = &progress {
+            pb.set_position(found as u64);
+        }
+    };
+    let paths = collect_paths(&args, &exclude, &mut keep, &found, &mut diagnostics)
+        .and_then(|paths| scopes.resolve(&paths).map(|()| paths))
+        .inspect_err(|_| {
+            if let Some(pb) = &progress {
+                pb.finish_and_clear();
+            }
+        })?;
+    metrics_logger.log_metric("nested_configs", scopes.nested_configs() as f64);
+
+    // REQ-3.3: Languages counted; a name no language has is most likely a typo
+    let filter = LanguageFilter::new(&app_config.languages, &args.only_lang, &args.exclude_lang);
+    for name in filter.names().filter(|name| !scopes.has_language(name)) {
+        let warn = || {
+            tracing::warn!(
+                "no language is named \"{}\" (languages.only/disabled, \
+                 --only-lang/--exclude-lang)",
+                name
+            )
+        };
+        match &progress {
+            Some(pb) => pb.suspend(warn),
+            None => warn(),
+        }
+    }
     metrics_logger.log_metric(
         "path_collection_time",
         path_collection_start.elapsed().as_secs_f64(),
     );
     metrics_logger.log_metric("total_files_to_process", paths.len() as f64);
+    tracing::info!(
+        "{} file(s) to count, found in {:.2}s ({} nested configuration(s))",
+        paths.len(),
+        path_collection_start.elapsed().as_secs_f64(),
+        scopes.nested_configs()
+    );
 
-    // REQ-9.4: Set up parallel processing (thread pool)
-    let thread_count = if args.threads > 0 {
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(args.threads)
-            .build_global()
-            .map_err(|e| SlocError::Parse(e.to_string()))?;
-        args.threads
-    } else {
-        rayon::current_num_threads()
-    };
-    metrics_logger.log_metric("thread_count", thread_count as f64);
+    // REQ-9.4: Set up parallel processing (a pool of this count, not the global one, so
+    // counts in the same process can each use their own thread count)
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| SlocError::Parse(e.to_string()))?;
+    metrics_logger.log_metric("thread_count", pool.current_num_threads() as f64);
+    // REQ-9.4: With -j 0, the files counted at once are tuned to the throughput
+    let tuner = (threads == 0 && pool.current_num_threads() > 1)
+        .then(|| ThreadTuner::new(pool.current_num_threads()));
+    metrics_logger.log_metric("io_concurrency", args.io_concurrency as f64);
 
-    // REQ-9.5: Progress indicator (barra avanzamento)
-    let progress = if !args.no_progress {
-        let pb = ProgressBar::new(paths.len() as u64);
+    // REQ-9.5: Progress indicator (barra avanzamento), with the time since the start and
+    // an estimate of the time left
+    let progress = progress.map(|pb| {
+        pb.disable_steady_tick();
         pb.set_style(
             ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg} | {per_sec}")
+                .template(if colors_enabled() {
+                    "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg} | {per_sec} | ETA {eta}"
+                } else {
+                    "[{elapsed_precise}] {bar:40} {pos}/{len} {msg} | {per_sec} | ETA {eta}"
+                })
                 .unwrap()
                 .progress_chars("##-"),
         );
-        Some(Arc::new(Mutex::new(pb)))
-    } else {
+        pb.set_length(paths.len() as u64);
+        pb.set_position(0);
+        pb.reset_eta();
+        BatchedBar::new(pb)
+    });
+    let tracker = args
+        .progress_json
+        .then(|| ProgressTracker::json_lines(paths.len()));
+
+    // Unchanged files since the previous run are taken from the cache
+    let cache_dir = args
+        .cache_dir
+        .clone()
+        .unwrap_or_else(CountCache::default_dir);
+    if args.clear_cache {
+        CountCache::clear(&cache_dir)?;
+    }
+    let cache = if args.no_cache {
         None
+    } else {
+        Some(CountCache::open(
+            &cache_dir,
+            args.config.as_deref(),
+            &scopes.counted_files(),
+            &args.language_override,
+            args.ignore_preprocessor,
+            Decoding {
+                encoding: args.encoding.fixed(),
+                skip_invalid_lines: args.on_invalid_utf8 == InvalidBytes::SkipLine,
+            },
+            &app_config.encodings,
+        )?)
     };
 
+    // Results of an interrupted scan are taken as recorded; the checkpoint starts with them
+    let checkpoint = match &args.checkpoint {
+        Some(path) => {
+            let scan = Checkpoint::scan_key(&args, &scopes.counted_files())?;
+            let recorded = if args.resume {
+                Checkpoint::load(path, &scan)?
+            } else {
+                Default::default()
+            };
+            let interval = std::time::Duration::from_secs(args.checkpoint_interval);
+            Some((
+                Checkpoint::create(path, &scan, interval, &recorded)?,
+                recorded,
+            ))
+        }
+        None => None,
+    };
+    if let Some((_, recorded)) = &checkpoint {
+        metrics_logger.log_metric("checkpoint_resumed_files", recorded.len() as f64);
+        if args.resume && verbosity == Verbosity::Normal {
+            println!(
+                "Resuming: {} of {} files already counted",
+                paths
+                    .iter()
+                    .filter(|path| recorded.contains_key(*path))
+                    .count(),
+                paths.len()
+            );
+        }
+    }
+
     // REQ-1.1, REQ-9.4: Count lines in parallel (core counting)
-    let detector = Arc::new(detector);
     let ignore_preprocessor = args.ignore_preprocessor;
+    let read = args.read;
+    let fixed_encoding = args.encoding.fixed();
+    let skip_invalid_lines = args.on_invalid_utf8 == InvalidBytes::SkipLine;
+    let io_limit = IoLimit::new(args.io_concurrency);
+    let io_limit = io_limit.as_ref();
     let metrics_clone = Arc::clone(&metrics_logger);
 
+    let file_timings = metrics_logger.is_enabled().then(FileTimings::default);
+    let memory = MemoryMonitor::start(args.max_memory);
+    // Files that could not be counted, which fail a `--strict` run, and plugin failures
+    let strict = args.strict;
+    let file_diagnostics = Mutex::new(Vec::new());
+
     let processing_start = Instant::now();
-    let file_results: Vec<_> = paths
-        .par_iter()
-        .map(|path| {
-            let file_start = Instant::now();
-            let result = count_file(path, &detector, ignore_preprocessor);
-
-            // Log per-file metrics
-            if let Ok(ref stats) = result {
-                let file_time = file_start.elapsed().as_secs_f64();
-                if file_time > 0.001 {
-                    metrics_clone.log_metric(
-                        &format!(
-                            "file_process_time_{}",
-                            path.file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("unknown")
-                        ),
-                        file_time,
-                    );
-                }
-                if stats.total_lines > 1000 {
-                    let throughput = stats.total_lines as f64 / file_time;
-                    metrics_clone.log_metric("large_file_throughput", throughput);
-                }
+    let count_one = |path: &PathBuf| {
+        if let Some(entry) = checkpoint
+            .as_ref()
+            .and_then(|(_, recorded)| recorded.get(path))
+        {
+            if let Some(pb) = &progress {
+                pb.inc();
             }
-
-            if let Some(ref pb) = progress {
-                let pb = pb.lock().unwrap();
-                pb.inc(1);
-                pb.set_message(format!("Processing: {}", path.display()));
+            if let Some(tracker) = &tracker {
+                tracker.file_done(path, entry.total_lines());
             }
+            tracing::debug!("{}: taken from the checkpoint", path.display());
+            return entry.clone().into_result();
+        }
 
-            match result {
-                Ok(stats) => {
-                    if stats.language == "Unknown" {
-                        Err(path.clone())
-                    } else {
-                        Ok(stats)
+        let permit = tuner.as_ref().map(ThreadTuner::acquire);
+        let file_start = Instant::now();
+        let detector = scopes.detector_for(path);
+        // REQ-9.2: `--encoding`, else the `[[encodings]]` of the configurations
+        let decoding = Decoding {
+            encoding: fixed_encoding.or_else(|| scopes.encoding_for(path)),
+            skip_invalid_lines,
+        };
+        let result = match &cache {
+            Some(cache) => cache.get_or_count(path, || {
+                count_file_limited(
+                    path,
+                    detector,
+                    ignore_preprocessor,
+                    read,
+                    decoding,
+                    io_limit,
+                )
+            }),
+            None => count_file_limited(
+                path,
+                detector,
+                ignore_preprocessor,
+                read,
+                decoding,
+                io_limit,
+            ),
+        };
+        if let (Some(permit), Ok(stats)) = (permit, &result) {
+            permit.counted(stats.total_lines);
+        }
+
+        // Plugin metrics are never cached: the plugin may depend on more than the file
+        let result = result.map(|mut stats| {
+            if stats.language != "Unknown" {
+                for plugin in &plugins {
+                    if let Err(e) = plugin.measure(&mut stats) {
+                        tracing::warn!("{}: {}", path.display(), e);
+                        metrics_clone.log_metric("plugin_errors", 1.0);
+                        file_diagnostics.lock().unwrap().push(Diagnostic {
+                            code: DiagnosticCode::PluginError,
+                            path: Some(path.clone()),
+                            message: e.to_string(),
+                        });
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error processing {}: {}", path.display(), e);
-                    metrics_clone.log_metric("file_errors", 1.0);
-                    // treat as unsupported for reporting
+            }
+            tracing::debug!(
+                "{}: {}, {} line(s), {}",
+                path.display(),
+                stats.language,
+                stats.total_lines,
+                stats.encoding.as_deref().unwrap_or("UTF-8")
+            );
+            stats
+        });
+
+        // Per-file times are logged as a distribution at the end of the run
+        if let (Some(timings), Ok(stats)) = (&file_timings, &result) {
+            timings.record(path, file_start.elapsed(), stats.total_lines);
+        }
+
+        if let Some(pb) = &progress {
+            pb.inc();
+        }
+        if let Some(tracker) = &tracker {
+            tracker.file_done(path, result.as_ref().map_or(0, |stats| stats.total_lines));
+        }
+
+        let failed = result.is_err();
+        let outcome = match result {
+            Ok(stats) => {
+                if stats.language == "Unknown" {
                     Err(path.clone())
+                } else {
+                    Ok(stats)
                 }
             }
-        })
-        .collect();
-
-    let (results, unsupported_files): (Vec<_>, Vec<_>) =
-        file_results.into_iter().partition(|res| res.is_ok());
-    let results: Vec<FileStats> = results.into_iter().map(|r| r.unwrap()).collect();
-    let unsupported_files: Vec<PathBuf> = unsupported_files
-        .into_iter()
-        .map(|e| e.unwrap_err())
-        .collect();
+            Err(e) => {
+                tracing::error!("cannot count {}: {}", path.display(), e);
+                metrics_clone.log_metric("file_errors", 1.0);
+                file_diagnostics.lock().unwrap().push(Diagnostic {
+                    code: DiagnosticCode::UnreadableFile,
+                    path: Some(path.clone()),
+                    message: e.to_string(),
+                });
+                // treat as unsupported for reporting
+                Err(path.clone())
+            }
+        };
+        // A strict run that failed counts these files again when resumed
+        if let Some((checkpoint, _)) = &checkpoint
+            && !(strict && failed)
+            && let Err(e) = checkpoint.record(&outcome)
+        {
+            tracing::warn!("cannot write the checkpoint: {}", e);
+        }
+        outcome
+    };
+    // REQ-6.4: Without anything that lists or exports files, each file is only added to
+    // the language totals, so memory stays flat however many files are counted. Past
+    // --max-memory, a count keeping its files switches to totals as well.
+    let keep_files = keeps_files(&args, !exports.is_empty());
+    let skip_minified = args.minified == MinifiedFiles::Skip;
+    let minified_skipped = AtomicUsize::new(0);
+    // Files of languages not counted: always their number, their paths with the files
+    let language_skipped = AtomicUsize::new(0);
+    let skipped_files = Mutex::new(Vec::new());
+    // REQ-9.2: Files with bytes that do not decode, whatever the policy
+    let skip_invalid_files = args.on_invalid_utf8 == InvalidBytes::SkipFile;
+    let invalid_files = Mutex::new(Vec::new());
+    let counted = pool.install(|| {
+        paths
+            .par_iter()
+            .map(count_one)
+            .filter(|outcome| {
+                let skip = skip_minified
+                    && outcome
+                        .as_ref()
+                        .is_ok_and(|stats| stats.language.ends_with(MINIFIED_SUFFIX));
+                if skip {
+                    minified_skipped.fetch_add(1, Ordering::Relaxed);
+                }
+                !skip
+            })
+            .filter(|outcome| match outcome {
+                Ok(stats) if !filter.counts(&stats.language) => {
+                    language_skipped.fetch_add(1, Ordering::Relaxed);
+                    if keep_files {
+                        skipped_files.lock().unwrap().push(SkippedFile {
+                            path: stats.path.clone(),
+                            language: stats.language.clone(),
+                        });
+                    }
+                    false
+                }
+                _ => true,
+            })
+            .filter(|outcome| match outcome {
+                Ok(stats) if stats.invalid_lines > 0 => {
+                    invalid_files.lock().unwrap().push(InvalidFile {
+                        path: stats.path.clone(),
+                        invalid_lines: stats.invalid_lines,
+                    });
+                    !skip_invalid_files
+                }
+                _ => true,
+            })
+            .fold(
+                || Counted::new(keep_files),
+                |counted, outcome| counted.add(outcome, memory.over_limit()),
+            )
+            .reduce(|| Counted::new(keep_files), Counted::merge)
+    });
 
     let processing_time = processing_start.elapsed();
     metrics_logger.log_metric("total_processing_time", processing_time.as_secs_f64());
+    if let Some(tuner) = &tuner {
+        metrics_logger.log_metric("auto_threads", tuner.threads() as f64);
+    }
+    let minified_skipped = minified_skipped.into_inner();
+    if skip_minified {
+        metrics_logger.log_metric("minified_files_skipped", minified_skipped as f64);
+    }
+    let language_skipped = language_skipped.into_inner();
+    if !filter.is_empty() {
+        metrics_logger.log_metric("language_files_skipped", language_skipped as f64);
+    }
+    if let Some(timings) = &file_timings {
+        metrics_logger.log_file_timings(timings);
+    }
 
-    if let Some(ref pb) = progress {
-        pb.lock().unwrap().finish_with_message("Complete!");
+    if let Some(pb) = &progress {
+        pb.finish_with_message("Complete!");
+    }
+    if let Some(tracker) = &tracker {
+        tracker.finish();
     }
 
-    // Log processing statistics
-    let total_lines: usize = results.iter().map(|r| r.total_lines).sum();
-    let logical_lines: usize = results.iter().map(|r| r.logical_lines).sum();
-    let comment_lines: usize = results.iter().map(|r| r.comment_lines).sum();
-    let empty_lines: usize = results.iter().map(|r| r.empty_lines).sum();
+    tracing::info!(
+        "{} file(s) counted in {:.2}s",
+        paths.len(),
+        processing_time.as_secs_f64()
+    );
+    if let Some(cache) = cache {
+        metrics_logger.log_metric("cache_hits", cache.hits() as f64);
+        tracing::info!("{} file(s) unchanged since the cached count", cache.hits());
+        if let Err(e) = cache.save() {
+            tracing::warn!("cannot update the count cache: {}", e);
+        }
+    }
 
-    metrics_logger.log_metric("files_processed_successfully", results.len() as f64);
-    metrics_logger.log_metric("total_lines_processed", total_lines as f64);
-    metrics_logger.log_metric("logical_lines_processed", logical_lines as f64);
-    metrics_logger.log_metric("comment_lines_processed", comment_lines as f64);
-    metrics_logger.log_metric("empty_lines_processed", empty_lines as f64);
+    if minified_skipped > 0 && verbosity == Verbosity::Normal {
+        println!("Skipped {} minified files", minified_skipped);
+    }
 
-    if processing_time.as_secs_f64() > 0.0 {
-        let throughput = total_lines as f64 / processing_time.as_secs_f64();
-        metrics_logger.log_metric("overall_throughput_lines_per_sec", throughput);
+    // Before the files with undecodable bytes: a file that was not read has none. The
+    // paths a walk or a glob could not read hold files that were never counted either.
+    let mut file_diagnostics = file_diagnostics.into_inner().unwrap();
+    file_diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+    let file_errors: Vec<&Diagnostic> = diagnostics
+        .iter()
+        .chain(&file_diagnostics)
+        .filter(|d| {
+            matches!(
+                d.code,
+                DiagnosticCode::UnreadableFile
+                    | DiagnosticCode::UnreadablePath
+                    | DiagnosticCode::GlobError
+            )
+        })
+        .collect();
+    if strict && !file_errors.is_empty() {
+        return Err(file_errors_error(&file_errors));
+    }
+    diagnostics.append(&mut file_diagnostics);
 
-        let files_per_sec = results.len() as f64 / processing_time.as_secs_f64();
-        metrics_logger.log_metric("files_per_second", files_per_sec);
+    let mut invalid_files = invalid_files.into_inner().unwrap();
+    invalid_files.sort_by(|a, b| a.path.cmp(&b.path));
+    metrics_logger.log_metric("invalid_files", invalid_files.len() as f64);
+    if args.on_invalid_utf8 == InvalidBytes::Error && !invalid_files.is_empty() {
+        return Err(invalid_bytes_error(&invalid_files));
     }
 
     // REQ-6.4, REQ-6.5, REQ-6.6: Create report (aggregazione risultati)
     let report_creation_start = Instant::now();
-    let mut report = Report::new(results, unsupported_files);
+    let totals_only = keep_files && matches!(counted, Counted::Totals(_));
+    if totals_only {
+        tracing::warn!(
+            "memory use exceeded --max-memory; per-file results were dropped and the \
+             report only has the language and global totals"
+        );
+        metrics_logger.log_metric("max_memory_exceeded", 1.0);
+    }
+    let mut report = match counted {
+        Counted::Files(file_results) => {
+            let (results, unsupported_files): (Vec<_>, Vec<_>) =
+                file_results.into_iter().partition(|res| res.is_ok());
+            let results: Vec<FileStats> = results.into_iter().map(|r| r.unwrap()).collect();
+            let unsupported_files: Vec<PathBuf> = unsupported_files
+                .into_iter()
+                .map(|e| e.unwrap_err())
+                .collect();
+            Report::new(results, unsupported_files)
+        }
+        Counted::Totals(totals) => totals.into_report(),
+    };
+    // REQ-9.3: Deterministic output, whatever order the threads finished in
+    let mut skipped_files = skipped_files.into_inner().unwrap();
+    skipped_files.sort_by(|a, b| a.path.cmp(&b.path));
+    report.set_skipped(skipped_files, language_skipped);
+    report.set_invalid(invalid_files);
+    // Marked, so that validating, comparing or merging the report does not take the
+    // missing files as removed
+    if totals_only {
+        diagnostics.push(Diagnostic {
+            code: DiagnosticCode::TotalsOnly,
+            path: None,
+            message: "per-file results dropped by --max-memory".to_string(),
+        });
+    }
+    metrics_logger.log_metric("diagnostics", diagnostics.len() as f64);
+    report.diagnostics = diagnostics;
     metrics_logger.log_metric(
         "report_creation_time",
         report_creation_start.elapsed().as_secs_f64(),
     );
 
+    // Log processing statistics
+    let summary = &report.summary;
+    metrics_logger.log_metric("files_processed_successfully", summary.total_files as f64);
+    metrics_logger.log_metric("total_lines_processed", summary.total_lines as f64);
+    metrics_logger.log_metric("logical_lines_processed", summary.logical_lines as f64);
+    metrics_logger.log_metric("comment_lines_processed", summary.comment_lines as f64);
+    metrics_logger.log_metric("empty_lines_processed", summary.empty_lines as f64);
+
+    if processing_time.as_secs_f64() > 0.0 {
+        let throughput = summary.total_lines as f64 / processing_time.as_secs_f64();
+        metrics_logger.log_metric("overall_throughput_lines_per_sec", throughput);
+
+        let files_per_sec = summary.total_files as f64 / processing_time.as_secs_f64();
+        metrics_logger.log_metric("files_per_second", files_per_sec);
+    }
+
+    if let Some(style) = args.path_style {
+        report.restyle_paths(style);
+    }
+    if let Some(depth) = args.by_dir {
+        report.group_by_directory(depth as usize);
+    }
+
     // REQ-6.9: Add checksum if requested (opzionale)
     if args.checksum {
         let checksum_start = Instant::now();
@@ -221,31 +671,44 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
 
     // REQ-5.1, REQ-5.2, REQ-5.3: Console output (tabella, dettagli, unsupported)
     let console_start = Instant::now();
-    let console = ConsoleOutput::new(args.sort, args.details);
-    console.display_summary(&report)?;
+    let console = ConsoleOutput::new(
+        args.sort,
+        FileRows::from_flags(args.details, args.top, args.show_files),
+    )
+    .with_tree(args.tree.map(|depth| depth as usize))
+    .with_languages(args.lang.clone())
+    .with_paths(args.path_style)
+    .with_columns(args.columns.clone())
+    .with_unsupported(args.details || args.show_unsupported);
+    match verbosity {
+        Verbosity::Normal => console.display_summary(&report)?,
+        Verbosity::Quiet => console.display_compact(&report),
+        Verbosity::Silent => {}
+    }
     metrics_logger.log_metric("console_output_time", console_start.elapsed().as_secs_f64());
 
-    // REQ-6.8: Export report if requested (json/xml/csv)
-    if let Some(format) = args.format {
-        // Determine output path: explicit CLI value or auto-generate using default base name from config
-        let output_path = if let Some(p) = args.output.clone() {
-            p
-        } else {
-            // build default file name: <base>.<ext>
-            let base = &app_config.defaults.output_file;
-            let ext = match format {
-                crate::cli::OutputFormat::Json => "json",
-                crate::cli::OutputFormat::Xml => "xml",
-                crate::cli::OutputFormat::Csv => "csv",
-            };
-            PathBuf::from(format!("{}.{ext}", base))
-        };
-
+    // REQ-6.8: Export report to every requested target (user template or built-in formats)
+    let exporter = ReportExporter::new(args.xml_style).with_elapsed(processing_time);
+    for (target, output_path) in &exports {
         let export_start = Instant::now();
-        let exporter = ReportExporter::new();
-        exporter.export(&report, &output_path, format)?;
+        match target {
+            ExportTarget::Template(template) => {
+                exporter.export_template(&report, template, output_path)?
+            }
+            ExportTarget::Format(format) => exporter.export(&report, output_path, *format)?,
+            ExportTarget::External(external) => {
+                exporter.export_external(&report, external, output_path)?
+            }
+        }
         metrics_logger.log_metric("report_export_time", export_start.elapsed().as_secs_f64());
-        println!("Report saved to: {}", output_path.display());
+        if verbosity == Verbosity::Normal {
+            println!("Report saved to: {}", output_path.display());
+        }
+    }
+
+    // Everything is counted and exported: an interrupted scan no longer needs resuming
+    if let Some((checkpoint, _)) = checkpoint {
+        checkpoint.finish()?;
     }
 
     // REQ-9.7: Log final completion metrics (fine operazione)
@@ -253,10 +716,36 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
     metrics_logger.log_completion(report.summary.total_files, report.summary.total_lines);
     metrics_logger.log_metric("total_operation_time", total_time.as_secs_f64());
 
-    // REQ-9.7: Log memory usage if possible (approximate)
-    let memory_estimate = report.files.len() * std::mem::size_of::<FileStats>()
-        + report.languages.len() * std::mem::size_of::<crate::report::LanguageStats>();
-    metrics_logger.log_metric("memory_usage_estimate_bytes", memory_estimate as f64);
+    // REQ-9.7: Log the resident memory, as reported by the operating system
+    let peak_memory = memory.finish();
+    if peak_memory > 0 {
+        metrics_logger.log_metric("peak_resident_memory_bytes", peak_memory as f64);
+    }
+
+    // Delta against the baseline, after the report itself
+    if let Some(baseline) = &baseline {
+        let comparison_start = Instant::now();
+        compare_to_baseline(
+            baseline,
+            &report,
+            args.baseline_export.as_deref(),
+            verbosity,
+        )?;
+        metrics_logger.log_metric(
+            "baseline_comparison_time",
+            comparison_start.elapsed().as_secs_f64(),
+        );
+    }
+
+    // Machine-readable result on stdout
+    if let Some(print) = args.print {
+        print_result(&report, print)?;
+    }
+
+    // Performance lines are part of the full console output only
+    if verbosity != Verbosity::Normal {
+        return Ok(());
+    }
 
     // REQ-9.7: Output performance: lines/sec (always, regardless of params, human readable)
     let elapsed_secs = total_time.as_secs_f64();
@@ -266,7 +755,9 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
     } else {
         0.0
     };
-    let thread_count = rayon::current_num_threads();
+    let thread_count = tuner
+        .as_ref()
+        .map_or(pool.current_num_threads(), ThreadTuner::threads);
     let perf_str = Formatter::new().with_decimals(2).format(lines_per_sec);
     println!(
         "Performance: {} lines/sec ({} threads)",
@@ -278,6 +769,9 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
         println!("  Total time: {:.2}s", total_time.as_secs_f64());
         println!("  Files processed: {}", report.summary.total_files);
         println!("  Lines processed: {}", report.summary.total_lines);
+        if peak_memory > 0 {
+            println!("  Peak memory: {}", format_bytes(peak_memory));
+        }
         if total_time.as_secs_f64() > 0.0 {
             println!(
                 "  Throughput: {:.0} lines/sec",
@@ -292,8 +786,236 @@ pub fn execute_count(args: CountArgs) -> Result<()> {
     Ok(())
 }
 
+/// Outcome of the counting pass of `count`: every file, or only their totals
+#[cfg(feature = "cli")]
+enum Counted {
+    Files(Vec<std::result::Result<FileStats, PathBuf>>),
+    Totals(ReportTotals),
+}
+
+#[cfg(feature = "cli")]
+impl Counted {
+    fn new(keep_files: bool) -> Self {
+        if keep_files {
+            Counted::Files(Vec::new())
+        } else {
+            Counted::Totals(ReportTotals::default())
+        }
+    }
+
+    /// Add the outcome of a file; with `drop_files` the files kept so far are added to the
+    /// totals and dropped
+    fn add(self, outcome: std::result::Result<FileStats, PathBuf>, drop_files: bool) -> Self {
+        let mut totals = match self {
+            Counted::Files(mut files) if !drop_files => {
+                files.push(outcome);
+                return Counted::Files(files);
+            }
+            counted => counted.into_totals(),
+        };
+        match outcome {
+            Ok(stats) => totals.add_file(&stats),
+            Err(_) => totals.add_unsupported(),
+        }
+        Counted::Totals(totals)
+    }
+
+    fn merge(self, other: Counted) -> Self {
+        match (self, other) {
+            (Counted::Files(mut files), Counted::Files(other)) => {
+                files.extend(other);
+                Counted::Files(files)
+            }
+            (counted, other) => Counted::Totals(counted.into_totals().merge(other.into_totals())),
+        }
+    }
+
+    fn into_totals(self) -> ReportTotals {
+        match self {
+            Counted::Files(files) => {
+                let mut totals = ReportTotals::default();
+                for outcome in files {
+                    match outcome {
+                        Ok(stats) => totals.add_file(&stats),
+                        Err(_) => totals.add_unsupported(),
+                    }
+                }
+                totals
+            }
+            Counted::Totals(totals) => totals,
+        }
+    }
+}
+
+/// Whether a count needs its per-file records (and unsupported paths): to list them, to
+/// export or print the report, or to derive directory, tree, checksum or baseline results
+#[cfg(feature = "cli")]
+fn keeps_files(args: &CountArgs, exports: bool) -> bool {
+    exports
+        || args.print.is_some()
+        || args.details
+        || args.show_files
+        || args.top.is_some()
+        || args.show_unsupported
+        || args.tree.is_some()
+        || args.by_dir.is_some()
+        || args.checksum
+        || args.baseline.is_some()
+}
+
+/// `--on-invalid-utf8 error`: the files that failed the run, the first few by name
+#[cfg(feature = "cli")]
+fn invalid_bytes_error(files: &[InvalidFile]) -> SlocError {
+    const LISTED: usize = 5;
+    let mut listed: Vec<String> = files
+        .iter()
+        .take(LISTED)
+        .map(|file| format!("{} ({} line(s))", file.path.display(), file.invalid_lines))
+        .collect();
+    if files.len() > LISTED {
+        listed.push(format!("and {} more", files.len() - LISTED));
+    }
+    SlocError::Encoding(format!(
+        "{} file(s) have bytes their encoding cannot decode: {}",
+        files.len(),
+        listed.join(", ")
+    ))
+}
+
+/// `--strict`: the files and paths that could not be counted, the first few with their
+/// error
+#[cfg(feature = "cli")]
+fn file_errors_error(errors: &[&Diagnostic]) -> SlocError {
+    const LISTED: usize = 10;
+    let mut summary = format!("{} path(s) could not be counted:", errors.len());
+    for error in errors.iter().take(LISTED) {
+        let path = error.path.as_deref().unwrap_or(Path::new(""));
+        summary.push_str(&format!("\n  - {}: {}", path.display(), error.message));
+    }
+    if errors.len() > LISTED {
+        summary.push_str(&format!("\n  and {} more", errors.len() - LISTED));
+    }
+    SlocError::FilesFailed(summary)
+}
+
+/// What a single export writes: a built-in format, a user template or an external exporter
+#[cfg(feature = "cli")]
+enum ExportTarget {
+    Format(OutputFormat),
+    Template(PathBuf),
+    External(ExternalExporter),
+}
+
+#[cfg(feature = "cli")]
+impl ExportTarget {
+    fn from_format(format: &ExportFormat) -> Result<Self> {
+        Ok(match format {
+            ExportFormat::Builtin(format) => ExportTarget::Format(*format),
+            ExportFormat::External(command) => {
+                ExportTarget::External(ExternalExporter::parse(command)?)
+            }
+        })
+    }
+
+    /// Extension of auto-named output files
+    fn extension(&self) -> String {
+        match self {
+            ExportTarget::Format(format) => format.extension().to_string(),
+            ExportTarget::Template(template) => ReportExporter::template_extension(template),
+            ExportTarget::External(exporter) => exporter.extension(),
+        }
+    }
+}
+
+/// REQ-6.8: Pair the repeated -f/-o values into export targets.
+/// - `-f a -o x -f b -o y`: paired in order
+/// - only `-o`: format inferred from each extension (JSON if unknown)
+/// - only `-f`: file names auto-generated as `<base>.<ext>` (`ext:CMD`: the program name)
+/// - `--template`: a single export to `-o` (or `<base>.<ext>` from the template name)
+#[cfg(feature = "cli")]
+fn resolve_exports(
+    args: &CountArgs,
+    defaults: &DefaultsConfig,
+) -> Result<Vec<(ExportTarget, PathBuf)>> {
+    let auto_path = |ext: &str| PathBuf::from(format!("{}.{}", defaults.output_file, ext));
+
+    let exports = if let Some(template) = &args.template {
+        if !args.format.is_empty() || args.output.len() > 1 {
+            return Err(SlocError::Parse(
+                "--template cannot be combined with --format or multiple --output values"
+                    .to_string(),
+            ));
+        }
+        let target = ExportTarget::Template(template.clone());
+        let path = args
+            .output
+            .first()
+            .cloned()
+            .unwrap_or_else(|| auto_path(&target.extension()));
+        vec![(target, path)]
+    } else if args.output.is_empty() {
+        args.format
+            .iter()
+            .map(|f| {
+                let target = ExportTarget::from_format(f)?;
+                let path = auto_path(&target.extension());
+                Ok((target, path))
+            })
+            .collect::<Result<_>>()?
+    } else if args.format.is_empty() {
+        args.output
+            .iter()
+            .map(|p| {
+                let format = OutputFormat::from_path(p).unwrap_or_else(|| defaults.format());
+                (ExportTarget::Format(format), p.clone())
+            })
+            .collect()
+    } else if args.format.len() == args.output.len() {
+        args.format
+            .iter()
+            .zip(&args.output)
+            .map(|(f, p)| Ok((ExportTarget::from_format(f)?, p.clone())))
+            .collect::<Result<_>>()?
+    } else {
+        return Err(SlocError::Parse(format!(
+            "--format given {} times but --output {} times; pass them in pairs",
+            args.format.len(),
+            args.output.len()
+        )));
+    };
+
+    if exports
+        .iter()
+        .any(|(target, _)| matches!(target, ExportTarget::Format(OutputFormat::Html)))
+    {
+        return Err(SlocError::Parse(
+            "html format is only available for comparisons".to_string(),
+        ));
+    }
+
+    // Two targets writing the same file (e.g. json and cloc-json auto-named) would clobber each other
+    let mut seen = std::collections::HashSet::new();
+    for (_, path) in &exports {
+        if !seen.insert(path) {
+            return Err(SlocError::Parse(format!(
+                "multiple exports would write to {}; pass an explicit --output for each format",
+                path.display()
+            )));
+        }
+    }
+
+    Ok(exports)
+}
+
 /// REQ-2.1, REQ-2.2, REQ-2.3, REQ-2.4: Collect file paths from various sources
-fn collect_paths(args: &CountArgs) -> Result<Vec<PathBuf>> {
+#[cfg(feature = "cli")]
+fn collect_paths(
+    args: &CountArgs,
+    exclude: &[Pattern],
+    keep: &mut dyn FnMut(&walkdir::DirEntry) -> bool,
+    found: &dyn Fn(usize),
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Vec<PathBuf>> {
     let mut paths = Vec::new();
 
     // REQ-2.4: Read from stdin if requested
@@ -305,26 +1027,98 @@ fn collect_paths(args: &CountArgs) -> Result<Vec<PathBuf>> {
             let path = PathBuf::from(line.trim());
             if path.exists() {
                 paths.push(path);
+                found(paths.len());
             } else {
-                eprintln!("Warning: Path does not exist: {}", path.display());
+                tracing::warn!("path does not exist: {}", path.display());
+                diagnostics.push(Diagnostic {
+                    code: DiagnosticCode::MissingStdinPath,
+                    path: Some(path),
+                    message: "path read from standard input does not exist".to_string(),
+                });
             }
         }
     }
 
-    // Process command-line paths
-    for path_str in &args.paths {
+    collect_input_paths_into(
+        &args.paths,
+        args.recursive,
+        exclude,
+        keep,
+        &mut paths,
+        found,
+        diagnostics,
+    )?;
+
+    // REQ-9.3: Ensure deterministic output
+    paths.sort();
+    paths.dedup();
+
+    Ok(paths)
+}
+
+/// REQ-2.1/2.2/2.3: Files named by command-line paths (files, directories, globs);
+/// directories are only traversed when `recursive`
+#[cfg(feature = "fs")]
+pub fn collect_input_paths(inputs: &[String], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let keep = &mut |_: &walkdir::DirEntry| true;
+    collect_input_paths_into(
+        inputs,
+        recursive,
+        &[],
+        keep,
+        &mut paths,
+        &|_| {},
+        &mut Vec::new(),
+    )?;
+    Ok(paths)
+}
+
+/// `collect_input_paths`, appending to `paths` and calling `found` with their number after
+/// each file found. Files matching an `exclude` pattern are left out: below a directory
+/// the patterns apply to the paths relative to it, and excluded directories are not walked;
+/// files named on the command line are always kept. Walks also leave out the entries
+/// `keep` rejects. The paths that could not be read or were left out are warned about and
+/// added to `diagnostics`.
+#[cfg(feature = "fs")]
+fn collect_input_paths_into(
+    inputs: &[String],
+    recursive: bool,
+    exclude: &[Pattern],
+    keep: &mut dyn FnMut(&walkdir::DirEntry) -> bool,
+    paths: &mut Vec<PathBuf>,
+    found: &dyn Fn(usize),
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<()> {
+    for path_str in inputs {
         // REQ-2.2: Handle wildcards
         if path_str.contains('*') || path_str.contains('?') {
             for entry in glob(path_str).map_err(|e| SlocError::Parse(e.to_string()))? {
                 match entry {
+                    Ok(path) if is_excluded(&path, exclude) => {}
                     Ok(path) => {
                         if path.is_file() {
                             paths.push(path);
-                        } else if path.is_dir() && args.recursive {
-                            collect_directory_files(&path, &mut paths)?;
+                            found(paths.len());
+                        } else if path.is_dir() && recursive {
+                            collect_directory_files(
+                                &path,
+                                exclude,
+                                keep,
+                                paths,
+                                found,
+                                diagnostics,
+                            );
                         }
                     }
-                    Err(e) => eprintln!("Warning: Glob error: {}", e),
+                    Err(e) => {
+                        tracing::warn!("glob error: {}", e);
+                        diagnostics.push(Diagnostic {
+                            code: DiagnosticCode::GlobError,
+                            path: Some(e.path().to_path_buf()),
+                            message: e.error().to_string(),
+                        });
+                    }
                 }
             }
         } else {
@@ -337,113 +1131,1125 @@ fn collect_paths(args: &CountArgs) -> Result<Vec<PathBuf>> {
 
             if path.is_file() {
                 paths.push(path);
+                found(paths.len());
             } else if path.is_dir() {
                 // REQ-2.3: Recursive directory traversal
-                if args.recursive {
-                    collect_directory_files(&path, &mut paths)?;
+                if recursive {
+                    collect_directory_files(&path, exclude, keep, paths, found, diagnostics);
                 } else {
-                    eprintln!(
-                        "Warning: {} is a directory. Use -r for recursive traversal.",
+                    tracing::warn!(
+                        "{} is a directory. Use -r for recursive traversal.",
                         path.display()
                     );
+                    diagnostics.push(Diagnostic {
+                        code: DiagnosticCode::DirectoryNotRecursive,
+                        path: Some(path),
+                        message: "directory not traversed without -r".to_string(),
+                    });
                 }
             }
         }
     }
-
-    // REQ-9.3: Ensure deterministic output
-    paths.sort();
-    paths.dedup();
-
-    Ok(paths)
+    Ok(())
 }
 
 /// REQ-2.3: Recursively collect files from directory
-fn collect_directory_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
-    for entry in WalkDir::new(dir).follow_links(true) {
+#[cfg(feature = "fs")]
+fn collect_directory_files(
+    dir: &Path,
+    exclude: &[Pattern],
+    keep: &mut dyn FnMut(&walkdir::DirEntry) -> bool,
+    paths: &mut Vec<PathBuf>,
+    found: &dyn Fn(usize),
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let walk = WalkDir::new(dir).follow_links(true).into_iter();
+    // The directories above an entry were kept, so only the entry itself is matched
+    for entry in walk.filter_entry(|entry| {
+        (exclude.is_empty()
+            || entry
+                .path()
+                .strip_prefix(dir)
+                .is_ok_and(|relative| !exclude.iter().any(|p| p.matches_path(relative))))
+            && keep(entry)
+    }) {
         match entry {
             Ok(entry) => {
                 if entry.file_type().is_file() {
                     paths.push(entry.path().to_path_buf());
+                    found(paths.len());
                 }
             }
-            Err(e) => eprintln!("Warning: Error accessing {}: {}", dir.display(), e),
+            Err(e) => {
+                tracing::warn!("error accessing {}: {}", dir.display(), e);
+                diagnostics.push(Diagnostic {
+                    code: DiagnosticCode::UnreadablePath,
+                    path: Some(e.path().unwrap_or(dir).to_path_buf()),
+                    message: e
+                        .io_error()
+                        .map_or_else(|| e.to_string(), |io| io.to_string()),
+                });
+            }
         }
     }
-    Ok(())
 }
 
-/// REQ-4.1: Count lines in a single file
-fn count_file(
+/// REQ-1.1, REQ-9.4: Count `paths` in parallel into a report; as in `count`, files of an
+/// unknown language and unreadable files are listed as unsupported. Unchanged files are
+/// taken from `cache` when given; every counted file is reported to `progress`.
+#[cfg(feature = "cli")]
+pub(crate) fn count_paths(
+    paths: Vec<PathBuf>,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    cache: Option<&CountCache>,
+    progress: Option<&ProgressTracker>,
+) -> Report {
+    let count = |path: &Path| {
+        let count = || count_file(path, detector, ignore_preprocessor);
+        match cache {
+            Some(cache) => cache.get_or_count(path, count),
+            None => count(),
+        }
+    };
+    let on_file = |path: &Path, stats: Option<&FileStats>| {
+        if let Some(progress) = progress {
+            progress.file_done(path, stats.map_or(0, |stats| stats.total_lines));
+        }
+    };
+    count_paths_with(paths, &count, &on_file, None)
+}
+
+/// Count `paths` in parallel with `count` into a report, calling `on_file` from the
+/// counting threads with each file as soon as it is done: its statistics, or `None` when
+/// it is unsupported. Once `cancel` is set, the files not started yet are skipped.
+#[cfg(feature = "fs")]
+fn count_paths_with(
+    paths: Vec<PathBuf>,
+    count: &(dyn Fn(&Path) -> Result<FileStats> + Sync),
+    on_file: &(dyn Fn(&Path, Option<&FileStats>) + Sync),
+    cancel: Option<&CancelToken>,
+) -> Report {
+    let counted: Vec<_> = paths
+        .into_par_iter()
+        .map(|path| {
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                return None;
+            }
+            let (stats, error) = match count(&path) {
+                Ok(stats) if stats.language != "Unknown" => (Some(stats), None),
+                Ok(_) => (None, None),
+                Err(e) => {
+                    tracing::error!("cannot count {}: {}", path.display(), e);
+                    (None, Some(e.to_string()))
+                }
+            };
+            on_file(&path, stats.as_ref());
+            Some((path, stats, error))
+        })
+        .while_some()
+        .collect();
+
+    let mut files = Vec::new();
+    let mut unsupported = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (path, stats, error) in counted {
+        if let Some(message) = error {
+            diagnostics.push(Diagnostic {
+                code: DiagnosticCode::UnreadableFile,
+                path: Some(path.clone()),
+                message,
+            });
+        }
+        match stats {
+            Some(stats) => files.push(stats),
+            None => unsupported.push(path),
+        }
+    }
+    let mut report = Report::new(files, unsupported);
+    report.diagnostics = diagnostics;
+    report
+}
+
+/// REQ-1.1, REQ-9.4: Count `paths` in parallel into a report with per-language and global
+/// summaries; files of an unknown language and unreadable files are listed as unsupported,
+/// the unreadable ones with their error in `diagnostics`
+#[cfg(feature = "fs")]
+pub fn count_files(
+    paths: Vec<PathBuf>,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+) -> Report {
+    let count = |path: &Path| count_file(path, detector, ignore_preprocessor);
+    count_paths_with(paths, &count, &|_, _| {}, None)
+}
+
+/// REQ-1.1, REQ-9.4: A configured count, for programmatic use without the command line:
+///
+/// ```no_run
+/// use counterlines::Counter;
+///
+/// let report = Counter::builder()
+///     .paths(["src", "tests"])
+///     .recursive(true)
+///     .exclude("**/generated")
+///     .threads(8)
+///     .run()?;
+/// println!("{} logical lines", report.summary.logical_lines);
+/// # Ok::<(), counterlines::SlocError>(())
+/// ```
+#[cfg(feature = "fs")]
+#[derive(Clone)]
+pub struct Counter {
+    paths: Vec<String>,
+    recursive: bool,
+    exclude: Vec<Pattern>,
+    threads: usize,
+    detector: LanguageDetector,
+    ignore_preprocessor: bool,
+    read: ReadStrategy,
+    encoding: TextEncoding,
+    io_limit: Option<Arc<IoLimit>>,
+}
+
+/// Settings of a [`Counter`]; the defaults are those of `count` without options
+#[cfg(feature = "fs")]
+#[derive(Default)]
+pub struct CounterBuilder {
+    paths: Vec<String>,
+    recursive: bool,
+    exclude: Vec<String>,
+    threads: usize,
+    detector: Option<LanguageDetector>,
+    config: Option<PathBuf>,
+    language_override: Vec<(String, String)>,
+    ignore_preprocessor: bool,
+    read: ReadStrategy,
+    encoding: TextEncoding,
+    io_concurrency: usize,
+}
+
+#[cfg(feature = "fs")]
+impl Counter {
+    pub fn builder() -> CounterBuilder {
+        CounterBuilder::default()
+    }
+
+    /// Collect the files and count them; can be run again to count the files as they are now
+    pub fn run(&self) -> Result<Report> {
+        self.run_with(|_, _| {})
+    }
+
+    /// `run`, calling `on_file` with each file as soon as it is counted: its statistics, or
+    /// `None` when it is unsupported. The calls come from the counting threads, in no
+    /// particular order.
+    pub fn run_with(&self, on_file: impl Fn(&Path, Option<&FileStats>) + Sync) -> Result<Report> {
+        let paths = self.collect()?;
+        let pool = self.pool()?;
+        Ok(pool.install(|| count_paths_with(paths, &|path| self.count(path), &on_file, None)))
+    }
+
+    /// Count on a background thread, yielding each file as it is counted; the report is
+    /// available from [`CountStream::report`] once the iteration is over
+    pub fn stream(self) -> Result<CountStream> {
+        let paths = self.collect()?;
+        let pool = self.pool()?;
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let on_file = |path: &Path, stats: Option<&FileStats>| {
+                let result = match stats {
+                    Some(stats) => FileResult::Counted(stats.clone()),
+                    None => FileResult::Unsupported(path.to_path_buf()),
+                };
+                // Nobody is listening any more: the report is still completed
+                let _ = sender.send(result);
+            };
+            pool.install(|| count_paths_with(paths, &|path| self.count(path), &on_file, None))
+        });
+        Ok(CountStream { receiver, handle })
+    }
+
+    /// `run`, stopping early with [`SlocError::Cancelled`] once `cancel` is cancelled: the
+    /// files being counted are finished, the others are skipped
+    pub fn run_cancellable(&self, cancel: &CancelToken) -> Result<Report> {
+        let paths = self.collect()?;
+        let pool = self.pool()?;
+        let report = pool.install(|| {
+            count_paths_with(paths, &|path| self.count(path), &|_, _| {}, Some(cancel))
+        });
+        if cancel.is_cancelled() {
+            return Err(SlocError::Cancelled);
+        }
+        Ok(report)
+    }
+
+    /// `run` for async services: the count runs on the blocking thread pool of the tokio
+    /// runtime instead of one of its workers. Dropping the future cancels the count.
+    #[cfg(feature = "async")]
+    pub async fn run_async(&self) -> Result<Report> {
+        let counter = self.clone();
+        let cancel = CancelToken::new();
+        // Set when the future completes or is dropped
+        let _cancel_on_drop = cancel.drop_guard();
+        let task_cancel = cancel.clone();
+        tokio::task::spawn_blocking(move || counter.run_cancellable(&task_cancel))
+            .await
+            .map_err(|e| match e.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(e) => SlocError::Parse(format!("the count task failed: {}", e)),
+            })?
+    }
+
+    fn count(&self, path: &Path) -> Result<FileStats> {
+        count_file_limited(
+            path,
+            &self.detector,
+            self.ignore_preprocessor,
+            self.read,
+            Decoding {
+                encoding: self.encoding.fixed(),
+                ..Decoding::default()
+            },
+            self.io_limit.as_deref(),
+        )
+    }
+
+    /// The files to count, without the excluded ones
+    fn collect(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = collect_input_paths(&self.paths, self.recursive)?;
+        paths.retain(|path| !self.is_excluded(path));
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn pool(&self) -> Result<rayon::ThreadPool> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+            .map_err(|e| SlocError::Parse(e.to_string()))
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        is_excluded(path, &self.exclude)
+    }
+}
+
+/// A file is excluded when its path, or a directory it is in, matches a pattern
+#[cfg(feature = "fs")]
+fn is_excluded(path: &Path, exclude: &[Pattern]) -> bool {
+    path.ancestors()
+        .any(|ancestor| exclude.iter().any(|p| p.matches_path(ancestor)))
+}
+
+/// Cooperative cancellation of a count ([`Counter::run_cancellable`]); clones share the
+/// same state, so the count can be cancelled from another thread
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+#[cfg(feature = "fs")]
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// A guard that cancels the count when it is dropped
+    pub fn drop_guard(&self) -> CancelOnDrop {
+        CancelOnDrop(self.clone())
+    }
+}
+
+/// Cancels its [`CancelToken`] when dropped ([`CancelToken::drop_guard`])
+#[cfg(feature = "fs")]
+pub struct CancelOnDrop(CancelToken);
+
+#[cfg(feature = "fs")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// One file of a count, as yielded by [`CountStream`]
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub enum FileResult {
+    Counted(FileStats),
+    /// Of an unknown language, or unreadable
+    Unsupported(PathBuf),
+}
+
+/// The files of a count running in the background ([`Counter::stream`]), in the order they
+/// are counted
+#[cfg(feature = "fs")]
+pub struct CountStream {
+    receiver: std::sync::mpsc::Receiver<FileResult>,
+    handle: std::thread::JoinHandle<Report>,
+}
+
+#[cfg(feature = "fs")]
+impl Iterator for CountStream {
+    type Item = FileResult;
+
+    fn next(&mut self) -> Option<FileResult> {
+        self.receiver.recv().ok()
+    }
+}
+
+#[cfg(feature = "fs")]
+impl CountStream {
+    /// The report of the count, waiting for it to finish; files not iterated yet are
+    /// still part of it
+    pub fn report(self) -> Report {
+        self.handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl CounterBuilder {
+    /// Files, directories or glob patterns to count, as given to `count`
+    pub fn paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.paths.extend(
+            paths
+                .into_iter()
+                .map(|path| path.as_ref().to_string_lossy().into_owned()),
+        );
+        self
+    }
+
+    /// One more file, directory or glob pattern to count
+    pub fn path(self, path: impl AsRef<Path>) -> Self {
+        self.paths([path])
+    }
+
+    /// Descend into directories (`-r`)
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Skip the files matching a glob pattern, or inside a directory matching it
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Size of the thread pool of the count; 0 (the default) uses one thread per CPU
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Language definitions to load (`--config`)
+    pub fn config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config = Some(path.into());
+        self
+    }
+
+    /// Count files with the extension `ext` as `language` (`--language-override`)
+    pub fn language_override(
+        mut self,
+        ext: impl Into<String>,
+        language: impl Into<String>,
+    ) -> Self {
+        self.language_override.push((ext.into(), language.into()));
+        self
+    }
+
+    /// A detector already set up, instead of the built-in languages
+    pub fn detector(mut self, detector: LanguageDetector) -> Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    /// Count preprocessor directives as comments (`--ignore-preprocessor`)
+    pub fn ignore_preprocessor(mut self, ignore: bool) -> Self {
+        self.ignore_preprocessor = ignore;
+        self
+    }
+
+    /// How files are read (`--read`); `ReadStrategy::Stream` by default
+    pub fn read(mut self, strategy: ReadStrategy) -> Self {
+        self.read = strategy;
+        self
+    }
+
+    /// Encoding of the counted files (`--encoding`); detected for each file by default
+    pub fn encoding(mut self, encoding: TextEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Most files read at the same time, whatever the number of threads
+    /// (`--io-concurrency`); 0, the default, does not limit them
+    pub fn io_concurrency(mut self, files: usize) -> Self {
+        self.io_concurrency = files;
+        self
+    }
+
+    /// Load the languages and check the exclude patterns
+    pub fn build(self) -> Result<Counter> {
+        if self.paths.is_empty() {
+            return Err(SlocError::Parse("no paths to count".to_string()));
+        }
+        let mut detector = self.detector.unwrap_or_default();
+        if let Some(config_path) = &self.config {
+            detector.load_from_config(config_path)?;
+        }
+        for (ext, lang) in self.language_override {
+            detector.add_override(ext, lang);
+        }
+        let exclude = self
+            .exclude
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern).map_err(|e| {
+                    SlocError::Parse(format!("invalid exclude pattern '{}': {}", pattern, e))
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Counter {
+            paths: self.paths,
+            recursive: self.recursive,
+            exclude,
+            threads: self.threads,
+            detector,
+            ignore_preprocessor: self.ignore_preprocessor,
+            read: self.read,
+            encoding: self.encoding,
+            io_limit: IoLimit::new(self.io_concurrency).map(Arc::new),
+        })
+    }
+
+    /// Build the counter and run it once
+    pub fn run(self) -> Result<Report> {
+        self.build()?.run()
+    }
+
+    /// Build the counter and stream its files ([`Counter::stream`])
+    pub fn stream(self) -> Result<CountStream> {
+        self.build()?.stream()
+    }
+
+    /// Build the counter and run it once, asynchronously ([`Counter::run_async`])
+    #[cfg(feature = "async")]
+    pub async fn run_async(self) -> Result<Report> {
+        self.build()?.run_async().await
+    }
+}
+
+/// REQ-4.1: Count lines in a single file; a file of an unknown language has the language
+/// `Unknown` and every non-empty line counted as logical
+#[cfg(feature = "fs")]
+pub fn count_file(
+    path: &Path,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+) -> Result<FileStats> {
+    count_reader(path, File::open(path)?, detector, ignore_preprocessor)
+}
+
+/// Files from this size on are mapped by `ReadStrategy::Mmap`; mapping costs more than
+/// reading a small file
+#[cfg(feature = "fs")]
+const MMAP_MIN_SIZE: u64 = 64 * 1024;
+
+/// Files from this size on are counted in parts on several threads, whatever the read
+/// strategy, so one huge file does not leave the other threads idle
+#[cfg(feature = "fs")]
+const SPLIT_MIN_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Size of the parts of a split file; each part is extended to the end of its last line
+#[cfg(feature = "fs")]
+const SPLIT_PART_SIZE: usize = 4 * 1024 * 1024;
+
+/// How a file is read for counting (`count --read`)
+#[cfg(feature = "fs")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReadStrategy {
+    /// Classify while reading through a buffer
+    #[default]
+    Stream,
+    /// Read the whole file into memory, then classify
+    Whole,
+    /// Map files of 64 KiB or more into memory (smaller ones are read whole), then classify
+    Mmap,
+}
+
+#[cfg(feature = "fs")]
+impl ReadStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReadStrategy::Stream => "stream",
+            ReadStrategy::Whole => "whole",
+            ReadStrategy::Mmap => "mmap",
+        }
+    }
+}
+
+/// REQ-4.1: Count lines in a single file, read with `strategy`
+#[cfg(feature = "fs")]
+pub fn count_file_with(
+    path: &Path,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    strategy: ReadStrategy,
+) -> Result<FileStats> {
+    count_file_as(
+        path,
+        detector,
+        ignore_preprocessor,
+        strategy,
+        TextEncoding::Auto,
+    )
+}
+
+/// REQ-4.1, REQ-9.2: Count lines in a single file, read with `strategy` and decoded as
+/// `encoding` says
+#[cfg(feature = "fs")]
+pub fn count_file_as(
+    path: &Path,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    strategy: ReadStrategy,
+    encoding: TextEncoding,
+) -> Result<FileStats> {
+    let decoding = Decoding {
+        encoding: encoding.fixed(),
+        ..Decoding::default()
+    };
+    count_file_decoded(path, detector, ignore_preprocessor, strategy, decoding)
+}
+
+/// `count_file_as`, with lines with undecodable bytes left out when `decoding` says so
+#[cfg(feature = "fs")]
+fn count_file_decoded(
+    path: &Path,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    strategy: ReadStrategy,
+    decoding: Decoding,
+) -> Result<FileStats> {
+    match strategy {
+        ReadStrategy::Stream => {
+            let file = File::open(path)?;
+            // Files to split are mapped: the parts are read in parallel
+            if file.metadata()?.len() >= SPLIT_MIN_SIZE {
+                return count_mapped(path, &file, detector, ignore_preprocessor, decoding);
+            }
+            let reader = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+            count_buffered(path, reader, detector, ignore_preprocessor, decoding)
+        }
+        ReadStrategy::Whole => {
+            let content = std::fs::read(path)?;
+            count_slice(path, &content, detector, ignore_preprocessor, decoding)
+        }
+        ReadStrategy::Mmap => {
+            let file = File::open(path)?;
+            if file.metadata()?.len() < MMAP_MIN_SIZE {
+                let strategy = ReadStrategy::Whole;
+                return count_file_decoded(path, detector, ignore_preprocessor, strategy, decoding);
+            }
+            count_mapped(path, &file, detector, ignore_preprocessor, decoding)
+        }
+    }
+}
+
+/// `count_file_decoded`, holding a permit of `io_limit` while the file is read: for
+/// `ReadStrategy::Whole` only while it is loaded, for the other strategies, whose reads are
+/// interleaved with the classification, until it is counted
+#[cfg(feature = "fs")]
+pub(crate) fn count_file_limited(
     path: &Path,
-    detector: &Arc<LanguageDetector>,
+    detector: &LanguageDetector,
     ignore_preprocessor: bool,
+    strategy: ReadStrategy,
+    decoding: Decoding,
+    io_limit: Option<&IoLimit>,
+) -> Result<FileStats> {
+    let Some(io_limit) = io_limit else {
+        return count_file_decoded(path, detector, ignore_preprocessor, strategy, decoding);
+    };
+    let permit = io_limit.acquire();
+    if strategy == ReadStrategy::Whole {
+        let content = std::fs::read(path)?;
+        drop(permit);
+        return count_slice(path, &content, detector, ignore_preprocessor, decoding);
+    }
+    count_file_decoded(path, detector, ignore_preprocessor, strategy, decoding)
+}
+
+/// Bounds the number of files read at the same time apart from the number of counting
+/// threads: network filesystems and spinning disks slow down under as many concurrent reads
+/// as there are cores. Threads beyond the limit wait for a permit.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub(crate) struct IoLimit {
+    available: Mutex<usize>,
+    returned: Condvar,
+}
+
+#[cfg(feature = "fs")]
+impl IoLimit {
+    /// A limit of `files` reads at once; `None` for 0, no limit
+    pub(crate) fn new(files: usize) -> Option<Self> {
+        (files > 0).then(|| IoLimit {
+            available: Mutex::new(files),
+            returned: Condvar::new(),
+        })
+    }
+
+    fn acquire(&self) -> IoPermit<'_> {
+        if READING.get() {
+            return IoPermit(None);
+        }
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.returned.wait(available).unwrap();
+        }
+        *available -= 1;
+        READING.set(true);
+        IoPermit(Some(self))
+    }
+}
+
+#[cfg(feature = "fs")]
+thread_local! {
+    /// Whether the thread holds an `IoPermit`: while a file is counted in parallel parts, its
+    /// thread may take another file from the pool, which must not wait for the permit the
+    /// thread already holds
+    static READING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// A read allowed by an `IoLimit`, until dropped; `None` for a file taken by a thread
+/// already holding a permit
+#[cfg(feature = "fs")]
+struct IoPermit<'a>(Option<&'a IoLimit>);
+
+#[cfg(feature = "fs")]
+impl Drop for IoPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(limit) = self.0 {
+            READING.set(false);
+            *limit.available.lock().unwrap() += 1;
+            limit.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+fn count_mapped(
+    path: &Path,
+    file: &File,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    decoding: Decoding,
+) -> Result<FileStats> {
+    // SAFETY: the map is only read. A file truncated by another process while it is
+    // counted makes the read fault, as it would for any memory-mapped reader.
+    let map = unsafe { memmap2::Mmap::map(file)? };
+    count_slice(path, &map, detector, ignore_preprocessor, decoding)
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Suffix of the language of a minified or generated file, e.g. `JavaScript (minified)`:
+/// such files are counted as a language of their own
+pub const MINIFIED_SUFFIX: &str = " (minified)";
+
+/// Bytes sampled at the start of a file to tell whether it is minified
+const MINIFIED_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Files with a shorter sample are never taken as minified
+const MINIFIED_MIN_SIZE: usize = 8 * 1024;
+
+/// Average line length, in bytes, of the sample of a minified file
+const MINIFIED_LINE_LENGTH: usize = 1000;
+
+/// Buffer of `count_reader`: lines are searched a buffer at a time
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// REQ-4.1: Count lines of `path` read from `input` (a file, or a blob from git); `path`
+/// only selects the language. The encoding of the input is detected.
+pub fn count_reader(
+    path: &Path,
+    input: impl Read,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+) -> Result<FileStats> {
+    let reader = BufReader::with_capacity(READ_BUFFER_SIZE, input);
+    let decoding = Decoding::default();
+    count_buffered(path, reader, detector, ignore_preprocessor, decoding)
+}
+
+/// `count_reader` on buffered input; a byte slice is its own buffer, so files read whole or
+/// mapped are classified in place
+fn count_buffered(
+    path: &Path,
+    mut reader: impl BufRead,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    decoding: Decoding,
+) -> Result<FileStats> {
+    // REQ-9.2: Input in another encoding than UTF-8 is counted once decoded to UTF-8
+    let skip_invalid = decoding.skip_invalid_lines;
+    let encoding = encoding::resolve(decoding.encoding, reader.fill_buf()?);
+    if encoding != UTF_8 {
+        let decoded = Decoder::new(reader, encoding);
+        let mut stats = count_utf8(path, decoded, detector, ignore_preprocessor, skip_invalid)?;
+        stats.encoding = Some(encoding.name().to_string());
+        return Ok(stats);
+    }
+    count_utf8(path, reader, detector, ignore_preprocessor, skip_invalid)
+}
+
+/// `count_buffered` on UTF-8 input
+fn count_utf8(
+    path: &Path,
+    mut reader: impl BufRead,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    skip_invalid: bool,
 ) -> Result<FileStats> {
     // REQ-3.2: Detect language
     let language = detector.detect(path);
-    let language_name = language
-        .map(|l| l.name.clone())
-        .unwrap_or_else(|| "Unknown".to_string());
-
-    // REQ-9.2: Handle different encodings
-    let file = File::open(path)?;
-    let reader = DecodeReaderBytesBuilder::new()
-        .encoding(Some(encoding_rs::UTF_8))
-        .build(file);
-    let reader = BufReader::new(reader);
-
-    let mut total_lines = 0;
-    let mut logical_lines = 0;
-    let mut comment_lines = 0;
-    let mut empty_lines = 0;
-
-    if let Some(lang) = language {
-        let parser = CommentParser::new(lang.clone(), ignore_preprocessor);
-        let mut in_multiline = false;
-        let mut depth = 0;
-
-        for line in reader.lines() {
-            let line = line?;
-            total_lines += 1;
-
-            // REQ-4.2, REQ-4.3: Handle multi-line comments
-            if parser.is_in_multiline_comment(&line, &mut in_multiline, &mut depth) {
-                // Line is part of a multi-line comment
-                let trimmed = line.trim();
-                if trimmed.is_empty() {
-                    empty_lines += 1;
-                } else {
-                    comment_lines += 1;
-                }
-            } else {
-                // REQ-4.4: Parse line type
-                match parser.parse_line(&line) {
-                    LineType::Empty => empty_lines += 1,
-                    LineType::Comment => comment_lines += 1,
-                    LineType::Logical | LineType::Mixed => logical_lines += 1,
-                }
-            }
+
+    // REQ-9.2: UTF-8, with or without a byte order mark. Lines are classified as bytes, so
+    // invalid sequences are not replaced but cannot be mistaken for comment markers or
+    // whitespace either; the lines holding them are counted apart.
+    if reader.fill_buf()?.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+
+    // Minified files have a few huge lines: they are only split into lines, not classified
+    // nor checked for invalid bytes
+    if language.is_some() && is_minified(reader.fill_buf()?) {
+        let mut stats = count_plain(reader)?.into_stats(path, language);
+        stats.language.push_str(MINIFIED_SUFFIX);
+        return Ok(stats);
+    }
+
+    // REQ-4.2, REQ-4.3, REQ-4.4: Classify each line (unknown languages: non-empty = logical)
+    let mut classifier = LineClassifier::new(language, ignore_preprocessor);
+    let counts = count_lines(reader, &mut classifier, skip_invalid)?;
+    Ok(counts.into_stats(path, language))
+}
+
+/// Line counts of a file or of a part of it
+#[derive(Debug, Clone, Copy, Default)]
+struct LineCounts {
+    total: usize,
+    logical: usize,
+    comment: usize,
+    empty: usize,
+    /// Lines with invalid UTF-8, counted or not
+    invalid: usize,
+}
+
+impl LineCounts {
+    #[cfg(feature = "fs")]
+    fn add(&mut self, other: LineCounts) {
+        self.total += other.total;
+        self.logical += other.logical;
+        self.comment += other.comment;
+        self.empty += other.empty;
+        self.invalid += other.invalid;
+    }
+
+    fn into_stats(self, path: &Path, language: Option<&Language>) -> FileStats {
+        FileStats {
+            path: path.to_path_buf(),
+            language: language
+                .map(|l| l.name.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            total_lines: self.total,
+            logical_lines: self.logical,
+            comment_lines: self.comment,
+            empty_lines: self.empty,
+            metrics: BTreeMap::new(),
+            encoding: None,
+            invalid_lines: self.invalid,
         }
-    } else {
-        // Unknown language - count non-empty lines as logical
-        for line in reader.lines() {
-            let line = line?;
-            total_lines += 1;
+    }
+}
 
-            if line.trim().is_empty() {
-                empty_lines += 1;
-            } else {
-                logical_lines += 1;
+/// Classify the lines of `reader` with `classifier`, which keeps the comment state reached
+/// at the end; lines with invalid UTF-8 are not counted when `skip_invalid`
+fn count_lines(
+    mut reader: impl BufRead,
+    classifier: &mut LineClassifier,
+    skip_invalid: bool,
+) -> Result<LineCounts> {
+    let mut counts = LineCounts::default();
+    let mut classify = |line: &[u8]| {
+        // A line left out is still classified, for the comment state of the next ones
+        let kind = classifier.classify_bytes(line);
+        if std::str::from_utf8(line).is_err() {
+            counts.invalid += 1;
+            if skip_invalid {
+                return;
             }
         }
+        counts.total += 1;
+        match kind {
+            LineType::Empty => counts.empty += 1,
+            LineType::Comment => counts.comment += 1,
+            LineType::Logical | LineType::Mixed => counts.logical += 1,
+        }
+    };
+    // Lines are found with a vectorized search for newlines and classified in the buffer;
+    // only a line split across two buffers is copied
+    let mut split_line = Vec::new();
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let mut start = 0;
+        for end in memchr::memchr_iter(b'\n', buffer) {
+            let line = if split_line.is_empty() {
+                &buffer[start..end]
+            } else {
+                split_line.extend_from_slice(&buffer[start..end]);
+                split_line.as_slice()
+            };
+            // Line endings as `BufRead::lines` strips them: "\n" or "\r\n"
+            classify(line.strip_suffix(b"\r").unwrap_or(line));
+            split_line.clear();
+            start = end + 1;
+        }
+        split_line.extend_from_slice(&buffer[start..]);
+        let consumed = buffer.len();
+        reader.consume(consumed);
+    }
+    // The last line, without a line ending
+    if !split_line.is_empty() {
+        classify(&split_line);
+    }
+    Ok(counts)
+}
+
+/// Whether the start of a file has lines as long as those of minified code (JavaScript or
+/// CSS bundles, generated one-line JSON)
+fn is_minified(start: &[u8]) -> bool {
+    let sample = &start[..start.len().min(MINIFIED_SAMPLE_SIZE)];
+    if sample.len() < MINIFIED_MIN_SIZE {
+        return false;
+    }
+    let lines = memchr::memchr_iter(b'\n', sample).count() + 1;
+    sample.len() / lines >= MINIFIED_LINE_LENGTH
+}
+
+/// Count the lines of `reader` without classifying them: each one is empty or logical. No
+/// line is copied, however long.
+fn count_plain(mut reader: impl BufRead) -> Result<LineCounts> {
+    let mut counts = LineCounts::default();
+    let mut count = |blank: bool| {
+        counts.total += 1;
+        if blank {
+            counts.empty += 1;
+        } else {
+            counts.logical += 1;
+        }
+    };
+    let is_blank = |bytes: &[u8]| bytes.iter().all(u8::is_ascii_whitespace);
+    // State of the current line, which may span several buffers
+    let mut started = false;
+    let mut blank = true;
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let mut start = 0;
+        for end in memchr::memchr_iter(b'\n', buffer) {
+            count(blank && is_blank(&buffer[start..end]));
+            started = false;
+            blank = true;
+            start = end + 1;
+        }
+        if start < buffer.len() {
+            started = true;
+            blank = blank && is_blank(&buffer[start..]);
+        }
+        let consumed = buffer.len();
+        reader.consume(consumed);
+    }
+    // The last line, without a line ending
+    if started {
+        count(blank);
+    }
+    Ok(counts)
+}
+
+/// Count a file in memory, in parts on several threads from `SPLIT_MIN_SIZE` on
+#[cfg(feature = "fs")]
+fn count_slice(
+    path: &Path,
+    content: &[u8],
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    decoding: Decoding,
+) -> Result<FileStats> {
+    let split = (SPLIT_MIN_SIZE, SPLIT_PART_SIZE);
+    count_slice_split(
+        path,
+        content,
+        detector,
+        ignore_preprocessor,
+        decoding,
+        split,
+    )
+}
+
+/// `count_slice` with the minimum size of a split file and the size of its parts given
+#[cfg(feature = "fs")]
+fn count_slice_split(
+    path: &Path,
+    content: &[u8],
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+    decoding: Decoding,
+    (min_size, part_size): (u64, usize),
+) -> Result<FileStats> {
+    let language = detector.detect(path);
+    // Minified files are not classified: there is nothing to split. Files in another
+    // encoding than UTF-8 are decoded as they are counted.
+    if (content.len() as u64) < min_size
+        || language.is_some() && is_minified(content)
+        || encoding::resolve(decoding.encoding, content) != UTF_8
+    {
+        return count_buffered(path, content, detector, ignore_preprocessor, decoding);
+    }
+
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+
+    // Parts of about `part_size` bytes, each ending after a line break
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < content.len() {
+        let end = (start + part_size).min(content.len());
+        let end = memchr::memchr(b'\n', &content[end..]).map_or(content.len(), |pos| end + pos + 1);
+        parts.push(&content[start..end]);
+        start = end;
+    }
+
+    let count_part = |part: &[u8], state: CommentState| {
+        let mut classifier = LineClassifier::new(language, ignore_preprocessor);
+        classifier.set_state(state);
+        let counts = count_lines(part, &mut classifier, decoding.skip_invalid_lines)?;
+        Ok::<_, SlocError>((counts, classifier.state()))
+    };
+    // REQ-4.3: Each part is counted as if it started outside comments, as nearly every part
+    // of a source file does. Only a part that actually starts inside a comment, as found
+    // from the end state of the part before, is counted again, in order.
+    let guesses: Vec<_> = parts
+        .par_iter()
+        .map(|part| count_part(part, CommentState::default()))
+        .collect();
+    let mut counts = LineCounts::default();
+    let mut state = CommentState::default();
+    for (part, guess) in parts.iter().zip(guesses) {
+        let (part_counts, end_state) = if state == CommentState::default() {
+            guess?
+        } else {
+            count_part(part, state)?
+        };
+        counts.add(part_counts);
+        state = end_state;
+    }
+    Ok(counts.into_stats(path, language))
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+
+    const RUST: &str = "\
+fn main() {
+    /* outer
+       /* nested
+          /* deeper */
+       */
+       still a comment
+    */
+    let a = \"/* opens in a string\";
+    let b = 1; // \"*/\"
+    /* one /* two */ one */ let c = 2;
+
+    let d = \"text\"; /* trailing
+    comment */ let e = 3;
+    /*
+     * doc
+     */
+}
+";
+
+    const C: &str = "\
+int main(void) {
+    /* a /* not nested
+       still a comment */
+    char *s = \"/* in a string\";
+    int x = 1; /* trailing
+       comment */
+    /* one */ int y = 2; /* two
+    */
+
+    const char *t = \"*/ also in a string\";
+    return 0;
+}
+";
+
+    /// Counts of `content` split at every possible part size, against the unsplit count
+    fn assert_split_matches(name: &str, content: &str) {
+        let detector = LanguageDetector::new();
+        let path = Path::new(name);
+        let expected = count_buffered(
+            path,
+            content.as_bytes(),
+            &detector,
+            false,
+            Decoding::default(),
+        )
+        .unwrap();
+        assert!(expected.comment_lines > 0);
+        for part_size in 1..=content.len() {
+            let split = count_slice_split(
+                path,
+                content.as_bytes(),
+                &detector,
+                false,
+                Decoding::default(),
+                (0, part_size),
+            )
+            .unwrap();
+            assert_eq!(split, expected, "{} in parts of {} bytes", name, part_size);
+        }
     }
 
-    Ok(FileStats {
-        path: path.to_path_buf(),
-        language: language_name,
-        total_lines,
-        logical_lines,
-        comment_lines,
-        empty_lines,
-    })
+    #[test]
+    fn split_counts_equal_buffered_counts_with_nested_comments() {
+        assert_split_matches("a.rs", RUST);
+    }
+
+    #[test]
+    fn split_counts_equal_buffered_counts_with_block_comments() {
+        assert_split_matches("a.c", C);
+    }
 }
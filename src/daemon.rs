@@ -0,0 +1,315 @@
+// daemon.rs - Long-running count service on a Unix socket (`daemon`)
+// Implements: REQ-1.1 (line counting), REQ-7.2 (comparison), REQ-9.4 (parallel processing)
+//
+// The daemon loads the language definitions, the count cache and the thread pool once and
+// answers requests over a Unix domain socket, so editors and scripts that count often don't
+// pay the startup cost on every call. Each connection is served by its own thread; counts
+// run one at a time on the shared pool.
+//
+// The protocol is one JSON object per line in each direction:
+//
+//   {"command": "count", "paths": ["src"], "recursive": true}
+//       -> {"ok": true, "report": {...}}            (the report as `-f json` writes it)
+//   {"command": "compare", "old": "v1.json", "new": "v2.json"}
+//       -> {"ok": true, "comparison": {...}}        (as `compare --print json` prints it)
+//   {"command": "ping"}      -> {"ok": true, "version": "...", "uptime_secs": 12, ...}
+//   {"command": "shutdown"}  -> {"ok": true}, then the daemon exits
+//
+// A failed request is answered with {"ok": false, "error": "..."}. Relative paths are
+// resolved against the daemon's working directory. Named pipes are not supported, so the
+// daemon is only available on Unix.
+
+use crate::cli::DaemonArgs;
+use crate::error::{Result, SlocError};
+
+#[cfg(not(unix))]
+pub fn execute_daemon(_args: DaemonArgs) -> Result<()> {
+    Err(SlocError::Parse(
+        "daemon mode needs Unix domain sockets and is not available on this platform".to_string(),
+    ))
+}
+
+#[cfg(unix)]
+pub use unix::execute_daemon;
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use crate::cache::CountCache;
+    use crate::cli::OutputFormat;
+    use crate::compare::compare_reports;
+    use crate::counter::{collect_input_paths, count_paths};
+    use crate::encoding::Decoding;
+    use crate::language::LanguageDetector;
+    use crate::report::Report;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Value, json};
+    use std::fs::DirBuilder;
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tracing::warn;
+
+    const SOCKET_NAME: &str = "rustedbytes-counterlines.sock";
+
+    /// Default socket: `rustedbytes-counterlines.sock` in the user runtime directory, else
+    /// in `rustedbytes-counterlines-UID` of the temporary directory, which only the user
+    /// may enter so no other user can claim or replace the socket
+    fn default_socket() -> Result<PathBuf> {
+        if let Some(dir) = dirs::runtime_dir() {
+            return Ok(dir.join(SOCKET_NAME));
+        }
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        let dir = std::env::temp_dir().join(format!("rustedbytes-counterlines-{}", uid));
+        match DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+        // The directory may have been made by another user before us
+        let metadata = std::fs::symlink_metadata(&dir)?;
+        if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+            return Err(SlocError::Parse(format!(
+                "{} is not a directory private to the current user; \
+                 set XDG_RUNTIME_DIR or pass --socket",
+                dir.display()
+            )));
+        }
+        Ok(dir.join(SOCKET_NAME))
+    }
+
+    /// A request line
+    #[derive(Debug, Deserialize)]
+    #[serde(tag = "command", rename_all = "lowercase", deny_unknown_fields)]
+    enum DaemonRequest {
+        Count {
+            paths: Vec<String>,
+            #[serde(default)]
+            recursive: bool,
+        },
+        Compare {
+            old: PathBuf,
+            new: PathBuf,
+        },
+        Ping,
+        Shutdown,
+    }
+
+    /// The warm state shared by all connections
+    struct Daemon {
+        socket: PathBuf,
+        detector: Arc<LanguageDetector>,
+        ignore_preprocessor: bool,
+        pool: rayon::ThreadPool,
+        /// Also serializes the counts
+        cache: Mutex<Option<CountCache>>,
+        started: Instant,
+        requests: AtomicUsize,
+    }
+
+    pub fn execute_daemon(args: DaemonArgs) -> Result<()> {
+        let socket = match &args.socket {
+            Some(socket) => socket.clone(),
+            None => default_socket()?,
+        };
+        if let Some(request) = &args.request {
+            return send(&socket, request);
+        }
+
+        let mut detector = LanguageDetector::new();
+        if let Some(config_path) = &args.config {
+            detector.load_from_config(config_path)?;
+        }
+        for (ext, lang) in &args.language_override {
+            detector.add_override(ext.clone(), lang.clone());
+        }
+        let cache = if args.no_cache {
+            None
+        } else {
+            Some(CountCache::open(
+                &args
+                    .cache_dir
+                    .clone()
+                    .unwrap_or_else(CountCache::default_dir),
+                args.config.as_deref(),
+                &[],
+                &args.language_override,
+                args.ignore_preprocessor,
+                Decoding::default(),
+                &[],
+            )?)
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads)
+            .build()
+            .map_err(|e| SlocError::Parse(e.to_string()))?;
+
+        // A socket file nobody answers on is left over from a daemon that was killed; any
+        // other file is not ours to delete
+        if let Ok(metadata) = std::fs::symlink_metadata(&socket) {
+            if !metadata.file_type().is_socket() {
+                return Err(SlocError::Parse(format!(
+                    "{} exists and is not a socket",
+                    socket.display()
+                )));
+            }
+            if UnixStream::connect(&socket).is_ok() {
+                return Err(SlocError::Parse(format!(
+                    "a daemon is already listening on {}",
+                    socket.display()
+                )));
+            }
+            std::fs::remove_file(&socket)?;
+        }
+        let listener = UnixListener::bind(&socket)?;
+        println!(
+            "Listening on {} ({} threads), Ctrl-C to stop",
+            socket.display(),
+            pool.current_num_threads()
+        );
+
+        let daemon = Arc::new(Daemon {
+            socket,
+            detector: Arc::new(detector),
+            ignore_preprocessor: args.ignore_preprocessor,
+            pool,
+            cache: Mutex::new(cache),
+            started: Instant::now(),
+            requests: AtomicUsize::new(0),
+        });
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let daemon = Arc::clone(&daemon);
+                    std::thread::spawn(move || daemon.serve(stream));
+                }
+                Err(e) => warn!("cannot accept a connection: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// `--request`: send one request line and print the response; a failed request exits 1
+    fn send(socket: &Path, request: &str) -> Result<()> {
+        let mut stream = UnixStream::connect(socket).map_err(|e| {
+            SlocError::Parse(format!(
+                "no daemon on {} ({}); start one with `rustedbytes-counterlines daemon`",
+                socket.display(),
+                e
+            ))
+        })?;
+        writeln!(stream, "{}", request.trim())?;
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response)?;
+        print!("{}", response);
+        let ok = serde_json::from_str::<Value>(&response)
+            .ok()
+            .and_then(|value| value.get("ok").and_then(Value::as_bool))
+            .unwrap_or(false);
+        if !ok {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    impl Daemon {
+        /// Answer the requests of one connection until it is closed
+        fn serve(&self, stream: UnixStream) {
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(e) => {
+                    warn!("cannot use a connection: {}", e);
+                    return;
+                }
+            };
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                self.requests.fetch_add(1, Ordering::Relaxed);
+                let request = serde_json::from_str::<DaemonRequest>(&line)
+                    .map_err(|e| SlocError::Parse(format!("invalid request: {}", e)));
+                let shutdown = matches!(request, Ok(DaemonRequest::Shutdown));
+                let response = match request.and_then(|request| self.handle(request)) {
+                    Ok(response) => response,
+                    Err(e) => json!({ "ok": false, "error": e.to_string() }),
+                };
+                if writeln!(writer, "{}", response).is_err() {
+                    return;
+                }
+                if shutdown {
+                    self.shutdown();
+                }
+            }
+        }
+
+        fn handle(&self, request: DaemonRequest) -> Result<Value> {
+            match request {
+                DaemonRequest::Count { paths, recursive } => {
+                    let mut paths = collect_input_paths(&paths, recursive)?;
+                    paths.sort();
+                    paths.dedup();
+                    let mut cache = self.cache.lock().unwrap();
+                    let report = self.pool.install(|| {
+                        count_paths(
+                            paths,
+                            &self.detector,
+                            self.ignore_preprocessor,
+                            cache.as_ref(),
+                            None,
+                        )
+                    });
+                    if let Some(cache) = cache.as_mut()
+                        && let Err(e) = cache.flush()
+                    {
+                        warn!("cannot update the count cache: {}", e);
+                    }
+                    Ok(json!({ "ok": true, "report": to_value(&report)? }))
+                }
+                DaemonRequest::Compare { old, new } => {
+                    let comparison = compare_reports(&load_report(&old)?, &load_report(&new)?);
+                    Ok(json!({ "ok": true, "comparison": to_value(&comparison)? }))
+                }
+                DaemonRequest::Ping => Ok(json!({
+                    "ok": true,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "uptime_secs": self.started.elapsed().as_secs(),
+                    "requests": self.requests.load(Ordering::Relaxed),
+                    "threads": self.pool.current_num_threads(),
+                })),
+                DaemonRequest::Shutdown => Ok(json!({ "ok": true })),
+            }
+        }
+
+        /// Remove the socket and exit, once a running count has finished
+        fn shutdown(&self) -> ! {
+            let _count = self.cache.lock().unwrap();
+            if let Err(e) = std::fs::remove_file(&self.socket) {
+                warn!("cannot remove {}: {}", self.socket.display(), e);
+            }
+            println!(
+                "Shut down after {} request(s)",
+                self.requests.load(Ordering::Relaxed)
+            );
+            std::process::exit(0);
+        }
+    }
+
+    fn to_value(value: &impl Serialize) -> Result<Value> {
+        serde_json::to_value(value).map_err(|e| SlocError::Serialization(e.to_string()))
+    }
+
+    /// A stored report, in the format given by its extension (JSON otherwise)
+    fn load_report(path: &Path) -> Result<Report> {
+        let format = OutputFormat::from_path(path).unwrap_or(OutputFormat::Json);
+        Report::from_file(path, format)
+    }
+}
@@ -0,0 +1,662 @@
+// diff.rs - Line accounting of a unified diff (`count --from-diff`)
+// Implements: REQ-1.1 (line counting), REQ-3.2 (language detection), REQ-4.2-4.5 (comments)
+//
+// The added and removed lines of every hunk are classified with the language of the file,
+// so the logical, comment and empty lines a change adds and removes can be accounted per
+// language from `git diff` alone, without counting the tree before and after. Each side of
+// a hunk is classified with its context lines; a hunk starting inside a block comment
+// whose opening line is not part of the hunk is classified as if it were code. Files
+// without hunks (pure renames, mode changes, binary files) change no lines and are left out.
+
+use crate::cli::{CountArgs, Verbosity};
+use crate::error::{Result, SlocError};
+use crate::language::{LanguageDetector, LineClassifier, LineType};
+use crate::output::{heavy_rule, print_result, print_table, rule};
+use colored::Colorize;
+use num_format::{Locale, ToFormattedString};
+use prettytable::{Cell, Row, Table};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Lines added or removed, by type; mixed lines count as logical
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct LineChanges {
+    pub logical: usize,
+    pub comment: usize,
+    pub empty: usize,
+}
+
+impl LineChanges {
+    fn add(&mut self, kind: LineType) {
+        match kind {
+            LineType::Logical | LineType::Mixed => self.logical += 1,
+            LineType::Comment => self.comment += 1,
+            LineType::Empty => self.empty += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &LineChanges) {
+        self.logical += other.logical;
+        self.comment += other.comment;
+        self.empty += other.empty;
+    }
+
+    pub fn total(&self) -> usize {
+        self.logical + self.comment + self.empty
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+}
+
+impl FileStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileStatus::Added => "added",
+            FileStatus::Deleted => "deleted",
+            FileStatus::Modified => "modified",
+            FileStatus::Renamed => "renamed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// Path before a rename
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub language: String,
+    pub status: FileStatus,
+    pub added: LineChanges,
+    pub removed: LineChanges,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageDiff {
+    pub language: String,
+    pub files: usize,
+    pub added: LineChanges,
+    pub removed: LineChanges,
+}
+
+/// The line accounting of a whole diff
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    pub added: LineChanges,
+    pub removed: LineChanges,
+    pub languages: Vec<LanguageDiff>,
+    pub files: Vec<FileDiff>,
+    /// Changed files of no known language, not accounted
+    pub unsupported: Vec<String>,
+}
+
+/// A file section of the diff: its paths and line changes so far
+struct Section {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    /// Header lines seen: a `---` line starts a section only once
+    headers: u8,
+    /// Hunks seen
+    hunks: usize,
+    added: LineChanges,
+    removed: LineChanges,
+}
+
+/// REQ-8.3: `count --from-diff [FILE]`
+pub fn execute_count_diff(args: &CountArgs, source: &Path) -> Result<()> {
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+
+    let input: Box<dyn Read> = if source.as_os_str() == "-" {
+        Box::new(std::io::stdin())
+    } else {
+        if !source.is_file() {
+            return Err(SlocError::FileNotFound {
+                path: source.to_path_buf(),
+            });
+        }
+        Box::new(std::fs::File::open(source)?)
+    };
+    let report = parse_diff(BufReader::new(input), &detector, args.ignore_preprocessor)?;
+
+    if let Some(print) = args.print {
+        return print_result(&report, print);
+    }
+    match Verbosity::from_flags(args.quiet, args.silent) {
+        Verbosity::Normal => display_diff(&report, args.details || args.show_files),
+        Verbosity::Quiet => println!(
+            "{} files: logical +{} -{}, comment +{} -{}, empty +{} -{}",
+            report.files.len(),
+            report.added.logical,
+            report.removed.logical,
+            report.added.comment,
+            report.removed.comment,
+            report.added.empty,
+            report.removed.empty
+        ),
+        Verbosity::Silent => {}
+    }
+    Ok(())
+}
+
+/// Account the added and removed lines of a unified diff (`git diff`, `diff -u`)
+pub fn parse_diff(
+    input: impl BufRead,
+    detector: &LanguageDetector,
+    ignore_preprocessor: bool,
+) -> Result<DiffReport> {
+    let mut files = Vec::new();
+    let mut unsupported = Vec::new();
+    let mut section: Option<Section> = None;
+    let mut git = false;
+    // Lines left in the current hunk on the old and the new side
+    let (mut old_left, mut new_left) = (0usize, 0usize);
+    let mut old_side = LineClassifier::new(None, ignore_preprocessor);
+    let mut new_side = LineClassifier::new(None, ignore_preprocessor);
+
+    let mut finish = |section: Option<Section>, git: bool| {
+        if let Some(section) = section {
+            close_section(section, git, detector, &mut files, &mut unsupported);
+        }
+    };
+
+    for line in input.lines() {
+        let line = line?;
+        if old_left > 0 || new_left > 0 {
+            match line.chars().next() {
+                Some('+') => {
+                    new_left = new_left.saturating_sub(1);
+                    let kind = new_side.classify(&line[1..]);
+                    if let Some(section) = section.as_mut() {
+                        section.added.add(kind);
+                    }
+                    continue;
+                }
+                Some('-') => {
+                    old_left = old_left.saturating_sub(1);
+                    let kind = old_side.classify(&line[1..]);
+                    if let Some(section) = section.as_mut() {
+                        section.removed.add(kind);
+                    }
+                    continue;
+                }
+                // "\ No newline at end of file"
+                Some('\\') => continue,
+                // Context; some tools strip the leading space of empty context lines
+                Some(' ') | None => {
+                    let text = line.get(1..).unwrap_or_default();
+                    old_side.classify(text);
+                    new_side.classify(text);
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                    continue;
+                }
+                // A truncated hunk: the line is a header again
+                Some(_) => (old_left, new_left) = (0, 0),
+            }
+        }
+
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            finish(section.take(), git);
+            git = true;
+            let (old, new) = git_header_paths(paths);
+            section = Some(Section {
+                old_path: old,
+                new_path: new,
+                headers: 0,
+                hunks: 0,
+                added: LineChanges::default(),
+                removed: LineChanges::default(),
+            });
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            // Plain `diff -u` output has no `diff --git` line between files
+            if section.as_ref().is_none_or(|s| s.headers > 0) {
+                finish(section.take(), git);
+                section = Some(Section {
+                    old_path: None,
+                    new_path: None,
+                    headers: 0,
+                    hunks: 0,
+                    added: LineChanges::default(),
+                    removed: LineChanges::default(),
+                });
+            }
+            if let Some(section) = section.as_mut() {
+                section.old_path = header_path(path, git.then_some("a/"));
+                section.headers += 1;
+            }
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(section) = section.as_mut() {
+                section.new_path = header_path(path, git.then_some("b/"));
+                section.headers += 1;
+            }
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            if let Some(section) = section.as_mut() {
+                section.old_path = Some(path.to_string());
+            }
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            if let Some(section) = section.as_mut() {
+                section.new_path = Some(path.to_string());
+            }
+        } else if let Some(ranges) = line.strip_prefix("@@ ") {
+            let Some((old, new)) = hunk_ranges(ranges) else {
+                return Err(SlocError::Parse(format!("invalid hunk header `{}`", line)));
+            };
+            (old_left, new_left) = (old, new);
+            if let Some(section) = section.as_mut() {
+                section.hunks += 1;
+            }
+            let language = section
+                .as_ref()
+                .and_then(|s| s.new_path.as_ref().or(s.old_path.as_ref()))
+                .and_then(|path| detector.detect(Path::new(path)));
+            old_side = LineClassifier::new(language, ignore_preprocessor);
+            new_side = LineClassifier::new(language, ignore_preprocessor);
+        }
+    }
+    finish(section.take(), git);
+
+    // Per language, most changed first
+    let mut languages: BTreeMap<&str, LanguageDiff> = BTreeMap::new();
+    for file in &files {
+        let entry = languages
+            .entry(file.language.as_str())
+            .or_insert_with(|| LanguageDiff {
+                language: file.language.clone(),
+                files: 0,
+                added: LineChanges::default(),
+                removed: LineChanges::default(),
+            });
+        entry.files += 1;
+        entry.added.merge(&file.added);
+        entry.removed.merge(&file.removed);
+    }
+    let mut languages: Vec<LanguageDiff> = languages.into_values().collect();
+    languages.sort_by_key(|l| std::cmp::Reverse(l.added.total() + l.removed.total()));
+
+    let mut added = LineChanges::default();
+    let mut removed = LineChanges::default();
+    for language in &languages {
+        added.merge(&language.added);
+        removed.merge(&language.removed);
+    }
+    Ok(DiffReport {
+        added,
+        removed,
+        languages,
+        files,
+        unsupported,
+    })
+}
+
+fn close_section(
+    section: Section,
+    git: bool,
+    detector: &LanguageDetector,
+    files: &mut Vec<FileDiff>,
+    unsupported: &mut Vec<String>,
+) {
+    let status = match (&section.old_path, &section.new_path) {
+        (None, Some(_)) => FileStatus::Added,
+        (Some(_), None) => FileStatus::Deleted,
+        (Some(old), Some(new)) if git && old != new => FileStatus::Renamed,
+        _ => FileStatus::Modified,
+    };
+    let Some(path) = section.new_path.clone().or(section.old_path.clone()) else {
+        return;
+    };
+    match detector.detect(Path::new(&path)) {
+        Some(_) if section.hunks == 0 => {}
+        Some(language) => files.push(FileDiff {
+            old_path: (status == FileStatus::Renamed)
+                .then(|| section.old_path.clone())
+                .flatten(),
+            path,
+            language: language.name.clone(),
+            status,
+            added: section.added,
+            removed: section.removed,
+        }),
+        None => unsupported.push(path),
+    }
+}
+
+/// `a/old b/new` of a `diff --git` line (paths with spaces are ambiguous and split in the middle)
+fn git_header_paths(paths: &str) -> (Option<String>, Option<String>) {
+    let strip = |path: &str, prefix: &str| path.strip_prefix(prefix).unwrap_or(path).to_string();
+    match paths.find(" b/") {
+        Some(split) => (
+            Some(strip(&paths[..split], "a/")),
+            Some(strip(&paths[split + 1..], "b/")),
+        ),
+        None => (None, None),
+    }
+}
+
+/// Path of a `---`/`+++` line: `None` for /dev/null; timestamps after a tab are dropped
+fn header_path(path: &str, prefix: Option<&str>) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or(path).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.trim_matches('"');
+    Some(
+        prefix
+            .and_then(|prefix| path.strip_prefix(prefix))
+            .unwrap_or(path)
+            .to_string(),
+    )
+}
+
+/// Line counts of both sides of `-a,b +c,d @@`; a missing count is 1
+fn hunk_ranges(ranges: &str) -> Option<(usize, usize)> {
+    let mut parts = ranges.split_whitespace();
+    let count = |range: &str| -> Option<usize> {
+        match range.split_once(',') {
+            Some((_, count)) => count.parse().ok(),
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    let old = count(parts.next()?.strip_prefix('-')?)?;
+    let new = count(parts.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+fn display_diff(report: &DiffReport, show_files: bool) {
+    println!("\n{}", heavy_rule(80).blue());
+    println!("{}", "Diff Summary".bold().cyan());
+    println!("{}", heavy_rule(80).blue());
+
+    let header = |first: &str, second: &str| {
+        Row::new(
+            [
+                first,
+                second,
+                "+Logical",
+                "-Logical",
+                "+Comment",
+                "-Comment",
+                "+Empty",
+                "-Empty",
+                "Net Logical",
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, h)| Cell::new(h).style_spec(if i == 0 { "b" } else { "br" }))
+            .collect(),
+        )
+    };
+    let counts = |second: String, added: &LineChanges, removed: &LineChanges| {
+        let net = added.logical as i64 - removed.logical as i64;
+        let net = match net {
+            n if n > 0 => format!("+{}", n.to_formatted_string(&Locale::en)).green(),
+            n if n < 0 => format!("-{}", n.abs().to_formatted_string(&Locale::en)).red(),
+            _ => "0".normal(),
+        };
+        vec![
+            Cell::new(&second).style_spec("r"),
+            Cell::new(&added.logical.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&removed.logical.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&added.comment.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&removed.comment.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&added.empty.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&removed.empty.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&net.to_string()).style_spec("r"),
+        ]
+    };
+
+    let mut table = Table::new();
+    table.add_row(header("Language", "Files"));
+    for language in &report.languages {
+        let mut cells = vec![Cell::new(&language.language)];
+        cells.extend(counts(
+            language.files.to_formatted_string(&Locale::en),
+            &language.added,
+            &language.removed,
+        ));
+        table.add_row(Row::new(cells));
+    }
+    let mut cells = vec![Cell::new("Total").style_spec("b")];
+    cells.extend(counts(
+        report.files.len().to_formatted_string(&Locale::en),
+        &report.added,
+        &report.removed,
+    ));
+    table.add_row(Row::new(cells));
+    print_table(&table);
+
+    if show_files && !report.files.is_empty() {
+        println!("\n{}", "Changed Files".bold().green());
+        println!("{}", rule(80).green());
+        let mut table = Table::new();
+        table.add_row(header("File", "Status"));
+        for file in &report.files {
+            let name = match &file.old_path {
+                Some(old) => format!("{} -> {}", old, file.path),
+                None => file.path.clone(),
+            };
+            let mut cells = vec![Cell::new(&name)];
+            cells.extend(counts(
+                file.status.name().to_string(),
+                &file.added,
+                &file.removed,
+            ));
+            table.add_row(Row::new(cells));
+        }
+        print_table(&table);
+    }
+
+    if !report.unsupported.is_empty() {
+        println!(
+            "\n{} changed file(s) of no known language not accounted: {}",
+            report.unsupported.len(),
+            report.unsupported.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(diff: &str) -> DiffReport {
+        parse_diff(diff.as_bytes(), &LanguageDetector::new(), false).unwrap()
+    }
+
+    /// Logical, comment and empty lines
+    fn of(lines: &LineChanges) -> (usize, usize, usize) {
+        (lines.logical, lines.comment, lines.empty)
+    }
+
+    #[test]
+    fn hunk_headers_without_counts_are_one_line() {
+        let report = parse(
+            "\
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1 +1 @@
+-let a = 1;
++// a
+@@ -5 +5,2 @@
+ fn f() {}
++
+",
+        );
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].status, FileStatus::Modified);
+        assert_eq!(of(&report.files[0].added), (0, 1, 1));
+        assert_eq!(of(&report.files[0].removed), (1, 0, 0));
+    }
+
+    #[test]
+    fn no_newline_markers_are_not_lines() {
+        let report = parse(
+            "\
+--- a.rs
++++ a.rs
+@@ -1,2 +1,2 @@
+ fn f() {}
+-let a = 1;
+\\ No newline at end of file
++let a = 2;
+\\ No newline at end of file
+",
+        );
+        assert_eq!(of(&report.files[0].added), (1, 0, 0));
+        assert_eq!(of(&report.files[0].removed), (1, 0, 0));
+    }
+
+    #[test]
+    fn renames_keep_the_old_path() {
+        let report = parse(
+            "\
+diff --git a/old.rs b/new.rs
+similarity index 80%
+rename from old.rs
+rename to new.rs
+--- a/old.rs
++++ b/new.rs
+@@ -1,2 +1,2 @@
+ fn f() {}
+-let a = 1;
++let a = 2;
+",
+        );
+        let file = &report.files[0];
+        assert_eq!(file.status, FileStatus::Renamed);
+        assert_eq!(file.path, "new.rs");
+        assert_eq!(file.old_path.as_deref(), Some("old.rs"));
+        assert_eq!(of(&file.added), (1, 0, 0));
+    }
+
+    #[test]
+    fn pure_renames_change_no_lines() {
+        let report = parse(
+            "\
+diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+diff --git a/b.py b/b.py
+--- a/b.py
++++ b/b.py
+@@ -1 +1,2 @@
+ x = 1
++# note
+",
+        );
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].path, "b.py");
+        assert_eq!(report.languages.len(), 1);
+        assert_eq!(report.languages[0].language, "Python");
+        assert!(report.unsupported.is_empty());
+    }
+
+    #[test]
+    fn binary_files_are_not_accounted() {
+        let report = parse(
+            "\
+diff --git a/logo.png b/logo.png
+index 1234567..89abcde 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/lib.rs b/lib.rs
+new file mode 100644
+index 0000000..1234567
+Binary files /dev/null and b/lib.rs differ
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1 +1 @@
+-let a = 1;
++let a = 2;
+",
+        );
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].path, "a.rs");
+        assert_eq!(report.unsupported, vec!["logo.png".to_string()]);
+    }
+
+    #[test]
+    fn dev_null_sides_are_additions_and_deletions() {
+        let report = parse(
+            "\
+diff --git a/new.rs b/new.rs
+new file mode 100644
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1,3 @@
++// new
++
++fn f() {}
+diff --git a/gone.rs b/gone.rs
+deleted file mode 100644
+--- a/gone.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn g() {}
+-// gone
+",
+        );
+        let (new, gone) = (&report.files[0], &report.files[1]);
+        assert_eq!(
+            (new.path.as_str(), new.status),
+            ("new.rs", FileStatus::Added)
+        );
+        assert_eq!(of(&new.added), (1, 1, 1));
+        assert_eq!(of(&new.removed), (0, 0, 0));
+        assert_eq!(
+            (gone.path.as_str(), gone.status),
+            ("gone.rs", FileStatus::Deleted)
+        );
+        assert_eq!(of(&gone.removed), (1, 1, 0));
+        assert_eq!(of(&gone.added), (0, 0, 0));
+    }
+
+    #[test]
+    fn multi_file_diffs_are_accounted_per_file_and_language() {
+        // `diff -u` output, without `diff --git` lines between the files
+        let report = parse(
+            "\
+--- a/one.rs\t2024-01-01 00:00:00
++++ b/one.rs\t2024-01-02 00:00:00
+@@ -1 +1,3 @@
+ fn a() {}
++fn b() {}
++// c
+--- a/two.rs
++++ b/two.rs
+@@ -3,2 +3,1 @@
+-let x = 1;
+ let y = 2;
+--- a/three.py
++++ b/three.py
+@@ -1 +1 @@
+-x = 1
++x = 2
+",
+        );
+        let paths: Vec<_> = report.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["b/one.rs", "b/two.rs", "b/three.py"]);
+        let rust = &report.languages[0];
+        assert_eq!((rust.language.as_str(), rust.files), ("Rust", 2));
+        assert_eq!(of(&rust.added), (1, 1, 0));
+        assert_eq!(of(&rust.removed), (1, 0, 0));
+        assert_eq!(of(&report.added), (2, 1, 0));
+        assert_eq!(of(&report.removed), (2, 0, 0));
+    }
+}
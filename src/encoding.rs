@@ -0,0 +1,320 @@
+// encoding.rs - Text encoding of the counted files (`count --encoding`)
+// Implements: REQ-9.2 (encoding handling), REQ-4.1 (line counting)
+//
+// Lines are classified as UTF-8 bytes, so a file in another encoding is decoded to UTF-8
+// before it is counted: the comment markers of a UTF-16 file (common with Visual Studio
+// and C#) have a NUL byte after each character and would never be found. The encoding is
+// told by the byte order mark, else by NUL bytes always on the same side of the byte pairs
+// (UTF-16 without a BOM), else by whether the start of the file is valid UTF-8, or valid
+// but for a few stray bytes; the start of any other file is given to chardetng, which guesses among the legacy encodings (Latin-1,
+// Shift_JIS, GBK, ...).
+//
+// Legacy codebases often mix encodings, which the detection can only guess from a short
+// sample: the `[[encodings]]` sections of a configuration set the encoding of the files
+// matching their path patterns instead, and `--encoding` that of every file of a run.
+//
+// Bytes that do not decode are never replaced silently: the lines holding them are
+// counted per file (`FileStats::invalid_lines`), and `--on-invalid-utf8` counts them as
+// they are, leaves them out, or leaves out or fails on their files. Invalid UTF-8 is
+// classified as bytes and found by validating each line; in other encodings, `Decoder`
+// writes a byte that is never valid UTF-8 in place of each undecodable sequence, so that
+// it is found the same way.
+
+use encoding_rs::{DecoderResult, Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use std::io::{self, BufRead, Read};
+
+#[cfg(feature = "fs")]
+use encoding_rs::WINDOWS_1252;
+
+#[cfg(feature = "cli")]
+use crate::config::EncodingConfig;
+#[cfg(feature = "cli")]
+use glob::Pattern;
+#[cfg(feature = "cli")]
+use std::path::Path;
+
+/// Bytes of the start of a file looked at to tell its encoding
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Size of the UTF-8 output buffer of `Decoder`
+const DECODE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Written by `Decoder` in place of an undecodable sequence: never valid in UTF-8, and
+/// neither whitespace nor part of a comment marker
+const UNDECODABLE: u8 = 0xFF;
+
+/// How the encoding of the counted files is chosen (`count --encoding`); a byte order mark
+/// always tells it
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TextEncoding {
+    /// Detect the encoding of each file: byte order mark, UTF-16 without one, UTF-8, or the
+    /// most likely legacy encoding
+    #[default]
+    Auto,
+    /// UTF-8, with or without a byte order mark
+    #[cfg_attr(feature = "cli", value(name = "utf-8"))]
+    Utf8,
+    /// UTF-16 little-endian, with or without a byte order mark
+    #[cfg_attr(feature = "cli", value(name = "utf-16le"))]
+    Utf16Le,
+    /// ISO 8859-1, read as its superset windows-1252
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Auto => "auto",
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Latin1 => "latin1",
+        }
+    }
+
+    /// The encoding it sets, `None` for `Auto`
+    #[cfg(feature = "fs")]
+    pub(crate) fn fixed(self) -> Option<&'static Encoding> {
+        match self {
+            TextEncoding::Auto => None,
+            TextEncoding::Utf8 => Some(UTF_8),
+            TextEncoding::Utf16Le => Some(UTF_16LE),
+            TextEncoding::Latin1 => Some(WINDOWS_1252),
+        }
+    }
+}
+
+/// The encoding of a file starting with `start`: the one of its byte order mark, else
+/// `fixed`, else the detected one
+pub(crate) fn resolve(fixed: Option<&'static Encoding>, start: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(start) {
+        return encoding;
+    }
+    fixed.unwrap_or_else(|| detect(start))
+}
+
+/// How the bytes of a file are read into lines
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Decoding {
+    /// Encoding of the file without a byte order mark, `None` to detect it
+    pub encoding: Option<&'static Encoding>,
+    /// Leave out the lines with bytes that do not decode (`--on-invalid-utf8 skip-line`)
+    pub skip_invalid_lines: bool,
+}
+
+/// `inner` decoded to UTF-8, without its byte order mark; each undecodable sequence becomes
+/// an `UNDECODABLE` byte
+pub(crate) struct Decoder<R> {
+    inner: R,
+    decoder: encoding_rs::Decoder,
+    output: Box<[u8]>,
+    /// Range of `output` not read yet
+    start: usize,
+    end: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Decoder<R> {
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Decoder {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            output: vec![0; DECODE_BUFFER_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.start == self.end && !self.done {
+            let input = self.inner.fill_buf()?;
+            let last = input.is_empty();
+            // The last byte is kept for `UNDECODABLE`
+            let space = self.output.len() - 1;
+            let (result, read, written) = self.decoder.decode_to_utf8_without_replacement(
+                input,
+                &mut self.output[..space],
+                last,
+            );
+            self.inner.consume(read);
+            self.start = 0;
+            self.end = written;
+            match result {
+                DecoderResult::Malformed(..) => {
+                    self.output[written] = UNDECODABLE;
+                    self.end += 1;
+                }
+                DecoderResult::InputEmpty => self.done = last,
+                DecoderResult::OutputFull => {}
+            }
+        }
+        Ok(&self.output[self.start..self.end])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.start = (self.start + amount).min(self.end);
+    }
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+/// The `[[encodings]]` sections of a configuration, compiled; the first section with a
+/// matching pattern sets the encoding of a file
+#[cfg(feature = "cli")]
+#[derive(Debug, Default)]
+pub(crate) struct EncodingRules {
+    rules: Vec<(Vec<Pattern>, &'static Encoding)>,
+}
+
+#[cfg(feature = "cli")]
+impl EncodingRules {
+    pub fn new(sections: &[EncodingConfig]) -> Self {
+        EncodingRules {
+            rules: sections
+                .iter()
+                .map(|section| (section.patterns(), section.encoding()))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The encoding set for the file at `path`, relative to the directory of the
+    /// configuration
+    pub fn encoding_for(&self, path: &Path) -> Option<&'static Encoding> {
+        self.rules
+            .iter()
+            .find(|(patterns, _)| patterns.iter().any(|p| p.matches_path(path)))
+            .map(|(_, encoding)| *encoding)
+    }
+}
+
+/// The most likely encoding of a file starting with `start`, without a byte order mark
+/// (`TextEncoding::Auto`)
+fn detect(start: &[u8]) -> &'static Encoding {
+    let sample = &start[..start.len().min(SAMPLE_SIZE)];
+    if let Some(encoding) = utf16_without_bom(sample) {
+        return encoding;
+    }
+    if is_utf8(sample) || mostly_utf8(sample) {
+        return UTF_8;
+    }
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(sample, false);
+    detector.guess(None, false)
+}
+
+/// UTF-16 text is mostly ASCII, so most of its byte pairs have a NUL byte, on the same side;
+/// binary files have NUL bytes on both sides
+fn utf16_without_bom(sample: &[u8]) -> Option<&'static Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let (mut first, mut second) = (0, 0);
+    for pair in sample.chunks_exact(2) {
+        first += usize::from(pair[0] == 0);
+        second += usize::from(pair[1] == 0);
+    }
+    let mostly = |nul: usize| nul * 2 > pairs;
+    let rarely = |nul: usize| nul * 20 < pairs;
+    if mostly(second) && rarely(first) {
+        Some(UTF_16LE)
+    } else if mostly(first) && rarely(second) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Whether `sample` is UTF-8, up to a sequence cut by its end
+fn is_utf8(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Whether `sample` is UTF-8 with a few stray bytes: its invalid sequences are outnumbered
+/// two to one by valid multi-byte characters, which legacy encodings hardly ever form by
+/// chance, or are at most one per KiB. Such a file is
+/// read as UTF-8, so `--on-invalid-utf8` applies to the lines with the stray bytes instead
+/// of the whole file being taken for a legacy encoding.
+fn mostly_utf8(sample: &[u8]) -> bool {
+    let (mut invalid, mut multibyte) = (0, 0);
+    let mut rest = sample;
+    loop {
+        let (valid, error) = match std::str::from_utf8(rest) {
+            Ok(text) => (text, None),
+            Err(e) => (
+                // The prefix up to the error is valid UTF-8
+                std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap_or_default(),
+                Some(e),
+            ),
+        };
+        multibyte += valid.chars().filter(|c| !c.is_ascii()).count();
+        match error.and_then(|e| e.error_len().map(|len| e.valid_up_to() + len)) {
+            Some(end) => {
+                invalid += 1;
+                rest = &rest[end..];
+            }
+            // Valid to the end, or up to a sequence cut by it
+            None => break,
+        }
+    }
+    invalid * 2 <= multibyte || invalid * 1024 <= sample.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    /// Text in UTF-8 with non-ASCII comments, and `stray` in the middle
+    fn utf8_with(stray: &[u8]) -> Vec<u8> {
+        let mut text = Vec::new();
+        for i in 0..50 {
+            text.extend_from_slice(
+                format!("// Größe {} — für später\nlet x{} = 1;\n", i, i).as_bytes(),
+            );
+        }
+        text.extend_from_slice(b"let bad = \"");
+        text.extend_from_slice(stray);
+        text.extend_from_slice(b"\";\n");
+        text
+    }
+
+    #[test]
+    fn utf8_with_a_stray_byte_is_utf8() {
+        assert_eq!(detect(&utf8_with(b"\xE9")), UTF_8);
+        assert_eq!(detect(&utf8_with(b"\xFF\xFE")), UTF_8);
+    }
+
+    #[test]
+    fn ascii_with_a_stray_byte_is_utf8() {
+        let mut text = "fn main() {}\n".repeat(200).into_bytes();
+        text.extend_from_slice(b"// \x93quoted\x94\n");
+        assert_eq!(detect(&text), UTF_8);
+    }
+
+    #[test]
+    fn legacy_text_is_not_utf8() {
+        // windows-1252: accented letters on most lines
+        let text = "// Größe für später, déjà vu\nlet x = 1;\n".repeat(50);
+        let (bytes, _, _) = WINDOWS_1252.encode(&text);
+        assert_ne!(detect(&bytes), UTF_8);
+    }
+}
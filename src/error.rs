@@ -25,6 +25,9 @@ pub enum SlocError {
     #[error("Parse error: {0}")]
     Parse(String),
 
+    #[error("Template error: {0}")]
+    Template(String),
+
     // Varianti che potrebbero essere usate in futuro
     // Usa l'attributo allow per silenziare i warning
     #[allow(dead_code)]
@@ -39,13 +42,49 @@ pub enum SlocError {
     #[error("Language not supported: {0}")]
     UnsupportedLanguage(String),
 
-    #[allow(dead_code)]
     #[error("Invalid report format: {0}")]
     InvalidReportFormat(String),
 
-    #[allow(dead_code)]
+    /// `count --on-invalid-utf8 error`: some files have bytes that do not decode
     #[error("Encoding error: {0}")]
     Encoding(String),
+
+    /// `count --strict`: some files or paths could not be counted
+    #[error("{0}")]
+    FilesFailed(String),
+
+    /// `compare --git-ref`: git is missing or failed
+    #[error("Git error: {0}")]
+    Git(String),
+
+    /// `count --metric-plugin`: a plugin could not be run or returned invalid output
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+
+    /// `record`, `trend --db`: the history database cannot be opened, read or written
+    #[error("Database error: {0}")]
+    Database(String),
+
+    /// Library: the count was cancelled through its `CancelToken`
+    #[error("Count cancelled")]
+    Cancelled,
+
+    /// `compare --fail-if`: the run succeeded but a threshold was breached
+    #[error("Threshold breached: {0}")]
+    ThresholdBreached(String),
+}
+
+/// REQ-8.4: Process exit codes (clap exits with 2 on usage errors)
+pub const EXIT_ERROR: u8 = 1;
+pub const EXIT_THRESHOLD: u8 = 3;
+
+impl SlocError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SlocError::ThresholdBreached(_) => EXIT_THRESHOLD,
+            _ => EXIT_ERROR,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, SlocError>;
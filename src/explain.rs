@@ -0,0 +1,147 @@
+// explain.rs - Language detection diagnosis (`explain`)
+// Implements: REQ-3.2 (language detection), REQ-3.3 (language configuration),
+//             REQ-3.4 (language overrides)
+//
+// For each path, prints the rule that decided its language and the definition that is
+// applied when it is counted. Detection only looks at the extension: `--language-override`
+// first, then the extensions of the built-in and configured languages. File names,
+// shebangs and content are never used, so extensionless scripts are always Unsupported.
+
+use crate::cli::ExplainArgs;
+use crate::error::Result;
+use crate::language::{Detection, DetectionRule, Language, LanguageDetector};
+use crate::output::rule;
+use colored::Colorize;
+use std::path::Path;
+
+pub fn execute_explain(args: ExplainArgs) -> Result<()> {
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+
+    for (index, path) in args.paths.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_detection(path, &detector.explain(path));
+    }
+    Ok(())
+}
+
+fn print_detection(path: &Path, detection: &Detection) {
+    println!("{}", path.display().to_string().bold());
+    println!("{}", rule(60));
+    if path.is_dir() {
+        println!("Note:       this is a directory; its files are detected one by one");
+    } else if !path.exists() {
+        println!("Note:       the file does not exist; detection only uses its name");
+    }
+    println!(
+        "Extension:  {}",
+        detection
+            .extension
+            .as_deref()
+            .map_or_else(|| "(none)".to_string(), |ext| format!(".{}", ext))
+    );
+
+    let ext = detection.extension.as_deref().unwrap_or_default();
+    let key = detection.key.as_deref().unwrap_or_default();
+    let decision = match detection.rule {
+        DetectionRule::Override => format!("override --language-override {}={}", ext, key),
+        DetectionRule::UnknownOverride => format!(
+            "override --language-override {}={}, but no language has the key `{}`",
+            ext, key, key
+        ),
+        DetectionRule::Extension => format!(".{} is an extension of `{}`", ext, key),
+        DetectionRule::UnknownExtension => format!("no language lists the .{} extension", ext),
+        DetectionRule::NoExtension => "files without an extension are never detected".to_string(),
+    };
+    println!("Rule:       {}", decision);
+    println!("            (filename, shebang and content heuristics are not used)");
+    if let Some(known) = &detection.case_mismatch {
+        println!(
+            "Hint:       extensions are case-sensitive; .{} is known",
+            known
+        );
+    }
+    if !detection.shadowed.is_empty() {
+        let precedence = match detection.rule {
+            DetectionRule::Extension => "the last definition loaded wins",
+            _ => "the override takes precedence",
+        };
+        println!(
+            "Also:       .{} is listed by {} ({})",
+            ext,
+            detection.shadowed.join(", "),
+            precedence
+        );
+    }
+
+    match detection.language {
+        Some(language) => {
+            println!(
+                "Origin:     {}",
+                detection.origin.map_or_else(
+                    || "built-in".to_string(),
+                    |origin| origin.display().to_string()
+                )
+            );
+            print_language(key, language);
+            println!("Result:     counted as {}", language.name.green());
+        }
+        None => {
+            println!(
+                "Result:     {} (listed under Unsupported, not counted)",
+                "Unknown".yellow()
+            );
+            println!(
+                "            add a language for it with --config, or map it with --language-override"
+            );
+        }
+    }
+}
+
+fn print_language(key: &str, language: &Language) {
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            "(none)".to_string()
+        } else {
+            items.join(" ")
+        }
+    };
+    println!("Language:   {} [{}]", language.name, key);
+    println!(
+        "Extensions: {}",
+        list(
+            language
+                .extensions
+                .iter()
+                .map(|ext| format!(".{}", ext))
+                .collect()
+        )
+    );
+    println!("Comments:   {}", list(language.single_line_comment.clone()));
+    println!(
+        "Blocks:     {}{}",
+        list(
+            language
+                .multi_line_comment
+                .iter()
+                .map(|(start, end)| format!("{} ... {}", start, end))
+                .collect()
+        ),
+        if language.nested_comments {
+            " (nested)"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "Directives: {}",
+        language.preprocessor_prefix.as_deref().unwrap_or("(none)")
+    );
+}
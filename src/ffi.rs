@@ -0,0 +1,126 @@
+// ffi.rs - C ABI of the library (feature `ffi`)
+// Implements: REQ-1.1 (line counting), REQ-6.1 (JSON report)
+//
+// A count is driven from C with one call that returns the report as the JSON string `count
+// -f json` writes; build systems and IDE plugins parse it with their own JSON library
+// instead of mirroring the report structures. Strings returned by the library are owned by
+// it and must be released with `counterlines_free_string`. On failure `NULL` is returned
+// and `counterlines_last_error` describes the error of the calling thread.
+//
+// The header `include/counterlines.h` is generated by the build script with cbindgen when
+// the feature is enabled. The shared library is built with
+// `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+
+use crate::counter::Counter;
+use crate::error::{Result, SlocError};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::path::PathBuf;
+
+thread_local! {
+    /// Message of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Count `paths` (files, directories or glob patterns, `path_count` of them) and return the
+/// report as a JSON string, or `NULL` on failure. `recursive` descends into directories,
+/// `threads` is the size of the thread pool (0: one per CPU) and `config`, when not `NULL`,
+/// is a language configuration file to load. The string must be released with
+/// `counterlines_free_string`.
+///
+/// # Safety
+///
+/// `paths` must point to `path_count` valid NUL-terminated strings, and `config` must be
+/// `NULL` or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn counterlines_count(
+    paths: *const *const c_char,
+    path_count: usize,
+    recursive: bool,
+    threads: usize,
+    config: *const c_char,
+) -> *mut c_char {
+    let count = || -> Result<String> {
+        if paths.is_null() {
+            return Err(SlocError::Parse("paths is NULL".to_string()));
+        }
+        // SAFETY: the caller passes `path_count` valid strings
+        let paths = unsafe { std::slice::from_raw_parts(paths, path_count) }
+            .iter()
+            .map(|&path| unsafe { to_str(path) })
+            .collect::<Result<Vec<_>>>()?;
+        let mut builder = Counter::builder()
+            .paths(paths)
+            .recursive(recursive)
+            .threads(threads);
+        if !config.is_null() {
+            builder = builder.config(PathBuf::from(unsafe { to_str(config) }?));
+        }
+        let report = builder.run()?;
+        serde_json::to_string_pretty(&report).map_err(|e| SlocError::Serialization(e.to_string()))
+    };
+    // A panic must not unwind into C
+    match std::panic::catch_unwind(count) {
+        Ok(Ok(json)) => into_c_string(json),
+        Ok(Err(e)) => fail(e.to_string()),
+        Err(_) => fail("internal error while counting".to_string()),
+    }
+}
+
+/// The error of the last call that returned `NULL` on this thread, or `NULL`; valid until
+/// the next call on the same thread
+#[unsafe(no_mangle)]
+pub extern "C" fn counterlines_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Release a string returned by the library; `NULL` is ignored
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by this library that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn counterlines_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the string was created by `CString::into_raw`
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Version of the library, as a static string
+#[unsafe(no_mangle)]
+pub extern "C" fn counterlines_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// # Safety
+///
+/// `string` must be `NULL` or a valid NUL-terminated string.
+unsafe fn to_str<'a>(string: *const c_char) -> Result<&'a str> {
+    if string.is_null() {
+        return Err(SlocError::Parse("a path is NULL".to_string()));
+    }
+    // SAFETY: checked for NULL above; the caller guarantees the termination
+    unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .map_err(|_| SlocError::Encoding("a path is not valid UTF-8".to_string()))
+}
+
+fn into_c_string(string: String) -> *mut c_char {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+    match CString::new(string) {
+        Ok(string) => string.into_raw(),
+        Err(_) => fail("the report contains a NUL character".to_string()),
+    }
+}
+
+fn fail(message: String) -> *mut c_char {
+    let message = CString::new(message.replace('\0', " ")).expect("NUL characters were removed");
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+    std::ptr::null_mut()
+}
@@ -0,0 +1,307 @@
+// git.rs - Line counts of git revisions and of the working tree (`compare --git-ref`, `history`),
+// commit metadata of recorded runs (`record`)
+// Implements: REQ-7.2 (comparison), REQ-9.4 (parallel processing)
+//
+// Only files known to git are counted: the tree of the revision (`git ls-tree`) and,
+// for the working tree, tracked plus untracked-but-not-ignored files (`git ls-files`).
+// Both list paths relative to the current directory, so they line up in a comparison.
+// Blobs of the revision are read through a single `git cat-file --batch` process, so
+// nothing is ever checked out.
+
+use crate::counter::count_reader;
+use crate::error::{Result, SlocError};
+use crate::language::LanguageDetector;
+use crate::report::{FileStats, Report};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::error;
+
+/// Count the files of `reference` (a branch, tag or commit) as they are in the repository
+pub fn count_revision(reference: &str, detector: &LanguageDetector) -> Result<Report> {
+    // `^{tree}` rejects unknown revisions with a clear message before listing files
+    git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{tree}}", reference),
+    ])
+    .map_err(|_| SlocError::Git(format!("unknown revision \"{}\"", reference)))?;
+
+    // `<mode> SP <type> SP <object> TAB <path>`; submodules and symlinks are skipped
+    let listing = git(&["ls-tree", "-r", "-z", reference])?;
+    let blobs: Vec<(String, PathBuf)> = split_nul(&listing)
+        .filter_map(|entry| {
+            let (meta, path) = entry.split_once('\t')?;
+            let mut fields = meta.split(' ');
+            let (mode, kind, object) = (fields.next()?, fields.next()?, fields.next()?);
+            (kind == "blob" && mode != "120000").then(|| (object.to_string(), PathBuf::from(path)))
+        })
+        .collect();
+
+    let contents = read_blobs(&blobs)?;
+    let results: Vec<_> = contents
+        .par_iter()
+        .map(|(path, content)| {
+            classify(
+                count_reader(path, content.as_slice(), detector, false),
+                path,
+            )
+        })
+        .collect();
+    Ok(into_report(results))
+}
+
+/// A commit to count in `history`: a tag name or abbreviated hash, and its commit time
+pub struct Revision {
+    pub label: String,
+    pub commit: String,
+    pub committed_at: DateTime<Utc>,
+}
+
+/// First-parent commits of `reference`, oldest first, optionally only those after `since`
+/// (any date git understands, e.g. `2024-01-01` or `6 months ago`)
+pub fn list_commits(reference: &str, since: Option<&str>) -> Result<Vec<Revision>> {
+    let mut args = vec![
+        "log".to_string(),
+        "--first-parent".to_string(),
+        "--reverse".to_string(),
+        "--format=%H %h %cI".to_string(),
+    ];
+    if let Some(since) = since {
+        args.push(format!("--since={}", since));
+    }
+    args.push(reference.to_string());
+    args.push("--".to_string());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let log = git(&args).map_err(|e| SlocError::Git(format!("{}: {}", reference, e)))?;
+
+    String::from_utf8_lossy(&log)
+        .lines()
+        .map(|line| {
+            let mut fields = line.split(' ');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(commit), Some(short), Some(date)) => Ok(Revision {
+                    label: short.to_string(),
+                    commit: commit.to_string(),
+                    committed_at: parse_date(date)?,
+                }),
+                _ => Err(SlocError::Git(format!(
+                    "unexpected `git log` output: {}",
+                    line
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Tags reachable from `reference`, oldest commit first, optionally only those after `since`
+pub fn list_tags(reference: &str, since: Option<&str>) -> Result<Vec<Revision>> {
+    // `*committerdate` is the commit of an annotated tag; empty for lightweight tags
+    let listing = git(&[
+        "for-each-ref",
+        &format!("--merged={}", reference),
+        "--format=%(refname:short) %(*objectname) %(objectname) %(*committerdate:iso-strict) %(committerdate:iso-strict)",
+        "refs/tags",
+    ])
+    .map_err(|e| SlocError::Git(format!("{}: {}", reference, e)))?;
+    let since = match since {
+        Some(since) => Some(commit_time_limit(since)?),
+        None => None,
+    };
+
+    let mut tags = Vec::new();
+    for line in String::from_utf8_lossy(&listing).lines() {
+        // Empty fields of lightweight tags leave consecutive spaces
+        let fields: Vec<&str> = line.split(' ').collect();
+        let [name, peeled, object, peeled_date, date] = fields[..] else {
+            return Err(SlocError::Git(format!(
+                "unexpected `git for-each-ref` output: {}",
+                line
+            )));
+        };
+        let (commit, date) = if peeled.is_empty() {
+            (object, date)
+        } else {
+            (peeled, peeled_date)
+        };
+        // Tags of trees or blobs have no commit date
+        if date.is_empty() {
+            continue;
+        }
+        let committed_at = parse_date(date)?;
+        if since.is_some_and(|since| committed_at < since) {
+            continue;
+        }
+        tags.push(Revision {
+            label: name.to_string(),
+            commit: commit.to_string(),
+            committed_at,
+        });
+    }
+    tags.sort_by_key(|tag| tag.committed_at);
+    Ok(tags)
+}
+
+/// The checked-out commit of a recorded run
+pub struct HeadCommit {
+    pub commit: String,
+    /// `None` on a detached HEAD
+    pub branch: Option<String>,
+    pub committed_at: DateTime<Utc>,
+    /// Whether the working tree has uncommitted changes
+    pub dirty: bool,
+}
+
+/// The commit checked out in the current directory; `None` outside a repository
+pub fn head_commit() -> Option<HeadCommit> {
+    let log = git(&["log", "-1", "--format=%H %cI"]).ok()?;
+    let log = String::from_utf8_lossy(&log);
+    let (commit, committed_at) = log.trim().split_once(' ')?;
+    let branch = git(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .ok()
+        .map(|name| String::from_utf8_lossy(&name).trim().to_string());
+    let dirty = git(&["status", "--porcelain"]).is_ok_and(|status| !status.is_empty());
+    Some(HeadCommit {
+        commit: commit.to_string(),
+        branch,
+        committed_at: parse_date(committed_at).ok()?,
+        dirty,
+    })
+}
+
+/// `--since` as git reads it, as a time (`git rev-parse --since` prints `--max-age=<secs>`)
+fn commit_time_limit(since: &str) -> Result<DateTime<Utc>> {
+    let output = git(&["rev-parse", &format!("--since={}", since)])?;
+    String::from_utf8_lossy(&output)
+        .trim()
+        .strip_prefix("--max-age=")
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| SlocError::Git(format!("invalid date \"{}\"", since)))
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|e| SlocError::Git(format!("unexpected date \"{}\": {}", date, e)))
+}
+
+/// Count the working tree: tracked files plus untracked files that are not ignored
+pub fn count_working_tree(detector: &LanguageDetector) -> Result<Report> {
+    let listing = git(&[
+        "ls-files",
+        "-z",
+        "--cached",
+        "--others",
+        "--exclude-standard",
+    ])?;
+    let mut paths: Vec<PathBuf> = split_nul(&listing).map(PathBuf::from).collect();
+    // Unmerged files are listed once per stage; deleted files are still tracked
+    paths.sort();
+    paths.dedup();
+    paths.retain(|path| path.is_file());
+
+    let results: Vec<_> = paths
+        .par_iter()
+        .map(|path| {
+            let stats = File::open(path)
+                .map_err(SlocError::from)
+                .and_then(|file| count_reader(path, file, detector, false));
+            classify(stats, path)
+        })
+        .collect();
+    Ok(into_report(results))
+}
+
+/// Same rule as `count`: unknown languages and unreadable files are unsupported
+fn classify(stats: Result<FileStats>, path: &Path) -> std::result::Result<FileStats, PathBuf> {
+    match stats {
+        Ok(stats) if stats.language != "Unknown" => Ok(stats),
+        Ok(_) => Err(path.to_path_buf()),
+        Err(e) => {
+            error!("cannot count {}: {}", path.display(), e);
+            Err(path.to_path_buf())
+        }
+    }
+}
+
+fn into_report(results: Vec<std::result::Result<FileStats, PathBuf>>) -> Report {
+    let (files, unsupported): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+    Report::new(
+        files.into_iter().filter_map(|r| r.ok()).collect(),
+        unsupported.into_iter().filter_map(|r| r.err()).collect(),
+    )
+}
+
+/// Contents of the given `(object, path)` blobs, in order
+fn read_blobs(blobs: &[(String, PathBuf)]) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| SlocError::Git(format!("cannot run git: {}", e)))?;
+
+    // Requests are written from another thread so a full stdout pipe cannot block them
+    let requests: String = blobs
+        .iter()
+        .map(|(object, _)| format!("{}\n", object))
+        .collect();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut contents = Vec::with_capacity(blobs.len());
+    for (_, path) in blobs {
+        // `<object> SP blob SP <size> LF <content> LF`
+        let mut header = String::new();
+        stdout.read_line(&mut header)?;
+        let size: usize = header
+            .split(' ')
+            .nth(2)
+            .and_then(|size| size.trim().parse().ok())
+            .ok_or_else(|| {
+                SlocError::Git(format!(
+                    "unexpected `git cat-file` output for {}: {}",
+                    path.display(),
+                    header.trim()
+                ))
+            })?;
+        let mut content = vec![0; size + 1];
+        stdout.read_exact(&mut content)?;
+        content.pop();
+        contents.push((path.clone(), content));
+    }
+
+    writer
+        .join()
+        .map_err(|_| SlocError::Git("`git cat-file` writer panicked".to_string()))??;
+    child.wait()?;
+    Ok(contents)
+}
+
+/// Run git and return its stdout, or its stderr as the error
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| SlocError::Git(format!("cannot run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(SlocError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// `-z` output: NUL-separated entries
+fn split_nul(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|b| *b == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| String::from_utf8_lossy(entry).into_owned())
+}
@@ -0,0 +1,95 @@
+// history.rs - Time series over the revisions of a git repository (`history`)
+// Implements: REQ-1.1 (line counting), REQ-5.1 (console summary), REQ-9.4 (parallel processing)
+//
+// Each selected revision is counted from git's object store (nothing is checked out) and
+// becomes one point of a `trend` series: the label is the tag name or abbreviated commit
+// hash, the time is the commit time. Display and exports are those of `trend`.
+
+use crate::cli::{HistoryArgs, HistoryEvery, OutputFormat};
+use crate::compression::is_stdout;
+use crate::error::{Result, SlocError};
+use crate::git;
+use crate::language::LanguageDetector;
+use crate::output::print_result;
+use crate::trend::{Trend, display_trend, export_trend, point};
+
+pub fn execute_history(args: HistoryArgs) -> Result<()> {
+    let export_to_stdout = args.export.as_deref().is_some_and(is_stdout);
+    if export_to_stdout && args.print.is_some() {
+        return Err(SlocError::Parse(
+            "--print cannot be combined with `--export -` (both write to stdout)".to_string(),
+        ));
+    }
+    let console = !export_to_stdout && args.print.is_none();
+
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+
+    let revisions = match args.every {
+        HistoryEvery::Commit => git::list_commits(&args.revision, args.since.as_deref())?,
+        HistoryEvery::Tag => git::list_tags(&args.revision, args.since.as_deref())?,
+    };
+    // Every Nth revision counted back from the newest, so the newest is always included
+    let step = args.step as usize;
+    let newest = revisions.len().saturating_sub(1);
+    let revisions: Vec<_> = revisions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| (newest - index) % step == 0)
+        .map(|(_, revision)| revision)
+        .collect();
+    if revisions.is_empty() {
+        return Err(SlocError::Git(format!(
+            "no {} to count in the history of {}",
+            match args.every {
+                HistoryEvery::Commit => "commits",
+                HistoryEvery::Tag => "tags",
+            },
+            args.revision
+        )));
+    }
+
+    let mut points = Vec::with_capacity(revisions.len());
+    for (index, revision) in revisions.iter().enumerate() {
+        if console {
+            eprintln!(
+                "[{}/{}] Counting {}",
+                index + 1,
+                revisions.len(),
+                revision.label
+            );
+        }
+        let report = git::count_revision(&revision.commit, &detector)?;
+        let mut point = point(revision.label.clone(), &report);
+        point.generated_at = revision.committed_at;
+        points.push(point);
+    }
+    let trend = Trend { points };
+
+    if console {
+        display_trend(&trend, "History");
+    }
+
+    if let Some(export_path) = &args.export {
+        let format = args
+            .format
+            .or_else(|| OutputFormat::from_path(export_path))
+            .unwrap_or(OutputFormat::Json);
+        export_trend(&trend, export_path, format)?;
+        if console {
+            println!("\nHistory exported to: {}", export_path.display());
+        }
+    }
+
+    // Machine-readable result on stdout
+    if let Some(print) = args.print {
+        print_result(&trend, print)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,352 @@
+// html.rs - HTML pages: report comparison (`compare --export diff.html`) and `serve` dashboard
+// Implements: REQ-7.3 (display comparison), REQ-7.4 (export comparison)
+//
+// A single self-contained page (inline CSS, no scripts) meant to be archived as a CI
+// artifact: global deltas, a diverging bar chart and table of the language deltas, the
+// directory deltas (`--by-dir`), the new, removed, renamed and modified files and the
+// unchanged-file count with the churn rate. The dashboard shares the style.
+
+use crate::output::xml_escape;
+use crate::processor::ComparisonResult;
+use crate::report::Report;
+use crate::serve::StoredReport;
+use num_format::{Locale, ToFormattedString};
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 70em; color: #222; }
+h1 { border-bottom: 3px solid #36c; padding-bottom: .3em; }
+h2 { margin-top: 2em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; margin: .5em 0; }
+th, td { padding: .3em .8em; border: 1px solid #ddd; text-align: left; }
+th { background: #f4f6f8; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+.pos { color: #1a7f37; }
+.neg { color: #cf222e; }
+tr.added td { background: #e6ffec; }
+tr.removed td { background: #ffebe9; }
+tr.renamed td { background: #ddf4ff; }
+tr.modified td { background: #fff8c5; }
+.chart td { border: none; padding: .15em .4em; }
+.chart .bar { height: 1em; }
+.chart .left { width: 15em; text-align: right; }
+.chart .right { width: 15em; }
+.chart .left .bar { background: #cf222e; margin-left: auto; }
+.chart .right .bar { background: #1a7f37; }
+.meta { color: #666; }
+form { display: inline-block; margin: .5em 1em .5em 0; }
+button, select { font: inherit; padding: .2em .6em; }
+";
+
+/// The comparison as a standalone HTML document
+pub fn comparison_page(comparison: &ComparisonResult) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>SLOC comparison</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str("<h1>SLOC comparison</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"meta\">Report 1: {}<br>Report 2: {}</p>\n",
+        comparison.report1_generated.format("%Y-%m-%d %H:%M:%S UTC"),
+        comparison.report2_generated.format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+
+    // Global changes
+    let g = &comparison.global_delta;
+    html.push_str("<h2>Global changes</h2>\n<table>\n<tr><th>Metric</th><th>Delta</th></tr>\n");
+    for (label, delta) in [
+        ("Files", g.files_delta),
+        ("Total lines", g.total_lines_delta),
+        ("Logical lines", g.logical_lines_delta),
+        ("Comment lines", g.comment_lines_delta),
+        ("Empty lines", g.empty_lines_delta),
+        ("Languages", g.languages_delta),
+    ] {
+        html.push_str(&format!(
+            "<tr><td>{}</td>{}</tr>\n",
+            label,
+            delta_cell(delta)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    // Language changes: chart of the total line deltas, then the table
+    if !comparison.language_deltas.is_empty() {
+        html.push_str("<h2>Language changes</h2>\n<table class=\"chart\">\n");
+        let max = comparison
+            .language_deltas
+            .iter()
+            .map(|l| l.total_lines_delta.unsigned_abs())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for lang in &comparison.language_deltas {
+            let width = lang.total_lines_delta.unsigned_abs() as f64 / max as f64 * 100.0;
+            let bar = format!("<div class=\"bar\" style=\"width: {:.1}%\"></div>", width);
+            let (left, right) = if lang.total_lines_delta < 0 {
+                (bar, String::new())
+            } else {
+                (String::new(), bar)
+            };
+            html.push_str(&format!(
+                "<tr><td class=\"left\">{}</td><td>{}</td><td class=\"right\">{}</td>{}</tr>\n",
+                left,
+                xml_escape(&lang.language),
+                right,
+                delta_cell(lang.total_lines_delta)
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str(
+            "<table>\n<tr><th>Language</th><th>Files &Delta;</th><th>Total &Delta;</th>\
+             <th>Logical &Delta;</th><th>Comment &Delta;</th><th>Empty &Delta;</th></tr>\n",
+        );
+        for lang in &comparison.language_deltas {
+            html.push_str(&format!(
+                "<tr><td>{}</td>{}{}{}{}{}</tr>\n",
+                xml_escape(&lang.language),
+                delta_cell(lang.files_delta),
+                delta_cell(lang.total_lines_delta),
+                delta_cell(lang.logical_lines_delta),
+                delta_cell(lang.comment_lines_delta),
+                delta_cell(lang.empty_lines_delta)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Directory changes (--by-dir)
+    if !comparison.directory_deltas.is_empty() {
+        html.push_str(
+            "<h2>Directory changes</h2>\n<table>\n<tr><th>Directory</th><th>Files &Delta;</th>\
+             <th>Total &Delta;</th><th>Logical &Delta;</th><th>Comment &Delta;</th><th>Empty &Delta;</th></tr>\n",
+        );
+        for dir in &comparison.directory_deltas {
+            html.push_str(&format!(
+                "<tr><td>{}</td>{}{}{}{}{}</tr>\n",
+                xml_escape(&dir.path),
+                delta_cell(dir.files_delta),
+                delta_cell(dir.total_lines_delta),
+                delta_cell(dir.logical_lines_delta),
+                delta_cell(dir.comment_lines_delta),
+                delta_cell(dir.empty_lines_delta)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // File changes, every file listed
+    if !comparison.new_files.is_empty() {
+        file_list(&mut html, "New files", "added", &comparison.new_files);
+    }
+    if !comparison.removed_files.is_empty() {
+        file_list(
+            &mut html,
+            "Removed files",
+            "removed",
+            &comparison.removed_files,
+        );
+    }
+    if !comparison.renamed_files.is_empty() {
+        html.push_str(&format!(
+            "<h2>Renamed files ({})</h2>\n<table>\n<tr><th>From</th><th>To</th></tr>\n",
+            comparison.renamed_files.len()
+        ));
+        for file in &comparison.renamed_files {
+            html.push_str(&format!(
+                "<tr class=\"renamed\"><td>{}</td><td>{}</td></tr>\n",
+                xml_escape(&file.from),
+                xml_escape(&file.to)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    if !comparison.modified_files.is_empty() {
+        html.push_str(&format!(
+            "<h2>Modified files ({})</h2>\n<table>\n<tr><th>File</th><th>Total &Delta;</th>\
+             <th>Logical &Delta;</th><th>Comment &Delta;</th><th>Empty &Delta;</th></tr>\n",
+            comparison.modified_files.len()
+        ));
+        for file in &comparison.modified_files {
+            html.push_str(&format!(
+                "<tr class=\"modified\"><td>{}</td>{}{}{}{}</tr>\n",
+                xml_escape(&file.path),
+                delta_cell(file.total_lines_delta),
+                delta_cell(file.logical_lines_delta),
+                delta_cell(file.comment_lines_delta),
+                delta_cell(file.empty_lines_delta)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    if comparison.summary_only {
+        html.push_str("<p class=\"meta\">Summary only: file lists were not computed</p>\n");
+    } else {
+        html.push_str(&format!(
+            "<h2>Unchanged files ({})</h2>\n\
+             <p>Churn rate: {:.2} % of the files present in both reports</p>\n",
+            comparison.unchanged_files,
+            comparison.churn_rate()
+        ));
+        if !comparison.unchanged_file_paths.is_empty() {
+            html.push_str("<table>\n<tr><th>File</th></tr>\n");
+            for file in &comparison.unchanged_file_paths {
+                html.push_str(&format!("<tr><td>{}</td></tr>\n", xml_escape(file)));
+            }
+            html.push_str("</table>\n");
+        }
+    }
+
+    html.push_str(&format!(
+        "<p class=\"meta\">Generated by rustedbytes-counterlines {}</p>\n</body>\n</html>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    html
+}
+
+/// `serve`: the latest report, the stored reports (newest first) and forms to count and compare
+pub fn dashboard_page(
+    paths: &[String],
+    stored: &[StoredReport],
+    latest: Option<&Report>,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>SLOC dashboard</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str("<h1>SLOC dashboard</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"meta\">Counting: {}</p>\n\
+         <form method=\"post\" action=\"/count\"><button>Count now</button></form>\n",
+        xml_escape(&paths.join(" "))
+    ));
+
+    match (stored.first(), latest) {
+        (Some(newest), Some(report)) => {
+            let s = &report.summary;
+            html.push_str(&format!(
+                "<h2>Latest report</h2>\n<p class=\"meta\">{} &middot; {}</p>\n\
+                 <table>\n<tr><th>Metric</th><th>Value</th></tr>\n",
+                xml_escape(&newest.name),
+                report.generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+            for (label, value) in [
+                ("Files", s.total_files),
+                ("Total lines", s.total_lines),
+                ("Logical lines", s.logical_lines),
+                ("Comment lines", s.comment_lines),
+                ("Empty lines", s.empty_lines),
+                ("Languages", s.languages_count),
+                ("Unsupported files", s.unsupported_files),
+            ] {
+                html.push_str(&format!(
+                    "<tr><td>{}</td>{}</tr>\n",
+                    label,
+                    count_cell(value)
+                ));
+            }
+            html.push_str("</table>\n");
+
+            html.push_str(
+                "<table>\n<tr><th>Language</th><th>Files</th><th>Total</th><th>Logical</th>\
+                 <th>Comment</th><th>Empty</th></tr>\n",
+            );
+            for lang in &report.languages {
+                html.push_str(&format!(
+                    "<tr><td>{}</td>{}{}{}{}{}</tr>\n",
+                    xml_escape(&lang.language),
+                    count_cell(lang.file_count),
+                    count_cell(lang.total_lines),
+                    count_cell(lang.logical_lines),
+                    count_cell(lang.comment_lines),
+                    count_cell(lang.empty_lines)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        _ => html.push_str("<p>No reports stored yet: count now to create the first one.</p>\n"),
+    }
+
+    if !stored.is_empty() {
+        html.push_str(&format!(
+            "<h2>Stored reports ({})</h2>\n<table>\n<tr><th>Report</th><th>Generated</th>\
+             <th>Files</th><th>Total lines</th><th>Total &Delta;</th></tr>\n",
+            stored.len()
+        ));
+        for (index, entry) in stored.iter().enumerate() {
+            // Delta against the next older report, none for the oldest
+            let delta = match stored.get(index + 1) {
+                Some(older) => {
+                    delta_cell(entry.summary.total_lines as i64 - older.summary.total_lines as i64)
+                }
+                None => "<td></td>".to_string(),
+            };
+            html.push_str(&format!(
+                "<tr><td><a href=\"/api/reports/{name}\">{name}</a></td><td>{}</td>{}{}{}</tr>\n",
+                entry.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                count_cell(entry.summary.total_files),
+                count_cell(entry.summary.total_lines),
+                delta,
+                name = xml_escape(&entry.name)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    if stored.len() > 1 {
+        html.push_str("<h2>Compare</h2>\n<form method=\"get\" action=\"/compare\">\n");
+        for (param, label, selected) in [("from", "From", 1), ("to", "to", 0)] {
+            html.push_str(&format!("{} <select name=\"{}\">", label, param));
+            for (index, entry) in stored.iter().enumerate() {
+                html.push_str(&format!(
+                    "<option{}>{}</option>",
+                    if index == selected { " selected" } else { "" },
+                    xml_escape(&entry.name)
+                ));
+            }
+            html.push_str("</select>\n");
+        }
+        html.push_str("<button>Compare</button>\n</form>\n");
+    }
+
+    html.push_str(&format!(
+        "<p class=\"meta\">Served by rustedbytes-counterlines {}</p>\n</body>\n</html>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    html
+}
+
+fn file_list(html: &mut String, title: &str, class: &str, files: &[String]) {
+    html.push_str(&format!(
+        "<h2>{} ({})</h2>\n<table>\n<tr><th>File</th></tr>\n",
+        title,
+        files.len()
+    ));
+    for file in files {
+        html.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td></tr>\n",
+            class,
+            xml_escape(file)
+        ));
+    }
+    html.push_str("</table>\n");
+}
+
+/// Right-aligned count with thousands separators
+fn count_cell(count: usize) -> String {
+    format!(
+        "<td class=\"num\">{}</td>",
+        count.to_formatted_string(&Locale::en)
+    )
+}
+
+/// Right-aligned signed delta, green when positive and red when negative
+fn delta_cell(delta: i64) -> String {
+    let (class, text) = match delta {
+        d if d > 0 => (" pos", format!("+{}", d.to_formatted_string(&Locale::en))),
+        d if d < 0 => (" neg", d.to_formatted_string(&Locale::en)),
+        _ => ("", "0".to_string()),
+    };
+    format!("<td class=\"num{}\">{}</td>", class, text)
+}
@@ -0,0 +1,248 @@
+// init.rs - Configuration scaffold (`init`)
+// Implements: REQ-3.3 (language configuration), REQ-9.7 (performance configuration)
+//
+// The project is scanned without reading any file: extensions give the languages, and
+// generated or vendored directories (`target`, `node_modules`, ...) are skipped and
+// added to the default exclude patterns. The written `.counterlines.toml` holds every current
+// default with a comment, and a commented sample language definition for the most common
+// extension no language knows.
+
+use crate::cli::InitArgs;
+use crate::compression::{create_writer, is_stdout};
+use crate::config::{DefaultsConfig, PerformanceConfig};
+use crate::error::{Result, SlocError};
+use crate::language::LanguageDetector;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Default configuration file name
+pub const CONFIG_FILE_NAME: &str = ".counterlines.toml";
+
+/// Directories that usually hold build output or third-party code
+const GENERATED_DIRS: [&str; 12] = [
+    ".git",
+    "target",
+    "node_modules",
+    "build",
+    "dist",
+    "out",
+    "vendor",
+    "__pycache__",
+    ".venv",
+    "venv",
+    "bin",
+    "obj",
+];
+
+/// What the scan found
+#[derive(Default)]
+struct Project {
+    /// Files per language name
+    languages: BTreeMap<String, usize>,
+    /// Files per extension without a language
+    unknown_extensions: BTreeMap<String, usize>,
+    /// Skipped directories, relative to the project
+    generated: BTreeSet<String>,
+    /// Whether sources were found below the top directory
+    nested_sources: bool,
+}
+
+pub fn execute_init(args: InitArgs) -> Result<()> {
+    if !args.path.is_dir() {
+        return Err(SlocError::FileNotFound { path: args.path });
+    }
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.path.join(CONFIG_FILE_NAME));
+    if !is_stdout(&output) && output.exists() && !args.force {
+        return Err(SlocError::Parse(format!(
+            "{} already exists (use --force to overwrite)",
+            output.display()
+        )));
+    }
+
+    let project = scan(&args.path);
+    let content = render(&project);
+    let mut writer = create_writer(&output)?;
+    writer.write_all(content.as_bytes())?;
+    writer.flush()?;
+
+    if !is_stdout(&output) {
+        println!("Configuration written to: {}", output.display());
+        if !project.languages.is_empty() {
+            println!(
+                "Detected languages: {}",
+                project
+                    .languages
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        println!(
+            "Use it with: rustedbytes-counterlines count . --config {}",
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+fn scan(root: &Path) -> Project {
+    let detector = LanguageDetector::new();
+    let mut project = Project::default();
+    let relative =
+        |path: &Path| -> PathBuf { path.strip_prefix(root).unwrap_or(path).to_path_buf() };
+
+    let mut generated = Vec::new();
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        let skip = entry.depth() > 0
+            && entry.file_type().is_dir()
+            && (GENERATED_DIRS.contains(&name.as_ref()) || name.starts_with('.'));
+        if skip && GENERATED_DIRS.contains(&name.as_ref()) {
+            generated.push(entry.path().to_path_buf());
+        }
+        !skip
+    });
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        match detector.detect(path) {
+            Some(language) => {
+                *project.languages.entry(language.name.clone()).or_default() += 1;
+                project.nested_sources |= entry.depth() > 1;
+            }
+            None => {
+                if let Some(ext) = path.extension() {
+                    *project
+                        .unknown_extensions
+                        .entry(ext.to_string_lossy().into_owned())
+                        .or_default() += 1;
+                }
+            }
+        }
+    }
+    project.generated = generated
+        .iter()
+        .map(|path| relative(path).to_string_lossy().replace('\\', "/"))
+        .collect();
+    project
+}
+
+fn render(project: &Project) -> String {
+    let performance = PerformanceConfig::default();
+    let defaults = DefaultsConfig::default();
+    let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "# {} - rustedbytes-counterlines configuration\n",
+        CONFIG_FILE_NAME
+    ));
+    out.push_str(&format!(
+        "# Generated by `rustedbytes-counterlines init` {} on {}\n",
+        env!("CARGO_PKG_VERSION"),
+        chrono::Local::now().format("%Y-%m-%d")
+    ));
+    if project.languages.is_empty() {
+        out.push_str("# No source files of a known language were found.\n");
+    } else {
+        out.push_str("# Languages found in the project:\n");
+        for (language, files) in &project.languages {
+            out.push_str(&format!("#   {} ({} files)\n", language, files));
+        }
+    }
+
+    out.push_str("\n[performance]\n");
+    out.push_str("# Worker threads, 0 for one per CPU (count -j)\n");
+    out.push_str(&format!(
+        "default_threads = {}\n",
+        performance.default_threads
+    ));
+    out.push_str(
+        "# Append timing metrics to metrics_file (count --enable-metrics, --metrics-file)\n",
+    );
+    out.push_str(&format!(
+        "enable_metrics = {}\n",
+        performance.enable_metrics
+    ));
+    out.push_str(&format!(
+        "metrics_file = {}\n",
+        quote(&performance.metrics_file)
+    ));
+
+    out.push_str("\n[defaults]\n");
+    out.push_str("# Traverse directories recursively (count -r)\n");
+    out.push_str(&format!(
+        "recursive = {}\n",
+        defaults.recursive || project.nested_sources
+    ));
+    out.push_str("# Hide the progress bar (count --no-progress)\n");
+    out.push_str(&format!("no_progress = {}\n", defaults.no_progress));
+    out.push_str(
+        "# Format of `report` without -f and of -o files without a known extension, and\n\
+         # base name of auto-named reports (count -f, -o)\n",
+    );
+    out.push_str(&format!(
+        "output_format = {}\n",
+        quote(&defaults.output_format)
+    ));
+    out.push_str(&format!("output_file = {}\n", quote(&defaults.output_file)));
+    out.push_str("# Order of the console tables without --sort (count -s), e.g. \"logical\"\n");
+    match &defaults.sort {
+        Some(sort) => out.push_str(&format!("sort = {}\n", quote(sort))),
+        None => out.push_str("# sort = \"total\"\n"),
+    }
+    // The default patterns, then those of the directories found that they miss
+    let mut exclude = defaults.exclude.clone();
+    for dir in &project.generated {
+        let pattern = format!("{}/**", dir);
+        if !exclude.contains(&pattern) {
+            exclude.push(pattern);
+        }
+    }
+    out.push_str(
+        "# Build output and third-party directories left out of directory walks\n\
+         # (count --no-default-excludes walks them too)\n",
+    );
+    let patterns: Vec<String> = exclude.iter().map(|pattern| quote(pattern)).collect();
+    out.push_str(&format!("exclude = [{}]\n", patterns.join(", ")));
+
+    // The most common unknown extension, or a placeholder
+    let (ext, files) = project
+        .unknown_extensions
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(ext, files)| (ext.as_str(), Some(*files)))
+        .unwrap_or(("ext", None));
+    out.push('\n');
+    match files {
+        Some(files) => {
+            out.push_str(&format!(
+                "# {} files with the .{} extension have no language: uncomment and adjust the\n",
+                files, ext
+            ));
+            out.push_str("# comment syntax to count them.\n");
+        }
+        None => {
+            out.push_str(
+                "# Custom language: uncomment and adjust to count files of another extension.\n",
+            );
+        }
+    }
+    let key = ext.to_lowercase();
+    out.push_str(&format!("# [languages.{}]\n", key));
+    out.push_str(&format!("# name = {}\n", quote(&key.to_uppercase())));
+    out.push_str(&format!("# extensions = [{}]\n", quote(ext)));
+    out.push_str("# single_line_comment = [\"#\"]\n");
+    out.push_str("# multi_line_comment = [{ start = \"/*\", end = \"*/\" }]\n");
+    out.push_str("# nested_comments = false\n");
+    out.push_str("# preprocessor_prefix = \"#\"\n");
+    out
+}
@@ -1,15 +1,25 @@
 // language.rs - Language detection and comment syntax definitions
 // Implements: REQ-3.1, REQ-3.2, REQ-3.3, REQ-3.4, REQ-4.2, REQ-4.3
 
+use crate::config::{AppConfig, is_app_config};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Language {
+    #[serde(deserialize_with = "crate::config::non_empty")]
+    #[schemars(length(min = 1))]
     pub name: String,
+    #[serde(deserialize_with = "crate::config::extensions")]
+    #[schemars(length(min = 1), inner(regex(pattern = r"^[^.]")))]
     pub extensions: Vec<String>,
+    #[serde(deserialize_with = "crate::config::comment_markers")]
+    #[schemars(inner(length(min = 1)))]
     pub single_line_comment: Vec<String>,
+    #[serde(deserialize_with = "crate::config::comment_pairs")]
     pub multi_line_comment: Vec<(String, String)>,
     pub nested_comments: bool, // REQ-4.3: Nested comments support
     pub preprocessor_prefix: Option<String>, // REQ-4.5: Preprocessor directives
@@ -20,6 +30,44 @@ pub struct LanguageDetector {
     languages: HashMap<String, Language>,
     extension_map: HashMap<String, String>,
     overrides: HashMap<String, String>, // REQ-3.4: Language overrides
+    /// Configuration file of each language loaded with `load_from_config`
+    origins: HashMap<String, PathBuf>,
+}
+
+/// Which rule decided the language of a file (`explain`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectionRule {
+    /// `--language-override` for the extension
+    Override,
+    /// `--language-override` naming a language that is not defined
+    UnknownOverride,
+    /// The extension of a built-in or configured language
+    Extension,
+    /// No language lists the extension
+    UnknownExtension,
+    /// The file name has no extension
+    NoExtension,
+}
+
+/// The detection of one file with everything that took part in it
+pub struct Detection<'a> {
+    pub extension: Option<String>,
+    pub rule: DetectionRule,
+    /// Key of the language definition (`rust`, `cpp`, or a `[languages.<key>]` section)
+    pub key: Option<String>,
+    pub language: Option<&'a Language>,
+    /// Configuration file the language comes from; `None` for built-in languages
+    pub origin: Option<&'a Path>,
+    /// Names of the other languages listing the same extension (the last definition wins)
+    pub shadowed: Vec<&'a str>,
+    /// A known extension that differs only in case (extensions are case-sensitive)
+    pub case_mismatch: Option<String>,
+}
+
+impl Default for LanguageDetector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LanguageDetector {
@@ -29,23 +77,50 @@ impl LanguageDetector {
             languages: HashMap::new(),
             extension_map: HashMap::new(),
             overrides: HashMap::new(),
+            origins: HashMap::new(),
         };
         detector.load_default_languages();
         detector
     }
 
-    /// REQ-3.3: Load additional language definitions
+    /// REQ-3.3: Load additional language definitions, either from the `[languages.<key>]`
+    /// sections of an application configuration or from a file of `[<key>]` sections
     pub fn load_from_config(&mut self, config_path: &Path) -> crate::error::Result<()> {
         let content = std::fs::read_to_string(config_path)?;
-        let languages: HashMap<String, Language> = toml::from_str(&content)
-            .map_err(|e| crate::error::SlocError::InvalidConfig(e.to_string()))?;
+        self.load_from_str(&content, config_path)
+    }
+
+    /// REQ-3.3: Load additional language definitions from the `content` of a configuration;
+    /// `origin` is where the definitions are reported to come from
+    pub fn load_from_str(&mut self, content: &str, origin: &Path) -> crate::error::Result<()> {
+        let invalid = |e: toml::de::Error| {
+            crate::error::SlocError::InvalidConfig(format!("{}: {}", origin.display(), e))
+        };
+        let table: toml::Table = toml::from_str(content).map_err(invalid)?;
 
-        for (key, lang) in languages {
-            self.add_language(key, lang);
+        if is_app_config(&table) {
+            let config: AppConfig = toml::from_str(content).map_err(invalid)?;
+            for (key, definition) in config.languages.definitions {
+                self.origins.insert(key.clone(), origin.to_path_buf());
+                self.add_language(key, definition.into());
+            }
+        } else {
+            let languages: HashMap<String, Language> = toml::from_str(content).map_err(invalid)?;
+            for (key, lang) in languages {
+                self.origins.insert(key.clone(), origin.to_path_buf());
+                self.add_language(key, lang);
+            }
         }
         Ok(())
     }
 
+    /// Whether a language is named `name`, regardless of case
+    pub fn has_language(&self, name: &str) -> bool {
+        self.languages
+            .values()
+            .any(|language| language.name.eq_ignore_ascii_case(name))
+    }
+
     /// REQ-3.4: Add language override
     pub fn add_override(&mut self, extension: String, language: String) {
         self.overrides.insert(extension, language);
@@ -65,6 +140,65 @@ impl LanguageDetector {
         self.languages.get(lang_name)
     }
 
+    /// REQ-3.2, REQ-3.4: The same decision as `detect`, with the rule that made it
+    pub fn explain(&self, path: &Path) -> Detection<'_> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+        let Some(ext) = extension.clone() else {
+            return Detection {
+                extension,
+                rule: DetectionRule::NoExtension,
+                key: None,
+                language: None,
+                origin: None,
+                shadowed: Vec::new(),
+                case_mismatch: None,
+            };
+        };
+
+        let (rule, key) = match self.overrides.get(&ext) {
+            Some(key) if self.languages.contains_key(key) => {
+                (DetectionRule::Override, Some(key.clone()))
+            }
+            Some(key) => (DetectionRule::UnknownOverride, Some(key.clone())),
+            None => match self.extension_map.get(&ext) {
+                Some(key) => (DetectionRule::Extension, Some(key.clone())),
+                None => (DetectionRule::UnknownExtension, None),
+            },
+        };
+        let language = key.as_ref().and_then(|key| self.languages.get(key));
+
+        let mut shadowed: Vec<&str> = self
+            .languages
+            .iter()
+            .filter(|(k, lang)| Some(*k) != key.as_ref() && lang.extensions.contains(&ext))
+            .map(|(_, lang)| lang.name.as_str())
+            .collect();
+        shadowed.sort();
+        let case_mismatch = if rule == DetectionRule::UnknownExtension {
+            self.extension_map
+                .keys()
+                .find(|known| known.eq_ignore_ascii_case(&ext))
+                .cloned()
+        } else {
+            None
+        };
+
+        Detection {
+            origin: key
+                .as_ref()
+                .and_then(|key| self.origins.get(key))
+                .map(PathBuf::as_path),
+            extension,
+            rule,
+            key,
+            language,
+            shadowed,
+            case_mismatch,
+        }
+    }
+
     fn add_language(&mut self, key: String, language: Language) {
         for ext in &language.extensions {
             self.extension_map.insert(ext.clone(), key.clone());
@@ -283,11 +417,13 @@ impl LanguageDetector {
 pub struct CommentParser {
     language: Language,
     ignore_preprocessor: bool,
+    markers: MarkerBytes,
 }
 
 impl CommentParser {
     pub fn new(language: Language, ignore_preprocessor: bool) -> Self {
         Self {
+            markers: MarkerBytes::new(&language, ignore_preprocessor),
             language,
             ignore_preprocessor,
         }
@@ -295,12 +431,18 @@ impl CommentParser {
 
     /// REQ-4.2, REQ-4.4: Identify comments and mixed lines
     pub fn parse_line(&self, line: &str) -> LineType {
-        let trimmed = line.trim();
+        self.parse_bytes(line.as_bytes())
+    }
+
+    /// [`parse_line`](Self::parse_line) on the raw bytes of a line, which need not be valid
+    /// UTF-8: comment markers are matched byte for byte
+    pub fn parse_bytes(&self, line: &[u8]) -> LineType {
+        let trimmed = trim(line);
 
         // REQ-4.5: Handle preprocessor directives
         if self.ignore_preprocessor {
             if let Some(prefix) = &self.language.preprocessor_prefix {
-                if trimmed.starts_with(prefix) {
+                if trimmed.starts_with(prefix.as_bytes()) {
                     return LineType::Empty;
                 }
             }
@@ -313,9 +455,9 @@ impl CommentParser {
 
         // Check for single-line comments
         for prefix in &self.language.single_line_comment {
-            if trimmed.starts_with(prefix) {
+            if trimmed.starts_with(prefix.as_bytes()) {
                 // Check if comment contains only whitespace
-                let comment_content = trimmed[prefix.len()..].trim();
+                let comment_content = trim(&trimmed[prefix.len()..]);
                 if comment_content.is_empty() {
                     return LineType::Empty;
                 }
@@ -323,9 +465,10 @@ impl CommentParser {
             }
         }
 
-        // Check if line contains both code and comments (REQ-4.4)
+        // Check if line contains both code and comments (REQ-4.4); the line does not start
+        // with a comment prefix, as checked above
         for prefix in &self.language.single_line_comment {
-            if line.contains(prefix) && !line.trim().starts_with(prefix) {
+            if contains(line, prefix) {
                 return LineType::Mixed;
             }
         }
@@ -341,71 +484,60 @@ impl CommentParser {
         in_comment: &mut bool,
         depth: &mut usize,
     ) -> bool {
+        self.multiline_bytes(line.as_bytes(), in_comment, depth)
+    }
+
+    fn multiline_bytes(&self, line: &[u8], in_comment: &mut bool, depth: &mut usize) -> bool {
         if self.language.multi_line_comment.is_empty() {
             return false;
         }
 
-        let mut line_copy = line.to_string();
         let mut result = *in_comment;
 
         for (start, end) in &self.language.multi_line_comment {
             if self.language.nested_comments {
                 // Handle nested comments (REQ-4.3)
-                while line_copy.contains(start) || line_copy.contains(end) {
-                    if let Some(start_pos) = line_copy.find(start) {
-                        if let Some(end_pos) = line_copy.find(end) {
-                            if start_pos < end_pos {
-                                *depth += 1;
-                                line_copy = line_copy[start_pos + start.len()..].to_string();
-                            } else {
-                                if *depth > 0 {
-                                    *depth -= 1;
-                                }
-                                line_copy = line_copy[end_pos + end.len()..].to_string();
-                            }
-                        } else {
+                let mut rest = line;
+                loop {
+                    match (find(rest, start), find(rest, end)) {
+                        (Some(start_pos), Some(end_pos)) if start_pos < end_pos => {
                             *depth += 1;
-                            line_copy = line_copy[start_pos + start.len()..].to_string();
+                            rest = &rest[start_pos + start.len()..];
                         }
-                    } else if let Some(end_pos) = line_copy.find(end) {
-                        if *depth > 0 {
-                            *depth -= 1;
+                        (Some(start_pos), None) => {
+                            *depth += 1;
+                            rest = &rest[start_pos + start.len()..];
+                        }
+                        (_, Some(end_pos)) => {
+                            *depth = depth.saturating_sub(1);
+                            rest = &rest[end_pos + end.len()..];
                         }
-                        line_copy = line_copy[end_pos + end.len()..].to_string();
-                    } else {
-                        break;
+                        (None, None) => break,
                     }
                 }
                 result = *depth > 0;
             } else {
                 // Simple multi-line comments
                 if *in_comment {
-                    if line.contains(end) {
+                    if let Some(pos) = find(line, end) {
                         *in_comment = false;
                         // Check if there's code after comment end
-                        if let Some(pos) = line.find(end) {
-                            let after = line[pos + end.len()..].trim();
-                            if !after.is_empty() {
-                                return false; // Mixed line
-                            }
+                        if !trim(&line[pos + end.len()..]).is_empty() {
+                            return false; // Mixed line
                         }
                     }
                     result = true;
-                } else if line.contains(start) {
+                } else if let Some(start_pos) = find(line, start) {
                     *in_comment = true;
                     // Check if comment closes on same line
-                    if let Some(start_pos) = line.find(start) {
-                        let after_start = &line[start_pos + start.len()..];
-                        if after_start.contains(end) {
-                            *in_comment = false;
-                            // Check for code before or after
-                            let before = line[..start_pos].trim();
-                            if let Some(end_pos) = after_start.find(end) {
-                                let after = after_start[end_pos + end.len()..].trim();
-                                if !before.is_empty() || !after.is_empty() {
-                                    return false; // Mixed line
-                                }
-                            }
+                    let after_start = &line[start_pos + start.len()..];
+                    if let Some(end_pos) = find(after_start, end) {
+                        *in_comment = false;
+                        // Check for code before or after
+                        let before = trim(&line[..start_pos]);
+                        let after = trim(&after_start[end_pos + end.len()..]);
+                        if !before.is_empty() || !after.is_empty() {
+                            return false; // Mixed line
                         }
                     }
                     result = true;
@@ -417,6 +549,183 @@ impl CommentParser {
     }
 }
 
+/// The first bytes of a language's comment markers (and of its preprocessor prefix when
+/// directives are ignored). A line containing none of them is, outside a multi-line comment,
+/// blank or code; most lines of most files are, so they skip the parser.
+enum MarkerBytes {
+    None,
+    One(u8),
+    Two(u8, u8),
+    Three(u8, u8, u8),
+    Many(Box<[bool; 256]>),
+    /// An empty marker matches every line
+    All,
+}
+
+impl MarkerBytes {
+    fn new(language: &Language, ignore_preprocessor: bool) -> Self {
+        let markers = language
+            .single_line_comment
+            .iter()
+            .chain(
+                language
+                    .multi_line_comment
+                    .iter()
+                    .flat_map(|(start, end)| [start, end]),
+            )
+            .chain(
+                language
+                    .preprocessor_prefix
+                    .iter()
+                    .filter(|_| ignore_preprocessor),
+            );
+        let mut bytes = Vec::new();
+        for marker in markers {
+            let Some(&first) = marker.as_bytes().first() else {
+                return MarkerBytes::All;
+            };
+            if !bytes.contains(&first) {
+                bytes.push(first);
+            }
+        }
+        match *bytes.as_slice() {
+            [] => MarkerBytes::None,
+            [a] => MarkerBytes::One(a),
+            [a, b] => MarkerBytes::Two(a, b),
+            [a, b, c] => MarkerBytes::Three(a, b, c),
+            _ => {
+                let mut table = Box::new([false; 256]);
+                for byte in bytes {
+                    table[usize::from(byte)] = true;
+                }
+                MarkerBytes::Many(table)
+            }
+        }
+    }
+
+    fn occur_in(&self, line: &[u8]) -> bool {
+        match self {
+            MarkerBytes::None => false,
+            MarkerBytes::One(a) => memchr::memchr(*a, line).is_some(),
+            MarkerBytes::Two(a, b) => memchr::memchr2(*a, *b, line).is_some(),
+            MarkerBytes::Three(a, b, c) => memchr::memchr3(*a, *b, *c, line).is_some(),
+            MarkerBytes::Many(table) => line.iter().any(|&byte| table[usize::from(byte)]),
+            MarkerBytes::All => true,
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &str) -> Option<usize> {
+    memchr::memmem::find(haystack, needle.as_bytes())
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    find(haystack, needle).is_some()
+}
+
+/// `str::trim` on bytes. ASCII whitespace is stripped directly; only when a non-ASCII byte
+/// is left at either end is that end decoded, to strip Unicode whitespace such as U+00A0.
+/// Invalid UTF-8 is not whitespace.
+fn trim(line: &[u8]) -> &[u8] {
+    let is_space = |b: &u8| matches!(b, b' ' | b'\t'..=b'\r');
+    let start = line.iter().position(|b| !is_space(b)).unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !is_space(b))
+        .map_or(start, |pos| pos + 1);
+    let mut trimmed = &line[start..end];
+    if trimmed.first().is_some_and(|b| !b.is_ascii())
+        && let Some(chunk) = trimmed.utf8_chunks().next()
+    {
+        let valid = chunk.valid();
+        trimmed = &trimmed[valid.len() - valid.trim_start().len()..];
+    }
+    if trimmed.last().is_some_and(|b| !b.is_ascii())
+        && let Some(chunk) = trimmed.utf8_chunks().last()
+        && chunk.invalid().is_empty()
+    {
+        let valid = chunk.valid();
+        trimmed = &trimmed[..trimmed.len() - (valid.len() - valid.trim_end().len())];
+    }
+    trimmed
+}
+
+/// REQ-4.2, REQ-4.3: Classifies the lines of one file in order, carrying the multi-line
+/// comment state from line to line. Without a language every non-empty line is logical, as
+/// is, outside comments, every non-blank line without a byte that starts a comment marker.
+pub struct LineClassifier {
+    parser: Option<CommentParser>,
+    in_multiline: bool,
+    depth: usize,
+}
+
+/// The multi-line comment state a [`LineClassifier`] carries between two lines, to classify
+/// a file in parts: the state after the last line of a part is the state before the first
+/// line of the next. The default is outside any comment, as at the start of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommentState {
+    in_multiline: bool,
+    depth: usize,
+}
+
+impl LineClassifier {
+    pub fn new(language: Option<&Language>, ignore_preprocessor: bool) -> Self {
+        Self {
+            parser: language.map(|lang| CommentParser::new(lang.clone(), ignore_preprocessor)),
+            in_multiline: false,
+            depth: 0,
+        }
+    }
+
+    pub fn classify(&mut self, line: &str) -> LineType {
+        self.classify_bytes(line.as_bytes())
+    }
+
+    /// The comment state after the lines classified so far
+    pub fn state(&self) -> CommentState {
+        CommentState {
+            in_multiline: self.in_multiline,
+            depth: self.depth,
+        }
+    }
+
+    /// Continue from `state`, as if the lines before had been classified
+    pub fn set_state(&mut self, state: CommentState) {
+        self.in_multiline = state.in_multiline;
+        self.depth = state.depth;
+    }
+
+    /// [`classify`](Self::classify) on the raw bytes of a line, without its line ending, so
+    /// a file can be read into one reused buffer instead of a `String` per line
+    pub fn classify_bytes(&mut self, line: &[u8]) -> LineType {
+        // An empty line changes no comment state
+        if line.is_empty() {
+            return LineType::Empty;
+        }
+        let Some(parser) = self
+            .parser
+            .as_ref()
+            .filter(|parser| self.in_multiline || self.depth > 0 || parser.markers.occur_in(line))
+        else {
+            return if trim(line).is_empty() {
+                LineType::Empty
+            } else {
+                LineType::Logical
+            };
+        };
+        // A line inside a multi-line comment is a comment line unless it is blank
+        if parser.multiline_bytes(line, &mut self.in_multiline, &mut self.depth) {
+            if trim(line).is_empty() {
+                LineType::Empty
+            } else {
+                LineType::Comment
+            }
+        } else {
+            parser.parse_bytes(line)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LineType {
     Empty,
@@ -0,0 +1,169 @@
+// lib.rs - Library crate: SLOC counting for other Rust tools
+// Implements:
+//   REQ-1.1: Logical, comment, empty lines counting
+//   REQ-3.1-3.4: Language detection and configuration
+//   REQ-6.4: Reports
+//   REQ-7.2: Comparison
+//
+// The `rustedbytes-counterlines` binary is a consumer of this crate: `main.rs` only parses
+// the command line and dispatches to the command modules, which are public for it but
+// hidden from the documentation.
+
+//! Source line counting: language detection, per-line classification, reports and
+//! comparisons, as used by the `rustedbytes-counterlines` command-line tool.
+//!
+//! ```no_run
+//! use counterlines::{LanguageDetector, Report, compare_reports, count_file};
+//! use std::path::Path;
+//!
+//! let detector = LanguageDetector::new();
+//! let stats = count_file(Path::new("src/main.rs"), &detector, false)?;
+//! println!("{}: {} logical lines", stats.language, stats.logical_lines);
+//!
+//! // A report of several files, with per-language and global summaries
+//! let report = Report::new(vec![stats], Vec::new());
+//! let comparison = compare_reports(&report, &report);
+//! assert_eq!(comparison.global_delta.logical_lines_delta, 0);
+//! # Ok::<(), counterlines::SlocError>(())
+//! ```
+//!
+//! [`Counter`] counts files, directories and glob patterns as the `count` command does,
+//! with exclusions and its own thread pool. Languages come from [`LanguageDetector`], which
+//! knows the built-in languages and can load more from a configuration file; a file's lines
+//! are classified one at a time with [`LineClassifier`] (or [`CommentParser`] for single
+//! lines without state).
+//!
+//! The report and comparison types are in [`model`], with the guarantees of their
+//! serialized form.
+//!
+//! Cargo features:
+//!
+//! - `cli` (default): the command-line tool and the report formats. Library users who
+//!   only count can turn it off with `default-features = false`.
+//! - `fs`: counting from the filesystem ([`Counter`], [`count_file`], [`count_files`]) and
+//!   [`compare_reports`]; enabled by `cli`. Without it only [`count_reader`] counts, from
+//!   any reader.
+//! - `ffi`: the C interface (`ffi` module).
+//! - `wasm`: the JavaScript interface for WebAssembly builds (`wasm` module).
+
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "fs")]
+pub mod compare;
+pub mod counter;
+pub mod encoding;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod language;
+pub mod model;
+#[cfg(feature = "cli")]
+pub mod processor;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod report;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "fs")]
+pub use compare::compare_reports;
+#[cfg(feature = "fs")]
+pub use counter::{
+    CancelOnDrop, CancelToken, CountStream, Counter, CounterBuilder, FileResult, ReadStrategy,
+    collect_input_paths, count_file, count_file_with, count_files,
+};
+pub use counter::{MINIFIED_SUFFIX, count_reader};
+pub use encoding::TextEncoding;
+pub use error::{Result, SlocError};
+pub use language::{
+    CommentParser, CommentState, Language, LanguageDetector, LineClassifier, LineType,
+};
+pub use model::{ComparisonResult, FileStats, GlobalSummary, LanguageStats, Report};
+
+// Command implementations of the binary
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod annotate;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod bench;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod check;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod daemon;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod explain;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod history;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod init;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod logging;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod merge;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod output;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod query;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod record;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod serve;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod settings;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod trend;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod tui;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod validate;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod watch;
+
+#[cfg(feature = "cli")]
+mod cache;
+#[cfg(feature = "cli")]
+mod checkpoint;
+#[cfg(feature = "cli")]
+mod compression;
+mod config;
+#[cfg(feature = "cli")]
+mod diff;
+#[cfg(feature = "cli")]
+mod git;
+#[cfg(feature = "cli")]
+mod html;
+#[cfg(feature = "cli")]
+mod memory;
+#[cfg(feature = "cli")]
+mod migrate;
+#[cfg(feature = "cli")]
+mod plugin;
+#[cfg(feature = "cli")]
+mod progress;
+#[cfg(feature = "cli")]
+mod scopes;
+#[cfg(feature = "cli")]
+mod threshold;
+#[cfg(feature = "cli")]
+mod tree;
+#[cfg(feature = "cli")]
+mod tuning;
+#[cfg(feature = "cli")]
+mod xml;
@@ -0,0 +1,73 @@
+// logging.rs - Diagnostic logging of the command-line tool (`-v`, `--log-level`, `--log-file`)
+// Implements: REQ-8.4 (error handling), REQ-9.7 (metrics logging)
+//
+// The library reports warnings and the steps of a scan as `tracing` events; the binary
+// installs the subscriber that writes them. A normal run only shows warnings and errors on
+// stderr; -v adds the steps of a scan (info), -vv what is decided per file (debug) and -vvv
+// everything (trace), so a misbehaving scan can be looked into without rebuilding.
+// `--log-file` appends the log to a file instead, with timestamps and module names.
+
+use crate::cli::{ColorChoice, LogLevel};
+use crate::error::Result;
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+
+/// Install the logger of the binary, at `level`, else at the level of `verbose` (-v
+/// flags) above warnings
+pub fn init(
+    verbose: u8,
+    level: Option<LogLevel>,
+    file: Option<&Path>,
+    color: ColorChoice,
+) -> Result<()> {
+    let level = match level {
+        Some(level) => filter(level),
+        None => match verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        },
+    };
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+    match file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            builder
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .init();
+        }
+        None => {
+            let ansi = match color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => {
+                    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+                }
+            };
+            // Module names only help when looking into the details
+            builder
+                .with_writer(std::io::stderr)
+                .with_ansi(ansi)
+                .without_time()
+                .with_target(level >= LevelFilter::DEBUG)
+                .init();
+        }
+    }
+    Ok(())
+}
+
+fn filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Off => LevelFilter::OFF,
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    }
+}
@@ -2,25 +2,52 @@
 // Implements:
 //   REQ-8.1: CLI interface
 //   REQ-8.2: Help/version
-//   REQ-8.3: Subcommands (count, report, process, compare)
+//   REQ-8.3: Subcommands (count, report, process, compare, validate, merge, trend)
 //   REQ-8.4: Error handling
-
-mod cli;
-mod config;
-mod counter;
-mod error;
-mod language;
-mod output;
-mod processor;
-mod report;
+//
+// The binary is a thin consumer of the `counterlines` library (lib.rs): it parses the
+// command line and dispatches to the command modules.
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
+use counterlines::cli::{Cli, Commands};
+use counterlines::{
+    annotate, bench, check, counter, daemon, error, explain, history, init, logging, merge, output,
+    processor, query, record, report, serve, settings, trend, tui, validate, watch,
+};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // REQ-8.4: Same report as returning the error from main, distinct exit codes
+            eprintln!("Error: {:?}", e);
+            let code = e
+                .downcast_ref::<error::SlocError>()
+                .map_or(error::EXIT_ERROR, error::SlocError::exit_code);
+            ExitCode::from(code)
+        }
+    }
+}
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     // REQ-8.1: Provide a command-line interface
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    cli.resolve_config();
+
+    // Console colors: --color, NO_COLOR, terminal detection
+    output::configure_colors(cli.color);
+    output::configure_tables(cli.table_style);
+    output::configure_charset(cli.ascii);
+
+    // Warnings and, with -v or --log-level, the steps of the command
+    logging::init(
+        cli.verbose,
+        cli.log_level,
+        cli.log_file.as_deref(),
+        cli.color,
+    )?;
 
     // REQ-8.3: Support multiple commands
     match cli.command {
@@ -40,6 +67,74 @@ fn main() -> Result<()> {
             // REQ-8.3: compare command
             processor::execute_compare(args)?;
         }
+        Commands::Validate(args) => {
+            // Structural, version and consistency checks on a report
+            validate::execute_validate(args)?;
+        }
+        Commands::Verify(args) => {
+            // Checksum and summary integrity of a report
+            validate::execute_verify(args)?;
+        }
+        Commands::Query(args) => {
+            // Scriptable value extraction
+            query::execute_query(args)?;
+        }
+        Commands::Annotate(args) => {
+            // Per-line classification for diagnosing miscounts
+            annotate::execute_annotate(args)?;
+        }
+        Commands::Explain(args) => {
+            // Language detection diagnosis
+            explain::execute_explain(args)?;
+        }
+        Commands::Watch(args) => {
+            // Live recount while editing
+            watch::execute_watch(args)?;
+        }
+        Commands::Tui(args) => {
+            // Interactive report browser
+            tui::execute_tui(args)?;
+        }
+        Commands::Serve(args) => {
+            // Dashboard and REST API over stored reports
+            serve::execute_serve(args)?;
+        }
+        Commands::Merge(args) => {
+            // Aggregate reports of separate scans
+            merge::execute_merge(args)?;
+        }
+        Commands::Trend(args) => {
+            // Time series over historical reports
+            trend::execute_trend(args)?;
+        }
+        Commands::Init(args) => {
+            // Configuration scaffold
+            init::execute_init(args)?;
+        }
+        Commands::Check(args) => {
+            // Policy rules as a CI quality gate
+            check::execute_check(args)?;
+        }
+        Commands::History(args) => {
+            // Time series over git revisions
+            history::execute_history(args)?;
+        }
+        Commands::Record(args) => {
+            // Run history database
+            record::execute_record(args)?;
+        }
+        Commands::Bench(args) => {
+            // Throughput per thread count and read strategy
+            bench::execute_bench(args)?;
+        }
+        Commands::Daemon(args) => {
+            // Warm count service for editors and scripts
+            daemon::execute_daemon(args)?;
+        }
+        Commands::Config(args) => {
+            // Strict configuration check and effective values
+            settings::execute_config(args)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,96 @@
+// memory.rs - Resident memory of the process (metrics, `count --max-memory`)
+// Implements: REQ-9.6 (memory efficiency), REQ-9.7 (metrics logging)
+//
+// The resident set size is asked from the operating system (`/proc/self/statm` on Linux)
+// instead of being estimated from the sizes of the report structures. While a count runs, a
+// monitor thread samples it to record the peak and to tell the counting threads when the
+// `--max-memory` cap is exceeded.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Time between two samples of the monitor
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Resident memory of the process in bytes, when the platform reports it
+pub fn resident_bytes() -> Option<u64> {
+    memory_stats::memory_stats().map(|stats| stats.physical_mem as u64)
+}
+
+/// A size in bytes with a binary unit, e.g. `12.5 MiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Samples the resident memory on a thread of its own until finished
+pub struct MemoryMonitor {
+    peak: Arc<AtomicU64>,
+    over_limit: Arc<AtomicBool>,
+    /// Dropped to stop the thread
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MemoryMonitor {
+    /// Start sampling; `over_limit` turns true once the resident memory exceeds `limit` bytes
+    pub fn start(limit: Option<u64>) -> Self {
+        let peak = Arc::new(AtomicU64::new(resident_bytes().unwrap_or(0)));
+        let over_limit = Arc::new(AtomicBool::new(false));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = {
+            let peak = Arc::clone(&peak);
+            let over_limit = Arc::clone(&over_limit);
+            std::thread::spawn(move || {
+                loop {
+                    if let Some(bytes) = resident_bytes() {
+                        peak.fetch_max(bytes, Ordering::Relaxed);
+                        if limit.is_some_and(|limit| bytes > limit) {
+                            over_limit.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    if stopped.recv_timeout(SAMPLE_INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                        break;
+                    }
+                }
+            })
+        };
+        MemoryMonitor {
+            peak,
+            over_limit,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Whether the resident memory has exceeded the limit since the start
+    pub fn over_limit(&self) -> bool {
+        self.over_limit.load(Ordering::Relaxed)
+    }
+
+    /// Stop sampling and return the peak resident memory in bytes (0 when the platform does
+    /// not report it)
+    pub fn finish(mut self) -> u64 {
+        self.stop = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Some(bytes) = resident_bytes() {
+            self.peak.fetch_max(bytes, Ordering::Relaxed);
+        }
+        self.peak.load(Ordering::Relaxed)
+    }
+}
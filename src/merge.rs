@@ -0,0 +1,173 @@
+// merge.rs - Merge several reports into one (`merge`)
+// Implements: REQ-6.4 (report structure), REQ-6.8 (report export), REQ-7.1 (report processing)
+//
+// Files, unsupported, skipped and undecodable paths and the diagnostics of every input are
+// concatenated in input order, optionally below a per-report prefix; language and global summaries are recomputed from the merged
+// files. Directory summaries are not carried over (`process --by-dir` regroups them).
+
+use crate::cli::{ConflictPolicy, MergeArgs, OutputFormat, Verbosity};
+use crate::compression::is_stdout;
+use crate::error::{Result, SlocError};
+use crate::output::{ConsoleOutput, ReportExporter};
+use crate::report::{Diagnostic, FileStats, InvalidFile, Report, SkippedFile};
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+pub fn execute_merge(args: MergeArgs) -> Result<()> {
+    if !args.prefix.is_empty() && args.prefix.len() != args.reports.len() {
+        return Err(SlocError::Parse(format!(
+            "{} prefixes given for {} reports (use one per report, \"\" for none)",
+            args.prefix.len(),
+            args.reports.len()
+        )));
+    }
+
+    // REQ-6.8: `-o -` owns stdout
+    let verbosity = if is_stdout(&args.output) {
+        Verbosity::Silent
+    } else {
+        Verbosity::from_flags(args.quiet, args.silent)
+    };
+
+    let mut files: Vec<FileStats> = Vec::new();
+    let mut unsupported_files: Vec<PathBuf> = Vec::new();
+    // Path -> (index in `files`, report it came from)
+    let mut seen: HashMap<PathBuf, (usize, usize)> = HashMap::new();
+    let mut seen_unsupported = HashSet::new();
+    let mut skipped_files: Vec<SkippedFile> = Vec::new();
+    let mut seen_skipped = HashSet::new();
+    let mut invalid_files: Vec<InvalidFile> = Vec::new();
+    let mut seen_invalid = HashSet::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut conflicts = 0;
+
+    for (index, path) in args.reports.iter().enumerate() {
+        let format = OutputFormat::from_path(path).unwrap_or(OutputFormat::Json);
+        let report = Report::from_file(path, format)?;
+        // Its summaries cannot be recomputed from files it does not have
+        if report.is_totals_only() {
+            return Err(SlocError::Parse(format!(
+                "{} only has totals (its files were dropped by --max-memory) and cannot be merged",
+                path.display()
+            )));
+        }
+        let prefix = args.prefix.get(index).map(String::as_str).unwrap_or("");
+
+        if verbosity == Verbosity::Normal {
+            println!(
+                "  {} {} ({} files{})",
+                "+".green(),
+                path.display(),
+                report.files.len(),
+                if prefix.is_empty() {
+                    String::new()
+                } else {
+                    format!(", under {}", prefix)
+                }
+            );
+        }
+
+        for mut file in report.files {
+            file.path = prefixed(prefix, &file.path);
+            match seen.get(&file.path) {
+                None => {
+                    seen.insert(file.path.clone(), (files.len(), index));
+                    files.push(file);
+                }
+                Some(&(position, first)) => {
+                    conflicts += 1;
+                    match args.on_conflict {
+                        ConflictPolicy::Error => {
+                            return Err(SlocError::InvalidReportFormat(format!(
+                                "{} is listed in both {} and {} (use --prefix or --on-conflict first|last)",
+                                file.path.display(),
+                                args.reports[first].display(),
+                                path.display()
+                            )));
+                        }
+                        ConflictPolicy::First => {}
+                        ConflictPolicy::Last => files[position] = file,
+                    }
+                }
+            }
+        }
+
+        for unsupported in report.unsupported_files {
+            let unsupported = prefixed(prefix, &unsupported);
+            if seen_unsupported.insert(unsupported.clone()) {
+                unsupported_files.push(unsupported);
+            }
+        }
+
+        for mut skipped in report.skipped_files {
+            skipped.path = prefixed(prefix, &skipped.path);
+            if seen_skipped.insert(skipped.path.clone()) {
+                skipped_files.push(skipped);
+            }
+        }
+
+        for mut invalid in report.invalid_files {
+            invalid.path = prefixed(prefix, &invalid.path);
+            if seen_invalid.insert(invalid.path.clone()) {
+                invalid_files.push(invalid);
+            }
+        }
+
+        for mut diagnostic in report.diagnostics {
+            diagnostic.path = diagnostic.path.map(|path| prefixed(prefix, &path));
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    let mut merged = Report::new(files, unsupported_files);
+    let skipped = skipped_files.len();
+    merged.set_skipped(skipped_files, skipped);
+    merged.set_invalid(invalid_files);
+    merged.diagnostics = diagnostics;
+    // REQ-6.9: Checksum over the merged statistics
+    if args.checksum {
+        merged.calculate_checksum();
+    }
+
+    let format = args
+        .format
+        .or_else(|| OutputFormat::from_path(&args.output))
+        .unwrap_or(OutputFormat::Json);
+    ReportExporter::new(args.xml_style).export(&merged, &args.output, format)?;
+
+    let console = ConsoleOutput::new(None, None);
+    match verbosity {
+        Verbosity::Normal => {
+            if conflicts > 0 {
+                println!(
+                    "  {} duplicate path(s) resolved (kept {})",
+                    conflicts,
+                    if args.on_conflict == ConflictPolicy::Last {
+                        "last"
+                    } else {
+                        "first"
+                    }
+                );
+            }
+            console.display_compact(&merged);
+            println!("\nMerged report written to: {}", args.output.display());
+        }
+        Verbosity::Quiet => console.display_compact(&merged),
+        Verbosity::Silent => {}
+    }
+
+    Ok(())
+}
+
+/// `prefix/path`, ignoring a leading `./` of the path; absolute paths are kept
+fn prefixed(prefix: &str, path: &Path) -> PathBuf {
+    if prefix.is_empty() || path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let relative: PathBuf = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    Path::new(prefix).join(relative)
+}
@@ -0,0 +1,244 @@
+// migrate.rs - Report format versioning and migration of older reports
+// Implements: REQ-6.6 (format version), REQ-7.2 (comparing reports across releases)
+//
+// Format history:
+//   0.1.x  package version as format version; no comment lines, no unsupported files
+//   0.2.x  package version as format version; comment lines and unsupported files added
+//   1.0    first independent format version (adds the optional `generatorVersion`)
+//   1.1    optional `directories` section (`--by-dir`)
+//   1.2    optional `skippedFiles` and `summary.skipped_files` (languages not counted), the
+//          optional `encoding` of files not in UTF-8, the optional `invalidFiles`,
+//          `summary.invalid_files` and per-file `invalid_lines` (undecodable bytes), and the
+//          optional `diagnostics` (warnings of the scan)
+//
+// Checksums without the `sha256-v2:` prefix were written by earlier releases: a hash of
+// the file statistics concatenated without separators, which left out most of the report.
+// A loaded report whose legacy checksum still matches gets a current one, so `verify`
+// accepts it; one that does not match keeps it and fails verification.
+
+use crate::error::{Result, SlocError};
+use crate::report::{CHECKSUM_PREFIX, REPORT_FORMAT_VERSION, Report};
+use sha2::{Digest, Sha256};
+
+/// Layout a report was written with, derived from its `reportFormatVersion`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Written by a 0.1.x release
+    Release01,
+    /// Written by a 0.2.x release
+    Release02,
+    /// Independent format version `MAJOR.MINOR`
+    Format { major: u64, minor: u64 },
+}
+
+impl Layout {
+    /// Classify a `reportFormatVersion` value
+    pub fn of(version: &str) -> Result<Self> {
+        let (major, minor) = parse_version(version).ok_or_else(|| {
+            SlocError::InvalidReportFormat(format!(
+                "unrecognized report format version \"{}\" (expected MAJOR.MINOR)",
+                version
+            ))
+        })?;
+
+        match (major, minor) {
+            (0, 1) => Ok(Layout::Release01),
+            (0, 2) => Ok(Layout::Release02),
+            (0, _) => Err(SlocError::InvalidReportFormat(format!(
+                "report format version {} does not match any release",
+                version
+            ))),
+            _ => Ok(Layout::Format { major, minor }),
+        }
+    }
+
+    /// Human readable description used in notes and `validate` output
+    pub fn describe(&self) -> String {
+        match self {
+            Layout::Release01 => "release 0.1.x layout".to_string(),
+            Layout::Release02 => "release 0.2.x layout".to_string(),
+            Layout::Format { major, minor } => format!("format {}.{}", major, minor),
+        }
+    }
+}
+
+/// Check that this release can read a report with the given format version
+pub fn check(version: &str) -> Result<Layout> {
+    let layout = Layout::of(version)?;
+    let (current_major, _) = current_version();
+
+    if let Layout::Format { major, .. } = layout
+        && major != current_major
+    {
+        return Err(SlocError::InvalidReportFormat(if major > current_major {
+            format!(
+                "report format version {} is newer than the supported {}.x; \
+                 upgrade rustedbytes-counterlines to read it",
+                version, current_major
+            )
+        } else {
+            format!(
+                "report format version {} is no longer supported (supported: {}.x)",
+                version, current_major
+            )
+        }));
+    }
+    Ok(layout)
+}
+
+/// REQ-6.6: Bring a loaded report up to [`REPORT_FORMAT_VERSION`].
+/// Returns a note describing what was done, or `None` when nothing had to change.
+pub fn upgrade(report: &mut Report) -> Result<Option<String>> {
+    let note = upgrade_layout(report)?;
+    upgrade_checksum(report);
+    Ok(note)
+}
+
+fn upgrade_layout(report: &mut Report) -> Result<Option<String>> {
+    let original = report.report_format_version.clone();
+    let layout = check(&original)?;
+    let (_, current_minor) = current_version();
+
+    match layout {
+        Layout::Format { minor, .. } if minor > current_minor => {
+            // Same major: newer minors only add fields, which are ignored
+            return Ok(Some(format!(
+                "report format {} is newer than {}; fields unknown to this release are ignored",
+                original, REPORT_FORMAT_VERSION
+            )));
+        }
+        Layout::Format { minor, .. } => {
+            // Older minors lack only optional fields: nothing to convert
+            if minor < current_minor {
+                report.report_format_version = REPORT_FORMAT_VERSION.to_string();
+            }
+            return Ok(None);
+        }
+        Layout::Release01 => release_01_to_02(report),
+        // 0.2.x -> 1.x only adds optional fields (`generatorVersion` is filled in below)
+        Layout::Release02 => {}
+    }
+
+    // Legacy layouts stored the release version as format version
+    report.generator_version.get_or_insert(original.clone());
+    report.report_format_version = REPORT_FORMAT_VERSION.to_string();
+
+    Ok(Some(format!(
+        "upgraded from report format {} ({}) to {}",
+        original,
+        layout.describe(),
+        REPORT_FORMAT_VERSION
+    )))
+}
+
+/// 0.1.x did not classify comment lines: every line that was neither logical nor
+/// empty was a comment line. 0.1.x checksums did not cover comment lines either,
+/// so a present checksum is recomputed over the migrated statistics.
+fn release_01_to_02(report: &mut Report) {
+    for file in &mut report.files {
+        file.comment_lines = file
+            .total_lines
+            .saturating_sub(file.logical_lines + file.empty_lines);
+    }
+
+    report.languages = Report::calculate_language_stats(&report.files);
+    report.summary.comment_lines = report.files.iter().map(|f| f.comment_lines).sum();
+    report.summary.languages_count = report.languages.len();
+    report.summary.unsupported_files = report.unsupported_files.len();
+
+    if report.checksum.is_some() {
+        report.calculate_checksum();
+    }
+}
+
+/// REQ-6.9: Replace a legacy checksum by a current one if it matches the report
+fn upgrade_checksum(report: &mut Report) {
+    if let Some(checksum) = &report.checksum
+        && !checksum.starts_with(CHECKSUM_PREFIX)
+        && *checksum == legacy_checksum(report)
+    {
+        report.calculate_checksum();
+    }
+}
+
+/// Checksum as written before `sha256-v2:`: path, language and counts of each file in
+/// path order, then its metrics, all run together
+fn legacy_checksum(report: &Report) -> String {
+    let mut files: Vec<_> = report.files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.path.to_string_lossy().as_bytes());
+        hasher.update(file.language.as_bytes());
+        hasher.update(file.total_lines.to_string().as_bytes());
+        hasher.update(file.logical_lines.to_string().as_bytes());
+        hasher.update(file.comment_lines.to_string().as_bytes());
+        hasher.update(file.empty_lines.to_string().as_bytes());
+        for (name, value) in &file.metrics {
+            hasher.update(name.as_bytes());
+            hasher.update(value.to_string().as_bytes());
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// `1.0`, `0.2.2`, `0.3.0-beta.1` -> (major, minor)
+fn parse_version(version: &str) -> Option<(u64, u64)> {
+    let core = version.trim().split(['-', '+']).next()?;
+    let parts: Vec<u64> = core
+        .split('.')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [major, minor] | [major, minor, _] => Some((*major, *minor)),
+        _ => None,
+    }
+}
+
+fn current_version() -> (u64, u64) {
+    parse_version(REPORT_FORMAT_VERSION).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::FileStats;
+
+    fn report() -> Report {
+        let mut file = FileStats::new("src/main.rs", "Rust");
+        file.total_lines = 12;
+        file.logical_lines = 3;
+        file.comment_lines = 4;
+        file.empty_lines = 5;
+        Report::new(vec![file], vec![])
+    }
+
+    #[test]
+    fn matching_legacy_checksum_is_replaced() {
+        let mut report = report();
+        report.checksum = Some(legacy_checksum(&report));
+        upgrade(&mut report).unwrap();
+
+        let mut expected = report.clone();
+        expected.calculate_checksum();
+        assert!(
+            report
+                .checksum
+                .as_ref()
+                .unwrap()
+                .starts_with(CHECKSUM_PREFIX)
+        );
+        assert_eq!(report.checksum, expected.checksum);
+    }
+
+    #[test]
+    fn edited_legacy_checksum_is_kept() {
+        let mut report = report();
+        let legacy = legacy_checksum(&report);
+        report.checksum = Some(legacy.clone());
+        report.files[0].logical_lines = 4;
+        upgrade(&mut report).unwrap();
+        assert_eq!(report.checksum, Some(legacy));
+    }
+}
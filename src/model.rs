@@ -0,0 +1,699 @@
+// model.rs - Public data model: reports and comparisons
+// Implements:
+//   REQ-1.1: Comment lines in stats
+//   REQ-3.5: Unsupported files
+//   REQ-6.4: File/language/global summary
+//   REQ-6.5: Timestamp
+//   REQ-6.6: Format version
+//   REQ-6.9: Checksum
+//   REQ-7.2: Comparison
+//
+// The types written and read by every command, kept apart from the code that builds them
+// (`report.rs`, `processor.rs`) so their shape only changes on purpose.
+
+//! Reports and comparisons, as written by `count -f json` and `compare --print json`.
+//!
+//! The serialized field names are part of the report format: they only change with a new
+//! major [`REPORT_FORMAT_VERSION`](crate::report::REPORT_FORMAT_VERSION), and a minor
+//! version only adds fields that older readers can ignore and that read as their default
+//! when absent. The Rust field names follow the serialized ones (`Report` fields are
+//! camelCase in JSON).
+//!
+//! Types computed by the library are `#[non_exhaustive]`: they gain fields in minor
+//! releases, so they are read field by field rather than built or destructured.
+//! [`FileStats`] is too, but it can be built by hand, to make a [`Report`] of counts from
+//! elsewhere: [`FileStats::new`] starts with no lines, and its public fields are then set
+//! one by one, so new fields keep their default.
+//!
+//! Every type serializes and deserializes without loss:
+//!
+//! ```
+//! use counterlines::model::{FileStats, Report};
+//!
+//! let mut file = FileStats::new("src/main.rs", "Rust");
+//! file.total_lines = 12;
+//! file.logical_lines = 8;
+//! file.comment_lines = 2;
+//! file.empty_lines = 2;
+//! file.metrics.insert("complexity".to_string(), 3.0);
+//! let report = Report::new(vec![file], vec!["logo.png".into()]);
+//!
+//! let json = serde_json::to_string(&report).unwrap();
+//! assert!(json.contains(r#""reportFormatVersion":"#));
+//! assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+//! ```
+//!
+//! ```
+//! use counterlines::model::ComparisonResult;
+//!
+//! let json = r#"{
+//!     "report1_generated": "2025-01-01T00:00:00Z",
+//!     "report2_generated": "2025-02-01T00:00:00Z",
+//!     "global_delta": {"files_delta": 1, "total_lines_delta": 10, "logical_lines_delta": 7,
+//!                      "comment_lines_delta": 2, "empty_lines_delta": 1, "languages_delta": 0},
+//!     "language_deltas": [{"language": "Rust", "files_delta": 1, "total_lines_delta": 10,
+//!                          "logical_lines_delta": 7, "comment_lines_delta": 2,
+//!                          "empty_lines_delta": 1}],
+//!     "new_files": ["src/new.rs"],
+//!     "removed_files": [],
+//!     "renamed_files": [{"from": "src/old.rs", "to": "src/lib.rs"}],
+//!     "modified_files": [{"path": "src/main.rs", "total_lines_delta": 2,
+//!                         "logical_lines_delta": 2, "comment_lines_delta": 0,
+//!                         "empty_lines_delta": 0}],
+//!     "ignored_modified_files": 0,
+//!     "unchanged_files": 3
+//! }"#;
+//! let comparison: ComparisonResult = serde_json::from_str(json).unwrap();
+//! let again = serde_json::to_string(&comparison).unwrap();
+//! assert_eq!(serde_json::from_str::<ComparisonResult>(&again).unwrap(), comparison);
+//! assert_eq!(comparison.global_delta.logical_lines_delta, 7);
+//! ```
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// REQ-6.4: File statistics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Line counts of a single source file")]
+/// REQ-1.1: File statistics including comment lines
+#[non_exhaustive]
+pub struct FileStats {
+    pub path: PathBuf,
+    pub language: String,
+    pub total_lines: usize,
+    pub logical_lines: usize,
+    /// Absent in 0.1.x reports (filled in by `migrate.rs`)
+    #[serde(default)]
+    pub comment_lines: usize,
+    pub empty_lines: usize,
+    /// Extra named metrics returned by `--metric-plugin` commands
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+    /// Encoding the file was decoded from (e.g. `UTF-16LE`, `windows-1252`); absent for
+    /// UTF-8 and before format 1.2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Lines with bytes the encoding cannot decode, counted unless `--on-invalid-utf8`
+    /// left them out; absent when there are none and before format 1.2
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub invalid_lines: usize,
+}
+
+impl FileStats {
+    /// Statistics of the file at `path` in `language`, with no lines yet
+    pub fn new(path: impl Into<PathBuf>, language: impl Into<String>) -> Self {
+        FileStats {
+            path: path.into(),
+            language: language.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A file of a known language left out by `languages.only`/`disabled` (`--only-lang`,
+/// `--exclude-lang`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A file of a language that was not counted")]
+#[non_exhaustive]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub language: String,
+}
+
+/// A file with bytes its encoding cannot decode (`--on-invalid-utf8`); it is among the
+/// counted files unless `skip-file` left it out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A file with bytes its encoding cannot decode")]
+#[non_exhaustive]
+pub struct InvalidFile {
+    pub path: PathBuf,
+    /// Lines holding such bytes
+    pub invalid_lines: usize,
+}
+
+/// A warning of the scan: a path it could not read or left out, so the counts may miss
+/// files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A warning about a path the scan could not read or left out")]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    /// Path concerned, absent when the warning has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub message: String,
+}
+
+/// What a [`Diagnostic`] is about; the codes are stable, and new ones may be added
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum DiagnosticCode {
+    /// A path matched by a glob pattern could not be read
+    GlobError,
+    /// A directory or entry could not be read while walking a directory
+    UnreadablePath,
+    /// A path read from standard input (`--stdin`) does not exist
+    MissingStdinPath,
+    /// A directory given without `-r`, whose files were not counted
+    DirectoryNotRecursive,
+    /// A file could not be counted; it is listed with the unsupported files
+    UnreadableFile,
+    /// A metric plugin failed on a file, whose metrics are missing
+    PluginError,
+    /// The per-file results were dropped (`count --max-memory`): the report only has the
+    /// language and global totals, and no file list
+    TotalsOnly,
+    /// A code of a newer release
+    #[serde(other)]
+    Other,
+}
+
+impl DiagnosticCode {
+    /// The serialized code, e.g. `glob-error`
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiagnosticCode::GlobError => "glob-error",
+            DiagnosticCode::UnreadablePath => "unreadable-path",
+            DiagnosticCode::MissingStdinPath => "missing-stdin-path",
+            DiagnosticCode::DirectoryNotRecursive => "directory-not-recursive",
+            DiagnosticCode::UnreadableFile => "unreadable-file",
+            DiagnosticCode::PluginError => "plugin-error",
+            DiagnosticCode::TotalsOnly => "totals-only",
+            DiagnosticCode::Other => "other",
+        }
+    }
+
+    /// The code named `name`, `Other` for a code of a newer release
+    pub fn from_name(name: &str) -> Self {
+        [
+            DiagnosticCode::GlobError,
+            DiagnosticCode::UnreadablePath,
+            DiagnosticCode::MissingStdinPath,
+            DiagnosticCode::DirectoryNotRecursive,
+            DiagnosticCode::UnreadableFile,
+            DiagnosticCode::PluginError,
+            DiagnosticCode::TotalsOnly,
+        ]
+        .into_iter()
+        .find(|code| code.name() == name)
+        .unwrap_or(DiagnosticCode::Other)
+    }
+}
+
+/// REQ-6.4: Language summary statistics (includes comment lines per REQ-1.1)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Line counts aggregated per language")]
+#[non_exhaustive]
+pub struct LanguageStats {
+    pub language: String,
+    pub file_count: usize,
+    pub total_lines: usize,
+    pub logical_lines: usize,
+    /// Absent in 0.1.x reports (filled in by `migrate.rs`)
+    #[serde(default)]
+    pub comment_lines: usize,
+    pub empty_lines: usize,
+}
+
+/// Directory summary statistics (`--by-dir`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Line counts aggregated per directory, up to the --by-dir depth")]
+#[non_exhaustive]
+pub struct DirectoryStats {
+    pub path: PathBuf,
+    pub file_count: usize,
+    pub total_lines: usize,
+    pub logical_lines: usize,
+    pub comment_lines: usize,
+    pub empty_lines: usize,
+}
+
+/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7: Report structure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Source line count report written by rustedbytes-counterlines")]
+#[serde(rename_all = "camelCase")]
+/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7: Report structure (includes comment lines per REQ-1.1)
+/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7, REQ-3.5: Report structure (includes unsupported files)
+#[non_exhaustive]
+pub struct Report {
+    /// REQ-6.6: Report format version
+    pub report_format_version: String,
+
+    /// REQ-6.5: Generation timestamp (RFC 3339 / ISO 8601)
+    pub generated_at: DateTime<Utc>,
+
+    /// REQ-6.4: Per-file statistics
+    pub files: Vec<FileStats>,
+
+    /// Language summaries
+    pub languages: Vec<LanguageStats>,
+
+    /// Directory summaries, only present when grouped with `--by-dir`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<DirectoryStats>,
+
+    /// Global summary
+    pub summary: GlobalSummary,
+
+    /// REQ-3.5: List of unsupported files (excluded from statistics, absent in 0.1.x reports)
+    #[serde(default)]
+    pub unsupported_files: Vec<std::path::PathBuf>,
+
+    /// Files of languages that were not counted (excluded from statistics, absent before
+    /// format 1.2)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_files: Vec<SkippedFile>,
+
+    /// Files with bytes their encoding cannot decode, whether counted or not (absent before
+    /// format 1.2)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_files: Vec<InvalidFile>,
+
+    /// Warnings of the scan about paths it could not read or left out (absent before
+    /// format 1.2, and when there were none)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Release that wrote the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_version: Option<String>,
+
+    /// REQ-6.9: Optional checksum: `sha256-v2:` and the SHA-256 of the counted fields (a
+    /// bare hash of the file statistics before)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Line counts aggregated over all files")]
+/// REQ-6.4: Global summary statistics (includes comment lines per REQ-1.1)
+#[non_exhaustive]
+pub struct GlobalSummary {
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub logical_lines: usize,
+    /// Absent in 0.1.x reports (filled in by `migrate.rs`)
+    #[serde(default)]
+    pub comment_lines: usize,
+    pub empty_lines: usize,
+    pub languages_count: usize,
+    /// Absent in 0.1.x reports
+    #[serde(default)]
+    pub unsupported_files: usize,
+    /// Absent before format 1.2, and when no file was skipped
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub skipped_files: usize,
+    /// Absent before format 1.2, and when every file decoded
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub invalid_files: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// REQ-7.2: Comparison result structure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ComparisonResult {
+    pub report1_generated: chrono::DateTime<chrono::Utc>,
+    pub report2_generated: chrono::DateTime<chrono::Utc>,
+    pub global_delta: GlobalDelta,
+    pub language_deltas: Vec<LanguageDelta>,
+    /// `--by-dir`: deltas per directory, only when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directory_deltas: Vec<DirectoryDelta>,
+    /// `--summary-only`: the file lists and counts below were not computed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summary_only: bool,
+    pub new_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    /// Files moved between the reports, matched by identical statistics
+    #[serde(default)]
+    pub renamed_files: Vec<RenamedFile>,
+    pub modified_files: Vec<FileDelta>,
+    /// Modified files left out as noise (`--min-delta`, `--min-delta-pct`)
+    #[serde(default)]
+    pub ignored_modified_files: usize,
+    /// Files present in both reports with identical statistics
+    #[serde(default)]
+    pub unchanged_files: usize,
+    /// `--list-unchanged`: paths of the unchanged files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unchanged_file_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct GlobalDelta {
+    pub files_delta: i64,
+    pub total_lines_delta: i64,
+    pub logical_lines_delta: i64,
+    /// Absent in comparisons exported before comment deltas were tracked
+    #[serde(default)]
+    pub comment_lines_delta: i64,
+    pub empty_lines_delta: i64,
+    pub languages_delta: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct LanguageDelta {
+    pub language: String,
+    pub files_delta: i64,
+    pub total_lines_delta: i64,
+    pub logical_lines_delta: i64,
+    /// Absent in comparisons exported before comment deltas were tracked
+    #[serde(default)]
+    pub comment_lines_delta: i64,
+    pub empty_lines_delta: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DirectoryDelta {
+    pub path: String,
+    pub files_delta: i64,
+    pub total_lines_delta: i64,
+    pub logical_lines_delta: i64,
+    /// Absent in comparisons exported before comment deltas were tracked
+    #[serde(default)]
+    pub comment_lines_delta: i64,
+    pub empty_lines_delta: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FileDelta {
+    pub path: String,
+    pub total_lines_delta: i64,
+    pub logical_lines_delta: i64,
+    /// Absent in comparisons exported before comment deltas were tracked
+    #[serde(default)]
+    pub comment_lines_delta: i64,
+    pub empty_lines_delta: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use std::fmt::Debug;
+
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value, "{json}");
+    }
+
+    fn date(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn file() -> FileStats {
+        let mut file = FileStats::new("src/main.rs", "Rust");
+        file.total_lines = 12;
+        file.logical_lines = 7;
+        file.comment_lines = 3;
+        file.empty_lines = 2;
+        file.metrics.insert("complexity".to_string(), 2.5);
+        file.encoding = Some("UTF-16LE".to_string());
+        file.invalid_lines = 1;
+        file
+    }
+
+    fn report() -> Report {
+        Report {
+            report_format_version: "1.2".to_string(),
+            generated_at: date("2025-03-01T12:30:00Z"),
+            files: vec![file(), FileStats::new("src/lib.rs", "Rust")],
+            languages: vec![LanguageStats {
+                language: "Rust".to_string(),
+                file_count: 2,
+                total_lines: 12,
+                logical_lines: 7,
+                comment_lines: 3,
+                empty_lines: 2,
+            }],
+            directories: vec![DirectoryStats {
+                path: PathBuf::from("src"),
+                file_count: 2,
+                total_lines: 12,
+                logical_lines: 7,
+                comment_lines: 3,
+                empty_lines: 2,
+            }],
+            summary: GlobalSummary {
+                total_files: 2,
+                total_lines: 12,
+                logical_lines: 7,
+                comment_lines: 3,
+                empty_lines: 2,
+                languages_count: 1,
+                unsupported_files: 1,
+                skipped_files: 1,
+                invalid_files: 1,
+            },
+            unsupported_files: vec![PathBuf::from("logo.png")],
+            skipped_files: vec![SkippedFile {
+                path: PathBuf::from("build.py"),
+                language: "Python".to_string(),
+            }],
+            invalid_files: vec![InvalidFile {
+                path: PathBuf::from("src/main.rs"),
+                invalid_lines: 1,
+            }],
+            diagnostics: vec![
+                Diagnostic {
+                    code: DiagnosticCode::UnreadablePath,
+                    path: Some(PathBuf::from("private")),
+                    message: "permission denied".to_string(),
+                },
+                Diagnostic {
+                    code: DiagnosticCode::TotalsOnly,
+                    path: None,
+                    message: "per-file results dropped".to_string(),
+                },
+            ],
+            generator_version: Some("0.3.0".to_string()),
+            checksum: Some("abc123".to_string()),
+        }
+    }
+
+    fn comparison() -> ComparisonResult {
+        ComparisonResult {
+            report1_generated: date("2025-01-01T00:00:00Z"),
+            report2_generated: date("2025-02-01T00:00:00Z"),
+            global_delta: GlobalDelta {
+                files_delta: 1,
+                total_lines_delta: -10,
+                logical_lines_delta: -7,
+                comment_lines_delta: 2,
+                empty_lines_delta: 1,
+                languages_delta: 0,
+            },
+            language_deltas: vec![LanguageDelta {
+                language: "Rust".to_string(),
+                files_delta: 1,
+                total_lines_delta: -10,
+                logical_lines_delta: -7,
+                comment_lines_delta: 2,
+                empty_lines_delta: 1,
+            }],
+            directory_deltas: vec![DirectoryDelta {
+                path: "src".to_string(),
+                files_delta: 1,
+                total_lines_delta: -10,
+                logical_lines_delta: -7,
+                comment_lines_delta: 2,
+                empty_lines_delta: 1,
+            }],
+            summary_only: false,
+            new_files: vec!["src/new.rs".to_string()],
+            removed_files: vec!["src/gone.rs".to_string()],
+            renamed_files: vec![RenamedFile {
+                from: "src/old.rs".to_string(),
+                to: "src/lib.rs".to_string(),
+            }],
+            modified_files: vec![FileDelta {
+                path: "src/main.rs".to_string(),
+                total_lines_delta: 2,
+                logical_lines_delta: 2,
+                comment_lines_delta: 0,
+                empty_lines_delta: 0,
+            }],
+            ignored_modified_files: 1,
+            unchanged_files: 3,
+            unchanged_file_paths: vec!["README.md".to_string()],
+        }
+    }
+
+    #[test]
+    fn report_types_round_trip() {
+        let report = report();
+        round_trip(&report);
+        round_trip(&report.files[0]);
+        round_trip(&report.files[1]);
+        round_trip(&report.languages[0]);
+        round_trip(&report.directories[0]);
+        round_trip(&report.summary);
+        round_trip(&report.skipped_files[0]);
+        round_trip(&report.invalid_files[0]);
+        round_trip(&report.diagnostics[0]);
+        round_trip(&report.diagnostics[1]);
+    }
+
+    #[test]
+    fn comparison_types_round_trip() {
+        let comparison = comparison();
+        round_trip(&comparison);
+        round_trip(&comparison.global_delta);
+        round_trip(&comparison.language_deltas[0]);
+        round_trip(&comparison.directory_deltas[0]);
+        round_trip(&comparison.renamed_files[0]);
+        round_trip(&comparison.modified_files[0]);
+
+        let summary_only = ComparisonResult {
+            summary_only: true,
+            ..comparison
+        };
+        round_trip(&summary_only);
+    }
+
+    #[test]
+    fn diagnostic_codes_round_trip() {
+        for code in [
+            DiagnosticCode::GlobError,
+            DiagnosticCode::UnreadablePath,
+            DiagnosticCode::MissingStdinPath,
+            DiagnosticCode::DirectoryNotRecursive,
+            DiagnosticCode::UnreadableFile,
+            DiagnosticCode::PluginError,
+            DiagnosticCode::TotalsOnly,
+            DiagnosticCode::Other,
+        ] {
+            round_trip(&code);
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code.name()));
+            assert_eq!(DiagnosticCode::from_name(code.name()), code);
+        }
+        let newer: DiagnosticCode = serde_json::from_str("\"from-a-newer-release\"").unwrap();
+        assert_eq!(newer, DiagnosticCode::Other);
+        assert_eq!(
+            DiagnosticCode::from_name("from-a-newer-release"),
+            DiagnosticCode::Other
+        );
+    }
+
+    #[test]
+    fn optional_fields_are_left_out() {
+        let mut report = report();
+        report.files = vec![FileStats::new("src/lib.rs", "Rust")];
+        report.directories.clear();
+        report.skipped_files.clear();
+        report.invalid_files.clear();
+        report.diagnostics.clear();
+        report.summary.skipped_files = 0;
+        report.summary.invalid_files = 0;
+        report.generator_version = None;
+        report.checksum = None;
+        let json = serde_json::to_string(&report).unwrap();
+        for field in [
+            "metrics",
+            "encoding",
+            "invalid_lines",
+            "directories",
+            "skippedFiles",
+            "invalidFiles",
+            "diagnostics",
+            "generatorVersion",
+            "checksum",
+            "skipped_files",
+            "invalid_files",
+        ] {
+            assert!(!json.contains(&format!("\"{field}\"")), "{field} in {json}");
+        }
+        round_trip(&report);
+
+        let mut comparison = comparison();
+        comparison.directory_deltas.clear();
+        comparison.unchanged_file_paths.clear();
+        let json = serde_json::to_string(&comparison).unwrap();
+        for field in ["directory_deltas", "summary_only", "unchanged_file_paths"] {
+            assert!(!json.contains(&format!("\"{field}\"")), "{field} in {json}");
+        }
+        round_trip(&comparison);
+    }
+
+    #[test]
+    fn reads_a_report_without_the_newer_fields() {
+        // As written by 0.1.x: no comment lines, unsupported files or later additions
+        let json = r#"{
+            "reportFormatVersion": "1.0",
+            "generatedAt": "2024-06-01T08:00:00Z",
+            "files": [{"path": "src/main.rs", "language": "Rust", "total_lines": 10,
+                       "logical_lines": 8, "empty_lines": 2}],
+            "languages": [{"language": "Rust", "file_count": 1, "total_lines": 10,
+                           "logical_lines": 8, "empty_lines": 2}],
+            "summary": {"total_files": 1, "total_lines": 10, "logical_lines": 8,
+                        "empty_lines": 2, "languages_count": 1}
+        }"#;
+        let report: Report = serde_json::from_str(json).unwrap();
+        let file = &report.files[0];
+        assert_eq!((file.total_lines, file.comment_lines), (10, 0));
+        assert!(file.metrics.is_empty());
+        assert_eq!((file.encoding.as_deref(), file.invalid_lines), (None, 0));
+        assert_eq!(report.languages[0].comment_lines, 0);
+        assert_eq!(report.summary.comment_lines, 0);
+        assert_eq!(report.summary.unsupported_files, 0);
+        assert_eq!(report.summary.skipped_files, 0);
+        assert_eq!(report.summary.invalid_files, 0);
+        assert!(report.directories.is_empty());
+        assert!(report.unsupported_files.is_empty());
+        assert!(report.skipped_files.is_empty());
+        assert!(report.invalid_files.is_empty());
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.generator_version, None);
+        assert_eq!(report.checksum, None);
+        round_trip(&report);
+    }
+
+    #[test]
+    fn reads_a_comparison_without_the_newer_fields() {
+        let json = r#"{
+            "report1_generated": "2025-01-01T00:00:00Z",
+            "report2_generated": "2025-02-01T00:00:00Z",
+            "global_delta": {"files_delta": 1, "total_lines_delta": 10,
+                             "logical_lines_delta": 7, "empty_lines_delta": 1,
+                             "languages_delta": 0},
+            "language_deltas": [{"language": "Rust", "files_delta": 1,
+                                 "total_lines_delta": 10, "logical_lines_delta": 7,
+                                 "empty_lines_delta": 1}],
+            "new_files": [],
+            "removed_files": [],
+            "modified_files": [{"path": "src/main.rs", "total_lines_delta": 2,
+                                "logical_lines_delta": 2, "empty_lines_delta": 0}]
+        }"#;
+        let comparison: ComparisonResult = serde_json::from_str(json).unwrap();
+        assert_eq!(comparison.global_delta.comment_lines_delta, 0);
+        assert_eq!(comparison.language_deltas[0].comment_lines_delta, 0);
+        assert_eq!(comparison.modified_files[0].comment_lines_delta, 0);
+        assert!(comparison.directory_deltas.is_empty());
+        assert!(!comparison.summary_only);
+        assert!(comparison.renamed_files.is_empty());
+        assert_eq!(comparison.ignored_modified_files, 0);
+        assert_eq!(comparison.unchanged_files, 0);
+        assert!(comparison.unchanged_file_paths.is_empty());
+        round_trip(&comparison);
+    }
+}
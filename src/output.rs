@@ -9,34 +9,192 @@
 //   REQ-6.7: Output options
 //   REQ-6.8: Output path
 
-use crate::cli::{OutputFormat, SortMetric};
+use crate::cli::{
+    ColorChoice, Column, OutputFormat, PathStyle, PrintFormat, SortMetric, TableStyle, XmlStyle,
+};
+use crate::compression::create_writer;
 use crate::error::{Result, SlocError};
-use crate::report::Report;
+use crate::plugin::ExternalExporter;
+use crate::report::{
+    DirectoryStats, FileStats, LanguageStats, Report, common_directory, style_path,
+};
 use colored::Colorize;
 use num_format::{Locale, ToFormattedString};
 use prettytable::{Cell, Row, Table};
-use std::fs::File;
+use serde::Serialize;
+use serde::ser::{SerializeMap, Serializer};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Rows shown by `--details` when neither `--top` nor `--all-files` is given
+pub const DEFAULT_TOP_FILES: usize = 20;
+
+/// Which files the file details table shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileRows {
+    /// The N largest files by the sort metric
+    Top(usize),
+    /// Every file
+    All,
+}
+
+impl FileRows {
+    /// `--top N` limits the table, `--show-files` shows every file, `--details` alone
+    /// shows the default top
+    pub fn from_flags(details: bool, top: Option<usize>, show_files: bool) -> Option<Self> {
+        match top {
+            Some(n) => Some(FileRows::Top(n)),
+            None if show_files => Some(FileRows::All),
+            None if details => Some(FileRows::Top(DEFAULT_TOP_FILES)),
+            None => None,
+        }
+    }
+}
 
 pub struct ConsoleOutput {
     sort_metric: Option<SortMetric>,
-    details: bool,
+    /// File details, `None` unless requested
+    file_rows: Option<FileRows>,
+    /// `--show-unsupported`: list the unsupported files
+    show_unsupported: bool,
+    /// `--tree` depth
+    tree_depth: Option<usize>,
+    /// `--lang`: languages shown in the language and file tables (all when empty)
+    languages: Vec<String>,
+    /// `--paths`: path form in the file tables (relative to the scanned root when not given)
+    path_style: Option<PathStyle>,
+    /// `--columns`: metric columns, the defaults when empty
+    columns: Vec<Column>,
+}
+
+/// Language table columns without `--columns`
+const DEFAULT_LANGUAGE_COLUMNS: &[Column] = &[
+    Column::Files,
+    Column::Total,
+    Column::Logical,
+    Column::Comment,
+    Column::Empty,
+    Column::Density,
+    Column::Share,
+];
+/// File table columns without `--columns`
+const DEFAULT_FILE_COLUMNS: &[Column] = &[
+    Column::Total,
+    Column::Logical,
+    Column::Comment,
+    Column::Empty,
+];
+
+/// Line counts behind one table row
+struct Counts {
+    files: usize,
+    total: usize,
+    logical: usize,
+    comment: usize,
+    empty: usize,
+}
+
+impl Column {
+    fn header(self) -> Cell {
+        let title = match self {
+            Column::Files => "Files",
+            Column::Total => "Total",
+            Column::Logical => "Logical",
+            Column::Comment => "Comment",
+            Column::Empty => "Empty",
+            Column::Density => "Density %",
+            Column::Share => "Share",
+        };
+        Cell::new(title).style_spec("br")
+    }
+
+    /// Value cell; `Share` is added separately by `add_share_column`
+    fn cell(self, counts: &Counts) -> Cell {
+        let text = match self {
+            Column::Files => counts.files.to_formatted_string(&Locale::en),
+            Column::Total => counts.total.to_formatted_string(&Locale::en),
+            Column::Logical => counts.logical.to_formatted_string(&Locale::en),
+            Column::Comment => counts.comment.to_formatted_string(&Locale::en),
+            Column::Empty => counts.empty.to_formatted_string(&Locale::en),
+            Column::Density => format!("{:.2} %", density(counts.logical, counts.total)),
+            Column::Share => String::new(),
+        };
+        Cell::new(&text).style_spec("r")
+    }
 }
 
 impl ConsoleOutput {
-    pub fn new(sort_metric: Option<SortMetric>, details: bool) -> Self {
+    pub fn new(sort_metric: Option<SortMetric>, file_rows: Option<FileRows>) -> Self {
         Self {
             sort_metric,
-            details,
+            file_rows,
+            show_unsupported: false,
+            tree_depth: None,
+            languages: Vec::new(),
+            path_style: None,
+            columns: Vec::new(),
         }
     }
 
+    /// Show these metric columns in the language and file tables
+    pub fn with_columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Metric columns of a table: `--columns` (minus those the table cannot show)
+    /// or its defaults
+    fn columns(&self, defaults: &[Column], unsupported: &[Column]) -> Vec<Column> {
+        if self.columns.is_empty() {
+            return defaults.to_vec();
+        }
+        self.columns
+            .iter()
+            .copied()
+            .filter(|c| !unsupported.contains(c))
+            .collect()
+    }
+
+    /// Show file paths in this form instead of the bare file name
+    pub fn with_paths(mut self, style: Option<PathStyle>) -> Self {
+        self.path_style = style;
+        self
+    }
+
+    /// Only show these languages (case-insensitive) in the language and file tables
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    fn shows_language(&self, language: &str) -> bool {
+        self.languages.is_empty()
+            || self
+                .languages
+                .iter()
+                .any(|l| l.eq_ignore_ascii_case(language))
+    }
+
+    /// Also list the unsupported files
+    pub fn with_unsupported(mut self, show: bool) -> Self {
+        self.show_unsupported = show;
+        self
+    }
+
+    /// Also show the directory tree, `depth` levels deep
+    pub fn with_tree(mut self, depth: Option<usize>) -> Self {
+        self.tree_depth = depth;
+        self
+    }
+
     /// REQ-5.1, REQ-5.2, REQ-5.3: Display summary tables (global, language, file, unsupported)
     pub fn display_summary(&self, report: &Report) -> Result<()> {
-        println!("\n{}", "═".repeat(80).blue());
+        println!("\n{}", heavy_rule(80).blue());
         println!("{}", "Source Lines of Code (SLOC) Report".bold().cyan());
-        println!("{}", "═".repeat(80).blue());
+        println!("{}", heavy_rule(80).blue());
 
         // Global summary
         self.display_global_summary(report);
@@ -44,25 +202,80 @@ impl ConsoleOutput {
         // Language summary (REQ-5.2)
         self.display_language_summary(report);
 
-        // File details and unsupported files only if --details is set
-        if self.details {
-            if report.files.len() <= 20 {
-                self.display_file_details(report);
-            } else {
-                println!(
-                    "\n{}",
-                    format!(
-                        "({} files processed, use --sort to see details)",
-                        report.files.len()
-                    )
-                    .yellow()
-                );
+        // Directory summary (--by-dir, or recorded in a processed report)
+        if !report.directories.is_empty() {
+            self.display_directory_summary(report);
+        }
+
+        // Directory tree (--tree)
+        if let Some(depth) = self.tree_depth {
+            println!("\n{}", "Directory Tree".bold().green());
+            println!("{}", rule(80).green());
+            crate::tree::display_tree(&report.files, depth);
+        }
+
+        // File details only if --details, --show-files or --top is set
+        if let Some(rows) = self.file_rows {
+            self.display_file_details(report, rows);
+        }
+
+        // REQ-3.5.3: Display unsupported files separately (--details or --show-unsupported)
+        if self.show_unsupported && !report.unsupported_files.is_empty() {
+            println!("\n{}", "Unsupported Files (not counted):".bold().red());
+            for path in &report.unsupported_files {
+                let path = match self.path_style {
+                    Some(style) => style_path(path, style),
+                    None => path.clone(),
+                };
+                println!("  - {}", path.display());
             }
-            // REQ-3.5.3: Display unsupported files separately
-            if !report.unsupported_files.is_empty() {
-                println!("\n{}", "Unsupported Files (not counted):".bold().red());
-                for path in &report.unsupported_files {
-                    println!("  - {}", path.display());
+        }
+
+        // Files of known languages left out by languages.only/disabled
+        if self.show_unsupported && !report.skipped_files.is_empty() {
+            println!(
+                "\n{}",
+                "Skipped Files (language not counted):".bold().yellow()
+            );
+            for file in &report.skipped_files {
+                let path = match self.path_style {
+                    Some(style) => style_path(&file.path, style),
+                    None => file.path.clone(),
+                };
+                println!("  - {} ({})", path.display(), file.language);
+            }
+        }
+
+        // Files with bytes their encoding cannot decode (`--on-invalid-utf8`)
+        if self.show_unsupported && !report.invalid_files.is_empty() {
+            println!("\n{}", "Files with Undecodable Bytes:".bold().yellow());
+            for file in &report.invalid_files {
+                let path = match self.path_style {
+                    Some(style) => style_path(&file.path, style),
+                    None => file.path.clone(),
+                };
+                println!("  - {} ({} line(s))", path.display(), file.invalid_lines);
+            }
+        }
+
+        // Warnings of the scan, as they were printed while counting
+        if self.show_unsupported && !report.diagnostics.is_empty() {
+            println!("\n{}", "Warnings:".bold().yellow());
+            for diagnostic in &report.diagnostics {
+                match &diagnostic.path {
+                    Some(path) => {
+                        let path = match self.path_style {
+                            Some(style) => style_path(path, style),
+                            None => path.clone(),
+                        };
+                        println!(
+                            "  - [{}] {}: {}",
+                            diagnostic.code.name(),
+                            path.display(),
+                            diagnostic.message
+                        );
+                    }
+                    None => println!("  - [{}] {}", diagnostic.code.name(), diagnostic.message),
                 }
             }
         }
@@ -75,10 +288,34 @@ impl ConsoleOutput {
         Ok(())
     }
 
+    /// `--quiet`: one line with the global counts instead of the tables
+    pub fn display_compact(&self, report: &Report) {
+        let s = &report.summary;
+        let mut line = format!(
+            "{} files, {} lines ({} logical, {} comment, {} empty), {} languages",
+            s.total_files.to_formatted_string(&Locale::en),
+            s.total_lines.to_formatted_string(&Locale::en),
+            s.logical_lines.to_formatted_string(&Locale::en),
+            s.comment_lines.to_formatted_string(&Locale::en),
+            s.empty_lines.to_formatted_string(&Locale::en),
+            s.languages_count
+        );
+        if s.unsupported_files > 0 {
+            line.push_str(&format!(", {} unsupported", s.unsupported_files));
+        }
+        if s.skipped_files > 0 {
+            line.push_str(&format!(", {} skipped", s.skipped_files));
+        }
+        if s.invalid_files > 0 {
+            line.push_str(&format!(", {} with undecodable bytes", s.invalid_files));
+        }
+        println!("{}", line);
+    }
+
     /// REQ-5.1: Display global summary
     fn display_global_summary(&self, report: &Report) {
         println!("\n{}", "Global Summary".bold().green());
-        println!("{}", "─".repeat(40).green());
+        println!("{}", rule(40).green());
 
         let mut table = Table::new();
         table.add_row(Row::new(vec![
@@ -107,6 +344,34 @@ impl ConsoleOutput {
             .style_spec("r"),
             Cell::new("").style_spec("r"),
         ]));
+        // Skipped Files (languages not counted), only when some were
+        if report.summary.skipped_files > 0 {
+            table.add_row(Row::new(vec![
+                Cell::new("Skipped Files"),
+                Cell::new(
+                    &report
+                        .summary
+                        .skipped_files
+                        .to_formatted_string(&Locale::en),
+                )
+                .style_spec("r"),
+                Cell::new("").style_spec("r"),
+            ]));
+        }
+        // Files with undecodable bytes, only when some were
+        if report.summary.invalid_files > 0 {
+            table.add_row(Row::new(vec![
+                Cell::new("Undecodable Files"),
+                Cell::new(
+                    &report
+                        .summary
+                        .invalid_files
+                        .to_formatted_string(&Locale::en),
+                )
+                .style_spec("r"),
+                Cell::new("").style_spec("r"),
+            ]));
+        }
         // Total Lines
         table.add_row(Row::new(vec![
             Cell::new("Total Lines"),
@@ -171,26 +436,73 @@ impl ConsoleOutput {
             Cell::new("").style_spec("r"),
         ]));
 
-        table.printstd();
+        print_table(&table);
+
+        if !self.languages.is_empty() {
+            self.display_language_filter_note(report);
+        }
+    }
+
+    /// `--lang`: say what the filtered tables below leave out
+    fn display_language_filter_note(&self, report: &Report) {
+        let shown: Vec<&LanguageStats> = report
+            .languages
+            .iter()
+            .filter(|l| self.shows_language(&l.language))
+            .collect();
+        let files: usize = shown.iter().map(|l| l.file_count).sum();
+        let lines: usize = shown.iter().map(|l| l.total_lines).sum();
+        println!(
+            "{}",
+            format!(
+                "Filtered by language ({}): tables below show {} of {} files, {} of {} lines",
+                self.languages.join(", "),
+                files.to_formatted_string(&Locale::en),
+                report.summary.total_files.to_formatted_string(&Locale::en),
+                lines.to_formatted_string(&Locale::en),
+                report.summary.total_lines.to_formatted_string(&Locale::en)
+            )
+            .yellow()
+        );
+
+        let missing: Vec<&str> = self
+            .languages
+            .iter()
+            .filter(|l| !shown.iter().any(|s| s.language.eq_ignore_ascii_case(l)))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            println!(
+                "{}",
+                format!("No files found for: {}", missing.join(", ")).yellow()
+            );
+        }
     }
 
     /// REQ-5.2: Display language summary
     fn display_language_summary(&self, report: &Report) {
         println!("\n{}", "Language Summary".bold().green());
-        println!("{}", "─".repeat(80).green());
+        println!("{}", rule(80).green());
+
+        // --columns; the share column is appended last, sized to the remaining width
+        let columns = self.columns(DEFAULT_LANGUAGE_COLUMNS, &[]);
+        let show_share = columns.contains(&Column::Share);
+        let columns: Vec<Column> = columns
+            .into_iter()
+            .filter(|c| *c != Column::Share)
+            .collect();
 
         let mut table = Table::new();
-        table.add_row(Row::new(vec![
-            Cell::new("Language").style_spec("b"),
-            Cell::new("Files").style_spec("br"),
-            Cell::new("Total").style_spec("br"),
-            Cell::new("Logical").style_spec("br"),
-            Cell::new("Comment").style_spec("br"),
-            Cell::new("Empty").style_spec("br"),
-            Cell::new("Density %").style_spec("br"),
-        ]));
+        let mut header = vec![Cell::new("Language").style_spec("b")];
+        header.extend(columns.iter().map(|c| c.header()));
+        table.add_row(Row::new(header));
 
-        let mut languages = report.languages.clone();
+        // --lang filter
+        let mut languages: Vec<&LanguageStats> = report
+            .languages
+            .iter()
+            .filter(|l| self.shows_language(&l.language))
+            .collect();
 
         // REQ-5.4: Sort by metric if specified
         match self.sort_metric {
@@ -199,157 +511,841 @@ impl ConsoleOutput {
                 languages.sort_by_key(|l| std::cmp::Reverse(l.logical_lines))
             }
             Some(SortMetric::Empty) => languages.sort_by_key(|l| std::cmp::Reverse(l.empty_lines)),
+            Some(SortMetric::Comment) => {
+                languages.sort_by_key(|l| std::cmp::Reverse(l.comment_lines))
+            }
+            Some(SortMetric::Density) => languages.sort_by(|a, b| {
+                density(b.logical_lines, b.total_lines)
+                    .total_cmp(&density(a.logical_lines, a.total_lines))
+            }),
             Some(SortMetric::Language) | Some(SortMetric::Name) | None => {
                 languages.sort_by(|a, b| a.language.cmp(&b.language))
             }
         }
 
         for lang in &languages {
-            let density = if lang.total_lines > 0 {
-                (lang.logical_lines as f64 / lang.total_lines as f64) * 100.0
+            let counts = Counts {
+                files: lang.file_count,
+                total: lang.total_lines,
+                logical: lang.logical_lines,
+                comment: lang.comment_lines,
+                empty: lang.empty_lines,
+            };
+            let mut row = vec![Cell::new(&lang.language)];
+            row.extend(columns.iter().map(|c| c.cell(&counts)));
+            table.add_row(Row::new(row));
+        }
+
+        if show_share {
+            self.add_share_column(&mut table, &languages, report.summary.total_lines);
+        }
+        print_table(&table);
+    }
+
+    /// Append each language's share of all lines as a bar plus percentage. The bar
+    /// shrinks to fit the terminal and is left out when there is no room (or in TSV).
+    fn add_share_column(&self, table: &mut Table, languages: &[&LanguageStats], total: usize) {
+        let style = current_table_style();
+        // " 100.00 %" after the bar, plus padding and border of the new column
+        let bar_width = match terminal_columns() {
+            _ if style == TableStyle::Tsv => 0,
+            Some(columns) => columns
+                .saturating_sub(rendered_width(&column_widths(table), style) + 12)
+                .min(SHARE_BAR_WIDTH),
+            None => SHARE_BAR_WIDTH,
+        };
+
+        let rows = std::iter::once(None).chain(languages.iter().map(Some));
+        for (row, lang) in table.row_iter_mut().zip(rows) {
+            let cell = match lang {
+                None => Cell::new("Share").style_spec("b"),
+                Some(lang) => {
+                    let share = if total > 0 {
+                        lang.total_lines as f64 / total as f64
+                    } else {
+                        0.0
+                    };
+                    if bar_width >= MIN_SHARE_BAR_WIDTH {
+                        Cell::new(&format!(
+                            "{} {:>6.2} %",
+                            share_bar(share, bar_width),
+                            share * 100.0
+                        ))
+                    } else {
+                        Cell::new(&format!("{:.2} %", share * 100.0)).style_spec("r")
+                    }
+                }
+            };
+            row.add_cell(cell);
+        }
+    }
+
+    /// Display directory summary, largest first unless sorted by name
+    fn display_directory_summary(&self, report: &Report) {
+        println!("\n{}", "Directory Summary".bold().green());
+        println!("{}", rule(80).green());
+
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Directory").style_spec("b"),
+            Cell::new("Files").style_spec("br"),
+            Cell::new("Total").style_spec("br"),
+            Cell::new("Logical").style_spec("br"),
+            Cell::new("Comment").style_spec("br"),
+            Cell::new("Empty").style_spec("br"),
+            Cell::new("Share %").style_spec("br"),
+        ]));
+
+        let mut directories: Vec<&DirectoryStats> = report.directories.iter().collect();
+
+        // REQ-5.4: Sort by metric
+        match self.sort_metric {
+            Some(SortMetric::Logical) => {
+                directories.sort_by_key(|d| std::cmp::Reverse(d.logical_lines))
+            }
+            Some(SortMetric::Empty) => {
+                directories.sort_by_key(|d| std::cmp::Reverse(d.empty_lines))
+            }
+            Some(SortMetric::Comment) => {
+                directories.sort_by_key(|d| std::cmp::Reverse(d.comment_lines))
+            }
+            Some(SortMetric::Density) => directories.sort_by(|a, b| {
+                density(b.logical_lines, b.total_lines)
+                    .total_cmp(&density(a.logical_lines, a.total_lines))
+            }),
+            Some(SortMetric::Name) => directories.sort_by(|a, b| a.path.cmp(&b.path)),
+            _ => directories.sort_by_key(|d| std::cmp::Reverse(d.total_lines)),
+        }
+
+        let total_lines = report.summary.total_lines as f64;
+        for dir in &directories {
+            let share = if total_lines > 0.0 {
+                (dir.total_lines as f64 / total_lines) * 100.0
             } else {
                 0.0
             };
 
             table.add_row(Row::new(vec![
-                Cell::new(&lang.language),
-                Cell::new(&lang.file_count.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&lang.total_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&lang.logical_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&lang.comment_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&lang.empty_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&format!("{:.2} %", density)).style_spec("r"),
+                Cell::new(&dir.path.display().to_string()),
+                Cell::new(&dir.file_count.to_formatted_string(&Locale::en)).style_spec("r"),
+                Cell::new(&dir.total_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+                Cell::new(&dir.logical_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+                Cell::new(&dir.comment_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+                Cell::new(&dir.empty_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+                Cell::new(&format!("{:.2} %", share)).style_spec("r"),
             ]));
         }
 
-        table.printstd();
+        print_table(&table);
     }
 
-    /// Display file details
-    fn display_file_details(&self, report: &Report) {
+    /// Display file details: the largest files first (by the sort metric when it is a line
+    /// count or the density, by total lines otherwise), limited to `rows`, then ordered by name or language
+    /// when that is the sort metric
+    fn display_file_details(&self, report: &Report, rows: FileRows) {
         println!("\n{}", "File Details".bold().green());
-        println!("{}", "─".repeat(80).green());
+        println!("{}", rule(80).green());
 
+        // Without --paths, paths are shown relative to the scanned root so that
+        // same-named files (`mod.rs`, `index.ts`) stay distinguishable
+        let root = common_directory(&report.files);
+        if self.path_style.is_none() && !root.as_os_str().is_empty() {
+            println!(
+                "{}",
+                format!("(paths relative to {})", root.display()).dimmed()
+            );
+        }
+
+        let columns = self.columns(DEFAULT_FILE_COLUMNS, &[Column::Files, Column::Share]);
         let mut table = Table::new();
-        table.add_row(Row::new(vec![
+        let mut header = vec![
             Cell::new("File").style_spec("b"),
             Cell::new("Language").style_spec("b"),
-            Cell::new("Total").style_spec("br"),
-            Cell::new("Logical").style_spec("br"),
-            Cell::new("Comment").style_spec("br"),
-            Cell::new("Empty").style_spec("br"),
-        ]));
+        ];
+        header.extend(columns.iter().map(|c| c.header()));
+        table.add_row(Row::new(header));
 
-        let mut files = report.files.clone();
+        // --lang filter
+        let mut files: Vec<&FileStats> = report
+            .files
+            .iter()
+            .filter(|f| self.shows_language(&f.language))
+            .collect();
+        let listed = files.len();
 
         // REQ-5.4: Sort by metric
         match self.sort_metric {
-            Some(SortMetric::Total) => files.sort_by_key(|f| std::cmp::Reverse(f.total_lines)),
             Some(SortMetric::Logical) => files.sort_by_key(|f| std::cmp::Reverse(f.logical_lines)),
             Some(SortMetric::Empty) => files.sort_by_key(|f| std::cmp::Reverse(f.empty_lines)),
+            Some(SortMetric::Comment) => files.sort_by_key(|f| std::cmp::Reverse(f.comment_lines)),
+            Some(SortMetric::Density) => files.sort_by(|a, b| {
+                density(b.logical_lines, b.total_lines)
+                    .total_cmp(&density(a.logical_lines, a.total_lines))
+            }),
+            _ => files.sort_by_key(|f| std::cmp::Reverse(f.total_lines)),
+        }
+        if let FileRows::Top(n) = rows {
+            files.truncate(n);
+        }
+        match self.sort_metric {
             Some(SortMetric::Name) => files.sort_by(|a, b| a.path.cmp(&b.path)),
             Some(SortMetric::Language) => files.sort_by(|a, b| a.language.cmp(&b.language)),
-            None => {}
+            _ => {}
         }
 
         for file in &files {
-            let filename = file
-                .path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("?");
+            let shown = match self.path_style {
+                Some(style) => style_path(&file.path, style),
+                None => relative_to_root(&file.path, &root),
+            };
 
-            table.add_row(Row::new(vec![
-                Cell::new(filename),
+            let counts = Counts {
+                files: 1,
+                total: file.total_lines,
+                logical: file.logical_lines,
+                comment: file.comment_lines,
+                empty: file.empty_lines,
+            };
+            let mut row = vec![
+                Cell::new(&shown.display().to_string()),
                 Cell::new(&file.language),
-                Cell::new(&file.total_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&file.logical_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&file.comment_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-                Cell::new(&file.empty_lines.to_formatted_string(&Locale::en)).style_spec("r"),
-            ]));
+            ];
+            row.extend(columns.iter().map(|c| c.cell(&counts)));
+            table.add_row(Row::new(row));
         }
 
-        table.printstd();
+        print_table(&table);
+
+        if files.len() < listed {
+            println!(
+                "{}",
+                format!(
+                    "(showing {} of {} files, use --top N or --show-files to see more)",
+                    files.len(),
+                    listed
+                )
+                .yellow()
+            );
+        }
     }
 }
 
-pub struct ReportExporter;
+pub struct ReportExporter {
+    xml_style: XmlStyle,
+    elapsed: Duration,
+}
 
 impl ReportExporter {
-    pub fn new() -> Self {
-        Self
+    pub fn new(xml_style: XmlStyle) -> Self {
+        Self {
+            xml_style,
+            elapsed: Duration::ZERO,
+        }
     }
 
-    /// REQ-6.8: Export report to file
+    /// Time the count took, for the timing fields of `cloc-json`
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+
+    /// REQ-6.8: Export report to file (compressed when the path ends in `.gz`/`.zst`)
     pub fn export(&self, report: &Report, path: &Path, format: OutputFormat) -> Result<()> {
         match format {
             OutputFormat::Json => self.export_json(report, path),
             OutputFormat::Xml => self.export_xml(report, path),
             OutputFormat::Csv => self.export_csv(report, path),
+            OutputFormat::ClocJson => self.export_cloc_json(report, path),
+            OutputFormat::ClocCsv => self.export_cloc_csv(report, path),
+            OutputFormat::Junit => self.export_junit(report, path),
+            OutputFormat::Html => Err(SlocError::InvalidReportFormat(
+                "html format is only available for comparisons".to_string(),
+            )),
         }
     }
 
-    /// REQ-6.1: Export as JSON
-    fn export_json(&self, report: &Report, path: &Path) -> Result<()> {
-        let json = serde_json::to_string_pretty(report)
+    /// Render the report through a user-supplied Tera template.
+    /// The template sees the serialized report fields (`files`, `languages`,
+    /// `summary`, `unsupported_files`, ...) as top-level variables.
+    pub fn export_template(&self, report: &Report, template: &Path, path: &Path) -> Result<()> {
+        let source = std::fs::read_to_string(template)?;
+        let context = tera::Context::from_serialize(report)
+            .map_err(|e| SlocError::Template(error_chain(&e)))?;
+        let rendered = tera::Tera::one_off(&source, &context, false).map_err(|e| {
+            SlocError::Template(format!("{}: {}", template.display(), error_chain(&e)))
+        })?;
+
+        let mut file = create_writer(path)?;
+        file.write_all(rendered.as_bytes())?;
+        Ok(())
+    }
+
+    /// Pipe the JSON report through an external exporter (`-f ext:CMD`) and write what
+    /// it prints
+    pub fn export_external(
+        &self,
+        report: &Report,
+        exporter: &ExternalExporter,
+        path: &Path,
+    ) -> Result<()> {
+        let json = serde_json::to_vec_pretty(report)
             .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        let exported = exporter.export(json, path)?;
+
+        let mut file = create_writer(path)?;
+        file.write_all(&exported)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Extension for auto-named template output: `confluence.wiki.tera` -> `wiki`
+    pub fn template_extension(template: &Path) -> String {
+        template
+            .file_stem()
+            .map(Path::new)
+            .and_then(|stem| stem.extension())
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("txt")
+            .to_string()
+    }
 
-        let mut file = File::create(path)?;
-        file.write_all(json.as_bytes())?;
+    /// REQ-6.1: Export as JSON (streamed, huge reports are never buffered as one string)
+    fn export_json(&self, report: &Report, path: &Path) -> Result<()> {
+        let mut file = create_writer(path)?;
+        serde_json::to_writer_pretty(&mut file, report)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        file.flush()?;
         Ok(())
     }
 
     /// REQ-6.2: Export as XML
     fn export_xml(&self, report: &Report, path: &Path) -> Result<()> {
-        let xml =
-            serde_xml_rs::to_string(report).map_err(|e| SlocError::Serialization(e.to_string()))?;
+        let xml = report.to_xml(self.xml_style)?;
 
-        let mut file = File::create(path)?;
+        let mut file = create_writer(path)?;
         file.write_all(xml.as_bytes())?;
         Ok(())
     }
 
-    /// REQ-6.3: Export as CSV
+    /// REQ-6.3: Export as CSV (sectioned layout that round-trips through `process`/`compare`)
     fn export_csv(&self, report: &Report, path: &Path) -> Result<()> {
-        let mut wtr = csv::Writer::from_path(path)
-            .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
+        let mut wtr = csv::Writer::from_writer(create_writer(path)?);
 
-        // Write header
-        wtr.write_record([
-            "Path",
-            "Language",
-            "Total Lines",
-            "Logical Lines",
-            "Comment Lines",
-            "Empty Lines",
-        ])
-        .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
+        for record in report.to_csv_records() {
+            wtr.serialize(record)
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        }
 
-        // Write file data
-        for file in &report.files {
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Export as cloc-compatible JSON (`cloc --json` layout)
+    fn export_cloc_json(&self, report: &Report, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&ClocJson {
+            report,
+            elapsed: self.elapsed,
+        })
+        .map_err(|e| SlocError::Serialization(e.to_string()))?;
+
+        let mut file = create_writer(path)?;
+        writeln!(file, "{}", json)?;
+        Ok(())
+    }
+
+    /// Export as cloc-compatible CSV (`cloc --csv` layout)
+    fn export_cloc_csv(&self, report: &Report, path: &Path) -> Result<()> {
+        // cloc's header row carries an extra banner column that data rows don't have
+        let mut wtr = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(create_writer(path)?);
+
+        let banner = format!("{} v {}", CLOC_URL, env!("CARGO_PKG_VERSION"));
+        wtr.write_record(["files", "language", "blank", "comment", "code", &banner])
+            .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
+
+        for lang in cloc_sorted_languages(report) {
             wtr.write_record(&[
-                file.path.to_string_lossy().to_string(),
-                file.language.clone(),
-                file.total_lines.to_string(),
-                file.logical_lines.to_string(),
-                file.comment_lines.to_string(),
-                file.empty_lines.to_string(),
+                lang.file_count.to_string(),
+                lang.language.clone(),
+                lang.empty_lines.to_string(),
+                lang.comment_lines.to_string(),
+                lang.logical_lines.to_string(),
             ])
             .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
         }
 
-        // REQ-3.5: Add unsupported files section
-        if !report.unsupported_files.is_empty() {
-            wtr.write_record(["--- Unsupported Files (not counted) ---"])
-                .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
-            for path in &report.unsupported_files {
-                wtr.write_record(&[path.to_string_lossy().to_string()])
-                    .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
-            }
-        }
+        wtr.write_record(&[
+            report.summary.total_files.to_string(),
+            "SUM".to_string(),
+            report.summary.empty_lines.to_string(),
+            report.summary.comment_lines.to_string(),
+            report.summary.logical_lines.to_string(),
+        ])
+        .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
 
         wtr.flush()
             .map_err(|e| SlocError::Io(std::io::Error::other(e.to_string())))?;
         Ok(())
     }
+
+    /// Export as JUnit XML: one test case per language carrying its counts,
+    /// so CI servers can render SLOC results in their native test UI
+    fn export_junit(&self, report: &Report, path: &Path) -> Result<()> {
+        let tests = report.languages.len();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"sloc\" tests=\"{tests}\" failures=\"0\" errors=\"0\">\n"
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"sloc\" tests=\"{tests}\" failures=\"0\" errors=\"0\" timestamp=\"{}\">\n",
+            report.generated_at.format("%Y-%m-%dT%H:%M:%S")
+        ));
+
+        xml.push_str("    <properties>\n");
+        for (name, value) in [
+            ("total_files", report.summary.total_files),
+            ("total_lines", report.summary.total_lines),
+            ("logical_lines", report.summary.logical_lines),
+            ("comment_lines", report.summary.comment_lines),
+            ("empty_lines", report.summary.empty_lines),
+            ("unsupported_files", report.summary.unsupported_files),
+        ] {
+            xml.push_str(&format!(
+                "      <property name=\"{name}\" value=\"{value}\"/>\n"
+            ));
+        }
+        xml.push_str("    </properties>\n");
+
+        for lang in &report.languages {
+            xml.push_str(&format!(
+                "    <testcase classname=\"sloc.language\" name=\"{}\">\n",
+                xml_escape(&lang.language)
+            ));
+            xml.push_str(&format!(
+                "      <system-out>files={} total={} logical={} comment={} empty={}</system-out>\n",
+                lang.file_count,
+                lang.total_lines,
+                lang.logical_lines,
+                lang.comment_lines,
+                lang.empty_lines
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+
+        let mut file = create_writer(path)?;
+        file.write_all(xml.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Width of the language share bars, in characters
+const SHARE_BAR_WIDTH: usize = 20;
+/// Narrower bars are left out
+const MIN_SHARE_BAR_WIDTH: usize = 4;
+
+/// Bar of `width` characters filled to `fraction` (0.0-1.0), in eighths of a character
+fn share_bar(fraction: f64, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let fraction = fraction.clamp(0.0, 1.0);
+    if ASCII_ONLY.load(Ordering::Relaxed) {
+        let full = (fraction * width as f64).round() as usize;
+        return format!("{}{}", "#".repeat(full), " ".repeat(width - full));
+    }
+
+    let eighths = (fraction * (width * 8) as f64).round() as usize;
+    let full = eighths / 8;
+    let mut bar = "█".repeat(full);
+    if full < width {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.push_str(&" ".repeat(width - full - 1));
+    }
+    bar
+}
+
+/// One character per value, scaled between the smallest and the largest value
+pub fn sparkline(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const ASCII: [char; 8] = ['_', '.', '-', '~', '=', '+', '*', '#'];
+    let levels = if ASCII_ONLY.load(Ordering::Relaxed) {
+        &ASCII
+    } else {
+        &BLOCKS
+    };
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                levels[((value - min) / (max - min) * 7.0).round() as usize]
+            } else {
+                levels[3]
+            }
+        })
+        .collect()
+}
+
+/// `path` below `root` (`.` components ignored), or unchanged when it is not inside it
+fn relative_to_root(path: &Path, root: &Path) -> PathBuf {
+    let normalized: PathBuf = path
+        .components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect();
+    match normalized.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// REQ-5.2: Density % (logical lines / total lines), 0 for empty files
+fn density(logical_lines: usize, total_lines: usize) -> f64 {
+    if total_lines > 0 {
+        (logical_lines as f64 / total_lines as f64) * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Apply `--color`. `auto` keeps the detection of the `colored` crate: NO_COLOR,
+/// CLICOLOR/CLICOLOR_FORCE and whether stdout is a terminal.
+pub fn configure_colors(choice: ColorChoice) {
+    match choice {
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+        ColorChoice::Auto => {}
+    }
+}
+
+/// `--ascii` or a non-UTF-8 locale, set once at startup
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Apply `--ascii`; without it, ASCII is still used when the locale is not UTF-8
+pub fn configure_charset(ascii: bool) {
+    ASCII_ONLY.store(ascii || !locale_is_utf8(), Ordering::Relaxed);
+}
+
+/// The first of LC_ALL, LC_CTYPE and LANG that is set decides; no locale at all
+/// (e.g. Windows) counts as UTF-8
+fn locale_is_utf8() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_none_or(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+}
+
+/// `unicode`, or its `ascii` replacement in ASCII-only mode
+pub fn glyph(unicode: &'static str, ascii: &'static str) -> &'static str {
+    if ASCII_ONLY.load(Ordering::Relaxed) {
+        ascii
+    } else {
+        unicode
+    }
+}
+
+/// Section rule under titles
+pub fn rule(width: usize) -> String {
+    glyph("─", "-").repeat(width)
+}
+
+/// Banner rule around report titles
+pub fn heavy_rule(width: usize) -> String {
+    glyph("═", "=").repeat(width)
+}
+
+/// Whether console output should carry ANSI colors (see [`configure_colors`])
+pub fn colors_enabled() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// `--table-style`, set once at startup
+static TABLE_STYLE: OnceLock<TableStyle> = OnceLock::new();
+
+/// Apply `--table-style` to every table printed afterwards
+pub fn configure_tables(style: TableStyle) {
+    let _ = TABLE_STYLE.set(style);
+}
+
+/// Narrowest the first column is truncated to when fitting the terminal width
+const MIN_FIRST_COLUMN_WIDTH: usize = 12;
+
+fn current_table_style() -> TableStyle {
+    TABLE_STYLE.get().copied().unwrap_or(TableStyle::Pretty)
+}
+
+/// Print a table to stdout in the `--table-style` layout. On a terminal, tables wider
+/// than the window get their first column (names, paths) truncated in the middle.
+/// Bold headers are only styled when colors are enabled.
+pub fn print_table(table: &Table) {
+    let style = current_table_style();
+    if style == TableStyle::Tsv {
+        print_tsv(table);
+        return;
+    }
+
+    let mut table = table.clone();
+    if style == TableStyle::Plain {
+        table.set_format(*prettytable::format::consts::FORMAT_CLEAN);
+    }
+    if let Some(columns) = terminal_columns() {
+        fit_width(&mut table, style, columns);
+    }
+
+    // Errors are ignored, as with `Table::printstd`
+    let _ = if colors_enabled() {
+        table.print_tty(true)
+    } else {
+        table.print(&mut std::io::stdout())
+    };
+}
+
+/// `--table-style tsv`: one line per row, cells separated by tabs
+fn print_tsv(table: &Table) {
+    let mut stdout = std::io::stdout().lock();
+    for row in table.row_iter() {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| cell.get_content().replace(['\t', '\n'], " "))
+            .collect();
+        let _ = writeln!(stdout, "{}", cells.join("\t"));
+    }
+}
+
+/// Shrink the first column so the rendered table fits in `max_width` characters
+fn fit_width(table: &mut Table, style: TableStyle, max_width: usize) {
+    let widths = column_widths(table);
+    let Some(&first) = widths.first() else {
+        return;
+    };
+    let total = rendered_width(&widths, style);
+    if total <= max_width || first <= MIN_FIRST_COLUMN_WIDTH {
+        return;
+    }
+
+    let limit = first
+        .saturating_sub(total - max_width)
+        .max(MIN_FIRST_COLUMN_WIDTH);
+    for (index, row) in table.row_iter_mut().enumerate() {
+        let Some(content) = row.get_cell(0).map(|c| c.get_content()) else {
+            continue;
+        };
+        if content.chars().count() <= limit {
+            continue;
+        }
+        let mut cell = Cell::new(&truncate_middle(&content, limit));
+        // Row 0 is the header row in every table
+        if index == 0 {
+            cell = cell.style_spec("b");
+        }
+        let _ = row.set_cell(cell, 0);
+    }
+}
+
+/// Widest cell content of every column
+fn column_widths(table: &Table) -> Vec<usize> {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in table.row_iter() {
+        for (index, cell) in row.iter().enumerate() {
+            let width = cell
+                .get_content()
+                .lines()
+                .map(|l| l.chars().count())
+                .max()
+                .unwrap_or(0);
+            if index >= widths.len() {
+                widths.push(width);
+            } else {
+                widths[index] = widths[index].max(width);
+            }
+        }
+    }
+    widths
+}
+
+/// Printed width of a table with these column widths: one space of padding on each
+/// side, and for pretty tables a border per column plus one
+fn rendered_width(widths: &[usize], style: TableStyle) -> usize {
+    let borders = match style {
+        TableStyle::Pretty => widths.len() + 1,
+        _ => 0,
+    };
+    widths.iter().map(|w| w + 2).sum::<usize>() + borders
+}
+
+/// Columns of the terminal stdout is attached to, `None` when it is not a terminal
+fn terminal_columns() -> Option<usize> {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+}
+
+/// Shorten `text` to `max` characters by replacing its middle with `…`, keeping more of
+/// the end; for paths the file name is kept whole when it fits
+fn truncate_middle(text: &str, max: usize) -> String {
+    let ellipsis = glyph("…", "...");
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max || max <= ellipsis.len() {
+        return text.to_string();
+    }
+    let keep = max - ellipsis.len();
+    let mut tail = keep - keep / 3;
+    if let Some(separator) = chars.iter().rposition(|&c| c == '/' || c == '\\') {
+        let name = chars.len() - separator;
+        if name < keep {
+            tail = tail.max(name);
+        }
+    }
+    let head = keep - tail;
+    let mut shortened: String = chars[..head].iter().collect();
+    shortened.push_str(ellipsis);
+    shortened.extend(&chars[chars.len() - tail..]);
+    shortened
+}
+
+/// `--print`: write a result to stdout in a machine-readable format
+pub fn print_result<T: Serialize>(value: &T, format: PrintFormat) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        PrintFormat::Json => serde_json::to_writer_pretty(&mut stdout, value)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?,
+    }
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Flatten an error and its sources into one message (Tera hides the
+/// useful part, e.g. the offending line, in the source chain)
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Escape the XML special characters in attribute and text content
+pub(crate) fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Value reported in the cloc `header` / CSV banner in place of cloc's own URL
+const CLOC_URL: &str = "github.com/mad4j/rustedbytes-counterlines";
+
+/// cloc lists languages by descending code lines, then by name
+fn cloc_sorted_languages(report: &Report) -> Vec<&LanguageStats> {
+    let mut languages: Vec<&LanguageStats> = report.languages.iter().collect();
+    languages.sort_by(|a, b| {
+        b.logical_lines
+            .cmp(&a.logical_lines)
+            .then_with(|| a.language.cmp(&b.language))
+    });
+    languages
+}
+
+/// Serializes a report using the key order and names produced by `cloc --json`:
+/// a `header` object, one object per language, and a trailing `SUM` object.
+struct ClocJson<'a> {
+    report: &'a Report,
+    /// Time the count took, zero for a report loaded from a file
+    elapsed: Duration,
+}
+
+/// `header` of `cloc --json`
+#[derive(Serialize)]
+struct ClocHeader {
+    cloc_url: &'static str,
+    cloc_version: &'static str,
+    elapsed_seconds: f64,
+    n_files: usize,
+    n_lines: usize,
+    files_per_second: f64,
+    lines_per_second: f64,
+}
+
+/// A language of `cloc --json`
+#[derive(Serialize)]
+struct ClocLanguage {
+    #[serde(rename = "nFiles")]
+    n_files: usize,
+    blank: usize,
+    comment: usize,
+    code: usize,
+}
+
+/// `SUM` of `cloc --json`, whose file count comes last
+#[derive(Serialize)]
+struct ClocSum {
+    blank: usize,
+    comment: usize,
+    code: usize,
+    #[serde(rename = "nFiles")]
+    n_files: usize,
+}
+
+impl Serialize for ClocJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let report = self.report;
+        let summary = &report.summary;
+        let seconds = self.elapsed.as_secs_f64();
+        let rate = |count: usize| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry(
+            "header",
+            &ClocHeader {
+                cloc_url: CLOC_URL,
+                cloc_version: env!("CARGO_PKG_VERSION"),
+                elapsed_seconds: seconds,
+                n_files: summary.total_files,
+                n_lines: summary.total_lines,
+                files_per_second: rate(summary.total_files),
+                lines_per_second: rate(summary.total_lines),
+            },
+        )?;
+
+        for lang in cloc_sorted_languages(report) {
+            map.serialize_entry(
+                &lang.language,
+                &ClocLanguage {
+                    n_files: lang.file_count,
+                    blank: lang.empty_lines,
+                    comment: lang.comment_lines,
+                    code: lang.logical_lines,
+                },
+            )?;
+        }
+
+        map.serialize_entry(
+            "SUM",
+            &ClocSum {
+                blank: summary.empty_lines,
+                comment: summary.comment_lines,
+                code: summary.logical_lines,
+                n_files: summary.total_files,
+            },
+        )?;
+
+        map.end()
+    }
 }
@@ -0,0 +1,153 @@
+// plugin.rs - External command plugins: per-file metrics (`count --metric-plugin`) and
+// exporters (`count -f ext:CMD`)
+// Implements: REQ-1.1 (line counting), REQ-6.4 (report creation), REQ-6.8 (report export)
+//
+// A metric plugin is any external command. It is run once per counted file with the
+// file's path as its last argument and the file's content on stdin; the line counts are
+// passed in `SLOC_LANGUAGE`, `SLOC_TOTAL_LINES`, `SLOC_LOGICAL_LINES`,
+// `SLOC_COMMENT_LINES` and `SLOC_EMPTY_LINES`. It prints a JSON object of named
+// numbers, which become the file's `metrics` in the report:
+//
+//     $ todo-counter src/main.rs < src/main.rs
+//     {"todos": 3, "fixmes": 0}
+//
+// Empty output means no metrics. When several plugins return the same name, the last
+// one given on the command line wins.
+//
+// An exporter receives the report as `-f json` writes it on stdin, with the output path
+// in `SLOC_OUTPUT`, and prints the exported document, which is written to the output
+// path like a built-in format (`-` for stdout, `.gz`/`.zst` compressed).
+
+use crate::error::{Result, SlocError};
+use crate::report::FileStats;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// A plugin command line, split with shell quoting rules (no shell is involved)
+struct PluginCommand {
+    /// Command line as given, for messages
+    line: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl PluginCommand {
+    fn parse(line: &str) -> Result<Self> {
+        let mut words = shlex::split(line)
+            .filter(|words| !words.is_empty())
+            .ok_or_else(|| SlocError::Plugin(format!("invalid plugin command line `{}`", line)))?;
+        let program = words.remove(0);
+        Ok(PluginCommand {
+            line: line.to_string(),
+            program,
+            args: words,
+        })
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+
+    /// Run `command` with `input` on stdin; a non-zero exit is an error carrying its stderr
+    fn run(&self, mut command: Command, input: Vec<u8>) -> Result<Output> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| SlocError::Plugin(format!("cannot run `{}`: {}", self.line, e)))?;
+
+        // Written from another thread so a plugin printing before it reads cannot deadlock;
+        // plugins that don't read stdin close the pipe early, which is not an error
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || match stdin.write_all(&input) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+            _ => Ok(()),
+        });
+        let output = child.wait_with_output()?;
+        writer.join().expect("plugin stdin writer panicked")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!("`{}` failed ({})", self.line, output.status);
+            if !stderr.trim().is_empty() {
+                message.push_str(&format!(": {}", stderr.trim()));
+            }
+            return Err(SlocError::Plugin(message));
+        }
+        Ok(output)
+    }
+}
+
+pub struct MetricPlugin(PluginCommand);
+
+impl MetricPlugin {
+    pub fn parse(line: &str) -> Result<Self> {
+        PluginCommand::parse(line).map(MetricPlugin)
+    }
+
+    /// Run the plugin on the counted file and merge its metrics into `stats`
+    pub fn measure(&self, stats: &mut FileStats) -> Result<()> {
+        let content = std::fs::read(&stats.path)?;
+        let mut command = self.0.command();
+        command
+            .arg(&stats.path)
+            .env("SLOC_LANGUAGE", &stats.language)
+            .env("SLOC_TOTAL_LINES", stats.total_lines.to_string())
+            .env("SLOC_LOGICAL_LINES", stats.logical_lines.to_string())
+            .env("SLOC_COMMENT_LINES", stats.comment_lines.to_string())
+            .env("SLOC_EMPTY_LINES", stats.empty_lines.to_string());
+        let output = self.0.run(command, content)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            return Ok(());
+        }
+        let metrics: BTreeMap<String, f64> = serde_json::from_str(&stdout).map_err(|e| {
+            SlocError::Plugin(format!(
+                "`{}` must print a JSON object of numbers: {}",
+                self.0.line, e
+            ))
+        })?;
+        // `;` and `=` separate the metrics in CSV reports
+        if let Some(name) = metrics
+            .keys()
+            .find(|name| name.is_empty() || name.contains([';', '=']))
+        {
+            return Err(SlocError::Plugin(format!(
+                "`{}` returned an invalid metric name `{}`",
+                self.0.line, name
+            )));
+        }
+        stats.metrics.extend(metrics);
+        Ok(())
+    }
+}
+
+pub struct ExternalExporter(PluginCommand);
+
+impl ExternalExporter {
+    pub fn parse(line: &str) -> Result<Self> {
+        PluginCommand::parse(line).map(ExternalExporter)
+    }
+
+    /// Extension for auto-named output: the program name, `ext:./to-markdown` -> `to-markdown`
+    pub fn extension(&self) -> String {
+        Path::new(&self.0.program)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("out")
+            .to_string()
+    }
+
+    /// The exported document for a JSON report
+    pub fn export(&self, json: Vec<u8>, output: &Path) -> Result<Vec<u8>> {
+        let mut command = self.0.command();
+        command.env("SLOC_OUTPUT", output);
+        Ok(self.0.run(command, json)?.stdout)
+    }
+}
@@ -1,16 +1,26 @@
 // processor.rs - Report processing and comparison
 // Implements: REQ-7.1, REQ-7.2, REQ-7.3, REQ-7.4, REQ-9.7
 
-use crate::cli::{CompareArgs, OutputFormat, ProcessArgs};
+use crate::cli::{CompareArgs, OutputFormat, PrintFormat, ProcessArgs, Verbosity};
+use crate::compare::{
+    DeltaMetric, FileFilter, NoiseThreshold, PathMapping, compare_reports, directory_deltas,
+};
+use crate::compression::{create_writer, is_stdout};
 use crate::config::{AppConfig, MetricsLogger};
 use crate::error::{Result, SlocError};
-use crate::output::{ConsoleOutput, ReportExporter};
+use crate::git;
+use crate::language::LanguageDetector;
+use crate::output::{
+    ConsoleOutput, FileRows, ReportExporter, glyph, heavy_rule, print_result, print_table, rule,
+};
 use crate::report::Report;
+use crate::threshold::{Check, MetricValues};
+use crate::validate;
 use colored::Colorize;
 use num_format::{Locale, ToFormattedString};
 use prettytable::{Cell, Row, Table};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
@@ -28,29 +38,70 @@ pub fn execute_process(args: ProcessArgs) -> Result<()> {
     metrics_logger.init_session("process", &args_summary);
     metrics_logger.log_system_info();
 
-    // Detect format from file extension
-    let format = args.format.unwrap_or_else(|| {
-        if args.report.extension().and_then(|e| e.to_str()) == Some("json") {
-            OutputFormat::Json
-        } else if args.report.extension().and_then(|e| e.to_str()) == Some("xml") {
-            OutputFormat::Xml
-        } else if args.report.extension().and_then(|e| e.to_str()) == Some("csv") {
-            OutputFormat::Csv
-        } else {
-            OutputFormat::Json // Default
-        }
-    });
+    // REQ-6.8: `--export -` and `--print` own stdout, so console output is suppressed
+    let verbosity = if stdout_claimed(args.export.as_deref(), args.print)? {
+        Verbosity::Silent
+    } else {
+        Verbosity::from_flags(args.quiet, args.silent)
+    };
+
+    // Detect input format from file extension (-f is the export format; it is only
+    // used for loading when the extension says nothing)
+    let format = OutputFormat::from_path(&args.report)
+        .or(args.format)
+        .unwrap_or(OutputFormat::Json);
 
     let load_start = Instant::now();
-    let report = Report::from_file(&args.report, format)?;
+    let mut report = Report::from_file(&args.report, format)?;
+    if args.verify_checksum {
+        validate::verify_loaded(&report, &args.report)?;
+    }
     metrics_logger.log_metric("report_load_time", load_start.elapsed().as_secs_f64());
     metrics_logger.log_metric("report_files_count", report.files.len() as f64);
     metrics_logger.log_metric("report_total_lines", report.summary.total_lines as f64);
 
+    // Slice the report; summaries are recomputed from the kept files
+    let filter = FileFilter {
+        languages: args.lang.clone(),
+        paths: args.path_filter.clone(),
+        min_lines: args.min_lines.unwrap_or(0),
+    };
+    if let Some(description) = filter.describe() {
+        if report.is_totals_only() {
+            return Err(totals_only_error(&args.report, &description));
+        }
+        let total = report.files.len();
+        report.retain_files(|f| filter.matches(f));
+        metrics_logger.log_metric("filtered_files_count", report.files.len() as f64);
+        if verbosity == Verbosity::Normal {
+            println!(
+                "Kept {} of {} files ({})",
+                report.files.len(),
+                total,
+                description
+            );
+        }
+    }
+
+    if let Some(depth) = args.by_dir {
+        report.group_by_directory(depth as usize);
+    }
+
     // Display summary (REQ-7.1: compute global statistics)
     let console_start = Instant::now();
-    let console = ConsoleOutput::new(args.sort, false);
-    console.display_summary(&report)?;
+    let console = ConsoleOutput::new(
+        args.sort,
+        FileRows::from_flags(false, args.top, args.show_files),
+    )
+    .with_tree(args.tree.map(|depth| depth as usize))
+    .with_paths(args.path_style)
+    .with_columns(args.columns.clone())
+    .with_unsupported(args.show_unsupported);
+    match verbosity {
+        Verbosity::Normal => console.display_summary(&report)?,
+        Verbosity::Quiet => console.display_compact(&report),
+        Verbosity::Silent => {}
+    }
     metrics_logger.log_metric(
         "console_display_time",
         console_start.elapsed().as_secs_f64(),
@@ -59,18 +110,29 @@ pub fn execute_process(args: ProcessArgs) -> Result<()> {
     // Export if requested
     if let Some(export_path) = args.export {
         let export_start = Instant::now();
-        let export_format = args.format.unwrap_or(OutputFormat::Json);
-        let exporter = ReportExporter::new();
-        exporter.export(&report, &export_path, export_format)?;
+        let exporter = ReportExporter::new(args.xml_style);
+        if let Some(template) = &args.template {
+            exporter.export_template(&report, template, &export_path)?;
+        } else {
+            let export_format = args.format.unwrap_or(OutputFormat::Json);
+            exporter.export(&report, &export_path, export_format)?;
+        }
         metrics_logger.log_metric("export_time", export_start.elapsed().as_secs_f64());
-        println!("\nProcessed report exported to: {}", export_path.display());
+        if verbosity == Verbosity::Normal {
+            println!("\nProcessed report exported to: {}", export_path.display());
+        }
+    }
+
+    // Machine-readable result on stdout
+    if let Some(print) = args.print {
+        print_result(&report, print)?;
     }
 
     let total_time = start_time.elapsed();
     metrics_logger.log_completion(report.summary.total_files, report.summary.total_lines);
     metrics_logger.log_metric("total_operation_time", total_time.as_secs_f64());
 
-    if metrics_logger.is_enabled() {
+    if metrics_logger.is_enabled() && verbosity == Verbosity::Normal {
         println!("Metrics logged to: {}", metrics_logger.file_path());
     }
 
@@ -86,33 +148,135 @@ pub fn execute_compare(args: CompareArgs) -> Result<()> {
         AppConfig::with_cli_overrides(None, args.enable_metrics, args.metrics_file.as_ref())?;
 
     let metrics_logger = Arc::new(MetricsLogger::new(&app_config.performance));
-    let args_summary = format!(
-        "report1={}, report2={}",
-        args.report1.display(),
-        args.report2.display()
-    );
+    let args_summary = match (&args.git_ref, &args.report1, &args.report2) {
+        (Some(reference), _, _) => format!("git_ref={}", reference),
+        (None, Some(report1), Some(report2)) => format!(
+            "report1={}, report2={}",
+            report1.display(),
+            report2.display()
+        ),
+        // clap requires both reports unless --git-ref is given
+        _ => String::new(),
+    };
     metrics_logger.init_session("compare", &args_summary);
     metrics_logger.log_system_info();
 
-    // Detect formats
-    let format1 = detect_format(&args.report1);
-    let format2 = detect_format(&args.report2);
+    if args.summary_only
+        && let Some(condition) = args.conditions().iter().find(|c| c.metric.is_per_file())
+    {
+        return Err(SlocError::Parse(format!(
+            "\"{}\" needs the per-file comparison, which --summary-only skips",
+            condition
+        )));
+    }
 
-    let load_start = Instant::now();
-    let report1 = Report::from_file(&args.report1, format1)?;
-    metrics_logger.log_metric("report1_load_time", load_start.elapsed().as_secs_f64());
+    // REQ-6.8: `--export -` and `--print` own stdout instead of the tables
+    let verbosity = if stdout_claimed(args.export.as_deref(), args.print)? {
+        Verbosity::Silent
+    } else {
+        Verbosity::from_flags(args.quiet, args.silent)
+    };
+
+    let (mut report1, mut report2) = match (&args.git_ref, &args.report1, &args.report2) {
+        // The files at the revision against the working tree
+        (Some(reference), _, _) => {
+            let detector = LanguageDetector::new();
+            let load_start = Instant::now();
+            let report1 = git::count_revision(reference, &detector)?;
+            metrics_logger.log_metric("report1_load_time", load_start.elapsed().as_secs_f64());
+
+            let load_start = Instant::now();
+            let report2 = git::count_working_tree(&detector)?;
+            metrics_logger.log_metric("report2_load_time", load_start.elapsed().as_secs_f64());
+            (report1, report2)
+        }
+        (None, Some(path1), Some(path2)) => {
+            // Detect formats
+            let format1 = detect_format(path1);
+            let format2 = detect_format(path2);
+
+            let load_start = Instant::now();
+            let report1 = Report::from_file(path1, format1)?;
+            if args.verify_checksum {
+                validate::verify_loaded(&report1, path1)?;
+            }
+            metrics_logger.log_metric("report1_load_time", load_start.elapsed().as_secs_f64());
 
-    let load_start = Instant::now();
-    let report2 = Report::from_file(&args.report2, format2)?;
-    metrics_logger.log_metric("report2_load_time", load_start.elapsed().as_secs_f64());
+            let load_start = Instant::now();
+            let report2 = Report::from_file(path2, format2)?;
+            if args.verify_checksum {
+                validate::verify_loaded(&report2, path2)?;
+            }
+            metrics_logger.log_metric("report2_load_time", load_start.elapsed().as_secs_f64());
+            (report1, report2)
+        }
+        _ => {
+            return Err(SlocError::Parse(
+                "compare needs two reports or --git-ref".to_string(),
+            ));
+        }
+    };
+
+    // A report without its files (`count --max-memory`) is only compared by its totals
+    for (report, path) in [(&report1, &args.report1), (&report2, &args.report2)] {
+        let Some(path) = path.as_deref().filter(|_| report.is_totals_only()) else {
+            continue;
+        };
+        let filter = FileFilter {
+            languages: args.lang.clone(),
+            paths: args.path_filter.clone(),
+            min_lines: 0,
+        };
+        if let Some(description) = filter.describe() {
+            return Err(totals_only_error(path, &description));
+        }
+        if let Some(condition) = args.conditions().iter().find(|c| c.metric.is_per_file()) {
+            return Err(totals_only_error(path, &condition.to_string()));
+        }
+    }
+
+    // Paths of both reports are mapped before files are matched up
+    let mapping = PathMapping {
+        strip_prefixes: args.strip_prefix.clone(),
+        normalize: args.normalize_paths,
+    };
+    mapping.apply(&mut report1);
+    mapping.apply(&mut report2);
 
     metrics_logger.log_metric("report1_files_count", report1.files.len() as f64);
     metrics_logger.log_metric("report2_files_count", report2.files.len() as f64);
     metrics_logger.log_metric("report1_total_lines", report1.summary.total_lines as f64);
     metrics_logger.log_metric("report2_total_lines", report2.summary.total_lines as f64);
 
+    let filter = FileFilter {
+        languages: args.lang.clone(),
+        paths: args.path_filter.clone(),
+        min_lines: 0,
+    };
     let comparison_start = Instant::now();
-    let comparison = ComparisonResult::compare(&report1, &report2);
+    let noise = NoiseThreshold {
+        min_lines: args.min_delta.unwrap_or(0),
+        min_percent: args.min_delta_pct.unwrap_or(0.0),
+    };
+    let mut comparison = if args.summary_only {
+        ComparisonResult::compare_summaries(&report1, &report2, &filter, &noise)
+    } else {
+        ComparisonResult::compare(&report1, &report2, &filter, &noise)
+    };
+    let sort = args.sort.unwrap_or_default();
+    comparison.sort_modified_files(sort);
+    if args.list_unchanged {
+        comparison.unchanged_file_paths.sort();
+    } else {
+        comparison.unchanged_file_paths.clear();
+    }
+    if let Some(depth) = args.by_dir {
+        comparison.directory_deltas = directory_deltas(
+            &filter.apply(&report1),
+            &filter.apply(&report2),
+            depth as usize,
+        );
+    }
     metrics_logger.log_metric("comparison_time", comparison_start.elapsed().as_secs_f64());
 
     // Log comparison metrics
@@ -125,12 +289,18 @@ pub fn execute_compare(args: CompareArgs) -> Result<()> {
         "logical_lines_delta",
         comparison.global_delta.logical_lines_delta as f64,
     );
+    metrics_logger.log_metric(
+        "comment_lines_delta",
+        comparison.global_delta.comment_lines_delta as f64,
+    );
     metrics_logger.log_metric("new_files_count", comparison.new_files.len() as f64);
     metrics_logger.log_metric("removed_files_count", comparison.removed_files.len() as f64);
+    metrics_logger.log_metric("renamed_files_count", comparison.renamed_files.len() as f64);
     metrics_logger.log_metric(
         "modified_files_count",
         comparison.modified_files.len() as f64,
     );
+    metrics_logger.log_metric("unchanged_files_count", comparison.unchanged_files as f64);
     metrics_logger.log_metric(
         "language_deltas_count",
         comparison.language_deltas.len() as f64,
@@ -138,16 +308,49 @@ pub fn execute_compare(args: CompareArgs) -> Result<()> {
 
     // REQ-7.3: Display comparison in console
     let display_start = Instant::now();
-    display_comparison(&comparison)?;
+    match verbosity {
+        Verbosity::Normal => display_comparison(&comparison, &filter, sort, args.top)?,
+        Verbosity::Quiet => display_comparison_compact(&comparison),
+        Verbosity::Silent => {}
+    }
     metrics_logger.log_metric("display_time", display_start.elapsed().as_secs_f64());
 
     // REQ-7.4: Export comparison if requested
-    if let Some(export_path) = args.export {
+    if let Some(export_path) = &args.export {
         let export_start = Instant::now();
-        let format = args.format.unwrap_or(OutputFormat::Json);
-        export_comparison(&comparison, &export_path, format)?;
+        let format = args
+            .format
+            .or_else(|| OutputFormat::from_path(export_path))
+            .unwrap_or(OutputFormat::Json);
+        export_comparison(&comparison, export_path, format)?;
         metrics_logger.log_metric("export_time", export_start.elapsed().as_secs_f64());
-        println!("\nComparison exported to: {}", export_path.display());
+        if verbosity == Verbosity::Normal {
+            println!("\nComparison exported to: {}", export_path.display());
+        }
+    }
+
+    // Machine-readable result on stdout
+    if let Some(print) = args.print {
+        print_result(&comparison, print)?;
+    }
+
+    // REQ-8.4: Threshold conditions, checked once everything has been written
+    // (per-file metrics are rejected with --summary-only before loading)
+    let conditions = args.conditions();
+    let (filtered1, filtered2) = (filter.apply(&report1), filter.apply(&report2));
+    let checks = MetricValues {
+        report1: &filtered1,
+        report2: &filtered2,
+        new_files: comparison.new_files.len(),
+        removed_files: comparison.removed_files.len(),
+        renamed_files: comparison.renamed_files.len(),
+        modified_files: comparison.modified_files.len(),
+        unchanged_files: comparison.unchanged_files,
+        churn_rate: comparison.churn_rate(),
+    }
+    .check(&conditions);
+    if verbosity == Verbosity::Normal && !checks.is_empty() {
+        display_checks(&checks);
     }
 
     let total_time = start_time.elapsed();
@@ -157,169 +360,164 @@ pub fn execute_compare(args: CompareArgs) -> Result<()> {
     metrics_logger.log_completion(total_files, total_lines);
     metrics_logger.log_metric("total_operation_time", total_time.as_secs_f64());
 
-    if metrics_logger.is_enabled() {
+    if metrics_logger.is_enabled() && verbosity == Verbosity::Normal {
         println!("Metrics logged to: {}", metrics_logger.file_path());
     }
 
+    let breached: Vec<_> = checks
+        .iter()
+        .filter(|check| check.breached)
+        .map(|check| {
+            format!(
+                "{} (actual {})",
+                check.condition,
+                format_value(check.actual)
+            )
+        })
+        .collect();
+    if !breached.is_empty() {
+        return Err(SlocError::ThresholdBreached(breached.join("; ")));
+    }
+
     Ok(())
 }
 
-fn detect_format(path: &Path) -> OutputFormat {
-    match path.extension().and_then(|e| e.to_str()) {
-        Some("json") => OutputFormat::Json,
-        Some("xml") => OutputFormat::Xml,
-        Some("csv") => OutputFormat::Csv,
-        _ => OutputFormat::Json,
+/// `count --baseline`: the comparison of a fresh count against an earlier report, shown
+/// and exported like `compare baseline.json new.json --export FILE`
+pub fn compare_to_baseline(
+    baseline: &Report,
+    report: &Report,
+    export: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<()> {
+    let comparison = compare_reports(baseline, report);
+
+    match verbosity {
+        Verbosity::Normal => {
+            display_comparison(&comparison, &FileFilter::default(), DeltaMetric::Total, 10)?
+        }
+        Verbosity::Quiet => display_comparison_compact(&comparison),
+        Verbosity::Silent => {}
     }
-}
 
-/// REQ-7.2: Comparison result structure
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ComparisonResult {
-    pub report1_generated: chrono::DateTime<chrono::Utc>,
-    pub report2_generated: chrono::DateTime<chrono::Utc>,
-    pub global_delta: GlobalDelta,
-    pub language_deltas: Vec<LanguageDelta>,
-    pub new_files: Vec<String>,
-    pub removed_files: Vec<String>,
-    pub modified_files: Vec<FileDelta>,
+    if let Some(export_path) = export {
+        let format = OutputFormat::from_path(export_path).unwrap_or(OutputFormat::Json);
+        export_comparison(&comparison, export_path, format)?;
+        if verbosity == Verbosity::Normal {
+            println!("\nComparison exported to: {}", export_path.display());
+        }
+    }
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GlobalDelta {
-    pub files_delta: i64,
-    pub total_lines_delta: i64,
-    pub logical_lines_delta: i64,
-    pub empty_lines_delta: i64,
-    pub languages_delta: i64,
+/// `--fail-if` and threshold flags: one line per condition
+pub(crate) fn display_checks(checks: &[Check]) {
+    println!("\n{}", "Thresholds".bold().green());
+    println!("{}", rule(40).green());
+    for check in checks {
+        let status = if check.breached {
+            glyph("✗", "FAIL").red()
+        } else {
+            glyph("✓", "ok").green()
+        };
+        println!(
+            "  {} {} (actual {})",
+            status,
+            check.condition,
+            format_value(check.actual)
+        );
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LanguageDelta {
-    pub language: String,
-    pub files_delta: i64,
-    pub total_lines_delta: i64,
-    pub logical_lines_delta: i64,
-    pub empty_lines_delta: i64,
+/// Integral metric values without decimals, percentages with two
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FileDelta {
-    pub path: String,
-    pub total_lines_delta: i64,
-    pub logical_lines_delta: i64,
-    pub empty_lines_delta: i64,
+/// Whether stdout carries a result (`--export -` or `--print`); both at once is an error
+fn stdout_claimed(export: Option<&Path>, print: Option<PrintFormat>) -> Result<bool> {
+    let export_to_stdout = export.is_some_and(is_stdout);
+    if export_to_stdout && print.is_some() {
+        return Err(SlocError::Parse(
+            "--print cannot be combined with `--export -` (both write to stdout)".to_string(),
+        ));
+    }
+    Ok(export_to_stdout || print.is_some())
 }
 
-impl ComparisonResult {
-    /// REQ-7.2: Compare two reports
-    fn compare(report1: &Report, report2: &Report) -> Self {
-        // Create file maps for comparison
-        let files1: HashMap<_, _> = report1.files.iter().map(|f| (f.path.clone(), f)).collect();
-        let files2: HashMap<_, _> = report2.files.iter().map(|f| (f.path.clone(), f)).collect();
-
-        // Find new, removed, and modified files
-        let mut new_files = Vec::new();
-        let mut removed_files = Vec::new();
-        let mut modified_files = Vec::new();
-
-        for (path, file2) in &files2 {
-            if let Some(file1) = files1.get(path) {
-                // File exists in both - check if modified
-                if file1.total_lines != file2.total_lines
-                    || file1.logical_lines != file2.logical_lines
-                    || file1.empty_lines != file2.empty_lines
-                {
-                    modified_files.push(FileDelta {
-                        path: path.to_string_lossy().to_string(),
-                        total_lines_delta: file2.total_lines as i64 - file1.total_lines as i64,
-                        logical_lines_delta: file2.logical_lines as i64
-                            - file1.logical_lines as i64,
-                        empty_lines_delta: file2.empty_lines as i64 - file1.empty_lines as i64,
-                    });
-                }
-            } else {
-                new_files.push(path.to_string_lossy().to_string());
-            }
-        }
-
-        for path in files1.keys() {
-            if !files2.contains_key(path) {
-                removed_files.push(path.to_string_lossy().to_string());
-            }
-        }
-
-        // Calculate global deltas
-        let global_delta = GlobalDelta {
-            files_delta: report2.summary.total_files as i64 - report1.summary.total_files as i64,
-            total_lines_delta: report2.summary.total_lines as i64
-                - report1.summary.total_lines as i64,
-            logical_lines_delta: report2.summary.logical_lines as i64
-                - report1.summary.logical_lines as i64,
-            empty_lines_delta: report2.summary.empty_lines as i64
-                - report1.summary.empty_lines as i64,
-            languages_delta: report2.summary.languages_count as i64
-                - report1.summary.languages_count as i64,
-        };
+/// Detect the report format from the file extension, ignoring `.gz`/`.zst` suffixes
+/// A filter or condition that needs the files of a report counted with `--max-memory`
+fn totals_only_error(report: &Path, what: &str) -> SlocError {
+    SlocError::Parse(format!(
+        "{} only has totals (its files were dropped by --max-memory); \"{}\" needs its files",
+        report.display(),
+        what
+    ))
+}
 
-        // Calculate language deltas
-        let lang1: HashMap<_, _> = report1
-            .languages
-            .iter()
-            .map(|l| (l.language.clone(), l))
-            .collect();
-        let lang2: HashMap<_, _> = report2
-            .languages
-            .iter()
-            .map(|l| (l.language.clone(), l))
-            .collect();
-
-        let mut language_deltas = Vec::new();
-        let all_languages = lang1
-            .keys()
-            .chain(lang2.keys())
-            .collect::<std::collections::HashSet<_>>();
-
-        for language in all_languages {
-            let stats1 = lang1.get(language);
-            let stats2 = lang2.get(language);
-
-            let delta = LanguageDelta {
-                language: language.to_string(),
-                files_delta: stats2.map(|s| s.file_count as i64).unwrap_or(0)
-                    - stats1.map(|s| s.file_count as i64).unwrap_or(0),
-                total_lines_delta: stats2.map(|s| s.total_lines as i64).unwrap_or(0)
-                    - stats1.map(|s| s.total_lines as i64).unwrap_or(0),
-                logical_lines_delta: stats2.map(|s| s.logical_lines as i64).unwrap_or(0)
-                    - stats1.map(|s| s.logical_lines as i64).unwrap_or(0),
-                empty_lines_delta: stats2.map(|s| s.empty_lines as i64).unwrap_or(0)
-                    - stats1.map(|s| s.empty_lines as i64).unwrap_or(0),
-            };
-
-            if delta.files_delta != 0 || delta.total_lines_delta != 0 {
-                language_deltas.push(delta);
-            }
-        }
+fn detect_format(path: &Path) -> OutputFormat {
+    OutputFormat::from_path(path).unwrap_or(OutputFormat::Json)
+}
 
-        language_deltas.sort_by(|a, b| a.language.cmp(&b.language));
+pub use crate::model::{
+    ComparisonResult, DirectoryDelta, FileDelta, GlobalDelta, LanguageDelta, RenamedFile,
+};
 
-        ComparisonResult {
-            report1_generated: report1.generated_at,
-            report2_generated: report2.generated_at,
-            global_delta,
-            language_deltas,
-            new_files,
-            removed_files,
-            modified_files,
-        }
+/// `--quiet`: one line with the global deltas instead of the tables
+fn display_comparison_compact(comparison: &ComparisonResult) {
+    let g = &comparison.global_delta;
+    if comparison.summary_only {
+        println!(
+            "files {:+}, lines {:+} (logical {:+}, comment {:+}, empty {:+}), languages {:+}",
+            g.files_delta,
+            g.total_lines_delta,
+            g.logical_lines_delta,
+            g.comment_lines_delta,
+            g.empty_lines_delta,
+            g.languages_delta
+        );
+        return;
     }
+    println!(
+        "files {:+} ({} new, {} removed, {} renamed, {} modified, {} unchanged), lines {:+} (logical {:+}, comment {:+}, empty {:+}), languages {:+}",
+        g.files_delta,
+        comparison.new_files.len(),
+        comparison.removed_files.len(),
+        comparison.renamed_files.len(),
+        comparison.modified_files.len(),
+        comparison.unchanged_files,
+        g.total_lines_delta,
+        g.logical_lines_delta,
+        g.comment_lines_delta,
+        g.empty_lines_delta,
+        g.languages_delta
+    );
 }
 
 /// REQ-7.3: Display comparison results in console
-fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
-    println!("\n{}", "═".repeat(80).blue());
+fn display_comparison(
+    comparison: &ComparisonResult,
+    filter: &FileFilter,
+    sort: DeltaMetric,
+    top: usize,
+) -> Result<()> {
+    println!("\n{}", heavy_rule(80).blue());
     println!("{}", "Report Comparison".bold().cyan());
-    println!("{}", "═".repeat(80).blue());
+    println!("{}", heavy_rule(80).blue());
+
+    if let Some(description) = filter.describe() {
+        println!("\n{} {}", "Only comparing".bold(), description);
+    }
+    if comparison.summary_only {
+        println!(
+            "\n{}",
+            "Summary only: file lists were not computed".dimmed()
+        );
+    }
 
     println!("\n{}", "Timestamps:".bold());
     println!(
@@ -333,7 +531,7 @@ fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
 
     // Global changes
     println!("\n{}", "Global Changes".bold().green());
-    println!("{}", "─".repeat(40).green());
+    println!("{}", rule(40).green());
 
     let mut table = Table::new();
     table.add_row(Row::new(vec![
@@ -352,6 +550,11 @@ fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
         "Logical Lines",
         comparison.global_delta.logical_lines_delta,
     );
+    display_delta_row(
+        &mut table,
+        "Comment Lines",
+        comparison.global_delta.comment_lines_delta,
+    );
     display_delta_row(
         &mut table,
         "Empty Lines",
@@ -363,20 +566,21 @@ fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
         comparison.global_delta.languages_delta,
     );
 
-    table.printstd();
+    print_table(&table);
 
     // Language changes
     if !comparison.language_deltas.is_empty() {
         println!("\n{}", "Language Changes".bold().green());
-        println!("{}", "─".repeat(80).green());
+        println!("{}", rule(80).green());
 
         let mut table = Table::new();
         table.add_row(Row::new(vec![
             Cell::new("Language").style_spec("b"),
-            Cell::new("Files Δ").style_spec("b"),
-            Cell::new("Total Δ").style_spec("b"),
-            Cell::new("Logical Δ").style_spec("b"),
-            Cell::new("Empty Δ").style_spec("b"),
+            Cell::new(&format!("Files {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Total {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Logical {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Comment {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Empty {}", glyph("Δ", "delta"))).style_spec("b"),
         ]));
 
         for lang in &comparison.language_deltas {
@@ -385,11 +589,41 @@ fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
                 Cell::new(&format_delta(lang.files_delta)),
                 Cell::new(&format_delta(lang.total_lines_delta)),
                 Cell::new(&format_delta(lang.logical_lines_delta)),
+                Cell::new(&format_delta(lang.comment_lines_delta)),
                 Cell::new(&format_delta(lang.empty_lines_delta)),
             ]));
         }
 
-        table.printstd();
+        print_table(&table);
+    }
+
+    // Directory changes (--by-dir)
+    if !comparison.directory_deltas.is_empty() {
+        println!("\n{}", "Directory Changes".bold().green());
+        println!("{}", rule(80).green());
+
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Directory").style_spec("b"),
+            Cell::new(&format!("Files {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Total {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Logical {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Comment {}", glyph("Δ", "delta"))).style_spec("b"),
+            Cell::new(&format!("Empty {}", glyph("Δ", "delta"))).style_spec("b"),
+        ]));
+
+        for dir in &comparison.directory_deltas {
+            table.add_row(Row::new(vec![
+                Cell::new(&dir.path),
+                Cell::new(&format_delta(dir.files_delta)),
+                Cell::new(&format_delta(dir.total_lines_delta)),
+                Cell::new(&format_delta(dir.logical_lines_delta)),
+                Cell::new(&format_delta(dir.comment_lines_delta)),
+                Cell::new(&format_delta(dir.empty_lines_delta)),
+            ]));
+        }
+
+        print_table(&table);
     }
 
     // File changes summary
@@ -429,29 +663,76 @@ fn display_comparison(comparison: &ComparisonResult) -> Result<()> {
         }
     }
 
-    if !comparison.modified_files.is_empty() {
+    if !comparison.renamed_files.is_empty() {
         println!(
             "\n{}: {}",
+            "Renamed Files".bold().blue(),
+            comparison.renamed_files.len()
+        );
+        for file in comparison.renamed_files.iter().take(10) {
+            println!(
+                "  {} {} {}",
+                file.from.blue(),
+                glyph("→", "->"),
+                file.to.blue()
+            );
+        }
+        if comparison.renamed_files.len() > 10 {
+            println!("  ... and {} more", comparison.renamed_files.len() - 10);
+        }
+    }
+
+    if !comparison.modified_files.is_empty() || comparison.ignored_modified_files > 0 {
+        println!(
+            "\n{}: {}{}",
             "Modified Files".bold().yellow(),
-            comparison.modified_files.len()
+            comparison.modified_files.len(),
+            if comparison.ignored_modified_files > 0 {
+                format!(
+                    " ({} more below --min-delta/--min-delta-pct not listed)",
+                    comparison.ignored_modified_files
+                )
+            } else {
+                String::new()
+            }
         );
-        if comparison.modified_files.len() <= 10 {
-            for file in &comparison.modified_files {
+        // Biggest movers first (`sort_modified_files`), `--top 0` lists all
+        let shown = if top == 0 {
+            comparison.modified_files.len()
+        } else {
+            top
+        };
+        for file in comparison.modified_files.iter().take(shown) {
+            if sort == DeltaMetric::Total {
                 println!(
                     "  ~ {} ({})",
                     file.path.yellow(),
                     format_delta(file.total_lines_delta)
                 );
-            }
-        } else {
-            for file in comparison.modified_files.iter().take(10) {
+            } else {
                 println!(
-                    "  ~ {} ({})",
+                    "  ~ {} ({} {}, total {})",
                     file.path.yellow(),
+                    format_delta(file.delta(sort)),
+                    sort.name(),
                     format_delta(file.total_lines_delta)
                 );
             }
-            println!("  ... and {} more", comparison.modified_files.len() - 10);
+        }
+        if comparison.modified_files.len() > shown {
+            println!("  ... and {} more", comparison.modified_files.len() - shown);
+        }
+    }
+
+    if comparison.unchanged_files > 0 {
+        println!(
+            "\n{}: {} (churn rate {:.2} %)",
+            "Unchanged Files".bold(),
+            comparison.unchanged_files,
+            comparison.churn_rate()
+        );
+        for file in &comparison.unchanged_file_paths {
+            println!("  = {}", file);
         }
     }
 
@@ -487,23 +768,29 @@ fn export_comparison(
         OutputFormat::Json => {
             let json = serde_json::to_string_pretty(comparison)
                 .map_err(|e| SlocError::Serialization(e.to_string()))?;
-            std::fs::write(path, json)?;
+            create_writer(path)?.write_all(json.as_bytes())?;
         }
         OutputFormat::Xml => {
-            let xml = serde_xml_rs::to_string(comparison)
+            let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            let mut serializer = quick_xml::se::Serializer::with_root(&mut xml, Some("comparison"))
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            serializer.indent(' ', 2);
+            comparison
+                .serialize(serializer)
                 .map_err(|e| SlocError::Serialization(e.to_string()))?;
-            std::fs::write(path, xml)?;
+            xml.push('\n');
+            create_writer(path)?.write_all(xml.as_bytes())?;
         }
         OutputFormat::Csv => {
             // CSV export for comparison - simplified format
-            let mut wtr = csv::Writer::from_path(path)
-                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            let mut wtr = csv::Writer::from_writer(create_writer(path)?);
             wtr.write_record([
                 "Type",
                 "Name",
                 "Files Delta",
                 "Total Delta",
                 "Logical Delta",
+                "Comment Delta",
                 "Empty Delta",
             ])
             .map_err(|e| SlocError::Serialization(e.to_string()))?;
@@ -515,6 +802,7 @@ fn export_comparison(
                 &comparison.global_delta.files_delta.to_string(),
                 &comparison.global_delta.total_lines_delta.to_string(),
                 &comparison.global_delta.logical_lines_delta.to_string(),
+                &comparison.global_delta.comment_lines_delta.to_string(),
                 &comparison.global_delta.empty_lines_delta.to_string(),
             ])
             .map_err(|e| SlocError::Serialization(e.to_string()))?;
@@ -527,14 +815,38 @@ fn export_comparison(
                     &lang.files_delta.to_string(),
                     &lang.total_lines_delta.to_string(),
                     &lang.logical_lines_delta.to_string(),
+                    &lang.comment_lines_delta.to_string(),
                     &lang.empty_lines_delta.to_string(),
                 ])
                 .map_err(|e| SlocError::Serialization(e.to_string()))?;
             }
 
+            // Directories (--by-dir)
+            for dir in &comparison.directory_deltas {
+                wtr.write_record([
+                    "Directory",
+                    &dir.path,
+                    &dir.files_delta.to_string(),
+                    &dir.total_lines_delta.to_string(),
+                    &dir.logical_lines_delta.to_string(),
+                    &dir.comment_lines_delta.to_string(),
+                    &dir.empty_lines_delta.to_string(),
+                ])
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            }
+
             wtr.flush()
                 .map_err(|e| SlocError::Serialization(e.to_string()))?;
         }
+        OutputFormat::Html => {
+            create_writer(path)?.write_all(crate::html::comparison_page(comparison).as_bytes())?;
+        }
+        OutputFormat::ClocJson | OutputFormat::ClocCsv | OutputFormat::Junit => {
+            return Err(SlocError::InvalidReportFormat(format!(
+                "{:?} format is not available for comparisons",
+                format
+            )));
+        }
     }
 
     Ok(())
@@ -0,0 +1,187 @@
+// progress.rs - Structured progress events (`count --progress-json`, `serve` /api/progress)
+// Implements: REQ-9.5 (progress indication)
+//
+// A count reports a `start` event, `progress` events at most every 100 ms and a `done`
+// event, each with the files done so far, the last counted path and the throughput. `count
+// --progress-json` writes them to stderr as JSON lines; `serve` streams them to every
+// client of `GET /api/progress` as server-sent events.
+//
+// The progress bar of `count` is advanced in batches the same way, so counting threads do
+// not queue on the bar's state for every file.
+
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Minimum time between two `progress` events
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Files passed to the progress bar at once, unless `INTERVAL` elapses first
+const BAR_BATCH: u64 = 256;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStage {
+    Start,
+    Progress,
+    Done,
+}
+
+impl ProgressStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgressStage::Start => "start",
+            ProgressStage::Progress => "progress",
+            ProgressStage::Done => "done",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub event: ProgressStage,
+    pub files_done: usize,
+    pub files_total: usize,
+    /// The last counted file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub lines: usize,
+    pub elapsed_secs: f64,
+    pub files_per_sec: f64,
+    pub lines_per_sec: f64,
+}
+
+type Sink = Box<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// Progress of one count, shared by the counting threads
+pub struct ProgressTracker {
+    total: usize,
+    done: AtomicUsize,
+    lines: AtomicUsize,
+    started: Instant,
+    /// When the last `progress` event was sent
+    last: Mutex<Instant>,
+    sink: Sink,
+}
+
+impl ProgressTracker {
+    /// Track a count of `total` files, sending the `start` event to `sink` right away
+    pub fn start(total: usize, sink: impl Fn(&ProgressEvent) + Send + Sync + 'static) -> Self {
+        let tracker = ProgressTracker {
+            total,
+            done: AtomicUsize::new(0),
+            lines: AtomicUsize::new(0),
+            started: Instant::now(),
+            last: Mutex::new(Instant::now()),
+            sink: Box::new(sink),
+        };
+        (tracker.sink)(&tracker.event(ProgressStage::Start, None));
+        tracker
+    }
+
+    /// JSON lines on stderr (`count --progress-json`)
+    pub fn json_lines(total: usize) -> Self {
+        Self::start(total, |event| {
+            if let Ok(json) = serde_json::to_string(event) {
+                let mut stderr = std::io::stderr().lock();
+                let _ = writeln!(stderr, "{}", json);
+            }
+        })
+    }
+
+    /// A file was counted (or failed, with 0 lines)
+    pub fn file_done(&self, path: &Path, lines: usize) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+        self.lines.fetch_add(lines, Ordering::Relaxed);
+        // Threads that find the clock taken skip the event instead of waiting
+        if let Ok(mut last) = self.last.try_lock()
+            && last.elapsed() >= INTERVAL
+        {
+            *last = Instant::now();
+            (self.sink)(&self.event(ProgressStage::Progress, Some(path)));
+        }
+    }
+
+    /// Send the `done` event
+    pub fn finish(&self) {
+        (self.sink)(&self.event(ProgressStage::Done, None));
+    }
+
+    fn event(&self, stage: ProgressStage, path: Option<&Path>) -> ProgressEvent {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let files_done = self.done.load(Ordering::Relaxed);
+        let lines = self.lines.load(Ordering::Relaxed);
+        let rate = |amount: usize| {
+            if elapsed > 0.0 {
+                amount as f64 / elapsed
+            } else {
+                0.0
+            }
+        };
+        ProgressEvent {
+            event: stage,
+            files_done,
+            files_total: self.total,
+            path: path.map(Path::to_path_buf),
+            lines,
+            elapsed_secs: elapsed,
+            files_per_sec: rate(files_done),
+            lines_per_sec: rate(lines),
+        }
+    }
+}
+
+/// A progress bar advanced from the counting threads: files done are added up in an atomic
+/// counter and handed to the bar every `BAR_BATCH` files or `INTERVAL`, whichever comes
+/// first
+pub struct BatchedBar {
+    bar: ProgressBar,
+    pending: AtomicU64,
+    started: Instant,
+    /// Nanoseconds from `started` to the last hand-over
+    last: AtomicU64,
+}
+
+impl BatchedBar {
+    pub fn new(bar: ProgressBar) -> Self {
+        BatchedBar {
+            bar,
+            pending: AtomicU64::new(0),
+            started: Instant::now(),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    /// A file was counted
+    pub fn inc(&self) {
+        let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        let now = self.started.elapsed().as_nanos() as u64;
+        let last = self.last.load(Ordering::Relaxed);
+        // Of the threads that find a hand-over due, one takes it and the others go on
+        if (pending >= BAR_BATCH || now.saturating_sub(last) >= INTERVAL.as_nanos() as u64)
+            && self
+                .last
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.flush();
+        }
+    }
+
+    /// Hand the files counted since the last update to the bar and finish it
+    pub fn finish_with_message(&self, message: &'static str) {
+        self.flush();
+        self.bar.finish_with_message(message);
+    }
+
+    fn flush(&self) {
+        let pending = self.pending.swap(0, Ordering::Relaxed);
+        if pending > 0 {
+            self.bar.inc(pending);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// python.rs - Python module `counterlines` (feature `python`)
+// Implements: REQ-1.1 (line counting), REQ-6.4 (reports), REQ-7.2 (comparison)
+//
+// Reports and comparisons cross the boundary as the dictionaries `json.loads` makes of
+// the JSON that `count -f json` and `compare --print json` write, so notebooks get plain
+// dicts and lists to feed to pandas rather than wrapper classes. The GIL is released while
+// counting.
+//
+// Built as an extension module with maturin (see pyproject.toml): `maturin develop
+// --release` installs it in the current virtualenv.
+
+use crate::cli::OutputFormat;
+use crate::compare::compare_reports;
+use crate::counter::Counter;
+use crate::error::SlocError;
+use crate::report::Report;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde::Serialize;
+use std::path::PathBuf;
+
+pyo3::create_exception!(
+    counterlines,
+    CounterlinesError,
+    PyException,
+    "A count or comparison failed"
+);
+
+impl From<SlocError> for PyErr {
+    fn from(e: SlocError) -> PyErr {
+        CounterlinesError::new_err(e.to_string())
+    }
+}
+
+/// Count files, directories or glob patterns and return the report as a dict
+#[pyfunction]
+#[pyo3(signature = (paths, recursive = false, exclude = Vec::new(), threads = 0, config = None))]
+fn count(
+    py: Python<'_>,
+    paths: Vec<PathBuf>,
+    recursive: bool,
+    exclude: Vec<String>,
+    threads: usize,
+    config: Option<PathBuf>,
+) -> PyResult<Py<PyAny>> {
+    let mut builder = Counter::builder()
+        .paths(paths)
+        .recursive(recursive)
+        .threads(threads);
+    for pattern in exclude {
+        builder = builder.exclude(pattern);
+    }
+    if let Some(config) = config {
+        builder = builder.config(config);
+    }
+    let report = py.detach(|| builder.run())?;
+    to_python(py, &report)
+}
+
+/// Compare two reports, each a dict returned by `count` or the path of a saved report,
+/// and return the comparison as a dict
+#[pyfunction]
+fn compare(py: Python<'_>, old: &Bound<'_, PyAny>, new: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+    let old = from_python(py, old)?;
+    let new = from_python(py, new)?;
+    to_python(py, &compare_reports(&old, &new))
+}
+
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<Py<PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| SlocError::Serialization(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// A report given as a dict, or as the path of a report file
+fn from_python(py: Python<'_>, report: &Bound<'_, PyAny>) -> PyResult<Report> {
+    if report.is_instance_of::<PyString>() || report.hasattr("__fspath__")? {
+        let path: PathBuf = report.extract()?;
+        let format = OutputFormat::from_path(&path).unwrap_or(OutputFormat::Json);
+        return Ok(py.detach(|| Report::from_file(&path, format))?);
+    }
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (report,))?
+        .extract()?;
+    Ok(serde_json::from_str(&json).map_err(|e| SlocError::Deserialization(e.to_string()))?)
+}
+
+#[pymodule]
+fn counterlines(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(count, m)?)?;
+    m.add_function(wrap_pyfunction!(compare, m)?)?;
+    m.add("CounterlinesError", m.py().get_type::<CounterlinesError>())?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
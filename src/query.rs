@@ -0,0 +1,932 @@
+// query.rs - Extract values from a report with a JMESPath-like expression (`query`)
+// Implements: REQ-7.1 (report processing)
+//
+// The report is queried as its JSON form, whatever format it was loaded from. Supported:
+// field access (`summary.total_lines`, `"quoted name"`), indices (`files[0]`, `files[-1]`),
+// projections (`languages[*].language`, `files[].path`), filters (`languages[?logical_lines
+// > 10000]`), comparisons, `&&`, `||`, `!`, pipes (`a | b`), multi-select lists (`[a, b]`)
+// and hashes (`{name: language, lines: total_lines}`), and the functions listed in `call`.
+// Unlike JMESPath, numbers may be written without backticks.
+
+use crate::cli::{OutputFormat, QueryArgs};
+use crate::error::{Result, SlocError};
+use crate::report::Report;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+pub fn execute_query(args: QueryArgs) -> Result<()> {
+    let expression = parse(&args.expression)
+        .map_err(|e| SlocError::Parse(format!("query \"{}\": {}", args.expression, e)))?;
+
+    let format = OutputFormat::from_path(&args.report)
+        .or(args.format)
+        .unwrap_or(OutputFormat::Json);
+    let report = Report::from_file(&args.report, format)?;
+    let document =
+        serde_json::to_value(&report).map_err(|e| SlocError::Serialization(e.to_string()))?;
+
+    let result = evaluate(&expression, &document)
+        .map_err(|e| SlocError::Parse(format!("query \"{}\": {}", args.expression, e)))?;
+
+    let mut stdout = std::io::stdout().lock();
+    match (&result, args.raw) {
+        // `--raw`: strings unquoted, arrays of scalars one per line
+        (Value::String(text), true) => writeln!(stdout, "{}", text)?,
+        (Value::Array(items), true) if items.iter().all(is_scalar) => {
+            for item in items {
+                match item {
+                    Value::String(text) => writeln!(stdout, "{}", text)?,
+                    other => writeln!(stdout, "{}", other)?,
+                }
+            }
+        }
+        _ => {
+            serde_json::to_writer_pretty(&mut stdout, &result)
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(f64),
+    Literal(Value),
+    Dot,
+    Star,
+    At,
+    Ampersand,
+    Question,
+    Comma,
+    Colon,
+    Pipe,
+    Or,
+    And,
+    Not,
+    Compare(&'static str),
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+}
+
+/// As written in an expression, quoted for error messages
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Token::Identifier(name) => name,
+            Token::Number(n) => return write!(f, "'{}'", n),
+            Token::Literal(Value::String(text)) => return write!(f, "'{}'", text),
+            Token::Literal(value) => return write!(f, "`{}`", value),
+            Token::Dot => ".",
+            Token::Star => "*",
+            Token::At => "@",
+            Token::Ampersand => "&",
+            Token::Question => "?",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Pipe => "|",
+            Token::Or => "||",
+            Token::And => "&&",
+            Token::Not => "!",
+            Token::Compare(op) => op,
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::LeftParen => "(",
+            Token::RightParen => ")",
+            Token::LeftBrace => "{",
+            Token::RightBrace => "}",
+        };
+        write!(f, "'{}'", text)
+    }
+}
+
+/// A token for error messages, or the end of the expression
+fn describe(token: Option<&Token>) -> String {
+    token.map_or_else(|| "the end of the expression".to_string(), Token::to_string)
+}
+
+const SLICE_ERROR: &str = "slices are not supported; use an index such as [0] or [-1], or a \
+                           projection such as [*]";
+
+#[derive(Debug, Clone)]
+enum Expr {
+    /// `@`, the value being evaluated
+    Current,
+    Field(String),
+    Index(i64),
+    Literal(Value),
+    /// Evaluate the right side on the result of the left side
+    Sub(Box<Expr>, Box<Expr>),
+    /// Evaluate the right side on every element of the (array) left side, dropping nulls
+    Project(Box<Expr>, Box<Expr>),
+    /// Like `Project`, for the elements where the condition (middle) is truthy
+    Filter(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `[]`: one level of nested arrays merged into the outer array
+    Flatten(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    List(Vec<Expr>),
+    Hash(Vec<(String, Expr)>),
+    Call(String, Vec<Expr>),
+    /// `&expr`, an expression handed to `sort_by`, `max_by` and `min_by`
+    Reference(Box<Expr>),
+}
+
+fn tokenize(input: &str) -> std::result::Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, width) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '.' => (Token::Dot, 1),
+            '*' => (Token::Star, 1),
+            '@' => (Token::At, 1),
+            '?' => (Token::Question, 1),
+            ',' => (Token::Comma, 1),
+            ':' => (Token::Colon, 1),
+            '[' => (Token::LeftBracket, 1),
+            ']' => (Token::RightBracket, 1),
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            '{' => (Token::LeftBrace, 1),
+            '}' => (Token::RightBrace, 1),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '|' => (Token::Pipe, 1),
+            '&' if next == Some('&') => (Token::And, 2),
+            '&' => (Token::Ampersand, 1),
+            '=' if next == Some('=') => (Token::Compare("=="), 2),
+            '!' if next == Some('=') => (Token::Compare("!="), 2),
+            '!' => (Token::Not, 1),
+            '<' if next == Some('=') => (Token::Compare("<="), 2),
+            '<' => (Token::Compare("<"), 1),
+            '>' if next == Some('=') => (Token::Compare(">="), 2),
+            '>' => (Token::Compare(">"), 1),
+            // 'raw string'
+            '\'' => {
+                let end = find_closing(&chars, i, '\'')?;
+                let text: String = chars[i + 1..end].iter().collect();
+                (
+                    Token::Literal(Value::String(text.replace("\\'", "'"))),
+                    end + 1 - i,
+                )
+            }
+            // "quoted identifier"
+            '"' => {
+                let end = find_closing(&chars, i, '"')?;
+                let text: String = chars[i..=end].iter().collect();
+                let name: String = serde_json::from_str(&text)
+                    .map_err(|_| format!("invalid quoted identifier {}", text))?;
+                (Token::Identifier(name), end + 1 - i)
+            }
+            // `JSON literal`
+            '`' => {
+                let end = find_closing(&chars, i, '`')?;
+                let text: String = chars[i + 1..end].iter().collect();
+                let value = serde_json::from_str(&text)
+                    .map_err(|_| format!("invalid JSON literal `{}`", text))?;
+                (Token::Literal(value), end + 1 - i)
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().collect();
+                let number = text
+                    .parse()
+                    .map_err(|_| format!("invalid number {}", text))?;
+                (Token::Number(number), end - i)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                    end += 1;
+                }
+                (Token::Identifier(chars[i..end].iter().collect()), end - i)
+            }
+            other => return Err(format!("unexpected character '{}' at {}", other, i + 1)),
+        };
+        tokens.push(token);
+        i += width;
+    }
+    Ok(tokens)
+}
+
+/// Position of the delimiter closing the one at `start`, skipping backslash escapes
+fn find_closing(
+    chars: &[char],
+    start: usize,
+    delimiter: char,
+) -> std::result::Result<usize, String> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == delimiter => return Ok(i),
+            _ => i += 1,
+        }
+    }
+    Err(format!("unterminated {} at {}", delimiter, start + 1))
+}
+
+fn parse(input: &str) -> std::result::Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+    };
+    let expr = parser.pipe()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {} after the expression", token)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> std::result::Result<(), String> {
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            token => Err(format!(
+                "expected {}, found {}",
+                expected,
+                describe(token.as_ref())
+            )),
+        }
+    }
+
+    // Precedence, loosest first: `|`, `||`, `&&`, `!`, comparisons, then access chains
+
+    fn pipe(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.or()?;
+        while self.peek() == Some(&Token::Pipe) {
+            self.advance();
+            left = Expr::Pipe(Box::new(left), Box::new(self.or()?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> std::result::Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> std::result::Result<Expr, String> {
+        let left = self.primary()?;
+        if let Some(Token::Compare(op)) = self.peek() {
+            let op = *op;
+            self.advance();
+            let right = self.primary()?;
+            return Ok(Expr::Compare(op, Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> std::result::Result<Expr, String> {
+        let base = match self.peek().cloned() {
+            Some(Token::Identifier(name)) => {
+                self.advance();
+                if self.peek() == Some(&Token::LeftParen) {
+                    self.advance();
+                    let arguments = self.arguments()?;
+                    Expr::Call(name, arguments)
+                } else {
+                    Expr::Field(name)
+                }
+            }
+            Some(Token::At) => {
+                self.advance();
+                Expr::Current
+            }
+            Some(Token::Number(n)) => {
+                self.advance();
+                return Ok(Expr::Literal(number(n)));
+            }
+            Some(Token::Literal(value)) => {
+                self.advance();
+                return Ok(Expr::Literal(value));
+            }
+            Some(Token::Ampersand) => {
+                self.advance();
+                return Ok(Expr::Reference(Box::new(self.pipe()?)));
+            }
+            Some(Token::LeftParen) => {
+                self.advance();
+                let inner = self.pipe()?;
+                self.expect(Token::RightParen)?;
+                inner
+            }
+            Some(Token::LeftBrace) => self.hash()?,
+            // `[0]`, `[*]`, `[]` or `[?...]` apply to the current value; others are lists
+            Some(Token::LeftBracket) if self.is_multi_select() => self.list()?,
+            Some(Token::LeftBracket) => Expr::Current,
+            Some(Token::Star) => {
+                // `*` alone: every value of the current object
+                self.advance();
+                return Ok(Expr::Project(
+                    Box::new(Expr::Call("values".to_string(), vec![Expr::Current])),
+                    Box::new(self.chain(Expr::Current)?),
+                ));
+            }
+            Some(token) => return Err(format!("unexpected {}", token)),
+            None => return Err("unexpected end of the expression".to_string()),
+        };
+        self.chain(base)
+    }
+
+    fn is_multi_select(&self) -> bool {
+        !matches!(
+            self.peek_at(1),
+            Some(
+                Token::Number(_)
+                    | Token::Star
+                    | Token::RightBracket
+                    | Token::Question
+                    | Token::Colon
+            )
+        )
+    }
+
+    /// `.field`, `[index]` and projections following `base`. A projection takes the rest
+    /// of the chain as the expression applied to each element.
+    fn chain(&mut self, base: Expr) -> std::result::Result<Expr, String> {
+        let mut expr = base;
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.advance();
+                    let field = match self.peek().cloned() {
+                        Some(Token::Identifier(name)) => {
+                            self.advance();
+                            Expr::Field(name)
+                        }
+                        Some(Token::LeftBracket) => self.list()?,
+                        Some(Token::LeftBrace) => self.hash()?,
+                        Some(Token::Star) => {
+                            self.advance();
+                            let values = Expr::Call("values".to_string(), vec![Expr::Current]);
+                            let rest = self.chain(Expr::Current)?;
+                            return Ok(Expr::Project(
+                                Box::new(Expr::Sub(Box::new(expr), Box::new(values))),
+                                Box::new(rest),
+                            ));
+                        }
+                        other => {
+                            return Err(format!(
+                                "expected a field name after '.', found {}",
+                                describe(other.as_ref())
+                            ));
+                        }
+                    };
+                    expr = Expr::Sub(Box::new(expr), Box::new(field));
+                }
+                Some(Token::LeftBracket) => {
+                    self.advance();
+                    match self.advance() {
+                        Some(Token::Number(_)) if self.peek() == Some(&Token::Colon) => {
+                            return Err(SLICE_ERROR.to_string());
+                        }
+                        Some(Token::Number(n)) if n.fract() == 0.0 => {
+                            self.expect(Token::RightBracket)?;
+                            expr = Expr::Sub(Box::new(expr), Box::new(Expr::Index(n as i64)));
+                        }
+                        Some(Token::Star) => {
+                            self.expect(Token::RightBracket)?;
+                            let rest = self.chain(Expr::Current)?;
+                            return Ok(Expr::Project(Box::new(expr), Box::new(rest)));
+                        }
+                        Some(Token::RightBracket) => {
+                            let rest = self.chain(Expr::Current)?;
+                            return Ok(Expr::Project(
+                                Box::new(Expr::Flatten(Box::new(expr))),
+                                Box::new(rest),
+                            ));
+                        }
+                        Some(Token::Question) => {
+                            let condition = self.pipe()?;
+                            self.expect(Token::RightBracket)?;
+                            let rest = self.chain(Expr::Current)?;
+                            return Ok(Expr::Filter(
+                                Box::new(expr),
+                                Box::new(condition),
+                                Box::new(rest),
+                            ));
+                        }
+                        Some(Token::Colon) => return Err(SLICE_ERROR.to_string()),
+                        other => {
+                            return Err(format!(
+                                "unexpected {} after '['",
+                                describe(other.as_ref())
+                            ));
+                        }
+                    }
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> std::result::Result<Vec<Expr>, String> {
+        let mut arguments = Vec::new();
+        if self.peek() == Some(&Token::RightParen) {
+            self.advance();
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.pipe()?);
+            match self.advance() {
+                Some(Token::Comma) => {}
+                Some(Token::RightParen) => return Ok(arguments),
+                other => {
+                    return Err(format!(
+                        "expected ',' or ')', found {}",
+                        describe(other.as_ref())
+                    ));
+                }
+            }
+        }
+    }
+
+    fn list(&mut self) -> std::result::Result<Expr, String> {
+        self.expect(Token::LeftBracket)?;
+        let mut items = Vec::new();
+        loop {
+            items.push(self.pipe()?);
+            match self.advance() {
+                Some(Token::Comma) => {}
+                Some(Token::RightBracket) => return Ok(Expr::List(items)),
+                other => {
+                    return Err(format!(
+                        "expected ',' or ']', found {}",
+                        describe(other.as_ref())
+                    ));
+                }
+            }
+        }
+    }
+
+    fn hash(&mut self) -> std::result::Result<Expr, String> {
+        self.expect(Token::LeftBrace)?;
+        let mut entries = Vec::new();
+        loop {
+            let key = match self.advance() {
+                Some(Token::Identifier(key)) => key,
+                other => {
+                    return Err(format!(
+                        "expected a key, found {}",
+                        describe(other.as_ref())
+                    ));
+                }
+            };
+            self.expect(Token::Colon)?;
+            entries.push((key, self.pipe()?));
+            match self.advance() {
+                Some(Token::Comma) => {}
+                Some(Token::RightBrace) => return Ok(Expr::Hash(entries)),
+                other => {
+                    return Err(format!(
+                        "expected ',' or '}}', found {}",
+                        describe(other.as_ref())
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn evaluate(expr: &Expr, current: &Value) -> std::result::Result<Value, String> {
+    Ok(match expr {
+        Expr::Current => current.clone(),
+        Expr::Field(name) => current.get(name).cloned().unwrap_or(Value::Null),
+        Expr::Index(index) => match current {
+            Value::Array(items) => {
+                let position = if *index < 0 {
+                    items.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(position)
+                    .ok()
+                    .and_then(|p| items.get(p))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            }
+            _ => Value::Null,
+        },
+        Expr::Literal(value) => value.clone(),
+        Expr::Sub(left, right) => match evaluate(left, current)? {
+            Value::Null => Value::Null,
+            value => evaluate(right, &value)?,
+        },
+        Expr::Project(left, right) => match evaluate(left, current)? {
+            Value::Array(items) => project(items.iter(), right)?,
+            _ => Value::Null,
+        },
+        Expr::Filter(left, condition, right) => match evaluate(left, current)? {
+            Value::Array(items) => {
+                let mut kept = Vec::new();
+                for item in items {
+                    if truthy(&evaluate(condition, &item)?) {
+                        kept.push(item);
+                    }
+                }
+                project(kept.iter(), right)?
+            }
+            _ => Value::Null,
+        },
+        Expr::Flatten(inner) => match evaluate(inner, current)? {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .flat_map(|item| match item {
+                        Value::Array(nested) => nested,
+                        other => vec![other],
+                    })
+                    .collect(),
+            ),
+            _ => Value::Null,
+        },
+        Expr::Pipe(left, right) => evaluate(right, &evaluate(left, current)?)?,
+        Expr::Or(left, right) => {
+            let value = evaluate(left, current)?;
+            if truthy(&value) {
+                value
+            } else {
+                evaluate(right, current)?
+            }
+        }
+        Expr::And(left, right) => {
+            let value = evaluate(left, current)?;
+            if truthy(&value) {
+                evaluate(right, current)?
+            } else {
+                value
+            }
+        }
+        Expr::Not(inner) => Value::Bool(!truthy(&evaluate(inner, current)?)),
+        Expr::Compare(op, left, right) => {
+            compare(op, &evaluate(left, current)?, &evaluate(right, current)?)
+        }
+        Expr::List(items) => {
+            if current.is_null() {
+                return Ok(Value::Null);
+            }
+            Value::Array(
+                items
+                    .iter()
+                    .map(|item| evaluate(item, current))
+                    .collect::<std::result::Result<_, _>>()?,
+            )
+        }
+        Expr::Hash(entries) => {
+            if current.is_null() {
+                return Ok(Value::Null);
+            }
+            let mut object = Map::new();
+            for (key, value) in entries {
+                object.insert(key.clone(), evaluate(value, current)?);
+            }
+            Value::Object(object)
+        }
+        Expr::Call(name, arguments) => call(name, arguments, current)?,
+        Expr::Reference(_) => {
+            return Err("&expression is only valid as a function argument".to_string());
+        }
+    })
+}
+
+fn project<'a>(
+    items: impl Iterator<Item = &'a Value>,
+    expr: &Expr,
+) -> std::result::Result<Value, String> {
+    let mut results = Vec::new();
+    for item in items {
+        let value = evaluate(expr, item)?;
+        if !value.is_null() {
+            results.push(value);
+        }
+    }
+    Ok(Value::Array(results))
+}
+
+/// JMESPath truthiness: null, false and empty strings, arrays and objects are false
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        Value::Number(_) => true,
+    }
+}
+
+/// `==` and `!=` on any values (numbers by value), ordering on numbers and strings only
+fn compare(op: &str, left: &Value, right: &Value) -> Value {
+    let equal = match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => left == right,
+    };
+    let ordering = match (left, right) {
+        (Value::Number(_), Value::Number(_)) => left.as_f64().partial_cmp(&right.as_f64()),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    };
+    match (op, ordering) {
+        ("==", _) => Value::Bool(equal),
+        ("!=", _) => Value::Bool(!equal),
+        (_, None) => Value::Null,
+        ("<", Some(o)) => Value::Bool(o.is_lt()),
+        ("<=", Some(o)) => Value::Bool(o.is_le()),
+        (">", Some(o)) => Value::Bool(o.is_gt()),
+        (_, Some(o)) => Value::Bool(o.is_ge()),
+    }
+}
+
+/// Whole numbers stay integers in the output
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+/// Functions: length, keys, values, sum, avg, min, max, sort, reverse, contains,
+/// starts_with, ends_with, sort_by, min_by, max_by
+fn call(name: &str, arguments: &[Expr], current: &Value) -> std::result::Result<Value, String> {
+    let arity = |expected: usize| {
+        if arguments.len() == expected {
+            Ok(())
+        } else {
+            Err(format!(
+                "{}() takes {} argument(s), {} given",
+                name,
+                expected,
+                arguments.len()
+            ))
+        }
+    };
+
+    // The `_by` functions evaluate their `&expr` argument on each element
+    if let "sort_by" | "min_by" | "max_by" = name {
+        arity(2)?;
+        let Value::Array(items) = evaluate(&arguments[0], current)? else {
+            return Err(format!("{}() expects an array", name));
+        };
+        let Expr::Reference(key) = &arguments[1] else {
+            return Err(format!(
+                "{}() expects &expression as its second argument",
+                name
+            ));
+        };
+        let mut keyed = Vec::new();
+        for item in items {
+            keyed.push((evaluate(key, &item)?, item));
+        }
+        let order = |a: &Value, b: &Value| match (a, b) {
+            (Value::Number(_), Value::Number(_)) => a
+                .as_f64()
+                .partial_cmp(&b.as_f64())
+                .unwrap_or(std::cmp::Ordering::Equal),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            _ => std::cmp::Ordering::Equal,
+        };
+        return Ok(match name {
+            "sort_by" => {
+                keyed.sort_by(|(a, _), (b, _)| order(a, b));
+                Value::Array(keyed.into_iter().map(|(_, item)| item).collect())
+            }
+            "min_by" => keyed
+                .into_iter()
+                .min_by(|(a, _), (b, _)| order(a, b))
+                .map(|(_, item)| item)
+                .unwrap_or(Value::Null),
+            _ => keyed
+                .into_iter()
+                .max_by(|(a, _), (b, _)| order(a, b))
+                .map(|(_, item)| item)
+                .unwrap_or(Value::Null),
+        });
+    }
+
+    let values: Vec<Value> = arguments
+        .iter()
+        .map(|argument| evaluate(argument, current))
+        .collect::<std::result::Result<_, _>>()?;
+    let numbers = |value: &Value| -> std::result::Result<Vec<f64>, String> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| item.as_f64())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("{}() expects an array of numbers", name)),
+            _ => Err(format!("{}() expects an array of numbers", name)),
+        }
+    };
+    let text = |value: &Value| -> std::result::Result<String, String> {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("{}() expects strings", name))
+    };
+
+    Ok(match name {
+        "length" => {
+            arity(1)?;
+            match &values[0] {
+                Value::String(s) => Value::from(s.chars().count()),
+                Value::Array(items) => Value::from(items.len()),
+                Value::Object(map) => Value::from(map.len()),
+                _ => return Err("length() expects a string, array or object".to_string()),
+            }
+        }
+        "keys" | "values" => {
+            arity(1)?;
+            match &values[0] {
+                Value::Object(map) if name == "keys" => {
+                    Value::Array(map.keys().cloned().map(Value::String).collect())
+                }
+                Value::Object(map) => Value::Array(map.values().cloned().collect()),
+                _ => Value::Null,
+            }
+        }
+        "sum" => {
+            arity(1)?;
+            number(numbers(&values[0])?.iter().sum())
+        }
+        "avg" => {
+            arity(1)?;
+            let numbers = numbers(&values[0])?;
+            if numbers.is_empty() {
+                Value::Null
+            } else {
+                Value::from(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+        }
+        "min" | "max" => {
+            arity(1)?;
+            let numbers = numbers(&values[0])?;
+            let extreme = if name == "min" {
+                numbers.into_iter().reduce(f64::min)
+            } else {
+                numbers.into_iter().reduce(f64::max)
+            };
+            extreme.map(number).unwrap_or(Value::Null)
+        }
+        "sort" => {
+            arity(1)?;
+            match &values[0] {
+                Value::Array(items) if items.iter().all(Value::is_string) => {
+                    let mut items = items.clone();
+                    items.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                    Value::Array(items)
+                }
+                value => {
+                    let mut numbers = numbers(value)?;
+                    numbers.sort_by(|a, b| a.total_cmp(b));
+                    Value::Array(numbers.into_iter().map(number).collect())
+                }
+            }
+        }
+        "reverse" => {
+            arity(1)?;
+            match &values[0] {
+                Value::Array(items) => Value::Array(items.iter().rev().cloned().collect()),
+                Value::String(s) => Value::String(s.chars().rev().collect()),
+                _ => return Err("reverse() expects an array or string".to_string()),
+            }
+        }
+        "contains" => {
+            arity(2)?;
+            match &values[0] {
+                Value::Array(items) => Value::Bool(items.contains(&values[1])),
+                subject => Value::Bool(text(subject)?.contains(&text(&values[1])?)),
+            }
+        }
+        "starts_with" => {
+            arity(2)?;
+            Value::Bool(text(&values[0])?.starts_with(&text(&values[1])?))
+        }
+        "ends_with" => {
+            arity(2)?;
+            Value::Bool(text(&values[0])?.ends_with(&text(&values[1])?))
+        }
+        _ => return Err(format!("unknown function {}()", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(expression: &str) -> String {
+        parse(expression).unwrap_err()
+    }
+
+    #[test]
+    fn errors_quote_the_token_text() {
+        assert_eq!(error("files[,]"), "unexpected ',' after '['");
+        assert_eq!(
+            error("summary."),
+            "expected a field name after '.', found the end of the expression"
+        );
+        assert_eq!(
+            error("(summary"),
+            "expected ')', found the end of the expression"
+        );
+        assert_eq!(error("{name language}"), "expected ':', found 'language'");
+        assert_eq!(error("length(files;"), "unexpected character ';' at 13");
+        assert_eq!(error("summary )"), "unexpected ')' after the expression");
+        assert_eq!(error("[a, `1` `2`]"), "expected ',' or ']', found `2`");
+    }
+
+    #[test]
+    fn slices_are_rejected() {
+        for expression in [
+            "files[1:3]",
+            "files[:3]",
+            "files[1:]",
+            "files[::2]",
+            "[0:1]",
+        ] {
+            assert!(
+                error(expression).starts_with("slices are not supported"),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn indices_and_projections_still_parse() {
+        let document = serde_json::json!({"files": [{"path": "a"}, {"path": "b"}]});
+        for (expression, expected) in [
+            ("files[-1].path", serde_json::json!("b")),
+            ("files[*].path", serde_json::json!(["a", "b"])),
+            ("[files[0].path, 'x']", serde_json::json!(["a", "x"])),
+        ] {
+            let expr = parse(expression).unwrap();
+            assert_eq!(
+                evaluate(&expr, &document).unwrap(),
+                expected,
+                "{expression}"
+            );
+        }
+    }
+}
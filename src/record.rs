@@ -0,0 +1,390 @@
+// record.rs - Run history database (`record`, `trend --db`)
+// Implements: REQ-6.4 (report summary), REQ-7.2 (comparison), REQ-8.4 (exit status for CI pipelines)
+//
+// Every `record` appends one run to a SQLite database: the summary and per-language totals
+// of a fresh count (or of an existing report) together with the checked-out commit, its
+// branch and whether the working tree was dirty. Before the run is stored, `--fail-if`
+// conditions are checked against the previous run of the same branch, so a CI job can
+// both keep the history and fail on a regression. `trend --db` shows the recorded series.
+
+use crate::cli::{OutputFormat, RecordArgs};
+use crate::counter::{collect_input_paths, count_paths};
+use crate::error::{Result, SlocError};
+use crate::git;
+use crate::language::LanguageDetector;
+use crate::processor::display_checks;
+use crate::report::{GlobalSummary, LanguageStats, Report};
+use crate::threshold::MetricValues;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use num_format::{Locale, ToFormattedString};
+use rusqlite::{Connection, params};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Schema version stored in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at TEXT NOT NULL,
+    generator_version TEXT NOT NULL,
+    git_commit TEXT,
+    git_branch TEXT,
+    committed_at TEXT,
+    dirty INTEGER NOT NULL DEFAULT 0,
+    total_files INTEGER NOT NULL,
+    total_lines INTEGER NOT NULL,
+    logical_lines INTEGER NOT NULL,
+    comment_lines INTEGER NOT NULL,
+    empty_lines INTEGER NOT NULL,
+    languages_count INTEGER NOT NULL,
+    unsupported_files INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS run_languages (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    language TEXT NOT NULL,
+    file_count INTEGER NOT NULL,
+    total_lines INTEGER NOT NULL,
+    logical_lines INTEGER NOT NULL,
+    comment_lines INTEGER NOT NULL,
+    empty_lines INTEGER NOT NULL,
+    PRIMARY KEY (run_id, language)
+);
+CREATE INDEX IF NOT EXISTS runs_branch ON runs (git_branch, id);
+";
+
+/// One recorded run
+pub struct RecordedRun {
+    pub id: i64,
+    pub recorded_at: DateTime<Utc>,
+    pub commit: Option<String>,
+    pub branch: Option<String>,
+    pub dirty: bool,
+    pub summary: GlobalSummary,
+    pub languages: Vec<LanguageStats>,
+}
+
+impl RecordedRun {
+    /// Abbreviated commit (`*` when the tree was dirty), or `#id` without git
+    pub fn label(&self) -> String {
+        match &self.commit {
+            Some(commit) => format!(
+                "{}{}",
+                &commit[..commit.len().min(7)],
+                if self.dirty { "*" } else { "" }
+            ),
+            None => format!("#{}", self.id),
+        }
+    }
+
+    /// A report holding only the recorded totals (no files)
+    pub fn to_report(&self) -> Report {
+        let mut report = Report::new(Vec::new(), Vec::new());
+        report.generated_at = self.recorded_at;
+        report.summary = self.summary.clone();
+        report.languages = self.languages.clone();
+        report
+    }
+}
+
+pub struct HistoryDb {
+    connection: Connection,
+}
+
+impl HistoryDb {
+    /// Open the database, creating it and its tables on first use
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path).map_err(|e| database(path, e))?;
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| database(path, e))?;
+        if version > SCHEMA_VERSION {
+            return Err(SlocError::Database(format!(
+                "{}: schema version {} is newer than this release supports ({})",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            )));
+        }
+        connection
+            .execute_batch(SCHEMA)
+            .and_then(|_| connection.pragma_update(None, "user_version", SCHEMA_VERSION))
+            .and_then(|_| connection.pragma_update(None, "foreign_keys", true))
+            .map_err(|e| database(path, e))?;
+        Ok(HistoryDb { connection })
+    }
+
+    /// Open an existing database for reading
+    pub fn open_existing(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Err(SlocError::FileNotFound {
+                path: path.to_path_buf(),
+            });
+        }
+        Self::open(path)
+    }
+
+    /// Append a run; returns its id
+    pub fn insert(&mut self, report: &Report, head: Option<&git::HeadCommit>) -> Result<i64> {
+        let tx = self.connection.transaction().map_err(sql)?;
+        let s = &report.summary;
+        tx.execute(
+            "INSERT INTO runs (recorded_at, generator_version, git_commit, git_branch,
+                 committed_at, dirty, total_files, total_lines, logical_lines, comment_lines,
+                 empty_lines, languages_count, unsupported_files)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                Utc::now().to_rfc3339(),
+                env!("CARGO_PKG_VERSION"),
+                head.map(|h| &h.commit),
+                head.and_then(|h| h.branch.as_ref()),
+                head.map(|h| h.committed_at.to_rfc3339()),
+                head.is_some_and(|h| h.dirty),
+                s.total_files as i64,
+                s.total_lines as i64,
+                s.logical_lines as i64,
+                s.comment_lines as i64,
+                s.empty_lines as i64,
+                s.languages_count as i64,
+                s.unsupported_files as i64,
+            ],
+        )
+        .map_err(sql)?;
+        let id = tx.last_insert_rowid();
+        for lang in &report.languages {
+            tx.execute(
+                "INSERT INTO run_languages (run_id, language, file_count, total_lines,
+                     logical_lines, comment_lines, empty_lines)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    lang.language,
+                    lang.file_count as i64,
+                    lang.total_lines as i64,
+                    lang.logical_lines as i64,
+                    lang.comment_lines as i64,
+                    lang.empty_lines as i64,
+                ],
+            )
+            .map_err(sql)?;
+        }
+        tx.commit().map_err(sql)?;
+        Ok(id)
+    }
+
+    /// Recorded runs, oldest first, optionally of one branch only
+    pub fn runs(&self, branch: Option<&str>) -> Result<Vec<RecordedRun>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT id, recorded_at, git_commit, git_branch, dirty, total_files,
+                     total_lines, logical_lines, comment_lines, empty_lines, languages_count,
+                     unsupported_files
+                 FROM runs WHERE ?1 IS NULL OR git_branch = ?1 ORDER BY id",
+            )
+            .map_err(sql)?;
+        let mut runs = statement
+            .query_map([branch], |row| {
+                let recorded_at: String = row.get(1)?;
+                Ok(RecordedRun {
+                    id: row.get(0)?,
+                    recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+                        .map(|t| t.with_timezone(&Utc))
+                        .unwrap_or_default(),
+                    commit: row.get(2)?,
+                    branch: row.get(3)?,
+                    dirty: row.get(4)?,
+                    summary: GlobalSummary {
+                        total_files: row.get::<_, i64>(5)? as usize,
+                        total_lines: row.get::<_, i64>(6)? as usize,
+                        logical_lines: row.get::<_, i64>(7)? as usize,
+                        comment_lines: row.get::<_, i64>(8)? as usize,
+                        empty_lines: row.get::<_, i64>(9)? as usize,
+                        languages_count: row.get::<_, i64>(10)? as usize,
+                        unsupported_files: row.get::<_, i64>(11)? as usize,
+                        skipped_files: 0,
+                        invalid_files: 0,
+                    },
+                    languages: Vec::new(),
+                })
+            })
+            .map_err(sql)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(sql)?;
+
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT language, file_count, total_lines, logical_lines, comment_lines,
+                     empty_lines
+                 FROM run_languages WHERE run_id = ?1 ORDER BY total_lines DESC, language",
+            )
+            .map_err(sql)?;
+        for run in &mut runs {
+            run.languages = statement
+                .query_map([run.id], |row| {
+                    Ok(LanguageStats {
+                        language: row.get(0)?,
+                        file_count: row.get::<_, i64>(1)? as usize,
+                        total_lines: row.get::<_, i64>(2)? as usize,
+                        logical_lines: row.get::<_, i64>(3)? as usize,
+                        comment_lines: row.get::<_, i64>(4)? as usize,
+                        empty_lines: row.get::<_, i64>(5)? as usize,
+                    })
+                })
+                .map_err(sql)?
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(sql)?;
+        }
+        Ok(runs)
+    }
+}
+
+fn database(path: &Path, e: rusqlite::Error) -> SlocError {
+    SlocError::Database(format!("{}: {}", path.display(), e))
+}
+
+fn sql(e: rusqlite::Error) -> SlocError {
+    SlocError::Database(e.to_string())
+}
+
+pub fn execute_record(args: RecordArgs) -> Result<()> {
+    if let Some(condition) = args.fail_if.iter().find(|c| c.metric.is_per_file()) {
+        return Err(SlocError::Parse(format!(
+            "\"{}\" needs a per-file comparison; recorded runs only keep summaries",
+            condition
+        )));
+    }
+
+    let report = match &args.report {
+        Some(path) => Report::from_file(
+            path,
+            OutputFormat::from_path(path).unwrap_or(OutputFormat::Json),
+        )?,
+        None => {
+            let mut detector = LanguageDetector::new();
+            if let Some(config_path) = &args.config {
+                detector.load_from_config(config_path)?;
+            }
+            for (ext, lang) in &args.language_override {
+                detector.add_override(ext.clone(), lang.clone());
+            }
+            let mut paths = collect_input_paths(&args.paths, args.recursive)?;
+            paths.sort();
+            paths.dedup();
+            count_paths(
+                paths,
+                &Arc::new(detector),
+                args.ignore_preprocessor,
+                None,
+                None,
+            )
+        }
+    };
+    let head = git::head_commit();
+
+    let mut db = HistoryDb::open(&args.db)?;
+    // The baseline: the chosen branch, else the current one, else whatever ran last
+    let branch = args
+        .baseline_branch
+        .clone()
+        .or_else(|| head.as_ref().and_then(|h| h.branch.clone()));
+    let mut candidates = db.runs(branch.as_deref())?;
+    if candidates.is_empty() && args.baseline_branch.is_none() {
+        candidates = db.runs(None)?;
+    }
+    let baseline = candidates.pop();
+
+    let s = &report.summary;
+    let commit = match &head {
+        Some(h) => format!(
+            " (commit {}{}{})",
+            &h.commit[..h.commit.len().min(7)],
+            h.branch
+                .as_ref()
+                .map(|b| format!(" on {}", b))
+                .unwrap_or_default(),
+            if h.dirty { ", uncommitted changes" } else { "" }
+        ),
+        None => String::new(),
+    };
+    if args.dry_run {
+        println!("Not recorded (--dry-run){}", commit);
+    } else {
+        let id = db.insert(&report, head.as_ref())?;
+        println!(
+            "Recorded run #{} in {}{}",
+            id.to_string().bold(),
+            args.db.display(),
+            commit
+        );
+    }
+    println!(
+        "  {} files, {} lines ({} logical, {} comment, {} empty)",
+        s.total_files.to_formatted_string(&Locale::en),
+        s.total_lines.to_formatted_string(&Locale::en),
+        s.logical_lines.to_formatted_string(&Locale::en),
+        s.comment_lines.to_formatted_string(&Locale::en),
+        s.empty_lines.to_formatted_string(&Locale::en)
+    );
+
+    let Some(baseline) = baseline else {
+        if !args.fail_if.is_empty() {
+            println!(
+                "{}",
+                "  No earlier run to compare with; --fail-if conditions skipped".yellow()
+            );
+        }
+        return Ok(());
+    };
+    let delta = |after: usize, before: usize| {
+        let delta = after as i64 - before as i64;
+        let text = format!("{:+}", delta);
+        match delta {
+            0 => text.normal(),
+            d if d > 0 => text.green(),
+            _ => text.red(),
+        }
+    };
+    println!(
+        "  Since run #{} ({}{}): {} files, {} lines",
+        baseline.id,
+        baseline.label(),
+        baseline
+            .branch
+            .as_ref()
+            .map(|b| format!(" on {}", b))
+            .unwrap_or_default(),
+        delta(s.total_files, baseline.summary.total_files),
+        delta(s.total_lines, baseline.summary.total_lines)
+    );
+
+    if args.fail_if.is_empty() {
+        return Ok(());
+    }
+    let before = baseline.to_report();
+    let checks = MetricValues {
+        report1: &before,
+        report2: &report,
+        new_files: 0,
+        removed_files: 0,
+        renamed_files: 0,
+        modified_files: 0,
+        unchanged_files: 0,
+        churn_rate: 0.0,
+    }
+    .check(&args.fail_if);
+    display_checks(&checks);
+
+    let breached: Vec<String> = checks
+        .iter()
+        .filter(|check| check.breached)
+        .map(|check| check.condition.to_string())
+        .collect();
+    if !breached.is_empty() {
+        return Err(SlocError::ThresholdBreached(breached.join("; ")));
+    }
+    Ok(())
+}
@@ -12,85 +12,51 @@
 //   REQ-6.9: Checksum
 //   REQ-9.7: Metrics logging
 
-use crate::cli::ReportArgs;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "cli")]
+use crate::cli::{PathStyle, ReportArgs};
+#[cfg(feature = "cli")]
 use crate::config::{AppConfig, MetricsLogger};
+#[cfg(feature = "cli")]
 use crate::counter;
+#[cfg(feature = "cli")]
 use crate::error::Result;
-use chrono::{DateTime, Utc};
+#[cfg(feature = "cli")]
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::PathBuf;
+#[cfg(feature = "cli")]
+use std::collections::BTreeMap;
+#[cfg(feature = "cli")]
+use std::io::Read;
+#[cfg(feature = "cli")]
 use std::sync::Arc;
+#[cfg(feature = "cli")]
 use std::time::Instant;
 
-/// REQ-6.6: Report format version
-// Use version from Cargo.toml at compile time
-pub const REPORT_FORMAT_VERSION: &str = env!("CARGO_PKG_VERSION");
-
-/// REQ-6.4: File statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
-/// REQ-1.1: File statistics including comment lines
-pub struct FileStats {
-    pub path: PathBuf,
-    pub language: String,
-    pub total_lines: usize,
-    pub logical_lines: usize,
-    pub comment_lines: usize,
-    pub empty_lines: usize,
-}
-
-/// REQ-6.4: Language summary statistics (includes comment lines per REQ-1.1)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LanguageStats {
-    pub language: String,
-    pub file_count: usize,
-    pub total_lines: usize,
-    pub logical_lines: usize,
-    pub comment_lines: usize,
-    pub empty_lines: usize,
-}
-
-/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7: Report structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7: Report structure (includes comment lines per REQ-1.1)
-/// REQ-6.4, REQ-6.5, REQ-6.6, REQ-6.7, REQ-3.5: Report structure (includes unsupported files)
-pub struct Report {
-    /// REQ-6.6: Report format version
-    pub report_format_version: String,
-
-    /// REQ-6.5: Generation timestamp (RFC 3339 / ISO 8601)
-    pub generated_at: DateTime<Utc>,
+/// REQ-6.6: Report format version (MAJOR.MINOR), independent of the release version.
+/// MAJOR changes break readers; MINOR changes only add fields. Releases up to 0.2.2
+/// wrote their package version here instead (see `migrate.rs`).
+pub const REPORT_FORMAT_VERSION: &str = "1.2";
 
-    /// REQ-6.4: Per-file statistics
-    pub files: Vec<FileStats>,
+/// Format version of the per-file CSV written by releases up to 0.2.2, which has no
+/// version of its own; `migrate.rs` upgrades it as a 0.2.x report
+#[cfg(feature = "cli")]
+const LEGACY_CSV_FORMAT_VERSION: &str = "0.2";
 
-    /// Language summaries
-    pub languages: Vec<LanguageStats>,
+/// REQ-6.9: Prefix of checksums computed by [`Report::calculate_checksum`]. Earlier
+/// releases wrote a bare hash of the file statistics, which `migrate.rs` still checks.
+pub const CHECKSUM_PREFIX: &str = "sha256-v2:";
 
-    /// Global summary
-    pub summary: GlobalSummary,
+/// Release that wrote the report (from Cargo.toml at compile time)
+pub const GENERATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-    /// REQ-3.5: List of unsupported files (excluded from statistics)
-    pub unsupported_files: Vec<std::path::PathBuf>,
-
-    /// REQ-6.9: Optional checksum
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub checksum: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-/// REQ-6.4: Global summary statistics (includes comment lines per REQ-1.1)
-pub struct GlobalSummary {
-    pub total_files: usize,
-    pub total_lines: usize,
-    pub logical_lines: usize,
-    pub comment_lines: usize,
-    pub empty_lines: usize,
-    pub languages_count: usize,
-    pub unsupported_files: usize,
-}
+pub use crate::model::{
+    Diagnostic, DiagnosticCode, DirectoryStats, FileStats, GlobalSummary, InvalidFile,
+    LanguageStats, Report, SkippedFile,
+};
 
 impl Report {
     /// Create a new report from file statistics
@@ -104,28 +70,108 @@ impl Report {
             generated_at: Utc::now(),
             files,
             languages,
+            directories: Vec::new(),
             summary,
             unsupported_files,
+            skipped_files: Vec::new(),
+            invalid_files: Vec::new(),
+            diagnostics: Vec::new(),
+            generator_version: Some(GENERATOR_VERSION.to_string()),
             checksum: None,
         }
     }
 
+    /// Record the `count` files of languages that were not counted, listed in `files`
+    /// unless only totals were kept
+    pub fn set_skipped(&mut self, files: Vec<SkippedFile>, count: usize) {
+        self.skipped_files = files;
+        self.summary.skipped_files = count;
+    }
+
+    /// Record the files with bytes their encoding cannot decode
+    pub fn set_invalid(&mut self, files: Vec<InvalidFile>) {
+        self.summary.invalid_files = files.len();
+        self.invalid_files = files;
+    }
+
+    /// Whether the per-file results were dropped (`count --max-memory`): the summaries
+    /// are complete but `files` is empty, so nothing per file can be derived from them
+    pub fn is_totals_only(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.code == DiagnosticCode::TotalsOnly)
+    }
+
     /// Calculate language statistics
-    fn calculate_language_stats(files: &[FileStats]) -> Vec<LanguageStats> {
-        let mut lang_map: HashMap<String, LanguageStats> = HashMap::new();
+    pub(crate) fn calculate_language_stats(files: &[FileStats]) -> Vec<LanguageStats> {
+        let mut totals = ReportTotals::default();
+        for file in files {
+            totals.add_file(file);
+        }
+        totals.into_languages()
+    }
+
+    /// `--paths`: store file and unsupported paths in the requested form
+    #[cfg(feature = "cli")]
+    pub fn restyle_paths(&mut self, style: PathStyle) {
+        for file in &mut self.files {
+            file.path = style_path(&file.path, style);
+        }
+        for path in &mut self.unsupported_files {
+            *path = style_path(path, style);
+        }
+        for file in &mut self.skipped_files {
+            file.path = style_path(&file.path, style);
+        }
+        for file in &mut self.invalid_files {
+            file.path = style_path(&file.path, style);
+        }
+        for path in self.diagnostics.iter_mut().filter_map(|d| d.path.as_mut()) {
+            *path = style_path(path, style);
+        }
+    }
+
+    /// Keep only the files matching `keep` and recompute the language and global
+    /// summaries. Directory summaries and the checksum no longer apply and are dropped.
+    pub fn retain_files(&mut self, keep: impl Fn(&FileStats) -> bool) {
+        self.files.retain(|f| keep(f));
+        self.languages = Self::calculate_language_stats(&self.files);
+        let GlobalSummary {
+            unsupported_files,
+            skipped_files,
+            invalid_files,
+            ..
+        } = self.summary;
+        self.summary = Self::calculate_summary(&self.files, &self.languages);
+        self.summary.unsupported_files = unsupported_files;
+        self.summary.skipped_files = skipped_files;
+        self.summary.invalid_files = invalid_files;
+        self.directories.clear();
+        self.checksum = None;
+    }
 
+    /// `--by-dir`: aggregate the files per directory. Directories are taken relative to the
+    /// deepest directory containing every file and cut after `depth` levels, so files in
+    /// deeper subdirectories count towards their ancestor at that depth.
+    pub fn group_by_directory(&mut self, depth: usize) {
+        self.directories = Self::calculate_directory_stats(&self.files, depth);
+    }
+
+    fn calculate_directory_stats(files: &[FileStats], depth: usize) -> Vec<DirectoryStats> {
+        let base = common_directory(files);
+
+        let mut dir_map: HashMap<PathBuf, DirectoryStats> = HashMap::new();
         for file in files {
-            let entry = lang_map
-                .entry(file.language.clone())
-                .or_insert(LanguageStats {
-                    language: file.language.clone(),
-                    file_count: 0,
-                    total_lines: 0,
-                    logical_lines: 0,
-                    comment_lines: 0,
-                    empty_lines: 0,
-                });
+            let key = directory_key(&file.path, &base, depth);
 
+            let entry = dir_map.entry(key.clone()).or_insert(DirectoryStats {
+                path: key,
+                file_count: 0,
+                total_lines: 0,
+                logical_lines: 0,
+                comment_lines: 0,
+                empty_lines: 0,
+            });
             entry.file_count += 1;
             entry.total_lines += file.total_lines;
             entry.logical_lines += file.logical_lines;
@@ -133,10 +179,10 @@ impl Report {
             entry.empty_lines += file.empty_lines;
         }
 
-        let mut languages: Vec<LanguageStats> = lang_map.into_values().collect();
+        let mut directories: Vec<DirectoryStats> = dir_map.into_values().collect();
         // REQ-9.3: Deterministic output
-        languages.sort_by(|a, b| a.language.cmp(&b.language));
-        languages
+        directories.sort_by(|a, b| a.path.cmp(&b.path));
+        directories
     }
 
     /// Calculate global summary
@@ -149,50 +195,101 @@ impl Report {
             empty_lines: files.iter().map(|f| f.empty_lines).sum(),
             languages_count: languages.len(),
             unsupported_files: 0, // sarà valorizzato in Report::new
+            skipped_files: 0,
+            invalid_files: 0,
         }
     }
 
-    /// REQ-6.9: Calculate SHA256 checksum
+    /// REQ-6.9: Calculate the SHA256 checksum of everything the report counted: the files
+    /// with all their fields, the summaries, the files left out and the diagnostics. The
+    /// hashed text is the report's JSON with the lists of paths sorted and without the
+    /// timestamp, versions and checksum, so reports of equal counts have equal checksums.
     pub fn calculate_checksum(&mut self) {
-        let mut hasher = Sha256::new();
+        let mut counted = self.clone();
+        counted.report_format_version.clear();
+        counted.generated_at = DateTime::<Utc>::UNIX_EPOCH;
+        counted.generator_version = None;
+        counted.checksum = None;
+        counted.files.sort_by(|a, b| a.path.cmp(&b.path));
+        counted.unsupported_files.sort();
+        counted.skipped_files.sort_by(|a, b| a.path.cmp(&b.path));
+        counted.invalid_files.sort_by(|a, b| a.path.cmp(&b.path));
 
-        // Hash all file stats in deterministic order
-        let mut sorted_files = self.files.clone();
-        sorted_files.sort_by(|a, b| a.path.cmp(&b.path));
+        // JSON only takes UTF-8 paths
+        let lossy = |path: &mut PathBuf| *path = PathBuf::from(path.to_string_lossy().as_ref());
+        counted.files.iter_mut().for_each(|f| lossy(&mut f.path));
+        counted
+            .directories
+            .iter_mut()
+            .for_each(|d| lossy(&mut d.path));
+        counted.unsupported_files.iter_mut().for_each(lossy);
+        counted
+            .skipped_files
+            .iter_mut()
+            .for_each(|f| lossy(&mut f.path));
+        counted
+            .invalid_files
+            .iter_mut()
+            .for_each(|f| lossy(&mut f.path));
+        counted
+            .diagnostics
+            .iter_mut()
+            .filter_map(|d| d.path.as_mut())
+            .for_each(lossy);
 
-        for file in &sorted_files {
-            hasher.update(file.path.to_string_lossy().as_bytes());
-            hasher.update(file.language.as_bytes());
-            hasher.update(file.total_lines.to_string().as_bytes());
-            hasher.update(file.logical_lines.to_string().as_bytes());
-            hasher.update(file.comment_lines.to_string().as_bytes());
-            hasher.update(file.empty_lines.to_string().as_bytes());
-        }
+        let json = serde_json::to_vec(&counted).expect("a report with UTF-8 paths serializes");
+        self.checksum = Some(format!(
+            "{}{}",
+            CHECKSUM_PREFIX,
+            hex::encode(Sha256::digest(&json))
+        ));
+    }
 
-        let result = hasher.finalize();
-        self.checksum = Some(hex::encode(result));
+    /// REQ-6.6: Load report from file and upgrade it to the current format version,
+    /// so reports written by older releases can be processed and compared
+    #[cfg(feature = "cli")]
+    pub fn from_file(path: &Path, format: crate::cli::OutputFormat) -> Result<Self> {
+        let mut report = Self::read_file(path, format)?;
+        if let Some(note) = crate::migrate::upgrade(&mut report)? {
+            tracing::warn!("{}: {}", path.display(), note);
+        }
+        Ok(report)
     }
 
-    /// Load report from file
-    pub fn from_file(path: &PathBuf, format: crate::cli::OutputFormat) -> Result<Self> {
+    /// Load report from file as written, without format migration (gzip/zstd compressed
+    /// files are decompressed transparently). JSON and CSV are parsed straight from the
+    /// buffered reader, so the raw file is never held in memory next to the parsed report.
+    #[cfg(feature = "cli")]
+    pub fn read_file(path: &Path, format: crate::cli::OutputFormat) -> Result<Self> {
         let load_start = Instant::now();
-        let content = std::fs::read_to_string(path)?;
+        let mut reader = crate::compression::open_reader(path)?;
 
         let report = match format {
-            crate::cli::OutputFormat::Json => serde_json::from_str(&content)
+            crate::cli::OutputFormat::Json => serde_json::from_reader(reader)
                 .map_err(|e| crate::error::SlocError::Deserialization(e.to_string()))?,
-            crate::cli::OutputFormat::Xml => serde_xml_rs::from_str(&content)
-                .map_err(|e| crate::error::SlocError::Deserialization(e.to_string()))?,
-            crate::cli::OutputFormat::Csv => {
-                // CSV requires special handling
-                Self::from_csv(&content)?
+            crate::cli::OutputFormat::Xml => {
+                // The validating XML loader works on the whole document
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                crate::xml::read_report(&content)?
+            }
+            crate::cli::OutputFormat::Csv => Self::from_csv(reader)?,
+            crate::cli::OutputFormat::ClocJson
+            | crate::cli::OutputFormat::ClocCsv
+            | crate::cli::OutputFormat::Junit
+            | crate::cli::OutputFormat::Html => {
+                // These outputs only carry aggregated totals, not a full report
+                return Err(crate::error::SlocError::InvalidReportFormat(format!(
+                    "{:?} output cannot be loaded as a report",
+                    format
+                )));
             }
         };
 
         // Log load performance if this takes a significant time
         let load_time = load_start.elapsed();
         if load_time.as_millis() > 100 {
-            println!(
+            tracing::info!(
                 "Report loaded in {:.2}s ({} files)",
                 load_time.as_secs_f64(),
                 report.files.len()
@@ -202,22 +299,564 @@ impl Report {
         Ok(report)
     }
 
-    /// Load report from CSV
-    fn from_csv(content: &str) -> Result<Self> {
-        let mut reader = csv::Reader::from_reader(content.as_bytes());
+    /// REQ-6.2: Serialize the report as XML (layout in `schemas/report*.xsd`)
+    #[cfg(feature = "cli")]
+    pub fn to_xml(&self, style: crate::cli::XmlStyle) -> Result<String> {
+        crate::xml::write_report(self, style)
+    }
+
+    /// REQ-6.3: Flatten the report into sectioned CSV records (see [`CsvRecord`])
+    #[cfg(feature = "cli")]
+    pub fn to_csv_records(&self) -> Vec<CsvRecord> {
+        let mut records = vec![
+            CsvRecord::meta("report_format_version", &self.report_format_version),
+            CsvRecord::meta("generated_at", &self.generated_at.to_rfc3339()),
+            CsvRecord::meta("languages_count", &self.summary.languages_count.to_string()),
+            CsvRecord::meta(
+                "unsupported_files",
+                &self.summary.unsupported_files.to_string(),
+            ),
+        ];
+        if let Some(generator) = &self.generator_version {
+            records.push(CsvRecord::meta("generator_version", generator));
+        }
+        if let Some(checksum) = &self.checksum {
+            records.push(CsvRecord::meta("checksum", checksum));
+        }
+
+        records.push(CsvRecord {
+            section: CsvSection::Summary,
+            name: "global".to_string(),
+            files: Some(self.summary.total_files),
+            total_lines: Some(self.summary.total_lines),
+            logical_lines: Some(self.summary.logical_lines),
+            comment_lines: Some(self.summary.comment_lines),
+            empty_lines: Some(self.summary.empty_lines),
+            ..Default::default()
+        });
+
+        for lang in &self.languages {
+            records.push(CsvRecord {
+                section: CsvSection::Language,
+                name: lang.language.clone(),
+                files: Some(lang.file_count),
+                total_lines: Some(lang.total_lines),
+                logical_lines: Some(lang.logical_lines),
+                comment_lines: Some(lang.comment_lines),
+                empty_lines: Some(lang.empty_lines),
+                ..Default::default()
+            });
+        }
+
+        for dir in &self.directories {
+            records.push(CsvRecord {
+                section: CsvSection::Directory,
+                name: dir.path.to_string_lossy().to_string(),
+                files: Some(dir.file_count),
+                total_lines: Some(dir.total_lines),
+                logical_lines: Some(dir.logical_lines),
+                comment_lines: Some(dir.comment_lines),
+                empty_lines: Some(dir.empty_lines),
+                ..Default::default()
+            });
+        }
+
+        for file in &self.files {
+            records.push(CsvRecord {
+                section: CsvSection::File,
+                name: file.path.to_string_lossy().to_string(),
+                language: Some(file.language.clone()),
+                total_lines: Some(file.total_lines),
+                logical_lines: Some(file.logical_lines),
+                comment_lines: Some(file.comment_lines),
+                empty_lines: Some(file.empty_lines),
+                value: (!file.metrics.is_empty()).then(|| {
+                    file.metrics
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect::<Vec<_>>()
+                        .join(";")
+                }),
+                ..Default::default()
+            });
+        }
+
+        for path in &self.unsupported_files {
+            records.push(CsvRecord {
+                section: CsvSection::Unsupported,
+                name: path.to_string_lossy().to_string(),
+                ..Default::default()
+            });
+        }
+
+        for diagnostic in &self.diagnostics {
+            records.push(CsvRecord {
+                section: CsvSection::Diagnostic,
+                name: diagnostic
+                    .path
+                    .as_ref()
+                    .map_or_else(String::new, |path| path.to_string_lossy().to_string()),
+                value: Some(format!(
+                    "{}: {}",
+                    diagnostic.code.name(),
+                    diagnostic.message
+                )),
+                ..Default::default()
+            });
+        }
+
+        records
+    }
+
+    /// Load report from CSV: the sectioned layout written by the exporter, or the
+    /// legacy per-file layout (`Path,Language,Total Lines,...`) of older releases
+    #[cfg(feature = "cli")]
+    fn from_csv(input: impl Read) -> Result<Self> {
+        // Flexible, because the legacy layout mixes full rows with single-column ones
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+        let is_sectioned = reader
+            .headers()
+            .map_err(|e| crate::error::SlocError::Deserialization(e.to_string()))?
+            .get(0)
+            .is_some_and(|first| first == "section");
+        if !is_sectioned {
+            return Self::from_legacy_csv(reader);
+        }
+
+        let mut meta = HashMap::new();
+        let mut files = Vec::new();
+        let mut languages = Vec::new();
+        let mut directories = Vec::new();
+        let mut summary = None;
+        let mut unsupported_files = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        for (index, result) in reader.deserialize::<CsvRecord>().enumerate() {
+            let record =
+                result.map_err(|e| crate::error::SlocError::Deserialization(e.to_string()))?;
+            // +2: 1-based numbering plus the header line
+            let line = index + 2;
+            match record.section {
+                CsvSection::Meta => {
+                    meta.insert(record.name, record.value.unwrap_or_default());
+                }
+                CsvSection::Summary => {
+                    summary = Some(GlobalSummary {
+                        total_files: record.required(record.files, "files", line)?,
+                        total_lines: record.required(record.total_lines, "total_lines", line)?,
+                        logical_lines: record.required(
+                            record.logical_lines,
+                            "logical_lines",
+                            line,
+                        )?,
+                        comment_lines: record.required(
+                            record.comment_lines,
+                            "comment_lines",
+                            line,
+                        )?,
+                        empty_lines: record.required(record.empty_lines, "empty_lines", line)?,
+                        languages_count: 0,
+                        unsupported_files: 0,
+                        skipped_files: 0,
+                        invalid_files: 0,
+                    });
+                }
+                CsvSection::Language => languages.push(LanguageStats {
+                    file_count: record.required(record.files, "files", line)?,
+                    total_lines: record.required(record.total_lines, "total_lines", line)?,
+                    logical_lines: record.required(record.logical_lines, "logical_lines", line)?,
+                    comment_lines: record.required(record.comment_lines, "comment_lines", line)?,
+                    empty_lines: record.required(record.empty_lines, "empty_lines", line)?,
+                    language: record.name,
+                }),
+                CsvSection::Directory => directories.push(DirectoryStats {
+                    file_count: record.required(record.files, "files", line)?,
+                    total_lines: record.required(record.total_lines, "total_lines", line)?,
+                    logical_lines: record.required(record.logical_lines, "logical_lines", line)?,
+                    comment_lines: record.required(record.comment_lines, "comment_lines", line)?,
+                    empty_lines: record.required(record.empty_lines, "empty_lines", line)?,
+                    path: PathBuf::from(record.name),
+                }),
+                CsvSection::File => files.push(FileStats {
+                    language: record.language.clone().unwrap_or_default(),
+                    total_lines: record.required(record.total_lines, "total_lines", line)?,
+                    logical_lines: record.required(record.logical_lines, "logical_lines", line)?,
+                    comment_lines: record.required(record.comment_lines, "comment_lines", line)?,
+                    empty_lines: record.required(record.empty_lines, "empty_lines", line)?,
+                    metrics: record.metrics(line)?,
+                    encoding: None,
+                    invalid_lines: 0,
+                    path: PathBuf::from(record.name),
+                }),
+                CsvSection::Unsupported => unsupported_files.push(PathBuf::from(record.name)),
+                CsvSection::Diagnostic => {
+                    let value = record.value.unwrap_or_default();
+                    let (code, message) = value.split_once(": ").unwrap_or((&value, ""));
+                    diagnostics.push(Diagnostic {
+                        code: DiagnosticCode::from_name(code),
+                        path: (!record.name.is_empty()).then(|| PathBuf::from(record.name)),
+                        message: message.to_string(),
+                    });
+                }
+            }
+        }
+
+        // Start from recomputed aggregates, then restore everything the file recorded
+        let mut report = Self::new(files, unsupported_files);
+        report.diagnostics = diagnostics;
+        if !languages.is_empty() {
+            report.languages = languages;
+        }
+        report.directories = directories;
+        if let Some(mut summary) = summary {
+            summary.languages_count = report.summary.languages_count;
+            summary.unsupported_files = report.summary.unsupported_files;
+            report.summary = summary;
+        }
+        // Every sectioned CSV records its format version; without it, the file cannot be
+        // told apart from a current one
+        report.report_format_version = meta.remove("report_format_version").ok_or_else(|| {
+            crate::error::SlocError::Deserialization(
+                "missing `meta,report_format_version` row".to_string(),
+            )
+        })?;
+        report.generator_version = meta.remove("generator_version");
+        if let Some(generated_at) = meta.remove("generated_at") {
+            report.generated_at = DateTime::parse_from_rfc3339(&generated_at)
+                .map_err(|e| {
+                    crate::error::SlocError::Deserialization(format!("generated_at: {}", e))
+                })?
+                .with_timezone(&Utc);
+        }
+        if let Some(count) = meta.remove("languages_count") {
+            report.summary.languages_count = count.parse().map_err(|e| {
+                crate::error::SlocError::Deserialization(format!("languages_count: {}", e))
+            })?;
+        }
+        if let Some(count) = meta.remove("unsupported_files") {
+            report.summary.unsupported_files = count.parse().map_err(|e| {
+                crate::error::SlocError::Deserialization(format!("unsupported_files: {}", e))
+            })?;
+        }
+        report.checksum = meta.remove("checksum");
+
+        Ok(report)
+    }
+
+    /// Load the per-file CSV written by releases up to 0.2.2, including its trailing
+    /// "--- Unsupported Files (not counted) ---" block of single-column rows
+    #[cfg(feature = "cli")]
+    fn from_legacy_csv(mut reader: csv::Reader<impl Read>) -> Result<Self> {
         let mut files = Vec::new();
+        let mut unsupported_files = Vec::new();
+        let mut in_unsupported = false;
 
-        for result in reader.deserialize() {
-            let file: FileStats =
+        for result in reader.records() {
+            let record =
                 result.map_err(|e| crate::error::SlocError::Deserialization(e.to_string()))?;
-            files.push(file);
+            let first = record.get(0).unwrap_or_default();
+
+            if first.starts_with("--- Unsupported") {
+                in_unsupported = true;
+            } else if in_unsupported {
+                unsupported_files.push(PathBuf::from(first));
+            } else {
+                let number = |index: usize| -> Result<usize> {
+                    record
+                        .get(index)
+                        .unwrap_or_default()
+                        .trim()
+                        .parse()
+                        .map_err(|e| {
+                            crate::error::SlocError::Deserialization(format!(
+                                "line {}: column {}: {}",
+                                record.position().map(|p| p.line()).unwrap_or(0),
+                                index + 1,
+                                e
+                            ))
+                        })
+                };
+                files.push(FileStats {
+                    path: PathBuf::from(first),
+                    language: record.get(1).unwrap_or_default().to_string(),
+                    total_lines: number(2)?,
+                    logical_lines: number(3)?,
+                    comment_lines: number(4)?,
+                    empty_lines: number(5)?,
+                    metrics: BTreeMap::new(),
+                    encoding: None,
+                    invalid_lines: 0,
+                });
+            }
+        }
+
+        // The legacy layout records neither its format nor the release that wrote it
+        let mut report = Self::new(files, unsupported_files);
+        report.report_format_version = LEGACY_CSV_FORMAT_VERSION.to_string();
+        report.generator_version = None;
+        Ok(report)
+    }
+}
+
+/// REQ-6.4: Language and global counts added up one file at a time, for counts that do not
+/// keep their files: memory grows with the number of languages, not of files. Totals of
+/// parts of a count are combined with `merge`.
+#[derive(Debug, Clone, Default)]
+pub struct ReportTotals {
+    languages: HashMap<String, LanguageStats>,
+    unsupported_files: usize,
+}
+
+impl ReportTotals {
+    pub fn add_file(&mut self, file: &FileStats) {
+        let entry = self
+            .languages
+            .entry(file.language.clone())
+            .or_insert(LanguageStats {
+                language: file.language.clone(),
+                file_count: 0,
+                total_lines: 0,
+                logical_lines: 0,
+                comment_lines: 0,
+                empty_lines: 0,
+            });
+
+        entry.file_count += 1;
+        entry.total_lines += file.total_lines;
+        entry.logical_lines += file.logical_lines;
+        entry.comment_lines += file.comment_lines;
+        entry.empty_lines += file.empty_lines;
+    }
+
+    pub fn add_unsupported(&mut self) {
+        self.unsupported_files += 1;
+    }
+
+    pub fn merge(mut self, other: ReportTotals) -> ReportTotals {
+        for (language, stats) in other.languages {
+            match self.languages.get_mut(&language) {
+                Some(entry) => {
+                    entry.file_count += stats.file_count;
+                    entry.total_lines += stats.total_lines;
+                    entry.logical_lines += stats.logical_lines;
+                    entry.comment_lines += stats.comment_lines;
+                    entry.empty_lines += stats.empty_lines;
+                }
+                None => {
+                    self.languages.insert(language, stats);
+                }
+            }
+        }
+        self.unsupported_files += other.unsupported_files;
+        self
+    }
+
+    /// A report with the language and global summaries of the totals and no file lists
+    pub fn into_report(self) -> Report {
+        let unsupported_files = self.unsupported_files;
+        let languages = self.into_languages();
+        let summary = GlobalSummary {
+            total_files: languages.iter().map(|l| l.file_count).sum(),
+            total_lines: languages.iter().map(|l| l.total_lines).sum(),
+            logical_lines: languages.iter().map(|l| l.logical_lines).sum(),
+            comment_lines: languages.iter().map(|l| l.comment_lines).sum(),
+            empty_lines: languages.iter().map(|l| l.empty_lines).sum(),
+            languages_count: languages.len(),
+            unsupported_files,
+            skipped_files: 0,
+            invalid_files: 0,
+        };
+
+        Report {
+            report_format_version: REPORT_FORMAT_VERSION.to_string(),
+            generated_at: Utc::now(),
+            files: Vec::new(),
+            languages,
+            directories: Vec::new(),
+            summary,
+            unsupported_files: Vec::new(),
+            skipped_files: Vec::new(),
+            invalid_files: Vec::new(),
+            diagnostics: Vec::new(),
+            generator_version: Some(GENERATOR_VERSION.to_string()),
+            checksum: None,
         }
+    }
 
-        Ok(Self::new(files, Vec::new()))
+    fn into_languages(self) -> Vec<LanguageStats> {
+        let mut languages: Vec<LanguageStats> = self.languages.into_values().collect();
+        // REQ-9.3: Deterministic output
+        languages.sort_by(|a, b| a.language.cmp(&b.language));
+        languages
+    }
+}
+
+/// `--paths`: rewrite a path in the requested form. Paths are resolved lexically
+/// against the current directory (symlinks are not followed); if that is not
+/// available the path is returned unchanged.
+#[cfg(feature = "cli")]
+pub fn style_path(path: &Path, style: PathStyle) -> PathBuf {
+    if style == PathStyle::Filename {
+        return path
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| path.to_path_buf());
+    }
+
+    let (Ok(absolute), Ok(cwd)) = (std::path::absolute(path), std::env::current_dir()) else {
+        return path.to_path_buf();
+    };
+    if style == PathStyle::Absolute {
+        return absolute;
+    }
+
+    // Relative: climb out of the current directory as far as needed
+    let target: Vec<_> = absolute.components().collect();
+    let base: Vec<_> = cwd.components().collect();
+    let shared = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in shared..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&target[shared..]);
+    relative
+}
+
+/// Directory of a file path without `.` components, so `./src/a.rs` and `src/b.rs`
+/// belong to the same directory
+pub(crate) fn parent_directory(path: &Path) -> PathBuf {
+    path.parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect()
+}
+
+/// Directory `path` is grouped under: its parent directory relative to `base`, cut
+/// after `depth` levels (`.` when `base` is empty and the file sits directly in it)
+pub(crate) fn directory_key(path: &Path, base: &Path, depth: usize) -> PathBuf {
+    let dir = parent_directory(path);
+    let relative = dir.strip_prefix(base).unwrap_or(&dir);
+    let prefix: PathBuf = relative.components().take(depth).collect();
+    match (base.as_os_str().is_empty(), prefix.as_os_str().is_empty()) {
+        (true, true) => PathBuf::from("."),
+        (false, true) => base.to_path_buf(),
+        _ => base.join(prefix),
+    }
+}
+
+/// Deepest directory containing every file (empty when they share no prefix)
+pub(crate) fn common_directory(files: &[FileStats]) -> PathBuf {
+    common_directory_of(files.iter().map(|f| f.path.as_path()))
+}
+
+/// Deepest directory containing every path (empty when they share no prefix)
+pub(crate) fn common_directory_of<'a>(paths: impl IntoIterator<Item = &'a Path>) -> PathBuf {
+    let mut base: Option<Vec<_>> = None;
+    for path in paths {
+        let dir = parent_directory(path);
+        match &mut base {
+            None => base = Some(dir.components().map(|c| c.as_os_str().to_owned()).collect()),
+            Some(base) => {
+                let shared = base
+                    .iter()
+                    .zip(dir.components())
+                    .take_while(|(a, b)| a.as_os_str() == b.as_os_str())
+                    .count();
+                base.truncate(shared);
+            }
+        }
+    }
+    base.unwrap_or_default().iter().collect()
+}
+
+/// REQ-6.3: Section a CSV record belongs to
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvSection {
+    #[default]
+    Meta,
+    Summary,
+    Language,
+    Directory,
+    File,
+    Unsupported,
+    Diagnostic,
+}
+
+/// REQ-6.3: One row of the CSV report. Every row has the same columns so standard
+/// CSV parsers can read the whole file; `section` tells what the row describes:
+/// - `meta`: `name` = key, `value` = value (version, timestamp, checksum, ...)
+/// - `summary`: global totals, `files` = total files
+/// - `language`: `name` = language, `files` = file count
+/// - `directory`: `name` = directory (`--by-dir`), `files` = file count
+/// - `file`: `name` = path, `language` = detected language, `value` = plugin metrics
+///   as `name=value` pairs separated by `;`
+/// - `unsupported`: `name` = path of a file excluded from statistics
+/// - `diagnostic`: `name` = path of a warning of the scan (empty without one), `value` =
+///   `code: message`
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvRecord {
+    pub section: CsvSection,
+    pub name: String,
+    pub language: Option<String>,
+    pub files: Option<usize>,
+    pub total_lines: Option<usize>,
+    pub logical_lines: Option<usize>,
+    pub comment_lines: Option<usize>,
+    pub empty_lines: Option<usize>,
+    pub value: Option<String>,
+}
+
+#[cfg(feature = "cli")]
+impl CsvRecord {
+    fn meta(name: &str, value: &str) -> Self {
+        Self {
+            section: CsvSection::Meta,
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Plugin metrics of a `file` row (`value` column)
+    fn metrics(&self, line: usize) -> Result<BTreeMap<String, f64>> {
+        let Some(value) = self.value.as_deref().filter(|v| !v.is_empty()) else {
+            return Ok(BTreeMap::new());
+        };
+        value
+            .split(';')
+            .map(|pair| {
+                pair.split_once('=')
+                    .and_then(|(name, number)| Some((name.to_string(), number.parse().ok()?)))
+                    .ok_or_else(|| {
+                        crate::error::SlocError::Deserialization(format!(
+                            "line {}: file row '{}' has an invalid metric '{}' (expected name=number)",
+                            line, self.name, pair
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Unwrap a column that this row's section requires
+    fn required(&self, value: Option<usize>, column: &str, line: usize) -> Result<usize> {
+        value.ok_or_else(|| {
+            crate::error::SlocError::Deserialization(format!(
+                "line {}: {} row '{}' is missing '{}'",
+                line,
+                format!("{:?}", self.section).to_lowercase(),
+                self.name,
+                column
+            ))
+        })
     }
 }
 
 /// Execute report generation command
+#[cfg(feature = "cli")]
 pub fn execute_report(args: ReportArgs) -> Result<()> {
     let start_time = Instant::now();
 
@@ -230,16 +869,28 @@ pub fn execute_report(args: ReportArgs) -> Result<()> {
 
     let metrics_logger = Arc::new(MetricsLogger::new(&app_config.performance));
 
-    // Determine output path (may be auto-generated later in count if None)
-    let output_display = args
-        .output
-        .as_ref()
-        .map(|p| p.display().to_string())
-        .unwrap_or_else(|| "<auto>".to_string());
+    // Determine output paths (auto-generated later in count if none given)
+    let output_display = if args.output.is_empty() {
+        "<auto>".to_string()
+    } else {
+        args.output
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    // The configured format (JSON by default) when neither a format, an output path nor a
+    // template is given
+    let format = if args.format.is_empty() && args.output.is_empty() && args.template.is_none() {
+        vec![app_config.defaults.format().into()]
+    } else {
+        args.format
+    };
     let args_summary = format!(
         "paths={}, format={:?}, output={}, recursive={}, checksum={}",
         args.paths.len(),
-        args.format,
+        format,
         output_display,
         args.recursive,
         args.checksum
@@ -252,16 +903,52 @@ pub fn execute_report(args: ReportArgs) -> Result<()> {
         details: args.details,
         paths: args.paths,
         recursive: args.recursive,
+        no_recursive: args.no_recursive,
+        no_default_excludes: args.no_default_excludes,
         stdin: false,
-        format: Some(args.format),
+        from_diff: None,
+        format,
         output: args.output.clone(),
+        template: args.template,
+        xml_style: args.xml_style,
         sort: None,
+        top: None,
+        by_dir: args.by_dir,
+        path_style: args.path_style,
+        tree: None,
+        lang: vec![],
+        columns: vec![],
+        show_files: false,
+        show_unsupported: false,
+        only_lang: args.only_lang,
+        exclude_lang: args.exclude_lang,
         language_override: vec![],
         config: args.config,
         no_progress: false,
+        progress: false,
         threads: args.threads,
+        read: crate::cli::ReadStrategy::Stream,
+        encoding: args.encoding,
+        on_invalid_utf8: args.on_invalid_utf8,
+        strict: args.strict,
+        io_concurrency: 0,
+        minified: crate::cli::MinifiedFiles::Separate,
+        max_memory: None,
+        progress_json: false,
+        checkpoint: None,
+        resume: false,
+        checkpoint_interval: 30,
         checksum: args.checksum,
+        baseline: None,
+        baseline_export: None,
         ignore_preprocessor: false,
+        metric_plugin: Vec::new(),
+        no_cache: false,
+        clear_cache: false,
+        cache_dir: None,
+        print: None,
+        quiet: args.quiet,
+        silent: args.silent,
         enable_metrics: args.enable_metrics,
         metrics_file: args.metrics_file,
         perf_summary_threshold: 5,
@@ -275,8 +962,17 @@ pub fn execute_report(args: ReportArgs) -> Result<()> {
     let total_time = start_time.elapsed();
     metrics_logger.log_metric("total_report_generation_time", total_time.as_secs_f64());
 
-    if let Some(ref p) = args.output {
-        println!("Report generated successfully: {}", p.display());
+    // REQ-6.8: Nothing else may be printed when the report itself goes to stdout
+    // (or when --quiet/--silent asked for less output)
+    if args.output.iter().any(|p| crate::compression::is_stdout(p))
+        || crate::cli::Verbosity::from_flags(args.quiet, args.silent)
+            != crate::cli::Verbosity::Normal
+    {
+        return Ok(());
+    }
+
+    if !args.output.is_empty() {
+        println!("Report generated successfully: {}", output_display);
     } else {
         println!("Report generated successfully (auto-generated file name used)");
     }
@@ -287,3 +983,163 @@ pub fn execute_report(args: ReportArgs) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use crate::cli::XmlStyle;
+
+    fn checksum(report: &Report) -> String {
+        let mut report = report.clone();
+        report.calculate_checksum();
+        report.checksum.unwrap()
+    }
+
+    #[test]
+    fn checksum_covers_every_counted_field() {
+        let report = full_report();
+        let base = checksum(&report);
+        assert!(base.starts_with(CHECKSUM_PREFIX));
+
+        // Counts that used to run together
+        let mut a = report.clone();
+        (a.files[0].total_lines, a.files[0].logical_lines) = (12, 3);
+        let mut b = report.clone();
+        (b.files[0].total_lines, b.files[0].logical_lines) = (1, 23);
+        assert_ne!(checksum(&a), checksum(&b));
+
+        let edits: [fn(&mut Report); 5] = [
+            |r| r.files[1].encoding = None,
+            |r| r.unsupported_files.clear(),
+            |r| r.skipped_files.clear(),
+            |r| r.invalid_files[0].invalid_lines += 1,
+            |r| r.diagnostics.truncate(1),
+        ];
+        for edit in edits {
+            let mut edited = report.clone();
+            edit(&mut edited);
+            assert_ne!(checksum(&edited), base);
+        }
+
+        // Neither when nor by which release the report was written
+        let mut rewritten = report.clone();
+        rewritten.generated_at = Utc::now();
+        rewritten.generator_version = None;
+        rewritten.files.reverse();
+        assert_eq!(checksum(&rewritten), base);
+    }
+
+    /// A report with every field set, optional ones included
+    fn full_report() -> Report {
+        let mut main = FileStats::new("src/main.rs", "Rust");
+        main.total_lines = 12;
+        main.logical_lines = 8;
+        main.comment_lines = 3;
+        main.empty_lines = 1;
+        main.metrics.insert("complexity".to_string(), 3.5);
+        main.metrics.insert("functions".to_string(), 2.0);
+        let mut legacy = FileStats::new("lib/legacy <old> & \"quoted\".c", "C");
+        legacy.total_lines = 5;
+        legacy.logical_lines = 4;
+        legacy.empty_lines = 1;
+        legacy.encoding = Some("windows-1252".to_string());
+        legacy.invalid_lines = 2;
+
+        let mut report = Report::new(vec![main, legacy], vec!["logo.png".into()]);
+        report.group_by_directory(1);
+        report.set_skipped(
+            vec![SkippedFile {
+                path: "tools/gen.py".into(),
+                language: "Python".to_string(),
+            }],
+            1,
+        );
+        report.set_invalid(vec![InvalidFile {
+            path: "lib/legacy <old> & \"quoted\".c".into(),
+            invalid_lines: 2,
+        }]);
+        report.diagnostics = vec![
+            Diagnostic {
+                code: DiagnosticCode::UnreadablePath,
+                path: Some("vendor/locked".into()),
+                message: "Permission denied (os error 13)".to_string(),
+            },
+            Diagnostic {
+                code: DiagnosticCode::TotalsOnly,
+                path: None,
+                message: "per-file results dropped by --max-memory".to_string(),
+            },
+        ];
+        report.calculate_checksum();
+        report
+    }
+
+    fn round_trip(report: &Report, style: XmlStyle) -> Report {
+        let xml = report.to_xml(style).unwrap();
+        crate::xml::read_report(&xml).unwrap()
+    }
+
+    #[test]
+    fn xml_elements_round_trip_every_field() {
+        let report = full_report();
+        assert_eq!(round_trip(&report, XmlStyle::Elements), report);
+    }
+
+    #[test]
+    fn xml_attributes_round_trip_every_field() {
+        let report = full_report();
+        assert_eq!(round_trip(&report, XmlStyle::Attributes), report);
+    }
+
+    #[test]
+    fn csv_round_trips_the_format_version() {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for record in full_report().to_csv_records() {
+            writer.serialize(record).unwrap();
+        }
+        let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let loaded = Report::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(loaded.report_format_version, REPORT_FORMAT_VERSION);
+
+        // A sectioned CSV without its version row is not taken for a current one
+        let without: String = csv
+            .lines()
+            .filter(|line| !line.starts_with("meta,report_format_version,"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let error = Report::from_csv(without.as_bytes()).unwrap_err();
+        assert!(
+            error.to_string().contains("report_format_version"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn legacy_csv_is_upgraded_from_the_release_02_layout() {
+        let csv = "\
+Path,Language,Total Lines,Logical Lines,Comment Lines,Empty Lines
+src/main.rs,Rust,10,6,2,2
+
+--- Unsupported Files (not counted) ---
+logo.png
+";
+        let mut report = Report::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(report.report_format_version, "0.2");
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.unsupported_files, vec![PathBuf::from("logo.png")]);
+
+        let note = crate::migrate::upgrade(&mut report).unwrap().unwrap();
+        assert!(note.contains("release 0.2.x layout"), "{}", note);
+        assert_eq!(report.report_format_version, REPORT_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn xml_round_trips_an_empty_report() {
+        let mut report = Report::new(Vec::new(), Vec::new());
+        report.generator_version = None;
+        for style in [XmlStyle::Elements, XmlStyle::Attributes] {
+            assert_eq!(round_trip(&report, style), report);
+        }
+    }
+}
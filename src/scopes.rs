@@ -0,0 +1,303 @@
+// scopes.rs - Nested configurations of a monorepo (`count`, `report`)
+// Implements: REQ-2.3 (directory traversal), REQ-3.3 (language configuration)
+//
+// A `.counterlines.toml` (or `counterlines.toml`) in a directory below the project root
+// applies to that directory's subtree only, as a nested .gitignore does: its
+// `defaults.exclude` patterns are matched relative to its directory while walking, and its
+// `[languages]` are added to the languages of the enclosing scope for the files below it,
+// and its `[[encodings]]` come before those of the enclosing scopes. The other settings of
+// a nested file are ignored, `languages.only` and `disabled` too. The project root is the
+// directory of the project configuration of the command, or the current directory without
+// one; nothing is looked for with `--no-config`. The `[[encodings]]` of the configuration
+// of the command are relative to the same directory.
+//
+// The nearest configuration of each directory is looked up once and kept, so a walk costs
+// one existence check per configuration name and directory.
+
+use crate::config::{AppConfig, EncodingConfig, PROJECT_CONFIG_FILES, discovery, read_config};
+use crate::encoding::EncodingRules;
+use crate::error::Result;
+use crate::language::LanguageDetector;
+use encoding_rs::Encoding;
+use glob::Pattern;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+use walkdir::DirEntry;
+
+/// A nested configuration and the scopes enclosing it
+struct Scope {
+    dir: PathBuf,
+    /// `None` when the file sets no `defaults.exclude`
+    exclude: Option<Vec<Pattern>>,
+    /// The languages of the enclosing scope, with those of the file
+    detector: Arc<LanguageDetector>,
+    /// Whether the file defines languages or encodings (and so changes the counts)
+    counted: Option<PathBuf>,
+    encodings: EncodingRules,
+    parent: Option<Arc<Scope>>,
+}
+
+/// The scopes of the directories met while collecting the files to count
+pub struct ConfigScopes {
+    /// Only the configurations strictly below this directory are nested ones
+    root: Option<PathBuf>,
+    base: Arc<LanguageDetector>,
+    /// The `[[encodings]]` of the configuration of the command, and their directory
+    encodings: EncodingRules,
+    encodings_dir: Option<PathBuf>,
+    /// Whether the `defaults.exclude` of nested files are applied
+    excludes: bool,
+    /// Nearest scope of each directory looked up, by `absolute` path
+    dirs: HashMap<PathBuf, Option<Arc<Scope>>>,
+    /// First nested file that could not be loaded
+    error: Option<crate::error::SlocError>,
+}
+
+impl ConfigScopes {
+    /// Scopes below the project root of `config` (the configuration of the command), over
+    /// its languages `base` and `encodings`
+    pub fn new(
+        config: Option<&Path>,
+        base: LanguageDetector,
+        encodings: &[EncodingConfig],
+        excludes: bool,
+    ) -> Self {
+        let config_dir = config_dir(config).as_deref().and_then(absolute);
+        ConfigScopes {
+            root: config_dir.clone().filter(|_| discovery()),
+            base: Arc::new(base),
+            encodings: EncodingRules::new(encodings),
+            encodings_dir: config_dir,
+            excludes,
+            dirs: HashMap::new(),
+            error: None,
+        }
+    }
+
+    /// Whether a walk keeps `entry`: it matches no exclude pattern of a nested
+    /// configuration enclosing it (the walked directory itself is always kept)
+    pub fn keep(&mut self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 || self.root.is_none() {
+            return true;
+        }
+        let Some(path) = absolute(entry.path()) else {
+            return true;
+        };
+        let Some(parent) = path.parent() else {
+            return true;
+        };
+        let mut scope = self.scope_of(parent);
+        while let Some(current) = scope {
+            if self.excludes
+                && let (Some(exclude), Ok(relative)) =
+                    (&current.exclude, path.strip_prefix(&current.dir))
+                && exclude.iter().any(|p| p.matches_path(relative))
+            {
+                return false;
+            }
+            scope = current.parent.clone();
+        }
+        true
+    }
+
+    /// Look up the scopes of the directories of `paths` (files found without a walk, or
+    /// in directories a walk did not look into); fails with the first nested
+    /// configuration that could not be loaded
+    pub fn resolve(&mut self, paths: &[PathBuf]) -> Result<()> {
+        if self.root.is_some() {
+            for path in paths {
+                if let Some(parent) = absolute(path).as_deref().and_then(Path::parent) {
+                    self.scope_of(parent);
+                }
+            }
+        }
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Languages of `path`, once its directory was looked up (see `resolve`)
+    pub fn detector_for(&self, path: &Path) -> &LanguageDetector {
+        if self.root.is_none() {
+            return &self.base;
+        }
+        absolute(path)
+            .as_deref()
+            .and_then(Path::parent)
+            .and_then(|parent| self.dirs.get(parent))
+            .and_then(|scope| scope.as_ref())
+            .map_or(&self.base, |scope| &scope.detector)
+    }
+
+    /// Encoding set for `path` by the nearest `[[encodings]]` matching it, once its
+    /// directory was looked up (see `resolve`)
+    pub fn encoding_for(&self, path: &Path) -> Option<&'static Encoding> {
+        if self.root.is_none() && self.encodings.is_empty() {
+            return None;
+        }
+        let path = absolute(path)?;
+        let mut scope = path
+            .parent()
+            .and_then(|parent| self.dirs.get(parent))
+            .and_then(|scope| scope.as_deref());
+        while let Some(current) = scope {
+            if let Ok(relative) = path.strip_prefix(&current.dir)
+                && let Some(encoding) = current.encodings.encoding_for(relative)
+            {
+                return Some(encoding);
+            }
+            scope = current.parent.as_deref();
+        }
+        let relative = self
+            .encodings_dir
+            .as_deref()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(&path);
+        self.encodings.encoding_for(relative)
+    }
+
+    /// Whether a language of the project or of a nested configuration is named `name`
+    pub fn has_language(&self, name: &str) -> bool {
+        self.base.has_language(name)
+            || self
+                .dirs
+                .values()
+                .flatten()
+                .any(|scope| scope.detector.has_language(name))
+    }
+
+    /// The nested files that define languages or encodings, sorted
+    pub fn counted_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .dirs
+            .values()
+            .flatten()
+            .filter_map(|scope| scope.counted.clone())
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Number of nested configurations found
+    pub fn nested_configs(&self) -> usize {
+        let mut dirs: Vec<&Path> = self
+            .dirs
+            .values()
+            .flatten()
+            .map(|scope| scope.dir.as_path())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs.len()
+    }
+
+    /// The nearest nested scope of the `absolute` directory `dir`, `None` in the project root
+    fn scope_of(&mut self, dir: &Path) -> Option<Arc<Scope>> {
+        if let Some(scope) = self.dirs.get(dir) {
+            return scope.clone();
+        }
+        let root = self.root.as_deref()?;
+        let scope = if dir == root || !dir.starts_with(root) {
+            None
+        } else {
+            let parent = dir.parent().and_then(|parent| self.scope_of(parent));
+            let file = PROJECT_CONFIG_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file());
+            match file {
+                Some(file) => match self.load(dir, &file, parent.clone()) {
+                    Ok(scope) => {
+                        debug!("nested configuration {}", file.display());
+                        Some(Arc::new(scope))
+                    }
+                    Err(e) => {
+                        self.error.get_or_insert(e);
+                        parent
+                    }
+                },
+                None => parent,
+            }
+        };
+        self.dirs.insert(dir.to_path_buf(), scope.clone());
+        scope
+    }
+
+    fn load(&self, dir: &Path, file: &Path, parent: Option<Arc<Scope>>) -> Result<Scope> {
+        let enclosing = parent
+            .as_ref()
+            .map_or(&self.base, |parent| &parent.detector);
+        // A plain language definition file has no table, and only languages
+        let table = read_config(file)?;
+        let defines_languages = table
+            .as_ref()
+            .is_none_or(|table| table.contains_key("languages"));
+        let detector = if defines_languages {
+            let mut detector = LanguageDetector::clone(enclosing);
+            detector.load_from_config(file)?;
+            Arc::new(detector)
+        } else {
+            Arc::clone(enclosing)
+        };
+        let sets_exclude = table.as_ref().is_some_and(|table| {
+            table
+                .get("defaults")
+                .and_then(|defaults| defaults.get("exclude"))
+                .is_some()
+        });
+        let (exclude, encodings) = match table {
+            Some(table) if sets_exclude || table.contains_key("encodings") => {
+                let config: AppConfig = table.try_into().map_err(|e: toml::de::Error| {
+                    crate::error::SlocError::InvalidConfig(format!("{}: {}", file.display(), e))
+                })?;
+                (
+                    sets_exclude.then(|| config.defaults.exclude_patterns()),
+                    EncodingRules::new(&config.encodings),
+                )
+            }
+            _ => (None, EncodingRules::default()),
+        };
+        Ok(Scope {
+            dir: dir.to_path_buf(),
+            exclude,
+            detector,
+            counted: (defines_languages || !encodings.is_empty()).then(|| file.to_path_buf()),
+            encodings,
+            parent,
+        })
+    }
+}
+
+/// `path` from the root, without `.` and `..` components: `../b` seen from `/a` is `/b`,
+/// which is not below `/a`
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let mut absolute = PathBuf::new();
+    for component in std::path::absolute(path).ok()?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    Some(absolute)
+}
+
+/// The directory of `config` when it is a project configuration, or else the current
+/// directory
+fn config_dir(config: Option<&Path>) -> Option<PathBuf> {
+    let project_config = config.filter(|config| {
+        config
+            .file_name()
+            .is_some_and(|name| PROJECT_CONFIG_FILES.iter().any(|file| name == *file))
+    });
+    match project_config.and_then(Path::parent) {
+        Some(dir) if !dir.as_os_str().is_empty() => Some(dir.to_path_buf()),
+        _ => std::env::current_dir().ok(),
+    }
+}
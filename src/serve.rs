@@ -0,0 +1,430 @@
+// serve.rs - HTTP dashboard and REST API (`serve`)
+// Implements: REQ-1.1 (line counting), REQ-6.1 (JSON reports), REQ-7.2 (comparison),
+//             REQ-9.4 (parallel processing)
+//
+// Every triggered count is stored as a JSON report (with checksum) in the store directory;
+// the store is read again on every request, so reports copied in by hand show up too.
+// Requests are handled one at a time: a count blocks the server until it is stored.
+//
+//   GET  /                       dashboard (latest report, stored reports, count and compare)
+//   POST /count                  count, then back to the dashboard
+//   GET  /compare?from=A&to=B    comparison page of two stored reports
+//   POST /api/count              count and store; returns the stored report's name and summary
+//   GET  /api/reports            stored reports, newest first
+//   GET  /api/reports/latest     newest stored report
+//   GET  /api/reports/NAME       stored report NAME
+//   GET  /api/compare?from=A&to=B comparison of two stored reports (default: the two newest)
+//   GET  /api/progress           server-sent events of the running counts (start, progress, done)
+//
+// Progress streams are served from their own threads, so they stay open while other
+// requests are answered.
+
+use crate::cli::{OutputFormat, ServeArgs};
+use crate::compare::compare_reports;
+use crate::counter::{collect_input_paths, count_paths};
+use crate::error::{Result, SlocError};
+use crate::html;
+use crate::language::LanguageDetector;
+use crate::progress::ProgressTracker;
+use crate::report::{GlobalSummary, Report};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{error, warn};
+
+/// A report of the store, as listed by `/api/reports`
+#[derive(Debug, Serialize)]
+pub struct StoredReport {
+    pub name: String,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub summary: GlobalSummary,
+}
+
+/// Response of a request: status, content type and body
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, value: &impl Serialize) -> Result<Self> {
+        let body = serde_json::to_string_pretty(value)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        Ok(Reply {
+            status,
+            content_type: "application/json",
+            body,
+        })
+    }
+
+    fn html(body: String) -> Self {
+        Reply {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body,
+        }
+    }
+
+    /// `{"error": message}`, or plain text outside the API
+    fn error(status: u16, message: &str, api: bool) -> Self {
+        if api {
+            Reply {
+                status,
+                content_type: "application/json",
+                body: serde_json::json!({ "error": message }).to_string(),
+            }
+        } else {
+            Reply {
+                status,
+                content_type: "text/plain; charset=utf-8",
+                body: message.to_string(),
+            }
+        }
+    }
+}
+
+/// A request that cannot be served: status and message
+struct Failure(u16, String);
+
+impl From<SlocError> for Failure {
+    fn from(e: SlocError) -> Self {
+        Failure(500, e.to_string())
+    }
+}
+
+/// Interval of the keep-alive comments of progress streams, which also detect closed clients
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// What requests are served from: the counted paths, the store and the detector
+struct Service {
+    args: ServeArgs,
+    detector: Arc<LanguageDetector>,
+    /// Open progress streams, each fed with ready-to-send events
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+pub fn execute_serve(args: ServeArgs) -> Result<()> {
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+    std::fs::create_dir_all(&args.store)?;
+
+    let server = Service {
+        detector: Arc::new(detector),
+        args,
+        subscribers: Arc::new(Mutex::new(Vec::new())),
+    };
+    if server.args.count_on_start {
+        let stored = server.count()?;
+        println!(
+            "Counted {} file(s) into {}",
+            stored.summary.total_files, stored.name
+        );
+    }
+
+    let http = Server::http(&server.args.bind)
+        .map_err(|e| SlocError::Parse(format!("cannot listen on {}: {}", server.args.bind, e)))?;
+    println!(
+        "Serving {} on http://{} (reports in {}), Ctrl-C to stop",
+        server.args.paths.join(" "),
+        server.args.bind,
+        server.args.store.display()
+    );
+
+    for request in http.incoming_requests() {
+        server.handle(request);
+    }
+    Ok(())
+}
+
+impl Service {
+    fn handle(&self, request: Request) {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        if path == "/api/progress" && *request.method() == Method::Get {
+            self.subscribe(request);
+            return;
+        }
+        let api = path.starts_with("/api/");
+        let reply = match self.route(request.method(), path, query) {
+            Ok(reply) => reply,
+            Err(Failure(status, message)) => Reply::error(status, &message, api),
+        };
+        if reply.status >= 500 {
+            error!("{} {}: {}", request.method(), url, reply.body);
+        }
+
+        let mut response = Response::from_string(reply.body).with_status_code(reply.status);
+        if let Ok(header) = Header::from_bytes("Content-Type", reply.content_type) {
+            response = response.with_header(header);
+        }
+        // POST /count: back to the dashboard (303 turns the reload into a GET)
+        if reply.status == 303
+            && let Ok(header) = Header::from_bytes("Location", "/")
+        {
+            response = response.with_header(header);
+        }
+        if let Err(e) = request.respond(response) {
+            warn!("cannot answer {}: {}", url, e);
+        }
+    }
+
+    fn route(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &str,
+    ) -> std::result::Result<Reply, Failure> {
+        match (method, path) {
+            (Method::Get, "/") => {
+                let stored = self.stored()?;
+                let latest = match stored.first() {
+                    Some(newest) => Some(self.load(&newest.name)?),
+                    None => None,
+                };
+                Ok(Reply::html(html::dashboard_page(
+                    &self.args.paths,
+                    &stored,
+                    latest.as_ref(),
+                )))
+            }
+            (Method::Post, "/count") => {
+                self.count()?;
+                Ok(Reply {
+                    status: 303,
+                    content_type: "text/plain; charset=utf-8",
+                    body: String::new(),
+                })
+            }
+            (Method::Get, "/compare") => {
+                let (from, to) = self.compared(query)?;
+                let comparison = compare_reports(&self.load(&from)?, &self.load(&to)?);
+                Ok(Reply::html(html::comparison_page(&comparison)))
+            }
+            (Method::Post, "/api/count") => Ok(Reply::json(201, &self.count()?)?),
+            (Method::Get, "/api/reports") => Ok(Reply::json(200, &self.stored()?)?),
+            (Method::Get, "/api/reports/latest") => {
+                let stored = self.stored()?;
+                let newest = stored
+                    .first()
+                    .ok_or_else(|| Failure(404, "no reports stored yet".to_string()))?;
+                Ok(Reply::json(200, &self.load(&newest.name)?)?)
+            }
+            (Method::Get, "/api/compare") => {
+                let (from, to) = self.compared(query)?;
+                let comparison = compare_reports(&self.load(&from)?, &self.load(&to)?);
+                Ok(Reply::json(200, &comparison)?)
+            }
+            (Method::Get, _) if path.starts_with("/api/reports/") => {
+                let name = decode(&path["/api/reports/".len()..]);
+                Ok(Reply::json(200, &self.load(&name)?)?)
+            }
+            (
+                _,
+                "/" | "/count" | "/compare" | "/api/count" | "/api/reports" | "/api/compare"
+                | "/api/progress",
+            ) => Err(Failure(
+                405,
+                format!("{} is not allowed on {}", method, path),
+            )),
+            _ if path.starts_with("/api/reports/") => Err(Failure(
+                405,
+                format!("{} is not allowed on {}", method, path),
+            )),
+            _ => Err(Failure(404, format!("no such page: {}", path))),
+        }
+    }
+
+    /// Count the configured paths and store the report as `sloc-<timestamp>.json`
+    fn count(&self) -> Result<StoredReport> {
+        let mut paths = collect_input_paths(&self.args.paths, self.args.recursive)?;
+        paths.sort();
+        paths.dedup();
+        let subscribers = Arc::clone(&self.subscribers);
+        let progress = ProgressTracker::start(paths.len(), move |event| {
+            let Ok(json) = serde_json::to_string(event) else {
+                return;
+            };
+            let message = format!("event: {}\ndata: {}\n\n", event.event.name(), json);
+            subscribers
+                .lock()
+                .unwrap()
+                .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        });
+        let mut report = count_paths(
+            paths,
+            &self.detector,
+            self.args.ignore_preprocessor,
+            None,
+            Some(&progress),
+        );
+        progress.finish();
+        report.calculate_checksum();
+
+        // Two counts within the same second get a numbered name
+        let stamp = report.generated_at.format("sloc-%Y%m%d-%H%M%S").to_string();
+        let mut name = format!("{}.json", stamp);
+        let mut suffix = 1;
+        while self.args.store.join(&name).exists() {
+            suffix += 1;
+            name = format!("{}-{}.json", stamp, suffix);
+        }
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        std::fs::write(self.args.store.join(&name), json)?;
+
+        Ok(StoredReport {
+            name,
+            generated_at: report.generated_at,
+            summary: report.summary,
+        })
+    }
+
+    /// `GET /api/progress`: stream the events of every following count until the client leaves
+    fn subscribe(&self, request: Request) {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        // The response never ends, so the headers are written by hand instead of by tiny_http
+        let writer = request.into_writer();
+        std::thread::spawn(move || stream_events(writer, receiver));
+    }
+
+    /// Reports of the store, newest first; unreadable files are skipped with a warning
+    fn stored(&self) -> Result<Vec<StoredReport>> {
+        let mut stored = Vec::new();
+        for entry in std::fs::read_dir(&self.args.store)? {
+            let path = entry?.path();
+            let Some(format) = report_format(&path) else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            match Report::from_file(&path, format) {
+                Ok(report) => stored.push(StoredReport {
+                    name: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    generated_at: report.generated_at,
+                    summary: report.summary,
+                }),
+                Err(e) => warn!("skipping {}: {}", path.display(), e),
+            }
+        }
+        stored.sort_by(|a, b| {
+            b.generated_at
+                .cmp(&a.generated_at)
+                .then_with(|| b.name.cmp(&a.name))
+        });
+        Ok(stored)
+    }
+
+    /// Stored report `name`; only plain file names of the store are accepted
+    fn load(&self, name: &str) -> std::result::Result<Report, Failure> {
+        let path = self.args.store.join(name);
+        let plain = !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']);
+        match report_format(&path) {
+            Some(format) if plain && path.is_file() => Ok(Report::from_file(&path, format)?),
+            _ => Err(Failure(404, format!("no such report: {}", name))),
+        }
+    }
+
+    /// `from` and `to` of a comparison query, defaulting to the two newest reports
+    fn compared(&self, query: &str) -> std::result::Result<(String, String), Failure> {
+        let param = |key: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| decode(v))
+                .filter(|v| !v.is_empty())
+        };
+        let (from, to) = (param("from"), param("to"));
+        if let (Some(from), Some(to)) = (&from, &to) {
+            return Ok((from.clone(), to.clone()));
+        }
+
+        let stored = self.stored()?;
+        let newest = |index: usize| {
+            stored.get(index).map(|s| s.name.clone()).ok_or_else(|| {
+                Failure(
+                    404,
+                    "not enough stored reports to compare (give from= and to=)".to_string(),
+                )
+            })
+        };
+        let to = match to {
+            Some(to) => to,
+            None => newest(0)?,
+        };
+        let from = match from {
+            Some(from) => from,
+            None => newest(if to == newest(0)? { 1 } else { 0 })?,
+        };
+        Ok((from, to))
+    }
+}
+
+/// Write server-sent events as they arrive; returns when the client has gone away
+fn stream_events(mut writer: Box<dyn Write + Send>, events: Receiver<String>) {
+    let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                   Cache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    let mut send = |text: &str| {
+        writer
+            .write_all(text.as_bytes())
+            .and_then(|_| writer.flush())
+    };
+    if send(headers).is_err() {
+        return;
+    }
+    loop {
+        let sent = match events.recv_timeout(KEEP_ALIVE) {
+            Ok(event) => send(&event),
+            Err(RecvTimeoutError::Timeout) => send(": keep-alive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+}
+
+/// Format of a report file of the store (JSON, XML or CSV, optionally compressed)
+fn report_format(path: &Path) -> Option<OutputFormat> {
+    OutputFormat::from_path(path).filter(|format| !matches!(format, OutputFormat::Html))
+}
+
+/// Percent-decoding of a URL path segment or query value (`+` is a space in forms)
+fn decode(input: &str) -> String {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' if tail.len() >= 2 => {
+                match u8::from_str_radix(&String::from_utf8_lossy(&tail[..2]), 16) {
+                    Ok(decoded) => {
+                        bytes.push(decoded);
+                        rest = &tail[2..];
+                        continue;
+                    }
+                    Err(_) => bytes.push(byte),
+                }
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,77 @@
+// settings.rs - Configuration inspection (`config check`, `config schema`)
+// Implements: REQ-3.3 (language configuration), REQ-9.7 (performance configuration)
+//
+// The configuration is resolved as every other command resolves it: the user
+// configuration, then the project (or `--config`) configuration, then the `COUNTERLINES_*`
+// variables. Each file is checked strictly, so a typo in a key or an unknown output format
+// fails here with its line and column instead of being ignored. The effective values are
+// printed as TOML, preceded by the sources as comments, so the output can be saved as a
+// configuration file of its own.
+//
+// `config schema` prints the JSON Schema of the configuration files, generated from the
+// same types (shipped as `schemas/config.schema.json` and `schemas/languages.schema.json`).
+
+use crate::cli::{ConfigArgs, ConfigCheckArgs, ConfigCommand};
+use crate::config::{AppConfig, user_config_path};
+use crate::error::{Result, SlocError};
+use crate::language::Language;
+use std::collections::HashMap;
+
+pub fn execute_config(args: ConfigArgs) -> Result<()> {
+    match args.command {
+        ConfigCommand::Check(args) => execute_check(args),
+        ConfigCommand::Schema(args) => {
+            println!("{}", config_json_schema(args.languages)?);
+            Ok(())
+        }
+    }
+}
+
+/// JSON Schema of the application configuration, or of a plain language definition file
+/// when `languages`
+pub fn config_json_schema(languages: bool) -> Result<String> {
+    // Describes what is read: keys with a default are optional
+    let generator = schemars::generate::SchemaSettings::draft2020_12()
+        .for_deserialize()
+        .into_generator();
+    let schema = if languages {
+        let mut schema = generator.into_root_schema_for::<HashMap<String, Language>>();
+        schema.insert(
+            "title".to_string(),
+            "Language definitions of rustedbytes-counterlines".into(),
+        );
+        schema
+    } else {
+        generator.into_root_schema_for::<AppConfig>()
+    };
+    serde_json::to_string_pretty(&schema).map_err(|e| SlocError::Serialization(e.to_string()))
+}
+
+fn execute_check(args: ConfigCheckArgs) -> Result<()> {
+    let (config, sources) = AppConfig::resolve(args.config.as_deref())?;
+    let table =
+        toml::Table::try_from(&config).map_err(|e| SlocError::Serialization(e.to_string()))?;
+
+    let user_config = user_config_path();
+    println!("# Sources, lowest precedence first:");
+    println!("#   built-in defaults");
+    for path in &sources.files {
+        let kind = if Some(path) == user_config.as_ref() {
+            "user configuration"
+        } else {
+            "project configuration"
+        };
+        let languages_only = if sources.language_files.contains(path) {
+            " (language definitions only, not shown)"
+        } else {
+            ""
+        };
+        println!("#   {}: {}{}", kind, path.display(), languages_only);
+    }
+    for variable in &sources.variables {
+        println!("#   environment: {}", variable);
+    }
+    println!();
+    print!("{}", table);
+    Ok(())
+}
@@ -0,0 +1,248 @@
+// threshold.rs - Threshold conditions on comparisons (`compare --fail-if`)
+// Implements: REQ-7.2 (comparison), REQ-8.4 (exit status for CI pipelines)
+//
+// A condition is `METRIC OP VALUE`, e.g. `total_lines_delta > 5000` or
+// `comment_density_delta < -2`. Any condition that holds is a breach: the comparison
+// is still displayed and exported, then the command exits with `EXIT_THRESHOLD`.
+
+use crate::report::Report;
+use std::fmt;
+
+/// Metrics a condition can test, computed from the two compared reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    FilesDelta,
+    TotalLinesDelta,
+    LogicalLinesDelta,
+    CommentLinesDelta,
+    EmptyLinesDelta,
+    LanguagesDelta,
+    /// Growth of the total lines relative to the first report, in percent
+    TotalLinesDeltaPercent,
+    /// Comment lines / total lines of the second report, in percent
+    CommentDensity,
+    /// Change of the comment density, in percentage points
+    CommentDensityDelta,
+    NewFiles,
+    RemovedFiles,
+    RenamedFiles,
+    ModifiedFiles,
+    UnchangedFiles,
+    /// Modified files in percent of the files present in both reports
+    ChurnRate,
+}
+
+const METRICS: [(&str, Metric); 15] = [
+    ("files_delta", Metric::FilesDelta),
+    ("total_lines_delta", Metric::TotalLinesDelta),
+    ("logical_lines_delta", Metric::LogicalLinesDelta),
+    ("comment_lines_delta", Metric::CommentLinesDelta),
+    ("empty_lines_delta", Metric::EmptyLinesDelta),
+    ("languages_delta", Metric::LanguagesDelta),
+    ("total_lines_delta_percent", Metric::TotalLinesDeltaPercent),
+    ("comment_density", Metric::CommentDensity),
+    ("comment_density_delta", Metric::CommentDensityDelta),
+    ("new_files", Metric::NewFiles),
+    ("removed_files", Metric::RemovedFiles),
+    ("renamed_files", Metric::RenamedFiles),
+    ("modified_files", Metric::ModifiedFiles),
+    ("unchanged_files", Metric::UnchangedFiles),
+    ("churn_rate", Metric::ChurnRate),
+];
+
+impl Metric {
+    /// Metrics counting files of the per-file comparison
+    pub fn is_per_file(&self) -> bool {
+        matches!(
+            self,
+            Metric::NewFiles
+                | Metric::RemovedFiles
+                | Metric::RenamedFiles
+                | Metric::ModifiedFiles
+                | Metric::UnchangedFiles
+                | Metric::ChurnRate
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        METRICS
+            .iter()
+            .find(|(_, metric)| metric == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+// Two-character operators first so `>=` is not read as `>`
+const OPERATORS: [(&str, Operator); 6] = [
+    (">=", Operator::GreaterOrEqual),
+    ("<=", Operator::LessOrEqual),
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    (">", Operator::Greater),
+    ("<", Operator::Less),
+];
+
+impl Operator {
+    fn symbol(&self) -> &'static str {
+        OPERATORS
+            .iter()
+            .find(|(_, op)| op == self)
+            .map(|(symbol, _)| *symbol)
+            .unwrap_or_default()
+    }
+
+    fn holds(&self, actual: f64, value: f64) -> bool {
+        match self {
+            Operator::Greater => actual > value,
+            Operator::GreaterOrEqual => actual >= value,
+            Operator::Less => actual < value,
+            Operator::LessOrEqual => actual <= value,
+            Operator::Equal => actual == value,
+            Operator::NotEqual => actual != value,
+        }
+    }
+}
+
+/// `METRIC OP VALUE`, breached when it holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub metric: Metric,
+    pub operator: Operator,
+    pub value: f64,
+}
+
+impl Condition {
+    /// clap value parser for `--fail-if`
+    pub fn parse(input: &str) -> std::result::Result<Self, String> {
+        let (position, symbol, operator) = OPERATORS
+            .iter()
+            .filter_map(|(symbol, op)| input.find(symbol).map(|pos| (pos, *symbol, *op)))
+            .min_by_key(|(pos, symbol, _)| (*pos, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| {
+                format!(
+                    "expected METRIC OP VALUE with OP one of {}",
+                    OPERATORS.map(|(symbol, _)| symbol).join(" ")
+                )
+            })?;
+
+        let name = input[..position].trim();
+        let metric = METRICS
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, metric)| *metric)
+            .ok_or_else(|| {
+                format!(
+                    "unknown metric \"{}\" (available: {})",
+                    name,
+                    METRICS.map(|(name, _)| name).join(", ")
+                )
+            })?;
+
+        let raw = input[position + symbol.len()..].trim();
+        let value = raw
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .map_err(|_| format!("\"{}\" is not a number", raw))?;
+
+        Ok(Condition {
+            metric,
+            operator,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.metric.name(),
+            self.operator.symbol(),
+            self.value
+        )
+    }
+}
+
+/// Result of one condition against a comparison
+pub struct Check {
+    pub condition: Condition,
+    pub actual: f64,
+    pub breached: bool,
+}
+
+/// Metric values of a comparison between `report1` (before) and `report2` (after)
+pub struct MetricValues<'a> {
+    pub report1: &'a Report,
+    pub report2: &'a Report,
+    pub new_files: usize,
+    pub removed_files: usize,
+    pub renamed_files: usize,
+    pub modified_files: usize,
+    pub unchanged_files: usize,
+    pub churn_rate: f64,
+}
+
+impl MetricValues<'_> {
+    fn get(&self, metric: Metric) -> f64 {
+        let (s1, s2) = (&self.report1.summary, &self.report2.summary);
+        let delta = |a: usize, b: usize| b as f64 - a as f64;
+        match metric {
+            Metric::FilesDelta => delta(s1.total_files, s2.total_files),
+            Metric::TotalLinesDelta => delta(s1.total_lines, s2.total_lines),
+            Metric::LogicalLinesDelta => delta(s1.logical_lines, s2.logical_lines),
+            Metric::CommentLinesDelta => delta(s1.comment_lines, s2.comment_lines),
+            Metric::EmptyLinesDelta => delta(s1.empty_lines, s2.empty_lines),
+            Metric::LanguagesDelta => delta(s1.languages_count, s2.languages_count),
+            Metric::TotalLinesDeltaPercent => percent(
+                s2.total_lines as f64 - s1.total_lines as f64,
+                s1.total_lines,
+            ),
+            Metric::CommentDensity => percent(s2.comment_lines as f64, s2.total_lines),
+            Metric::CommentDensityDelta => {
+                percent(s2.comment_lines as f64, s2.total_lines)
+                    - percent(s1.comment_lines as f64, s1.total_lines)
+            }
+            Metric::NewFiles => self.new_files as f64,
+            Metric::RemovedFiles => self.removed_files as f64,
+            Metric::RenamedFiles => self.renamed_files as f64,
+            Metric::ModifiedFiles => self.modified_files as f64,
+            Metric::UnchangedFiles => self.unchanged_files as f64,
+            Metric::ChurnRate => self.churn_rate,
+        }
+    }
+
+    /// Evaluate every condition, in the given order
+    pub fn check(&self, conditions: &[Condition]) -> Vec<Check> {
+        conditions
+            .iter()
+            .map(|condition| {
+                let actual = self.get(condition.metric);
+                Check {
+                    condition: condition.clone(),
+                    actual,
+                    breached: condition.operator.holds(actual, condition.value),
+                }
+            })
+            .collect()
+    }
+}
+
+fn percent(part: f64, whole: usize) -> f64 {
+    if whole > 0 {
+        part / whole as f64 * 100.0
+    } else {
+        0.0
+    }
+}
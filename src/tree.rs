@@ -0,0 +1,151 @@
+// tree.rs - Directory tree view of line counts (`--tree`)
+// Implements: REQ-5.1 (console summary), REQ-5.3 (number formatting)
+//
+// Files are placed below the deepest directory containing all of them. Nodes deeper
+// than the requested depth are folded into their ancestor, so every node's total
+// still covers its whole subtree.
+
+use crate::output::glyph;
+use crate::report::{FileStats, common_directory, parent_directory};
+use colored::Colorize;
+use num_format::{Locale, ToFormattedString};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct Node {
+    total_lines: usize,
+    file_count: usize,
+    is_dir: bool,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn add(&mut self, components: &[String], total_lines: usize, depth: usize) {
+        self.total_lines += total_lines;
+        self.file_count += 1;
+
+        if let Some((first, rest)) = components.split_first()
+            && depth > 0
+        {
+            let child = self.children.entry(first.clone()).or_default();
+            // The last component is the file itself
+            child.is_dir = !rest.is_empty();
+            child.add(rest, total_lines, depth - 1);
+        }
+    }
+}
+
+/// One rendered line: tree prefix + name, then the counts
+struct Line {
+    label: String,
+    is_dir: bool,
+    total_lines: usize,
+    file_count: usize,
+}
+
+/// Print the directory tree with per-node totals and their share of all lines.
+/// Children are listed largest first; `depth` counts levels below the root.
+pub fn display_tree(files: &[FileStats], depth: usize) {
+    let base = common_directory(files);
+    let mut root = Node {
+        is_dir: true,
+        ..Default::default()
+    };
+    for file in files {
+        let dir = parent_directory(&file.path);
+        let relative = dir.strip_prefix(&base).unwrap_or(&dir);
+        let mut components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        components.push(
+            file.path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        root.add(&components, file.total_lines, depth);
+    }
+
+    let root_name = if base.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        base.display().to_string()
+    };
+    let mut lines = vec![Line {
+        label: format!("{}/", root_name),
+        is_dir: true,
+        total_lines: root.total_lines,
+        file_count: root.file_count,
+    }];
+    collect_lines(&root, "", &mut lines);
+
+    let width = lines
+        .iter()
+        .map(|l| l.label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let grand_total = root.total_lines as f64;
+
+    for line in &lines {
+        let share = if grand_total > 0.0 {
+            (line.total_lines as f64 / grand_total) * 100.0
+        } else {
+            0.0
+        };
+        let padding = " ".repeat(width - line.label.chars().count());
+        let counts = format!(
+            "{:>12}  {:>6.2} %",
+            line.total_lines.to_formatted_string(&Locale::en),
+            share
+        );
+
+        if line.is_dir {
+            println!(
+                "{}{}  {}  {}",
+                line.label.blue().bold(),
+                padding,
+                counts,
+                format!(
+                    "({} files)",
+                    line.file_count.to_formatted_string(&Locale::en)
+                )
+                .dimmed()
+            );
+        } else {
+            println!("{}{}  {}", line.label, padding, counts);
+        }
+    }
+}
+
+fn collect_lines(node: &Node, prefix: &str, lines: &mut Vec<Line>) {
+    let mut children: Vec<_> = node.children.iter().collect();
+    children.sort_by(|a, b| b.1.total_lines.cmp(&a.1.total_lines).then(a.0.cmp(b.0)));
+
+    for (index, (name, child)) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        let branch = if last {
+            glyph("└── ", "`-- ")
+        } else {
+            glyph("├── ", "|-- ")
+        };
+        let suffix = if child.is_dir { "/" } else { "" };
+        lines.push(Line {
+            label: format!("{}{}{}{}", prefix, branch, name, suffix),
+            is_dir: child.is_dir,
+            total_lines: child.total_lines,
+            file_count: child.file_count,
+        });
+
+        let nested = format!(
+            "{}{}",
+            prefix,
+            if last {
+                "    "
+            } else {
+                glyph("│   ", "|   ")
+            }
+        );
+        collect_lines(child, &nested, lines);
+    }
+}
@@ -0,0 +1,304 @@
+// trend.rs - Time series over a series of reports (`trend`)
+// Implements: REQ-5.1 (console summary), REQ-5.3 (number formatting), REQ-7.1 (report processing)
+//
+// Every report (or run recorded with `record`, with `--db`) becomes one point of the series. The console shows each metric's first and
+// last value with a sparkline; exports carry every point, including per-language totals.
+
+use crate::cli::{OutputFormat, TrendArgs};
+use crate::compression::{create_writer, is_stdout};
+use crate::error::{Result, SlocError};
+use crate::output::{heavy_rule, print_result, print_table, rule, sparkline};
+use crate::record::HistoryDb;
+use crate::report::Report;
+use colored::Colorize;
+use num_format::{Locale, ToFormattedString};
+use prettytable::{Cell, Row, Table};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One report of the series (one revision for `history`)
+#[derive(Debug, Serialize)]
+pub struct TrendPoint {
+    pub report: String,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub logical_lines: usize,
+    pub comment_lines: usize,
+    pub empty_lines: usize,
+    pub languages_count: usize,
+    /// Comment lines / total lines, in percent
+    pub comment_density: f64,
+    /// Total lines per language
+    pub languages: BTreeMap<String, usize>,
+}
+
+/// Value of one metric at a point
+type MetricFn = fn(&TrendPoint) -> f64;
+
+#[derive(Debug, Serialize)]
+pub struct Trend {
+    pub points: Vec<TrendPoint>,
+}
+
+pub fn execute_trend(args: TrendArgs) -> Result<()> {
+    let export_to_stdout = args.export.as_deref().is_some_and(is_stdout);
+    if export_to_stdout && args.print.is_some() {
+        return Err(SlocError::Parse(
+            "--print cannot be combined with `--export -` (both write to stdout)".to_string(),
+        ));
+    }
+
+    let mut points = Vec::new();
+    if let Some(db) = &args.db {
+        let runs = HistoryDb::open_existing(db)?.runs(args.branch.as_deref())?;
+        let skip = args.last.map_or(0, |last| runs.len().saturating_sub(last));
+        for run in runs.iter().skip(skip) {
+            points.push(point(run.label(), &run.to_report()));
+        }
+        if points.is_empty() {
+            return Err(SlocError::Database(format!(
+                "{}: no recorded runs{}",
+                db.display(),
+                args.branch
+                    .as_ref()
+                    .map(|b| format!(" on branch {}", b))
+                    .unwrap_or_default()
+            )));
+        }
+    }
+    for path in expand(&args.reports)? {
+        let format = OutputFormat::from_path(&path).unwrap_or(OutputFormat::Json);
+        let report = Report::from_file(&path, format)?;
+        points.push(point(path.display().to_string(), &report));
+    }
+    if args.by_date {
+        points.sort_by_key(|p| p.generated_at);
+    }
+    let trend = Trend { points };
+
+    if !export_to_stdout && args.print.is_none() {
+        display_trend(&trend, "Trend");
+    }
+
+    if let Some(export_path) = &args.export {
+        let format = args
+            .format
+            .or_else(|| OutputFormat::from_path(export_path))
+            .unwrap_or(OutputFormat::Json);
+        export_trend(&trend, export_path, format)?;
+        if !export_to_stdout && args.print.is_none() {
+            println!("\nTrend exported to: {}", export_path.display());
+        }
+    }
+
+    // Machine-readable result on stdout
+    if let Some(print) = args.print {
+        print_result(&trend, print)?;
+    }
+
+    Ok(())
+}
+
+/// Report paths in order; glob patterns expand to their matches in name order
+fn expand(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let matches: Vec<PathBuf> = glob::glob(input)
+            .map_err(|e| SlocError::Parse(format!("{}: {}", input, e)))?
+            .filter_map(|entry| entry.ok())
+            .collect();
+        if matches.is_empty() {
+            return Err(SlocError::FileNotFound {
+                path: PathBuf::from(input),
+            });
+        }
+        paths.extend(matches);
+    }
+    Ok(paths)
+}
+
+pub(crate) fn point(name: String, report: &Report) -> TrendPoint {
+    let s = &report.summary;
+    TrendPoint {
+        report: name,
+        generated_at: report.generated_at,
+        total_files: s.total_files,
+        total_lines: s.total_lines,
+        logical_lines: s.logical_lines,
+        comment_lines: s.comment_lines,
+        empty_lines: s.empty_lines,
+        languages_count: s.languages_count,
+        comment_density: if s.total_lines > 0 {
+            s.comment_lines as f64 / s.total_lines as f64 * 100.0
+        } else {
+            0.0
+        },
+        languages: report
+            .languages
+            .iter()
+            .map(|l| (l.language.clone(), l.total_lines))
+            .collect(),
+    }
+}
+
+pub(crate) fn display_trend(trend: &Trend, title: &str) {
+    println!("\n{}", heavy_rule(80).blue());
+    println!("{}", title.bold().cyan());
+    println!("{}", heavy_rule(80).blue());
+
+    // One row per report
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        [
+            "#",
+            "Report",
+            "Generated",
+            "Files",
+            "Total",
+            "Logical",
+            "Comment",
+            "Languages",
+        ]
+        .iter()
+        .map(|h| Cell::new(h).style_spec("b"))
+        .collect(),
+    ));
+    for (index, p) in trend.points.iter().enumerate() {
+        table.add_row(Row::new(vec![
+            Cell::new(&(index + 1).to_string()),
+            Cell::new(&p.report),
+            Cell::new(&p.generated_at.format("%Y-%m-%d %H:%M").to_string()),
+            Cell::new(&p.total_files.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&p.total_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&p.logical_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&p.comment_lines.to_formatted_string(&Locale::en)).style_spec("r"),
+            Cell::new(&p.languages_count.to_string()).style_spec("r"),
+        ]));
+    }
+    print_table(&table);
+
+    // One row per metric, first to last
+    println!("\n{}", "Metrics".bold().green());
+    println!("{}", rule(40).green());
+    let metrics: [(&str, MetricFn); 7] = [
+        ("Files", |p| p.total_files as f64),
+        ("Total Lines", |p| p.total_lines as f64),
+        ("Logical Lines", |p| p.logical_lines as f64),
+        ("Comment Lines", |p| p.comment_lines as f64),
+        ("Empty Lines", |p| p.empty_lines as f64),
+        ("Comment %", |p| p.comment_density),
+        ("Languages", |p| p.languages_count as f64),
+    ];
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Metric", "First", "Last", "Change", "Trend"]
+            .iter()
+            .map(|h| Cell::new(h).style_spec("b"))
+            .collect(),
+    ));
+    for (label, value) in metrics {
+        let values: Vec<f64> = trend.points.iter().map(value).collect();
+        let (first, last) = (values[0], values[values.len() - 1]);
+        table.add_row(Row::new(vec![
+            Cell::new(label),
+            Cell::new(&format_value(first)).style_spec("r"),
+            Cell::new(&format_value(last)).style_spec("r"),
+            Cell::new(&format_change(last - first)).style_spec("r"),
+            Cell::new(&sparkline(&values)),
+        ]));
+    }
+    print_table(&table);
+}
+
+/// Counts with thousands separators, percentages with two decimals
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        (value as i64).to_formatted_string(&Locale::en)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn format_change(delta: f64) -> String {
+    let text = format_value(delta.abs());
+    if delta > 0.0 {
+        format!("+{}", text).green().to_string()
+    } else if delta < 0.0 {
+        format!("-{}", text).red().to_string()
+    } else {
+        "0".to_string()
+    }
+}
+
+pub(crate) fn export_trend(
+    trend: &Trend,
+    path: &std::path::Path,
+    format: OutputFormat,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(trend)
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            create_writer(path)?.write_all(json.as_bytes())?;
+        }
+        OutputFormat::Csv => {
+            // One row per report; per-language totals as `lines_<language>` columns
+            let languages: BTreeSet<&String> = trend
+                .points
+                .iter()
+                .flat_map(|p| p.languages.keys())
+                .collect();
+            let mut wtr = csv::Writer::from_writer(create_writer(path)?);
+            let mut header: Vec<String> = [
+                "report",
+                "generated_at",
+                "total_files",
+                "total_lines",
+                "logical_lines",
+                "comment_lines",
+                "empty_lines",
+                "languages_count",
+                "comment_density",
+            ]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+            header.extend(languages.iter().map(|l| format!("lines_{}", l)));
+            wtr.write_record(&header)
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+
+            for p in &trend.points {
+                let mut record = vec![
+                    p.report.clone(),
+                    p.generated_at.to_rfc3339(),
+                    p.total_files.to_string(),
+                    p.total_lines.to_string(),
+                    p.logical_lines.to_string(),
+                    p.comment_lines.to_string(),
+                    p.empty_lines.to_string(),
+                    p.languages_count.to_string(),
+                    format!("{:.2}", p.comment_density),
+                ];
+                record.extend(
+                    languages
+                        .iter()
+                        .map(|l| p.languages.get(*l).copied().unwrap_or(0).to_string()),
+                );
+                wtr.write_record(&record)
+                    .map_err(|e| SlocError::Serialization(e.to_string()))?;
+            }
+            wtr.flush()
+                .map_err(|e| SlocError::Serialization(e.to_string()))?;
+        }
+        _ => {
+            return Err(SlocError::InvalidReportFormat(format!(
+                "{:?} format is not available for trends (use json or csv)",
+                format
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,452 @@
+// tui.rs - Interactive report browser (`tui`)
+// Implements: REQ-5.1 (console summary), REQ-5.2 (sorting), REQ-5.3 (number formatting),
+//             REQ-7.1 (report processing)
+//
+// Three views of the same report: languages, directories (the files' parent directories)
+// and files. Every view sorts by any count column and filters by name as you type; Enter
+// on a language or directory drills down to its files.
+//
+// Keys: Tab/1-3 view, arrows/PgUp/PgDn/Home/End move, s sort column, r reverse,
+//       / filter (Enter keeps it, Esc clears it), Enter drill down, Backspace back, q quit
+
+use crate::cli::{OutputFormat, TuiArgs};
+use crate::counter::{collect_input_paths, count_paths};
+use crate::error::{Result, SlocError};
+use crate::language::LanguageDetector;
+use crate::report::{Report, parent_directory};
+use num_format::{Locale, ToFormattedString};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum View {
+    Languages,
+    Directories,
+    Files,
+}
+
+const VIEWS: [(View, &str); 3] = [
+    (View::Languages, "Languages"),
+    (View::Directories, "Directories"),
+    (View::Files, "Files"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Name,
+    Files,
+    Total,
+    Logical,
+    Comment,
+    Empty,
+}
+
+const SORT_KEYS: [SortKey; 6] = [
+    SortKey::Name,
+    SortKey::Files,
+    SortKey::Total,
+    SortKey::Logical,
+    SortKey::Comment,
+    SortKey::Empty,
+];
+
+/// One row of a view; `files` is 1 for a file
+struct Entry {
+    name: String,
+    language: String,
+    directory: String,
+    files: usize,
+    total: usize,
+    logical: usize,
+    comment: usize,
+    empty: usize,
+}
+
+impl Entry {
+    fn key(&self, sort: SortKey) -> usize {
+        match sort {
+            SortKey::Name => 0,
+            SortKey::Files => self.files,
+            SortKey::Total => self.total,
+            SortKey::Logical => self.logical,
+            SortKey::Comment => self.comment,
+            SortKey::Empty => self.empty,
+        }
+    }
+}
+
+/// Drill-down: the files of a language or of a directory
+enum Scope {
+    Language(String),
+    Directory(String),
+}
+
+struct App {
+    source: String,
+    languages: Vec<Entry>,
+    directories: Vec<Entry>,
+    files: Vec<Entry>,
+    view: View,
+    sort: SortKey,
+    descending: bool,
+    filter: String,
+    editing: bool,
+    scope: Option<Scope>,
+    table: TableState,
+}
+
+pub fn execute_tui(args: TuiArgs) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(SlocError::Parse(
+            "tui needs an interactive terminal (use count or process to print tables)".to_string(),
+        ));
+    }
+
+    // A single report file is browsed as is; anything else is counted first
+    let report = match args.inputs.as_slice() {
+        [input]
+            if Path::new(input).is_file()
+                && OutputFormat::from_path(Path::new(input)).is_some() =>
+        {
+            let path = Path::new(input);
+            Report::from_file(
+                path,
+                OutputFormat::from_path(path).unwrap_or(OutputFormat::Json),
+            )?
+        }
+        _ => {
+            let mut detector = LanguageDetector::new();
+            if let Some(config_path) = &args.config {
+                detector.load_from_config(config_path)?;
+            }
+            for (ext, lang) in &args.language_override {
+                detector.add_override(ext.clone(), lang.clone());
+            }
+            let mut paths = collect_input_paths(&args.inputs, args.recursive)?;
+            paths.sort();
+            paths.dedup();
+            count_paths(
+                paths,
+                &Arc::new(detector),
+                args.ignore_preprocessor,
+                None,
+                None,
+            )
+        }
+    };
+
+    let mut app = App::new(args.inputs.join(" "), &report);
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn new(source: String, report: &Report) -> Self {
+        let languages = report
+            .languages
+            .iter()
+            .map(|lang| Entry {
+                name: lang.language.clone(),
+                language: lang.language.clone(),
+                directory: String::new(),
+                files: lang.file_count,
+                total: lang.total_lines,
+                logical: lang.logical_lines,
+                comment: lang.comment_lines,
+                empty: lang.empty_lines,
+            })
+            .collect();
+
+        let files: Vec<Entry> = report
+            .files
+            .iter()
+            .map(|file| Entry {
+                name: file.path.display().to_string(),
+                language: file.language.clone(),
+                directory: parent_directory(&file.path).display().to_string(),
+                files: 1,
+                total: file.total_lines,
+                logical: file.logical_lines,
+                comment: file.comment_lines,
+                empty: file.empty_lines,
+            })
+            .collect();
+
+        let mut directories: BTreeMap<&str, Entry> = BTreeMap::new();
+        for file in &files {
+            let dir = directories.entry(&file.directory).or_insert_with(|| Entry {
+                name: file.directory.clone(),
+                language: String::new(),
+                directory: file.directory.clone(),
+                files: 0,
+                total: 0,
+                logical: 0,
+                comment: 0,
+                empty: 0,
+            });
+            dir.files += 1;
+            dir.total += file.total;
+            dir.logical += file.logical;
+            dir.comment += file.comment;
+            dir.empty += file.empty;
+        }
+        let directories = directories.into_values().collect();
+
+        let mut table = TableState::default();
+        table.select(Some(0));
+        App {
+            source,
+            languages,
+            directories,
+            files,
+            view: View::Languages,
+            sort: SortKey::Total,
+            descending: true,
+            filter: String::new(),
+            editing: false,
+            scope: None,
+            table,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.editing {
+                match key.code {
+                    KeyCode::Enter => self.editing = false,
+                    KeyCode::Esc => {
+                        self.editing = false;
+                        self.filter.clear();
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => {}
+                }
+                self.table.select(Some(0));
+                continue;
+            }
+
+            let rows = self.visible().len();
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Tab => {
+                    let index = VIEWS.iter().position(|(v, _)| *v == self.view).unwrap_or(0);
+                    self.switch(VIEWS[(index + 1) % VIEWS.len()].0);
+                }
+                KeyCode::BackTab => {
+                    let index = VIEWS.iter().position(|(v, _)| *v == self.view).unwrap_or(0);
+                    self.switch(VIEWS[(index + VIEWS.len() - 1) % VIEWS.len()].0);
+                }
+                KeyCode::Char(c @ '1'..='3') => self.switch(VIEWS[c as usize - '1' as usize].0),
+                KeyCode::Char('s') => {
+                    let index = SORT_KEYS.iter().position(|k| *k == self.sort).unwrap_or(0);
+                    self.sort = SORT_KEYS[(index + 1) % SORT_KEYS.len()];
+                    self.descending = self.sort != SortKey::Name;
+                }
+                KeyCode::Char('r') => self.descending = !self.descending,
+                KeyCode::Char('/') => {
+                    self.editing = true;
+                }
+                KeyCode::Esc => self.filter.clear(),
+                KeyCode::Up | KeyCode::Char('k') => self.step(-1, rows),
+                KeyCode::Down | KeyCode::Char('j') => self.step(1, rows),
+                KeyCode::PageUp => self.step(-20, rows),
+                KeyCode::PageDown => self.step(20, rows),
+                KeyCode::Home => self.table.select(Some(0)),
+                KeyCode::End => self.table.select(Some(rows.saturating_sub(1))),
+                KeyCode::Enter => self.drill_down(),
+                KeyCode::Backspace if self.scope.take().is_some() => self.table.select(Some(0)),
+                _ => {}
+            }
+        }
+    }
+
+    fn switch(&mut self, view: View) {
+        self.view = view;
+        self.filter.clear();
+        self.table.select(Some(0));
+    }
+
+    fn step(&mut self, delta: isize, rows: usize) {
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let last = rows.saturating_sub(1) as isize;
+        self.table
+            .select(Some((current + delta).clamp(0, last) as usize));
+    }
+
+    /// Enter on a language or directory: its files
+    fn drill_down(&mut self) {
+        let selected = self.table.selected().unwrap_or(0);
+        let scope = match self.visible().get(selected) {
+            Some(entry) if self.view == View::Languages => Scope::Language(entry.language.clone()),
+            Some(entry) if self.view == View::Directories => {
+                Scope::Directory(entry.directory.clone())
+            }
+            _ => return,
+        };
+        self.scope = Some(scope);
+        self.switch(View::Files);
+    }
+
+    /// Rows of the current view: scoped, filtered and sorted
+    fn visible(&self) -> Vec<&Entry> {
+        let entries = match self.view {
+            View::Languages => &self.languages,
+            View::Directories => &self.directories,
+            View::Files => &self.files,
+        };
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<&Entry> = entries
+            .iter()
+            .filter(|entry| match (&self.scope, self.view) {
+                (Some(Scope::Language(language)), View::Files) => entry.language == *language,
+                (Some(Scope::Directory(directory)), View::Files) => entry.directory == *directory,
+                _ => true,
+            })
+            .filter(|entry| filter.is_empty() || entry.name.to_lowercase().contains(&filter))
+            .collect();
+        rows.sort_by(|a, b| {
+            let order = a
+                .key(self.sort)
+                .cmp(&b.key(self.sort))
+                .then_with(|| a.name.cmp(&b.name));
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        rows
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, table_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let selected = VIEWS.iter().position(|(v, _)| *v == self.view).unwrap_or(0);
+        let tabs = Tabs::new(
+            VIEWS
+                .iter()
+                .enumerate()
+                .map(|(index, (_, title))| format!("{} {}", index + 1, title)),
+        )
+        .select(selected)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" SLOC: {} ", self.source)),
+        );
+        frame.render_widget(tabs, tabs_area);
+
+        let rows = self.visible();
+        let count = rows.len();
+        let name = match self.view {
+            View::Languages => "Language",
+            View::Directories => "Directory",
+            View::Files => "File",
+        };
+        let header = Row::new(SORT_KEYS.iter().map(|key| {
+            let label = match key {
+                SortKey::Name => name,
+                SortKey::Files if self.view == View::Files => "Language",
+                SortKey::Files => "Files",
+                SortKey::Total => "Total",
+                SortKey::Logical => "Logical",
+                SortKey::Comment => "Comment",
+                SortKey::Empty => "Empty",
+            };
+            if *key == self.sort {
+                let arrow = if self.descending { "▼" } else { "▲" };
+                Cell::from(format!("{} {}", label, arrow)).style(Style::default().fg(Color::Yellow))
+            } else {
+                Cell::from(label)
+            }
+        }))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let number = |n: usize| Line::from(n.to_formatted_string(&Locale::en)).right_aligned();
+        let table_rows: Vec<Row> = rows
+            .iter()
+            .map(|entry| {
+                let second = if self.view == View::Files {
+                    Line::from(entry.language.clone())
+                } else {
+                    number(entry.files)
+                };
+                Row::new(vec![
+                    Cell::from(entry.name.clone()),
+                    Cell::from(second),
+                    Cell::from(number(entry.total)),
+                    Cell::from(number(entry.logical)),
+                    Cell::from(number(entry.comment)),
+                    Cell::from(number(entry.empty)),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Min(20),
+                Constraint::Length(12),
+                Constraint::Length(11),
+                Constraint::Length(11),
+                Constraint::Length(11),
+                Constraint::Length(11),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().bg(Color::DarkGray))
+        .block(Block::default().borders(Borders::LEFT | Borders::RIGHT));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let scope = match &self.scope {
+            Some(Scope::Language(language)) => format!("  language: {}", language),
+            Some(Scope::Directory(directory)) => format!("  directory: {}", directory),
+            None => String::new(),
+        };
+        let filter = if self.editing {
+            format!("  filter: {}_", self.filter)
+        } else if !self.filter.is_empty() {
+            format!("  filter: {}", self.filter)
+        } else {
+            String::new()
+        };
+        let status = Paragraph::new(vec![
+            Line::from(format!("{} row(s){}{}", count, scope, filter)),
+            Line::from(
+                "Tab/1-3 view  s sort  r reverse  / filter  Enter drill down  Backspace back  q quit",
+            )
+            .style(Style::default().fg(Color::DarkGray)),
+        ]);
+        frame.render_widget(status, status_area);
+    }
+}
@@ -0,0 +1,154 @@
+// tuning.rs - Thread count auto-tuning (`count -j 0`)
+// Implements: REQ-9.4 (parallel processing), REQ-9.7 (metrics logging)
+//
+// A thread pool cannot shrink once built, so the pool keeps one thread per CPU and the
+// tuner bounds how many of them count a file at the same time. During the first seconds
+// the bound is halved for as long as the throughput holds: on storage that cannot feed
+// every core (network filesystems, spinning disks) the extra threads only add seeks. When
+// halving costs more than `TOLERANCE`, the previous bound is restored and kept.
+
+use std::cell::Cell;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Time each bound is measured for
+const WINDOW: Duration = Duration::from_millis(500);
+
+/// Time after which the bound is no longer changed
+const PROBE_TIME: Duration = Duration::from_secs(3);
+
+/// Fraction of the throughput that a halved bound may lose and still be kept
+const TOLERANCE: f64 = 0.1;
+
+thread_local! {
+    /// Whether the thread counts a file under a permit: while a large file is counted in
+    /// parallel parts, its thread may take another file from the pool, which must not wait
+    /// for the permit the thread already holds
+    static HOLDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Bounds the files counted at once, adjusting the bound to the measured throughput
+#[derive(Debug)]
+pub struct ThreadTuner {
+    state: Mutex<TunerState>,
+    released: Condvar,
+}
+
+#[derive(Debug)]
+struct TunerState {
+    active: usize,
+    limit: usize,
+    started: Instant,
+    /// Start of the measure of the current bound and lines counted since
+    window_start: Instant,
+    window_lines: usize,
+    /// The previous bound and its throughput in lines per second
+    previous: Option<(usize, f64)>,
+    settled: bool,
+}
+
+impl ThreadTuner {
+    /// A tuner starting with `threads` files at once
+    pub fn new(threads: usize) -> Self {
+        let now = Instant::now();
+        ThreadTuner {
+            state: Mutex::new(TunerState {
+                active: 0,
+                limit: threads.max(1),
+                started: now,
+                window_start: now,
+                window_lines: 0,
+                previous: None,
+                settled: threads <= 1,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait until one more file may be counted
+    pub fn acquire(&self) -> TunerPermit<'_> {
+        if HOLDING.get() {
+            return TunerPermit {
+                tuner: None,
+                lines: 0,
+            };
+        }
+        let mut state = self.state.lock().unwrap();
+        while state.active >= state.limit {
+            state = self.released.wait(state).unwrap();
+        }
+        state.active += 1;
+        HOLDING.set(true);
+        TunerPermit {
+            tuner: Some(self),
+            lines: 0,
+        }
+    }
+
+    /// The bound chosen so far
+    pub fn threads(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    fn release(&self, lines: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.active -= 1;
+        if !state.settled {
+            state.window_lines += lines;
+            state.adjust();
+        }
+        // A restored bound lets several threads go at once
+        self.released.notify_all();
+    }
+}
+
+impl TunerState {
+    /// At the end of a window, halve the bound or settle
+    fn adjust(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < WINDOW {
+            return;
+        }
+        let rate = self.window_lines as f64 / elapsed.as_secs_f64();
+        match self.previous {
+            // Halving cost too much: back to the previous bound
+            Some((limit, previous_rate)) if rate < previous_rate * (1.0 - TOLERANCE) => {
+                self.limit = limit;
+                self.settled = true;
+            }
+            _ => {
+                self.previous = Some((self.limit, rate));
+                if self.limit == 1 || self.started.elapsed() >= PROBE_TIME {
+                    self.settled = true;
+                } else {
+                    self.limit /= 2;
+                }
+            }
+        }
+        self.window_start = Instant::now();
+        self.window_lines = 0;
+    }
+}
+
+/// A file being counted under a `ThreadTuner`, until dropped
+pub struct TunerPermit<'a> {
+    /// `None` for a file taken by a thread already holding a permit
+    tuner: Option<&'a ThreadTuner>,
+    lines: usize,
+}
+
+impl TunerPermit<'_> {
+    /// The file was counted, with `lines` lines
+    pub fn counted(mut self, lines: usize) {
+        self.lines = lines;
+    }
+}
+
+impl Drop for TunerPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(tuner) = self.tuner {
+            HOLDING.set(false);
+            tuner.release(self.lines);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// validate.rs - Report validation, integrity verification and JSON Schema
+// Implements:
+//   REQ-6.6: Format version compatibility (see migrate.rs)
+//   REQ-6.9: Checksum verification (`verify`, `--verify-checksum`)
+//   REQ-7.1: Structural checks before reports enter downstream pipelines
+
+use crate::cli::{OutputFormat, ValidateArgs, VerifyArgs};
+use crate::error::{Result, SlocError};
+use crate::migrate;
+use crate::output::glyph;
+use crate::report::{FileStats, LanguageStats, Report};
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// JSON Schema of the report format, generated from the `Report` types
+/// (shipped as `schemas/report.schema.json`)
+pub fn report_json_schema() -> Result<String> {
+    // Serialization contract: describes what is written (fields that older
+    // reports may omit on load are still required here)
+    let schema = schemars::generate::SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<Report>();
+    serde_json::to_string_pretty(&schema).map_err(|e| SlocError::Serialization(e.to_string()))
+}
+
+/// Validate a report: structure (it loads into the report types, which is what the
+/// JSON Schema describes), format version and internal consistency
+pub fn execute_validate(args: ValidateArgs) -> Result<()> {
+    if args.print_schema {
+        println!("{}", report_json_schema()?);
+        return Ok(());
+    }
+
+    // clap guarantees a report path unless --print-schema is given
+    let Some(path) = args.report else {
+        return Ok(());
+    };
+    let format = OutputFormat::from_path(&path)
+        .or(args.format)
+        .unwrap_or(OutputFormat::Json);
+
+    println!("{} {}", "Validating".bold().cyan(), path.display());
+
+    // 1. Structure: required fields, types, non-negative counts
+    let mut report = Report::read_file(&path, format)?;
+    println!(
+        "  {} structure ({:?}, {} files, {} languages)",
+        glyph("✓", "ok").green(),
+        format,
+        report.files.len(),
+        report.languages.len()
+    );
+
+    // 2. Format version: readable by this release, upgraded when older
+    let version = report.report_format_version.clone();
+    match migrate::upgrade(&mut report)? {
+        None if version == report.report_format_version => {
+            println!(
+                "  {} format version {} (current)",
+                glyph("✓", "ok").green(),
+                version
+            )
+        }
+        None => println!(
+            "  {} format version {} (compatible with {})",
+            glyph("✓", "ok").green(),
+            version,
+            report.report_format_version
+        ),
+        Some(note) => println!(
+            "  {} format version {}: {}",
+            glyph("✓", "ok").green(),
+            version,
+            note
+        ),
+    }
+
+    // 3. Internal consistency
+    let problems = consistency_problems(&report);
+    if problems.is_empty() {
+        println!(
+            "  {} consistency (summary, languages, checksum)",
+            glyph("✓", "ok").green()
+        );
+        println!("\n{}", "Report is valid".bold().green());
+        return Ok(());
+    }
+
+    println!(
+        "  {} consistency: {} problem(s)",
+        glyph("✗", "FAIL").red(),
+        problems.len()
+    );
+    for problem in &problems {
+        println!("      - {}", problem);
+    }
+    Err(SlocError::InvalidReportFormat(format!(
+        "{} failed validation ({} problem(s))",
+        path.display(),
+        problems.len()
+    )))
+}
+
+/// REQ-6.9: Check that a report has not been edited since it was written: the checksum
+/// must be present and match the file statistics, and every summary must match the files
+pub fn execute_verify(args: VerifyArgs) -> Result<()> {
+    let format = OutputFormat::from_path(&args.report)
+        .or(args.format)
+        .unwrap_or(OutputFormat::Json);
+    let report = Report::from_file(&args.report, format)?;
+
+    println!("{} {}", "Verifying".bold().cyan(), args.report.display());
+    let problems = integrity_problems(&report);
+    if problems.is_empty() {
+        println!(
+            "  {} checksum {}",
+            glyph("✓", "ok").green(),
+            report.checksum.as_deref().unwrap_or_default()
+        );
+        println!(
+            "  {} summaries match the {} files",
+            glyph("✓", "ok").green(),
+            report.files.len()
+        );
+        println!("\n{}", "Report is intact".bold().green());
+        return Ok(());
+    }
+
+    println!(
+        "  {} {} mismatch(es)",
+        glyph("✗", "FAIL").red(),
+        problems.len()
+    );
+    for problem in &problems {
+        println!("      - {}", problem);
+    }
+    Err(SlocError::InvalidReportFormat(format!(
+        "{} failed verification ({} mismatch(es))",
+        args.report.display(),
+        problems.len()
+    )))
+}
+
+/// `process/compare --verify-checksum`: reject a loaded report that `verify` would reject
+pub fn verify_loaded(report: &Report, path: &Path) -> Result<()> {
+    let problems = integrity_problems(report);
+    match problems.first() {
+        None => Ok(()),
+        Some(first) => Err(SlocError::InvalidReportFormat(format!(
+            "{} failed verification: {}{}",
+            path.display(),
+            first,
+            if problems.len() > 1 {
+                format!(" (and {} more, see `verify`)", problems.len() - 1)
+            } else {
+                String::new()
+            }
+        ))),
+    }
+}
+
+/// Consistency problems, plus a missing checksum: without one, edits to the file
+/// statistics cannot be detected
+fn integrity_problems(report: &Report) -> Vec<String> {
+    let mut problems = Vec::new();
+    if report.checksum.is_none() {
+        problems.push("no checksum recorded (create the report with --checksum)".to_string());
+    }
+    problems.extend(consistency_problems(report));
+    problems
+}
+
+/// Cross-check per-file lines, language totals, the global summary and the checksum
+fn consistency_problems(report: &Report) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // Every line is exactly one of logical / comment / empty
+    let mut seen = HashSet::new();
+    for file in &report.files {
+        let parts = file.logical_lines + file.comment_lines + file.empty_lines;
+        if parts != file.total_lines {
+            problems.push(format!(
+                "{}: total_lines is {} but logical + comment + empty is {}",
+                file.path.display(),
+                file.total_lines,
+                parts
+            ));
+        }
+        if !seen.insert(&file.path) {
+            problems.push(format!("{}: listed more than once", file.path.display()));
+        }
+    }
+
+    // Language summaries must match the files they aggregate; a totals-only report
+    // (`count --max-memory`) has no files, and only its global summary is checked
+    let totals_only = report.is_totals_only();
+    if !totals_only {
+        let expected: HashMap<_, _> = Report::calculate_language_stats(&report.files)
+            .into_iter()
+            .map(|lang| (lang.language.clone(), lang))
+            .collect();
+        let mut listed = HashSet::new();
+        for lang in &report.languages {
+            listed.insert(lang.language.as_str());
+            let Some(exp) = expected.get(&lang.language) else {
+                problems.push(format!(
+                    "language {}: listed in languages but no file uses it",
+                    lang.language
+                ));
+                continue;
+            };
+            let fields = [
+                ("file_count", lang.file_count, exp.file_count),
+                ("total_lines", lang.total_lines, exp.total_lines),
+                ("logical_lines", lang.logical_lines, exp.logical_lines),
+                ("comment_lines", lang.comment_lines, exp.comment_lines),
+                ("empty_lines", lang.empty_lines, exp.empty_lines),
+            ];
+            for (name, actual, expected) in fields {
+                if actual != expected {
+                    problems.push(format!(
+                        "language {}: {} is {} but its files sum to {}",
+                        lang.language, name, actual, expected
+                    ));
+                }
+            }
+        }
+        let mut missing: Vec<_> = expected
+            .keys()
+            .filter(|name| !listed.contains(name.as_str()))
+            .collect();
+        missing.sort();
+        for name in missing {
+            problems.push(format!(
+                "language {}: used by files but missing from languages",
+                name
+            ));
+        }
+    }
+
+    // Directory summaries partition the files
+    if !report.directories.is_empty() {
+        let grouped: usize = report.directories.iter().map(|d| d.file_count).sum();
+        if grouped != report.files.len() {
+            problems.push(format!(
+                "directories: file_count sums to {} but the report contains {} files",
+                grouped,
+                report.files.len()
+            ));
+        }
+    }
+
+    // Global summary must equal the sum of the files (of the languages without files)
+    let s = &report.summary;
+    let sums = if totals_only {
+        let sum = |f: fn(&LanguageStats) -> usize| report.languages.iter().map(f).sum::<usize>();
+        [
+            sum(|l| l.file_count),
+            sum(|l| l.total_lines),
+            sum(|l| l.logical_lines),
+            sum(|l| l.comment_lines),
+            sum(|l| l.empty_lines),
+        ]
+    } else {
+        let sum = |f: fn(&FileStats) -> usize| report.files.iter().map(f).sum::<usize>();
+        [
+            report.files.len(),
+            sum(|f| f.total_lines),
+            sum(|f| f.logical_lines),
+            sum(|f| f.comment_lines),
+            sum(|f| f.empty_lines),
+        ]
+    };
+    let fields = [
+        ("total_files", s.total_files, sums[0]),
+        ("total_lines", s.total_lines, sums[1]),
+        ("logical_lines", s.logical_lines, sums[2]),
+        ("comment_lines", s.comment_lines, sums[3]),
+        ("empty_lines", s.empty_lines, sums[4]),
+        ("languages_count", s.languages_count, report.languages.len()),
+        (
+            "unsupported_files",
+            s.unsupported_files,
+            report.unsupported_files.len(),
+        ),
+        ("skipped_files", s.skipped_files, report.skipped_files.len()),
+        ("invalid_files", s.invalid_files, report.invalid_files.len()),
+    ];
+    for (name, actual, expected) in fields {
+        if actual != expected {
+            problems.push(format!(
+                "summary.{} is {} but the report contains {}",
+                name, actual, expected
+            ));
+        }
+    }
+
+    // REQ-6.9: A present checksum must match the file statistics
+    if let Some(checksum) = &report.checksum {
+        let mut recomputed = report.clone();
+        recomputed.calculate_checksum();
+        if recomputed.checksum.as_ref() != Some(checksum) {
+            problems.push(format!(
+                "checksum {} does not match the report's counts",
+                checksum
+            ));
+        }
+    }
+
+    problems
+}
@@ -0,0 +1,241 @@
+// watch.rs - Live recount on file changes (`watch`)
+// Implements: REQ-1.1 (line counting), REQ-2.1/2.2/2.3 (inputs), REQ-5.1 (console summary),
+//             REQ-6.8 (report export), REQ-9.4 (parallel processing)
+//
+// After an initial count, filesystem notifications are collected until the tree has been
+// quiet for the debounce interval. The input paths are then listed again (new and deleted
+// files), and only new or changed files are counted before the summary is redrawn and
+// the export, if any, rewritten.
+
+use crate::cli::{OutputFormat, WatchArgs, XmlStyle};
+use crate::config::AppConfig;
+use crate::counter::{collect_input_paths, count_file};
+use crate::error::{Result, SlocError};
+use crate::language::LanguageDetector;
+use crate::output::{ConsoleOutput, ReportExporter, glyph};
+use crate::report::{FileStats, Report};
+use colored::Colorize;
+use notify::{RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// A counted file: its canonical path (to match notifications) and statistics, `None`
+/// when unsupported
+struct Entry {
+    canonical: PathBuf,
+    stats: Option<FileStats>,
+}
+
+pub fn execute_watch(args: WatchArgs) -> Result<()> {
+    let mut detector = LanguageDetector::new();
+    if let Some(config_path) = &args.config {
+        detector.load_from_config(config_path)?;
+    }
+    for (ext, lang) in &args.language_override {
+        detector.add_override(ext.clone(), lang.clone());
+    }
+    let detector = Arc::new(detector);
+    // REQ-9.4: Recounts run on `performance.default_threads` threads (0 = one per CPU)
+    let (config, _) = AppConfig::resolve(args.config.as_deref())?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.performance.default_threads)
+        .build()
+        .map_err(|e| SlocError::Parse(e.to_string()))?;
+
+    let mut entries: BTreeMap<PathBuf, Entry> = BTreeMap::new();
+    let initial = list_inputs(&args, args.output.as_deref())?;
+    let recounted =
+        pool.install(|| recount(&mut entries, initial, &detector, args.ignore_preprocessor));
+    let mut output = refresh(&entries, &args, recounted)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| SlocError::Parse(format!("cannot watch files: {}", e)))?;
+    for (root, mode) in watch_roots(&args.paths, args.recursive) {
+        watcher
+            .watch(&root, mode)
+            .map_err(|e| SlocError::Parse(format!("cannot watch {}: {}", root.display(), e)))?;
+    }
+
+    let debounce = Duration::from_millis(args.debounce);
+    // Blocks until the next notification; ends when the watcher goes away
+    while let Ok(event) = receiver.recv() {
+        let mut changed = HashSet::new();
+        let mut collect = |event: notify::Result<notify::Event>| {
+            // Reads (including our own recounts) are reported as access events
+            if let Ok(event) = event
+                && !event.kind.is_access()
+            {
+                changed.extend(event.paths);
+            }
+        };
+        collect(event);
+        // Editors write in bursts (temp file, rename, metadata): wait for quiet
+        while let Ok(event) = receiver.recv_timeout(debounce) {
+            collect(event);
+        }
+        // Rewriting the export inside a watched directory must not trigger a recount
+        if let Some(output) = &output {
+            changed.remove(output);
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        let current = match list_inputs(&args, output.as_deref()) {
+            Ok(current) => current,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        let listed: HashSet<&PathBuf> = current.iter().collect();
+        let before = entries.len();
+        entries.retain(|path, _| listed.contains(path));
+        let removed = before - entries.len();
+
+        let stale: Vec<PathBuf> = current
+            .iter()
+            .filter(|path| {
+                entries
+                    .get(*path)
+                    .is_none_or(|entry| changed.contains(&entry.canonical))
+            })
+            .cloned()
+            .collect();
+        if stale.is_empty() && removed == 0 {
+            continue;
+        }
+        let recounted =
+            pool.install(|| recount(&mut entries, stale, &detector, args.ignore_preprocessor));
+        output = refresh(&entries, &args, recounted + removed)?;
+    }
+    Ok(())
+}
+
+/// Files to count: the input paths, without the export written by `watch` itself
+fn list_inputs(args: &WatchArgs, output: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let mut paths = collect_input_paths(&args.paths, args.recursive)?;
+    if let Some(output) = output.and_then(|o| std::fs::canonicalize(o).ok()) {
+        paths.retain(|path| std::fs::canonicalize(path).ok().as_ref() != Some(&output));
+    }
+    Ok(paths)
+}
+
+/// Count `paths` in parallel into `entries`; returns how many were counted
+fn recount(
+    entries: &mut BTreeMap<PathBuf, Entry>,
+    paths: Vec<PathBuf>,
+    detector: &Arc<LanguageDetector>,
+    ignore_preprocessor: bool,
+) -> usize {
+    let counted: Vec<(PathBuf, Entry)> = paths
+        .into_par_iter()
+        .map(|path| {
+            let stats = match count_file(&path, detector, ignore_preprocessor) {
+                Ok(stats) if stats.language != "Unknown" => Some(stats),
+                Ok(_) => None,
+                Err(e) => {
+                    error!("cannot count {}: {}", path.display(), e);
+                    None
+                }
+            };
+            let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            (path, Entry { canonical, stats })
+        })
+        .collect();
+    let count = counted.len();
+    entries.extend(counted);
+    count
+}
+
+/// Redraw the summary and rewrite the export; returns the export's canonical path
+fn refresh(
+    entries: &BTreeMap<PathBuf, Entry>,
+    args: &WatchArgs,
+    changed: usize,
+) -> Result<Option<PathBuf>> {
+    let mut files = Vec::new();
+    let mut unsupported = Vec::new();
+    for (path, entry) in entries {
+        match &entry.stats {
+            Some(stats) => files.push(stats.clone()),
+            None => unsupported.push(path.clone()),
+        }
+    }
+    let report = Report::new(files, unsupported);
+
+    let output = match &args.output {
+        Some(path) => {
+            let format = args
+                .format
+                .or_else(|| OutputFormat::from_path(path))
+                .unwrap_or(OutputFormat::Json);
+            ReportExporter::new(XmlStyle::Elements).export(&report, path, format)?;
+            Some(std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        }
+        None => None,
+    };
+
+    let console = ConsoleOutput::new(None, None);
+    if args.quiet {
+        console.display_compact(&report);
+        return Ok(output);
+    }
+    // Redraw in place on a terminal, append when piped to a log
+    if std::io::stdout().is_terminal() {
+        print!("\x1b[2J\x1b[H");
+    }
+    console.display_summary(&report)?;
+    let dot = glyph("·", "-");
+    println!(
+        "\n{} {} {} updated {} {} {} file(s) counted{} {} Ctrl-C to stop",
+        "Watching".bold().cyan(),
+        args.paths.join(" "),
+        dot,
+        chrono::Local::now().format("%H:%M:%S"),
+        dot,
+        changed,
+        match &args.output {
+            Some(path) => format!(" {} written to {}", dot, path.display()),
+            None => String::new(),
+        },
+        dot
+    );
+    Ok(output)
+}
+
+/// Paths to register with the watcher: globs are watched from their directory part down
+fn watch_roots(inputs: &[String], recursive: bool) -> Vec<(PathBuf, RecursiveMode)> {
+    inputs
+        .iter()
+        .map(|input| {
+            if input.contains('*') || input.contains('?') {
+                let root: PathBuf = Path::new(input)
+                    .components()
+                    .take_while(|c| {
+                        let c = c.as_os_str().to_string_lossy();
+                        !c.contains('*') && !c.contains('?')
+                    })
+                    .collect();
+                let root = if root.as_os_str().is_empty() {
+                    PathBuf::from(".")
+                } else {
+                    root
+                };
+                (root, RecursiveMode::Recursive)
+            } else if recursive && Path::new(input).is_dir() {
+                (PathBuf::from(input), RecursiveMode::Recursive)
+            } else {
+                (PathBuf::from(input), RecursiveMode::NonRecursive)
+            }
+        })
+        // Notifications then carry canonical paths, like `Entry::canonical`
+        .map(|(root, mode)| (std::fs::canonicalize(&root).unwrap_or(root), mode))
+        .collect()
+}
@@ -0,0 +1,734 @@
+// xml.rs - XML report layout, styles and validated loading
+// Implements: REQ-6.2 (XML export/import), REQ-7.1 (loading XML reports)
+//
+// The layout is published as XML Schemas in `schemas/report.xsd` (element style)
+// and `schemas/report-attributes.xsd` (attribute style). Reports are written and
+// read through a small element tree so that loading can point at the offending
+// element instead of failing with a bare deserializer message.
+
+use crate::cli::XmlStyle;
+use crate::error::{Result, SlocError};
+use crate::report::{
+    Diagnostic, DiagnosticCode, DirectoryStats, FileStats, GlobalSummary, InvalidFile,
+    LanguageStats, Report, SkippedFile,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::Writer;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::reader::Reader;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Scalar fields of the `<report>` root element
+const REPORT_FIELDS: &[&str] = &[
+    "reportFormatVersion",
+    "generatedAt",
+    "generatorVersion",
+    "checksum",
+];
+/// Child lists and records of the `<report>` root element
+const REPORT_CHILDREN: &[&str] = &[
+    "files",
+    "languages",
+    "directories",
+    "summary",
+    "unsupportedFiles",
+    "skippedFiles",
+    "invalidFiles",
+    "diagnostics",
+];
+/// `encoding` and `invalid_lines` are only written when set
+const FILE_FIELDS: &[&str] = &[
+    "path",
+    "language",
+    "total_lines",
+    "logical_lines",
+    "comment_lines",
+    "empty_lines",
+    "encoding",
+    "invalid_lines",
+];
+/// Plugin metrics of a file (`--metric-plugin`), only written when present
+const FILE_CHILDREN: &[&str] = &["metrics"];
+const METRIC_FIELDS: &[&str] = &["name", "value"];
+const LANGUAGE_FIELDS: &[&str] = &[
+    "language",
+    "file_count",
+    "total_lines",
+    "logical_lines",
+    "comment_lines",
+    "empty_lines",
+];
+const DIRECTORY_FIELDS: &[&str] = &[
+    "path",
+    "file_count",
+    "total_lines",
+    "logical_lines",
+    "comment_lines",
+    "empty_lines",
+];
+const SKIPPED_FIELDS: &[&str] = &["path", "language"];
+const INVALID_FIELDS: &[&str] = &["path", "invalid_lines"];
+/// Fields of a warning of the scan; `message` is always an element
+const DIAGNOSTIC_FIELDS: &[&str] = &["code", "path", "message"];
+const SUMMARY_FIELDS: &[&str] = &[
+    "total_files",
+    "total_lines",
+    "logical_lines",
+    "comment_lines",
+    "empty_lines",
+    "languages_count",
+    "unsupported_files",
+    "skipped_files",
+    "invalid_files",
+];
+
+/// Minimal XML element tree (no mixed content, no namespaces)
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+    /// 1-based line of the start tag (0 for elements built in memory)
+    line: usize,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn with_text(name: &str, text: String) -> Self {
+        Self {
+            text,
+            ..Self::new(name)
+        }
+    }
+
+    /// Add a scalar field as attribute or child element depending on the style
+    fn field(&mut self, style: XmlStyle, name: &str, value: String) {
+        match style {
+            XmlStyle::Attributes => self.attributes.push((name.to_string(), value)),
+            XmlStyle::Elements => self.children.push(Self::with_text(name, value)),
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Writing
+// ---------------------------------------------------------------------------
+
+/// REQ-6.2: Serialize a report as XML in the requested style
+pub fn write_report(report: &Report, style: XmlStyle) -> Result<String> {
+    let mut root = Element::new("report");
+    root.field(
+        style,
+        "reportFormatVersion",
+        report.report_format_version.clone(),
+    );
+    root.field(style, "generatedAt", format_timestamp(&report.generated_at));
+
+    let mut files = Element::new("files");
+    for file in &report.files {
+        let mut item = Element::new("file");
+        item.field(style, "path", file.path.to_string_lossy().into_owned());
+        item.field(style, "language", file.language.clone());
+        item.field(style, "total_lines", file.total_lines.to_string());
+        item.field(style, "logical_lines", file.logical_lines.to_string());
+        item.field(style, "comment_lines", file.comment_lines.to_string());
+        item.field(style, "empty_lines", file.empty_lines.to_string());
+        if let Some(encoding) = &file.encoding {
+            item.field(style, "encoding", encoding.clone());
+        }
+        if file.invalid_lines > 0 {
+            item.field(style, "invalid_lines", file.invalid_lines.to_string());
+        }
+        if !file.metrics.is_empty() {
+            let mut metrics = Element::new("metrics");
+            for (name, value) in &file.metrics {
+                let mut metric = Element::new("metric");
+                metric.field(style, "name", name.clone());
+                metric.field(style, "value", value.to_string());
+                metrics.children.push(metric);
+            }
+            item.children.push(metrics);
+        }
+        files.children.push(item);
+    }
+    root.children.push(files);
+
+    let mut languages = Element::new("languages");
+    for lang in &report.languages {
+        let mut item = Element::new("language");
+        item.field(style, "language", lang.language.clone());
+        item.field(style, "file_count", lang.file_count.to_string());
+        item.field(style, "total_lines", lang.total_lines.to_string());
+        item.field(style, "logical_lines", lang.logical_lines.to_string());
+        item.field(style, "comment_lines", lang.comment_lines.to_string());
+        item.field(style, "empty_lines", lang.empty_lines.to_string());
+        languages.children.push(item);
+    }
+    root.children.push(languages);
+
+    // Only written when grouped with --by-dir
+    if !report.directories.is_empty() {
+        let mut directories = Element::new("directories");
+        for dir in &report.directories {
+            let mut item = Element::new("directory");
+            item.field(style, "path", dir.path.to_string_lossy().into_owned());
+            item.field(style, "file_count", dir.file_count.to_string());
+            item.field(style, "total_lines", dir.total_lines.to_string());
+            item.field(style, "logical_lines", dir.logical_lines.to_string());
+            item.field(style, "comment_lines", dir.comment_lines.to_string());
+            item.field(style, "empty_lines", dir.empty_lines.to_string());
+            directories.children.push(item);
+        }
+        root.children.push(directories);
+    }
+
+    let s = &report.summary;
+    let mut summary = Element::new("summary");
+    summary.field(style, "total_files", s.total_files.to_string());
+    summary.field(style, "total_lines", s.total_lines.to_string());
+    summary.field(style, "logical_lines", s.logical_lines.to_string());
+    summary.field(style, "comment_lines", s.comment_lines.to_string());
+    summary.field(style, "empty_lines", s.empty_lines.to_string());
+    summary.field(style, "languages_count", s.languages_count.to_string());
+    summary.field(style, "unsupported_files", s.unsupported_files.to_string());
+    if s.skipped_files > 0 {
+        summary.field(style, "skipped_files", s.skipped_files.to_string());
+    }
+    if s.invalid_files > 0 {
+        summary.field(style, "invalid_files", s.invalid_files.to_string());
+    }
+    root.children.push(summary);
+
+    // Paths are free text, so they stay as elements in both styles
+    let mut unsupported = Element::new("unsupportedFiles");
+    for path in &report.unsupported_files {
+        unsupported.children.push(Element::with_text(
+            "path",
+            path.to_string_lossy().into_owned(),
+        ));
+    }
+    root.children.push(unsupported);
+
+    // Only written when languages were left out, or files had undecodable bytes
+    if !report.skipped_files.is_empty() {
+        let mut skipped = Element::new("skippedFiles");
+        for file in &report.skipped_files {
+            let mut item = Element::new("skippedFile");
+            item.field(style, "path", file.path.to_string_lossy().into_owned());
+            item.field(style, "language", file.language.clone());
+            skipped.children.push(item);
+        }
+        root.children.push(skipped);
+    }
+    if !report.invalid_files.is_empty() {
+        let mut invalid = Element::new("invalidFiles");
+        for file in &report.invalid_files {
+            let mut item = Element::new("invalidFile");
+            item.field(style, "path", file.path.to_string_lossy().into_owned());
+            item.field(style, "invalid_lines", file.invalid_lines.to_string());
+            invalid.children.push(item);
+        }
+        root.children.push(invalid);
+    }
+
+    // Only written when the scan had warnings
+    if !report.diagnostics.is_empty() {
+        let mut diagnostics = Element::new("diagnostics");
+        for diagnostic in &report.diagnostics {
+            let mut item = Element::new("diagnostic");
+            item.field(style, "code", diagnostic.code.name().to_string());
+            if let Some(path) = &diagnostic.path {
+                item.field(style, "path", path.to_string_lossy().into_owned());
+            }
+            item.children
+                .push(Element::with_text("message", diagnostic.message.clone()));
+            diagnostics.children.push(item);
+        }
+        root.children.push(diagnostics);
+    }
+
+    if let Some(generator) = &report.generator_version {
+        root.field(style, "generatorVersion", generator.clone());
+    }
+    if let Some(checksum) = &report.checksum {
+        root.field(style, "checksum", checksum.clone());
+    }
+
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer
+        .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+        .and_then(|_| write_element(&mut writer, &root))
+        .map_err(|e| SlocError::Serialization(e.to_string()))?;
+
+    let mut xml = String::from_utf8(writer.into_inner())
+        .map_err(|e| SlocError::Serialization(e.to_string()))?;
+    xml.push('\n');
+    Ok(xml)
+}
+
+fn write_element(writer: &mut Writer<Vec<u8>>, element: &Element) -> std::io::Result<()> {
+    let mut start = BytesStart::new(element.name.as_str());
+    for (name, value) in &element.attributes {
+        start.push_attribute((name.as_str(), value.as_str()));
+    }
+
+    if element.children.is_empty() && element.text.is_empty() {
+        return writer.write_event(Event::Empty(start));
+    }
+
+    writer.write_event(Event::Start(start))?;
+    if element.children.is_empty() {
+        writer.write_event(Event::Text(BytesText::new(&element.text)))?;
+    } else {
+        for child in &element.children {
+            write_element(writer, child)?;
+        }
+    }
+    writer.write_event(Event::End(BytesEnd::new(element.name.as_str())))
+}
+
+/// Same representation serde uses for `DateTime<Utc>` in JSON reports
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+// ---------------------------------------------------------------------------
+// Reading and validation
+// ---------------------------------------------------------------------------
+
+/// REQ-7.1: Load an XML report written in either style, validating it against
+/// the published schema. Errors name the line and element path, e.g.
+///
+/// ```text
+/// XML line 14, report/files/file[2]: `total_lines` must be a non-negative integer, found "abc"
+/// ```
+pub fn read_report(content: &str) -> Result<Report> {
+    let root = parse(content)?;
+    if root.name != "report" {
+        return Err(invalid(
+            root.line,
+            &root.name,
+            "expected root element <report>".to_string(),
+        ));
+    }
+
+    let path = "report";
+    check_names(&root, path, REPORT_FIELDS, REPORT_CHILDREN)?;
+    let fields = Fields::new(&root, path);
+
+    let mut files = Vec::new();
+    for (item, item_path) in list(&root, path, "files", "file")? {
+        check_names(item, &item_path, FILE_FIELDS, FILE_CHILDREN)?;
+        let f = Fields::new(item, &item_path);
+        let mut metrics = BTreeMap::new();
+        for (metric, metric_path) in list(item, &item_path, "metrics", "metric")? {
+            check_names(metric, &metric_path, METRIC_FIELDS, &[])?;
+            let m = Fields::new(metric, &metric_path);
+            metrics.insert(m.text("name")?, m.number("value")?);
+        }
+        files.push(FileStats {
+            path: PathBuf::from(f.text("path")?),
+            language: f.text("language")?,
+            total_lines: f.count("total_lines")?,
+            logical_lines: f.count("logical_lines")?,
+            comment_lines: f.count("comment_lines")?,
+            empty_lines: f.count("empty_lines")?,
+            metrics,
+            encoding: f.optional_text("encoding"),
+            invalid_lines: f.optional_count("invalid_lines")?,
+        });
+    }
+
+    let mut languages = Vec::new();
+    for (item, item_path) in list(&root, path, "languages", "language")? {
+        check_names(item, &item_path, LANGUAGE_FIELDS, &[])?;
+        let f = Fields::new(item, &item_path);
+        languages.push(LanguageStats {
+            language: f.text("language")?,
+            file_count: f.count("file_count")?,
+            total_lines: f.count("total_lines")?,
+            logical_lines: f.count("logical_lines")?,
+            comment_lines: f.count("comment_lines")?,
+            empty_lines: f.count("empty_lines")?,
+        });
+    }
+
+    let mut directories = Vec::new();
+    for (item, item_path) in list(&root, path, "directories", "directory")? {
+        check_names(item, &item_path, DIRECTORY_FIELDS, &[])?;
+        let f = Fields::new(item, &item_path);
+        directories.push(DirectoryStats {
+            path: PathBuf::from(f.text("path")?),
+            file_count: f.count("file_count")?,
+            total_lines: f.count("total_lines")?,
+            logical_lines: f.count("logical_lines")?,
+            comment_lines: f.count("comment_lines")?,
+            empty_lines: f.count("empty_lines")?,
+        });
+    }
+
+    let summary_element = root
+        .child("summary")
+        .ok_or_else(|| invalid(root.line, path, "missing element <summary>".to_string()))?;
+    let summary_path = format!("{}/summary", path);
+    check_names(summary_element, &summary_path, SUMMARY_FIELDS, &[])?;
+    let s = Fields::new(summary_element, &summary_path);
+    let summary = GlobalSummary {
+        total_files: s.count("total_files")?,
+        total_lines: s.count("total_lines")?,
+        logical_lines: s.count("logical_lines")?,
+        comment_lines: s.count("comment_lines")?,
+        empty_lines: s.count("empty_lines")?,
+        languages_count: s.count("languages_count")?,
+        unsupported_files: s.count("unsupported_files")?,
+        skipped_files: s.optional_count("skipped_files")?,
+        invalid_files: s.optional_count("invalid_files")?,
+    };
+
+    let mut unsupported_files = Vec::new();
+    for (item, item_path) in list(&root, path, "unsupportedFiles", "path")? {
+        check_names(item, &item_path, &[], &[])?;
+        if item.text.is_empty() {
+            return Err(invalid(item.line, &item_path, "empty path".to_string()));
+        }
+        unsupported_files.push(PathBuf::from(&item.text));
+    }
+
+    let mut skipped_files = Vec::new();
+    for (item, item_path) in list(&root, path, "skippedFiles", "skippedFile")? {
+        check_names(item, &item_path, SKIPPED_FIELDS, &[])?;
+        let f = Fields::new(item, &item_path);
+        skipped_files.push(SkippedFile {
+            path: PathBuf::from(f.text("path")?),
+            language: f.text("language")?,
+        });
+    }
+
+    let mut invalid_files = Vec::new();
+    for (item, item_path) in list(&root, path, "invalidFiles", "invalidFile")? {
+        check_names(item, &item_path, INVALID_FIELDS, &[])?;
+        let f = Fields::new(item, &item_path);
+        invalid_files.push(InvalidFile {
+            path: PathBuf::from(f.text("path")?),
+            invalid_lines: f.count("invalid_lines")?,
+        });
+    }
+
+    let mut diagnostics = Vec::new();
+    for (item, item_path) in list(&root, path, "diagnostics", "diagnostic")? {
+        check_names(item, &item_path, DIAGNOSTIC_FIELDS, &[])?;
+        let f = Fields::new(item, &item_path);
+        diagnostics.push(Diagnostic {
+            code: DiagnosticCode::from_name(&f.text("code")?),
+            path: f.optional_text("path").map(PathBuf::from),
+            message: f.text("message")?,
+        });
+    }
+
+    Ok(Report {
+        report_format_version: fields.text("reportFormatVersion")?,
+        generated_at: fields.timestamp("generatedAt")?,
+        files,
+        languages,
+        directories,
+        summary,
+        unsupported_files,
+        skipped_files,
+        invalid_files,
+        diagnostics,
+        generator_version: fields.optional_text("generatorVersion"),
+        checksum: fields.optional_text("checksum"),
+    })
+}
+
+/// Schema violation at `path` (line 0 means "unknown")
+fn invalid(line: usize, path: &str, message: String) -> SlocError {
+    SlocError::InvalidReportFormat(format!("XML line {}, {}: {}", line, path, message))
+}
+
+/// Reject attributes and child elements the schema does not define.
+/// `fields` may appear either as attribute or child element; `children` only as elements.
+fn check_names(element: &Element, path: &str, fields: &[&str], children: &[&str]) -> Result<()> {
+    for (name, _) in &element.attributes {
+        // Namespace declarations and xsi:schemaLocation hints are always allowed
+        if name.starts_with("xmlns") || name.starts_with("xsi:") {
+            continue;
+        }
+        if !fields.contains(&name.as_str()) {
+            return Err(invalid(
+                element.line,
+                path,
+                format!("unexpected attribute `{}`", name),
+            ));
+        }
+    }
+
+    for child in &element.children {
+        let name = child.name.as_str();
+        if !fields.contains(&name) && !children.contains(&name) {
+            return Err(invalid(
+                child.line,
+                path,
+                format!("unexpected element <{}>", name),
+            ));
+        }
+        if fields.contains(&name) && !child.children.is_empty() {
+            return Err(invalid(
+                child.line,
+                path,
+                format!("<{}> must contain text only", name),
+            ));
+        }
+    }
+
+    if !element.text.is_empty() && !element.children.is_empty() {
+        return Err(invalid(
+            element.line,
+            path,
+            "unexpected text content".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Items of an optional list wrapper (`<files><file/>...</files>`) with their element paths
+fn list<'a>(
+    parent: &'a Element,
+    path: &str,
+    wrapper: &str,
+    item: &str,
+) -> Result<Vec<(&'a Element, String)>> {
+    let Some(wrapper_element) = parent.child(wrapper) else {
+        return Ok(Vec::new());
+    };
+    let wrapper_path = format!("{}/{}", path, wrapper);
+    check_names(wrapper_element, &wrapper_path, &[], &[item])?;
+
+    Ok(wrapper_element
+        .children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| (child, format!("{}/{}[{}]", wrapper_path, item, i + 1)))
+        .collect())
+}
+
+/// Scalar field lookup on a record element (attribute or child element)
+struct Fields<'a> {
+    element: &'a Element,
+    path: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn new(element: &'a Element, path: &'a str) -> Self {
+        Self { element, path }
+    }
+
+    /// Field value with the line it was found on
+    fn lookup(&self, name: &str) -> Option<(&'a str, usize)> {
+        self.element
+            .attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| (v.as_str(), self.element.line))
+            .or_else(|| self.element.child(name).map(|c| (c.text.as_str(), c.line)))
+    }
+
+    fn required(&self, name: &str) -> Result<(&'a str, usize)> {
+        self.lookup(name).ok_or_else(|| {
+            invalid(
+                self.element.line,
+                self.path,
+                format!("missing `{}` (attribute or child element)", name),
+            )
+        })
+    }
+
+    fn text(&self, name: &str) -> Result<String> {
+        self.required(name).map(|(value, _)| value.to_string())
+    }
+
+    fn optional_text(&self, name: &str) -> Option<String> {
+        self.lookup(name).map(|(value, _)| value.to_string())
+    }
+
+    fn count(&self, name: &str) -> Result<usize> {
+        let (value, line) = self.required(name)?;
+        value.trim().parse().map_err(|_| {
+            invalid(
+                line,
+                self.path,
+                format!(
+                    "`{}` must be a non-negative integer, found \"{}\"",
+                    name, value
+                ),
+            )
+        })
+    }
+
+    /// A count written only when not zero
+    fn optional_count(&self, name: &str) -> Result<usize> {
+        match self.lookup(name) {
+            Some(_) => self.count(name),
+            None => Ok(0),
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<f64> {
+        let (value, line) = self.required(name)?;
+        value.trim().parse().map_err(|_| {
+            invalid(
+                line,
+                self.path,
+                format!("`{}` must be a number, found \"{}\"", name, value),
+            )
+        })
+    }
+
+    fn timestamp(&self, name: &str) -> Result<DateTime<Utc>> {
+        let (value, line) = self.required(name)?;
+        DateTime::parse_from_rfc3339(value.trim())
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                invalid(
+                    line,
+                    self.path,
+                    format!(
+                        "`{}` must be an RFC 3339 timestamp, found \"{}\" ({})",
+                        name, value, e
+                    ),
+                )
+            })
+    }
+}
+
+/// Parse the document into an element tree, reporting malformed XML with its line
+fn parse(content: &str) -> Result<Element> {
+    // Text is kept as written (a path may start or end with spaces, or have spaces next
+    // to an escaped character); the indentation between elements is dropped on close
+    let mut reader = Reader::from_str(content);
+
+    let line_at = |pos: u64| {
+        let end = (pos as usize).min(content.len());
+        content.as_bytes()[..end]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count()
+            + 1
+    };
+    let malformed = |pos: u64, message: String| {
+        SlocError::InvalidReportFormat(format!("XML line {}: {}", line_at(pos), message))
+    };
+
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root: Option<Element> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| malformed(reader.error_position(), e.to_string()))?;
+        let position = reader.buffer_position();
+
+        match event {
+            Event::Start(start) | Event::Empty(start) if root.is_some() => {
+                return Err(malformed(
+                    position,
+                    format!(
+                        "unexpected element <{}> after the root element",
+                        String::from_utf8_lossy(start.name().as_ref())
+                    ),
+                ));
+            }
+            Event::Start(start) => {
+                stack.push(
+                    start_element(&start, line_at(position))
+                        .map_err(|e| malformed(position, e.to_string()))?,
+                );
+            }
+            Event::Empty(start) => {
+                let element = start_element(&start, line_at(position))
+                    .map_err(|e| malformed(position, e.to_string()))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+            Event::End(_) => {
+                // quick-xml already checks that end tags match their start tags
+                if let Some(mut element) = stack.pop() {
+                    if element.text.trim().is_empty() {
+                        element.text.clear();
+                    }
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => root = Some(element),
+                    }
+                }
+            }
+            Event::Text(text) => {
+                let decoded = text
+                    .xml_content()
+                    .map_err(|e| malformed(position, e.to_string()))?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&decoded);
+                }
+            }
+            Event::CData(cdata) => {
+                let decoded = cdata
+                    .decode()
+                    .map_err(|e| malformed(position, e.to_string()))?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&decoded);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                let name = reference
+                    .decode()
+                    .map_err(|e| malformed(position, e.to_string()))?;
+                let raw = format!("&{};", name);
+                let resolved = quick_xml::escape::unescape(&raw)
+                    .map_err(|e| malformed(position, e.to_string()))?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if let Some(open) = stack.last() {
+        return Err(malformed(
+            content.len() as u64,
+            format!("element <{}> is never closed", open.name),
+        ));
+    }
+    root.ok_or_else(|| malformed(0, "document has no root element".to_string()))
+}
+
+fn start_element(
+    start: &BytesStart,
+    line: usize,
+) -> std::result::Result<Element, quick_xml::Error> {
+    let mut element = Element::new(&String::from_utf8_lossy(start.name().as_ref()));
+    element.line = line;
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        element.attributes.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            attribute.unescape_value()?.into_owned(),
+        ));
+    }
+    Ok(element)
+}
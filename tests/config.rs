@@ -0,0 +1,82 @@
+// config.rs - Settings taken from the user configuration and the environment
+// Implements: REQ-3.3 (configuration files), REQ-9.4 (parallel processing)
+
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use std::fs;
+use std::path::Path;
+
+/// `count` in `dir`, with `home` as the user configuration directory
+fn count(home: &Path, dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("rustedbytes-counterlines").unwrap();
+    cmd.current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env_remove("COUNTERLINES_THREADS")
+        .args(["count", ".", "-r", "--no-cache", "--no-progress"]);
+    cmd
+}
+
+fn project() -> (tempfile::TempDir, tempfile::TempDir) {
+    let home = tempfile::tempdir().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.rs"), "fn main() {}\n").unwrap();
+    (home, dir)
+}
+
+fn user_config(home: &Path, contents: &str) {
+    let config = home.join(".config").join("counterlines");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("config.toml"), contents).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn user_config_sets_the_thread_count() {
+    let (home, dir) = project();
+    user_config(home.path(), "[performance]\ndefault_threads = 3\n");
+
+    count(home.path(), dir.path())
+        .assert()
+        .success()
+        .stdout(contains("(3 threads)"));
+    // An explicit -j wins
+    count(home.path(), dir.path())
+        .args(["-j", "2"])
+        .assert()
+        .success()
+        .stdout(contains("(2 threads)"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn environment_overrides_the_user_config_thread_count() {
+    let (home, dir) = project();
+    user_config(home.path(), "[performance]\ndefault_threads = 3\n");
+
+    count(home.path(), dir.path())
+        .env("COUNTERLINES_THREADS", "2")
+        .assert()
+        .success()
+        .stdout(contains("(2 threads)"));
+    count(home.path(), dir.path())
+        .env("COUNTERLINES_THREADS", "2")
+        .args(["-j", "1"])
+        .assert()
+        .success()
+        .stdout(contains("(1 threads)"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn chunk_size_is_no_longer_accepted() {
+    let (home, dir) = project();
+    user_config(home.path(), "[performance]\nchunk_size = 1000\n");
+
+    count(home.path(), dir.path())
+        .assert()
+        .failure()
+        .stderr(contains("unknown field `chunk_size`"));
+}
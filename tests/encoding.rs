@@ -0,0 +1,79 @@
+// encoding.rs - `count --on-invalid-utf8` on UTF-8 files with a stray byte
+// Implements: REQ-9.2 (encoding handling)
+
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use serde_json::Value;
+use std::fs;
+
+/// A UTF-8 source file with one byte that is not UTF-8, on its third line
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("a.rs"),
+        b"// Gr\xC3\xB6\xC3\x9Fe f\xC3\xBCr sp\xC3\xA4ter\nfn a() {}\nlet s = \"\xE9\";\n// done \xE2\x80\x94 ok\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn count(dir: &std::path::Path, policy: &str) -> Command {
+    let mut cmd = Command::cargo_bin("rustedbytes-counterlines").unwrap();
+    cmd.args(["--no-config", "count", "-r", "--no-cache", "--no-progress"])
+        .args(["--on-invalid-utf8", policy, "-f", "json", "-o", "-"])
+        .arg(dir);
+    cmd
+}
+
+fn report(dir: &std::path::Path, policy: &str) -> Value {
+    let output = count(dir, policy)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn replace_counts_the_line_and_lists_it() {
+    let dir = project();
+    let report = report(dir.path(), "replace");
+    let file = &report["files"][0];
+    assert_eq!(file["total_lines"], 4);
+    assert_eq!(file["logical_lines"], 2);
+    assert_eq!(file["invalid_lines"], 1);
+    // Read as UTF-8, not as a legacy encoding
+    assert!(file.get("encoding").is_none(), "{file}");
+    assert_eq!(report["summary"]["invalid_files"], 1);
+}
+
+#[test]
+fn skip_line_leaves_the_line_out() {
+    let dir = project();
+    let report = report(dir.path(), "skip-line");
+    let file = &report["files"][0];
+    assert_eq!(file["total_lines"], 3);
+    assert_eq!(file["logical_lines"], 1);
+    assert_eq!(file["invalid_lines"], 1);
+}
+
+#[test]
+fn skip_file_leaves_the_file_out() {
+    let dir = project();
+    let report = report(dir.path(), "skip-file");
+    assert_eq!(report["summary"]["total_files"], 0);
+    assert_eq!(report["invalidFiles"][0]["invalid_lines"], 1);
+}
+
+#[test]
+fn error_fails_the_run() {
+    let dir = project();
+    count(dir.path(), "error")
+        .assert()
+        .failure()
+        .stderr(contains("cannot decode"))
+        .stderr(contains("a.rs (1 line(s))"));
+}
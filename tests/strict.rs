@@ -0,0 +1,48 @@
+// strict.rs - `count --strict` on paths that cannot be counted
+// Implements: REQ-2.5 (error handling), REQ-8.4
+
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use std::fs;
+
+fn count(dir: &std::path::Path, strict: bool) -> Command {
+    let mut cmd = Command::cargo_bin("rustedbytes-counterlines").unwrap();
+    cmd.args([
+        "--no-config",
+        "count",
+        "-r",
+        "-q",
+        "--no-cache",
+        "--no-progress",
+    ])
+    .arg(dir);
+    if strict {
+        cmd.arg("--strict");
+    }
+    cmd
+}
+
+#[test]
+fn strict_passes_a_complete_count() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.rs"), "fn main() {}\n").unwrap();
+    count(dir.path(), true).assert().success();
+}
+
+#[cfg(unix)]
+#[test]
+fn strict_fails_on_a_dangling_symlink() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.rs"), "fn main() {}\n").unwrap();
+    std::os::unix::fs::symlink(dir.path().join("missing.rs"), dir.path().join("b.rs")).unwrap();
+
+    // Only a warning without --strict
+    count(dir.path(), false).assert().success();
+    count(dir.path(), true)
+        .assert()
+        .code(1)
+        .stderr(contains("1 path(s) could not be counted"))
+        .stderr(contains("b.rs"));
+}